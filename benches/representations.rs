@@ -0,0 +1,134 @@
+//! the tree compared to a plain `Vec<String>` of paths, on generated layouts: build time,
+//! heap size, partition pruning and rendering the full listing.
+//!
+//! run with `cargo bench`. heap sizes are measured by counting allocations and printed
+//! before the timings.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use deltatree::tree::generate::Layout;
+use deltatree::tree::predicate::{self, PartitionPredicate};
+use deltatree::tree::DeltaTree;
+use std::alloc::{GlobalAlloc, Layout as AllocLayout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// counts the bytes currently allocated on the heap.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: AllocLayout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: AllocLayout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// the value built by `f` along with the bytes it keeps allocated.
+fn heap_size<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let value = f();
+    (value, ALLOCATED.load(Ordering::Relaxed) - before)
+}
+
+/// layouts from a handful of large partitions to many small ones, with the predicate
+/// selecting about a tenth of each.
+fn layouts() -> Vec<(&'static str, Layout, PartitionPredicate)> {
+    let layout = |columns: &[&str], files| {
+        let columns = columns.iter().map(|c| c.parse().unwrap()).collect();
+        Layout::new(columns, files)
+    };
+    vec![
+        (
+            "daily",
+            layout(&["date:100"], 100_000),
+            "date>=090".parse().unwrap(),
+        ),
+        (
+            "hourly",
+            layout(&["date:100", "hour:24"], 100_000),
+            "date>=090".parse().unwrap(),
+        ),
+        (
+            "skewed",
+            layout(&["date:100", "country:50"], 100_000).skew(1.0),
+            "country<05".parse().unwrap(),
+        ),
+        (
+            "sparse",
+            layout(&["date:365", "country:50", "device:8"], 100_000),
+            "device=0".parse().unwrap(),
+        ),
+    ]
+}
+
+fn report_memory() {
+    println!(
+        "{:<10} {:>12} {:>12} {:>10}",
+        "layout", "vec", "tree", "relative"
+    );
+    for (name, layout, _) in layouts() {
+        let paths = layout.paths();
+        let (copy, vec_bytes) = heap_size(|| paths.clone());
+        let (tree, tree_bytes) = heap_size(|| DeltaTree::from_paths(&paths));
+        println!(
+            "{:<10} {:>12} {:>12} {:>9} %",
+            name,
+            vec_bytes,
+            tree_bytes,
+            100 * tree_bytes / vec_bytes
+        );
+        drop((copy, tree));
+    }
+}
+
+fn representations(c: &mut Criterion) {
+    report_memory();
+    for (name, layout, filter) in layouts() {
+        let paths = layout.paths();
+        let tree = DeltaTree::from_paths(&paths);
+        let filters = [filter];
+
+        let mut group = c.benchmark_group("build");
+        group.bench_with_input(BenchmarkId::new("vec", name), &paths, |b, paths| {
+            b.iter_with_large_drop(|| paths.clone())
+        });
+        group.bench_with_input(BenchmarkId::new("tree", name), &paths, |b, paths| {
+            b.iter_with_large_drop(|| DeltaTree::from_paths(paths))
+        });
+        group.finish();
+
+        let mut group = c.benchmark_group("prune");
+        group.bench_with_input(BenchmarkId::new("vec", name), &paths, |b, paths| {
+            b.iter(|| {
+                paths
+                    .iter()
+                    .filter(|path| predicate::path_matches(path, black_box(&filters)))
+                    .count()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("tree", name), &tree, |b, tree| {
+            b.iter(|| tree.file_iter(black_box(&filters)).count())
+        });
+        group.finish();
+
+        let mut group = c.benchmark_group("listing");
+        group.bench_with_input(BenchmarkId::new("vec", name), &paths, |b, paths| {
+            b.iter_with_large_drop(|| paths.to_vec())
+        });
+        group.bench_with_input(BenchmarkId::new("tree", name), &tree, |b, tree| {
+            b.iter_with_large_drop(|| tree.files())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, representations);
+criterion_main!(benches);
@@ -0,0 +1,11 @@
+//! parsing arbitrary file names never panics, and the names of parsed files parse back to the
+//! same file.
+#![no_main]
+use deltatree::tree::codec::{FileNameCodec, SparkFileNameCodec};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|name: &str| {
+    if let Some(file) = SparkFileNameCodec.decode(name) {
+        assert_eq!(SparkFileNameCodec.decode(&file.name()), Some(file));
+    }
+});
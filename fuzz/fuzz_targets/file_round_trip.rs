@@ -0,0 +1,11 @@
+//! file names round trip through both codecs.
+#![no_main]
+use deltatree::tree::codec::{FileNameCodec, RegexFileNameCodec, SparkFileNameCodec};
+use deltatree::tree::ParquetDeltaFile;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|file: ParquetDeltaFile| {
+    let name = file.name();
+    assert_eq!(SparkFileNameCodec.decode(&name), Some(file));
+    assert_eq!(RegexFileNameCodec.decode(&name), Some(file));
+});
@@ -0,0 +1,14 @@
+//! decoding arbitrary bytes as a tree fails without panicking, and whatever decodes survives
+//! a round trip.
+#![no_main]
+use deltatree::tree::serialize::{read_tree, write_tree};
+use deltatree::tree::FxBuildHasher;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Some((tree, version)) = read_tree::<FxBuildHasher>(bytes) {
+        let mut written = vec![];
+        write_tree(&tree, version, &mut written);
+        assert_eq!(read_tree::<FxBuildHasher>(&written), Some((tree, version)));
+    }
+});
@@ -0,0 +1,13 @@
+//! a tree lists exactly the paths it was built from.
+#![no_main]
+use deltatree::tree::testing::TablePaths;
+use deltatree::tree::DeltaTree;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|paths: TablePaths| {
+    let TablePaths(mut paths) = paths;
+    let mut files = DeltaTree::from_paths(&paths).files_with_prefix();
+    files.sort();
+    paths.sort();
+    assert_eq!(files, paths);
+});
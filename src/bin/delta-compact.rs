@@ -0,0 +1,152 @@
+extern crate anyhow;
+extern crate deltalake;
+
+use clap::Parser;
+use deltalake::storage::StorageBackend;
+use deltatree::tree::optimize::{OptimizePlan, Rewrite};
+use deltatree::tree::sized::parse_size;
+use deltatree::tree::storage::StorageOptions;
+use deltatree::tree::DeltaTree;
+use parquet::arrow::{ArrowReader, ArrowWriter, ParquetFileArrowReader};
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use parquet::file::serialized_reader::SerializedFileReader;
+use parquet::util::cursor::{InMemoryWriteableCursor, SliceableCursor};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// rewrite the small files of a delta table into files of about the target size, following
+/// the compaction plan of `delta-tree compact-plan`, and commit the replacement as the version
+/// following the one it was planned on. fails if a later commit removed any of the rewritten
+/// files. the data doesn't change, so the commit doesn't affect streaming readers.
+#[derive(Parser)]
+#[clap(name = "delta-compact", version)]
+struct Cli {
+    /// path or URI of the delta table.
+    table: String,
+    /// the size of the compacted files, e.g. `256MB`.
+    #[clap(long, default_value = "256MB", parse(try_from_str = parse_size))]
+    target_size: u64,
+    /// only print the plan, neither write files nor commit.
+    #[clap(long)]
+    dry_run: bool,
+    /// object store option, e.g. `AWS_REGION=eu-central-1`. may be repeated.
+    #[clap(long = "storage-option", value_name = "KEY=VALUE")]
+    storage_options: Vec<String>,
+}
+
+/// rows per record batch while copying.
+const BATCH_SIZE: usize = 64 * 1024;
+
+/// the reader version of tables with reader features such as deletion vectors.
+const DELETION_VECTORS_READER_VERSION: i32 = 3;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    anyhow::ensure!(cli.target_size > 0, "--target-size must be positive");
+    let storage = StorageOptions::new()
+        .parse_options(&cli.storage_options)
+        .map_err(anyhow::Error::msg)?;
+
+    let mut delta_table = storage.open_table(&cli.table).await?;
+    // the deletion vectors of the add actions aren't applied when copying rows, so compacting
+    // a file with one would bring its deleted rows back.
+    anyhow::ensure!(
+        delta_table.get_min_reader_version() < DELETION_VECTORS_READER_VERSION,
+        "tables with reader version {} or later may use deletion vectors, which aren't supported",
+        DELETION_VECTORS_READER_VERSION
+    );
+    let planned_version = delta_table.version;
+    let plan = OptimizePlan::new(&DeltaTree::new_sized(&delta_table), cli.target_size);
+    println!(
+        "version {}: {} files ({} bytes) to be rewritten into {} files",
+        planned_version,
+        plan.files,
+        plan.bytes,
+        plan.rewrites.len()
+    );
+    if plan.rewrites.is_empty() {
+        return Ok(());
+    }
+    if cli.dry_run {
+        for rewrite in &plan.rewrites {
+            println!(
+                "{}: {} files, {} bytes",
+                rewrite.output,
+                rewrite.inputs.len(),
+                rewrite.bytes
+            );
+            for input in &rewrite.inputs {
+                println!("  - {}", input.path);
+            }
+        }
+        return Ok(());
+    }
+
+    let backend = storage.backend(&cli.table)?;
+    let mut written = HashMap::new();
+    for rewrite in &plan.rewrites {
+        let size = merge(backend.as_ref(), &cli.table, rewrite, &storage).await?;
+        println!("{} files -> {}", rewrite.inputs.len(), rewrite.output);
+        written.insert(rewrite.output.clone(), size);
+    }
+    let commit = plan
+        .commit(&written)
+        .read_version(planned_version)
+        .commit(&mut delta_table)
+        .await?;
+    match commit {
+        Some(version) => println!("committed version {}", version),
+        None => println!("nothing to commit"),
+    }
+    Ok(())
+}
+
+/// merge the inputs of `rewrite` into its output, returning the size of the output.
+async fn merge(
+    backend: &dyn StorageBackend,
+    table_uri: &str,
+    rewrite: &Rewrite,
+    storage: &StorageOptions,
+) -> anyhow::Result<u64> {
+    let output = InMemoryWriteableCursor::default();
+    let mut writer = None;
+    let mut schema = None;
+    for input in &rewrite.inputs {
+        let uri = backend.join_path(table_uri, &input.path);
+        let bytes = storage.retry(|| backend.get_obj(&uri)).await?;
+        let reader = SerializedFileReader::new(SliceableCursor::new(bytes))?;
+        let mut reader = ParquetFileArrowReader::new(Arc::new(reader));
+        let file_schema = reader.get_schema()?;
+        match &schema {
+            None => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let schema_ref = Arc::new(file_schema.clone());
+                writer = Some(ArrowWriter::try_new(
+                    output.clone(),
+                    schema_ref,
+                    Some(properties),
+                )?);
+                schema = Some(file_schema);
+            }
+            Some(schema) => anyhow::ensure!(
+                *schema == file_schema,
+                "{} has a different schema than the other files of its group",
+                input.path
+            ),
+        }
+        let writer = writer.as_mut().expect("writer created with the first file");
+        for batch in reader.get_record_reader(BATCH_SIZE)? {
+            writer.write(&batch?)?;
+        }
+    }
+    writer.expect("rewrites are never empty").close()?;
+
+    let bytes = output.data();
+    let uri = backend.join_path(table_uri, &rewrite.output);
+    storage.retry(|| backend.put_obj(&uri, &bytes)).await?;
+    Ok(bytes.len() as u64)
+}
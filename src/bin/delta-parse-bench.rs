@@ -0,0 +1,70 @@
+use deltatree::tree::codec::{FileNameCodec, RegexFileNameCodec, SparkFileNameCodec};
+use deltatree::tree::DeltaTree;
+use std::env;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// compare the regex based file name parser with the hand-rolled one, both in isolation and
+/// for building a complete tree from synthetic paths.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let num_files = args
+        .get(1)
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(1_000_000);
+
+    let paths = synthetic_paths(num_files);
+    println!("parsing {} synthetic paths", paths.len());
+
+    let names: Vec<&str> = paths
+        .iter()
+        .map(|p| p.rsplit('/').next().unwrap())
+        .collect();
+    let regex_parse = time_parse(&names, &RegexFileNameCodec);
+    let manual_parse = time_parse(&names, &SparkFileNameCodec);
+    report("file name parsing", regex_parse, manual_parse);
+
+    let start_regex = Instant::now();
+    let regex_tree = DeltaTree::from_paths_with_codec(&paths, &RegexFileNameCodec);
+    let regex_build = start_regex.elapsed();
+    let start_manual = Instant::now();
+    let manual_tree = DeltaTree::from_paths(&paths);
+    let manual_build = start_manual.elapsed();
+    assert_eq!(regex_tree, manual_tree);
+    report("tree build", regex_build, manual_build);
+}
+
+fn time_parse<C: FileNameCodec>(names: &[&str], codec: &C) -> Duration {
+    let start = Instant::now();
+    let parsed = names.iter().filter_map(|n| codec.decode(n)).count();
+    assert_eq!(parsed, names.len());
+    start.elapsed()
+}
+
+fn report(what: &str, regex: Duration, manual: Duration) {
+    println!(
+        "{}: regex {:?}, hand-rolled {:?} (speedup: {:.1}x)",
+        what,
+        regex,
+        manual,
+        regex.as_secs_f64() / manual.as_secs_f64()
+    );
+}
+
+/// paths in two partition levels, `date` and `hour`, with a few files per leaf.
+fn synthetic_paths(num_files: usize) -> Vec<String> {
+    (0..num_files)
+        .map(|idx| {
+            let uuid = Uuid::from_u128(
+                (idx as u128).wrapping_mul(0x9e37_79b9_7f4a_7c15_f39c_c060_5ced_c835),
+            );
+            format!(
+                "date=2021-03-{:02}/hour={:02}/part-{:05}-{}.c000.snappy.parquet",
+                idx % 28 + 1,
+                idx / 28 % 24,
+                idx % 100_000,
+                uuid
+            )
+        })
+        .collect()
+}
@@ -22,7 +22,6 @@ fn read_some_data(delta_table: &deltalake::DeltaTable) -> () {
     let num_files = delta_table.get_files().len();
     let files = delta_table.get_files().iter().take(3);
     println!("delta has #{} parquet files", num_files);
-    println!("schema: {:?}", delta_table.schema());
     println!(
         "characters: {:5}",
         estimate_file_name_memory_consumption(delta_table)
@@ -0,0 +1,176 @@
+use crate::{Context, Format};
+use clap::Args;
+use deltalake::DeltaDataTypeVersion;
+use deltatree::tree::memory::{representation_report, RepresentationReport};
+use deltatree::tree::predicate::PartitionPredicate;
+use deltatree::tree::DeltaTree;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// how often each step is repeated. the first run is cold, the others warm.
+    #[clap(long, default_value = "5")]
+    iterations: usize,
+    /// the pruning query to time, e.g. `date=2021-03-01`. may be repeated. by default the
+    /// first value of the outermost partition column.
+    #[clap(long = "where", value_name = "PREDICATE")]
+    predicates: Vec<PartitionPredicate>,
+    #[clap(long, arg_enum, default_value = "json")]
+    format: Format,
+}
+
+/// the timings of a table, e.g. to track regressions between releases.
+#[derive(Serialize)]
+struct BenchReport {
+    table: String,
+    version: DeltaDataTypeVersion,
+    iterations: usize,
+    files: usize,
+    /// replaying the log.
+    load: Timings,
+    /// building the tree from the table's files.
+    build: Timings,
+    /// listing the files matching the predicates.
+    query: Timings,
+    predicates: Vec<String>,
+    matching_files: usize,
+    /// rendering all paths of the tree.
+    reconstruction: Timings,
+    memory: RepresentationReport,
+}
+
+/// milliseconds of the first, cold run and of the warm runs after it.
+#[derive(Serialize)]
+struct Timings {
+    cold: f64,
+    warm_min: f64,
+    warm_median: f64,
+    warm_max: f64,
+}
+
+impl Timings {
+    fn new(samples: &[Duration]) -> Timings {
+        let millis = |d: &Duration| d.as_secs_f64() * 1000.0;
+        let cold = millis(&samples[0]);
+        let mut warm: Vec<f64> = samples[1..].iter().map(millis).collect();
+        if warm.is_empty() {
+            warm.push(cold);
+        }
+        warm.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Timings {
+            cold,
+            warm_min: warm[0],
+            warm_median: warm[warm.len() / 2],
+            warm_max: warm[warm.len() - 1],
+        }
+    }
+}
+
+/// run `f` `iterations` times, returning the last result and the duration of each run.
+fn time<T, F: FnMut() -> T>(iterations: usize, mut f: F) -> (T, Vec<Duration>) {
+    let mut samples = Vec::with_capacity(iterations);
+    let mut result = None;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        result = Some(f());
+        samples.push(start.elapsed());
+    }
+    (result.unwrap(), samples)
+}
+
+pub async fn run(args: BenchArgs, ctx: &Context) -> anyhow::Result<()> {
+    anyhow::ensure!(args.iterations > 0, "at least one iteration is required");
+    let table = ctx.table(&args.table).await?;
+    let mut load = Vec::with_capacity(args.iterations);
+    let mut delta_table = None;
+    for _ in 0..args.iterations {
+        let start = Instant::now();
+        delta_table = Some(table.storage.open_table(&table.uri).await?);
+        load.push(start.elapsed());
+    }
+    let delta_table = delta_table.unwrap();
+
+    let (tree, build) = time(args.iterations, || {
+        DeltaTree::load_filtered(&delta_table, &table.filters)
+    });
+
+    let predicates = if args.predicates.is_empty() {
+        default_predicates(&tree)
+    } else {
+        args.predicates
+    };
+    let (matching_files, query) = time(args.iterations, || tree.file_iter(&predicates).count());
+    let (files, reconstruction) = time(args.iterations, || tree.files().len());
+
+    let report = BenchReport {
+        table: args.table,
+        version: delta_table.version,
+        iterations: args.iterations,
+        files,
+        load: Timings::new(&load),
+        build: Timings::new(&build),
+        query: Timings::new(&query),
+        predicates: predicates.iter().map(|p| format!("{:?}", p)).collect(),
+        matching_files,
+        reconstruction: Timings::new(&reconstruction),
+        memory: representation_report(delta_table.get_files()),
+    };
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        Format::Text => print_text(&report, ctx),
+    }
+    Ok(())
+}
+
+/// the first value of the outermost partition column, nothing for unpartitioned tables.
+fn default_predicates(tree: &DeltaTree) -> Vec<PartitionPredicate> {
+    let cursor = tree.cursor();
+    match (cursor.column(), cursor.values().first()) {
+        (Some(column), Some(value)) => vec![PartitionPredicate::Eq(
+            column.to_string(),
+            value.map(str::to_string),
+        )],
+        _ => vec![],
+    }
+}
+
+fn print_text(report: &BenchReport, ctx: &Context) {
+    let style = ctx.style;
+    println!(
+        "{} version {}: {} files, {} iterations",
+        report.table,
+        report.version,
+        style.count(report.files),
+        report.iterations
+    );
+    println!(
+        "{:<16} {:>10} {:>10} {:>10} {:>10}",
+        "ms", "cold", "min", "median", "max"
+    );
+    let steps = [
+        ("load", &report.load),
+        ("build", &report.build),
+        ("query", &report.query),
+        ("reconstruction", &report.reconstruction),
+    ];
+    for (step, t) in steps.iter() {
+        println!(
+            "{:<16} {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+            step, t.cold, t.warm_min, t.warm_median, t.warm_max
+        );
+    }
+    println!(
+        "query {} matched {} files",
+        report.predicates.join(", "),
+        style.count(report.matching_files)
+    );
+    println!(
+        "memory: paths {}, tree {}, packed tree {}",
+        style.bytes(report.memory.paths as u64),
+        style.bytes(report.memory.tree as u64),
+        style.bytes(report.memory.packed_tree as u64)
+    );
+}
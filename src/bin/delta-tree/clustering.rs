@@ -0,0 +1,65 @@
+use crate::output::Style;
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::clustering::ClusteringReport;
+
+#[derive(Args)]
+pub struct ClustersArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// how many of the most skewed partitions to show.
+    #[clap(long, default_value = "10")]
+    top: usize,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+/// a skew above this is highlighted.
+const SKEW_WARNING: f64 = 2.0;
+
+pub async fn run(args: ClustersArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+    let report = ClusteringReport::new(&tree);
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        Format::Text => print_text(&report, args.top, ctx.style),
+    }
+    Ok(())
+}
+
+fn print_text(report: &ClusteringReport, top: usize, style: Style) {
+    println!(
+        "clustered partitions: {} of {}",
+        style.count(report.clustered_partitions),
+        style.count(report.partitions)
+    );
+    println!(
+        "unclustered files:    {} of {}",
+        style.count(report.unclustered_files),
+        style.count(report.files)
+    );
+    if report.details.is_empty() {
+        return;
+    }
+    let mut partitions: Vec<_> = report.details.iter().collect();
+    partitions.sort_by(|a, b| b.skew.total_cmp(&a.skew).then_with(|| a.path.cmp(&b.path)));
+
+    println!();
+    println!("most skewed partitions:");
+    println!("{:>8} {:>8} {:>8}  partition", "files", "clusters", "skew");
+    for partition in partitions.into_iter().take(top) {
+        println!(
+            "{:>8} {:>8} {}  {}",
+            style.count(partition.files),
+            style.count(partition.clusters.len()),
+            style.warn_if(
+                partition.skew > SKEW_WARNING,
+                format!("{:>8.2}", partition.skew)
+            ),
+            partition.path
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::output::Style;
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::compaction::CompactionPlan;
+
+#[derive(Args)]
+pub struct CompactPlanArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// the size of the compacted files, e.g. `256MB`.
+    #[clap(long, default_value = "256MB", parse(try_from_str = crate::parse_size))]
+    target_size: u64,
+    /// also list the files of each proposed group.
+    #[clap(long)]
+    files: bool,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+pub async fn run(args: CompactPlanArgs, ctx: &Context) -> anyhow::Result<()> {
+    anyhow::ensure!(args.target_size > 0, "--target-size must be positive");
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+    let plan = CompactionPlan::new(&tree, args.target_size);
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&plan)?),
+        Format::Text => print_text(&plan, args.files, ctx.style),
+    }
+    Ok(())
+}
+
+fn print_text(plan: &CompactionPlan, files: bool, style: Style) {
+    if plan.partitions.is_empty() {
+        println!("no partitions need compaction.");
+        return;
+    }
+    println!("{:>8} {:>8} {:>14}  partition", "files", "output", "bytes");
+    for partition in &plan.partitions {
+        println!(
+            "{} {:>8} {:>14}  {}",
+            style.files(
+                partition.files,
+                format!("{:>8}", style.count(partition.files))
+            ),
+            style.count(partition.groups.len()),
+            style.bytes(partition.bytes),
+            partition.path
+        );
+        if files {
+            for (i, group) in partition.groups.iter().enumerate() {
+                println!("  group {} ({}):", i + 1, style.bytes(group.bytes));
+                for file in &group.files {
+                    println!("    {}", file);
+                }
+            }
+        }
+    }
+    println!(
+        "{:>8} {:>8} {:>14}  total, {} partitions",
+        style.count(plan.files),
+        style.count(plan.output_files),
+        style.bytes(plan.bytes),
+        style.count(plan.partitions.len())
+    );
+}
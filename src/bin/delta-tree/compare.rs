@@ -0,0 +1,103 @@
+use crate::output::Style;
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::compare::TableComparison;
+use deltatree::tree::stats::PartitionSize;
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// path or URI of the first table.
+    left: String,
+    /// path or URI of the second table, e.g. a backfill copy of the first.
+    right: String,
+    /// files below this size count as small, e.g. `32MiB`.
+    #[clap(long, default_value = "32MiB", parse(try_from_str = crate::parse_size))]
+    small_file_size: u64,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+pub async fn run(args: CompareArgs, ctx: &Context) -> anyhow::Result<()> {
+    let left = ctx.table(&args.left).await?;
+    let left_table = left.open(None).await?;
+    let left_tree = crate::progress::sized_tree(&left_table, &left.filters);
+    let right = ctx.table(&args.right).await?;
+    let right_table = right.open(None).await?;
+    let right_tree = crate::progress::sized_tree(&right_table, &right.filters);
+    let comparison = TableComparison::new(&left_tree, &right_tree, args.small_file_size, 0);
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&comparison)?),
+        Format::Text => print_text(&args, &comparison, ctx.style),
+    }
+    Ok(())
+}
+
+fn print_text(args: &CompareArgs, comparison: &TableComparison, style: Style) {
+    let (left, right) = (&comparison.left, &comparison.right);
+    println!("{:<12} {:>14} {:>14}", "", "left", "right");
+    let row = |name: &str, l: String, r: String| {
+        let text = format!("{:<12} {:>14} {:>14}", name, l, r);
+        println!("{}", style.warn_if(l != r, text));
+    };
+    row("files", style.count(left.files), style.count(right.files));
+    row("bytes", style.bytes(left.bytes), style.bytes(right.bytes));
+    row(
+        "partitions",
+        style.count(left.partitions),
+        style.count(right.partitions),
+    );
+    row("columns", left.columns.join(","), right.columns.join(","));
+    for (i, column) in left.columns.iter().enumerate() {
+        if let Some(r) = right.cardinalities.get(i) {
+            row(
+                &format!("  {}", column),
+                style.count(left.cardinalities[i]),
+                style.count(*r),
+            );
+        }
+    }
+    row(
+        "small files",
+        style.count(left.small_files),
+        style.count(right.small_files),
+    );
+    let (l, r) = (&comparison.left_sizes, &comparison.right_sizes);
+    for (name, l, r) in [
+        ("min size", l.min, r.min),
+        ("p10 size", l.p10, r.p10),
+        ("median size", l.p50, r.p50),
+        ("p90 size", l.p90, r.p90),
+        ("max size", l.max, r.max),
+    ] {
+        row(name, style.bytes(l), style.bytes(r));
+    }
+
+    println!();
+    if comparison.is_same_layout() {
+        println!("all partitions have the same files and bytes.");
+        return;
+    }
+    println!("{} differing partitions:", comparison.partitions.len());
+    println!("left:  {}", args.left);
+    println!("right: {}", args.right);
+    println!(
+        "{:>8} {:>14} {:>8} {:>14}  partition",
+        "files", "bytes", "files", "bytes"
+    );
+    for partition in &comparison.partitions {
+        println!(
+            "{} {}  {}",
+            partition_size(partition.left.as_ref(), style),
+            partition_size(partition.right.as_ref(), style),
+            partition.path
+        );
+    }
+}
+
+fn partition_size(partition: Option<&PartitionSize>, style: Style) -> String {
+    match partition {
+        Some(p) => format!("{:>8} {:>14}", style.count(p.files), style.bytes(p.bytes)),
+        None => format!("{:>8} {:>14}", "-", "-"),
+    }
+}
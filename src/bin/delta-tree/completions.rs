@@ -0,0 +1,50 @@
+use crate::Cli;
+use clap::{Args, CommandFactory};
+use clap_complete::Shell;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// the shell to complete in, e.g. `bash` or `zsh`.
+    #[clap(arg_enum)]
+    shell: Shell,
+}
+
+#[derive(Args)]
+pub struct ManArgs {
+    /// directory for `delta-tree.1` and a page per subcommand, e.g. for packaging.
+    #[clap(long, default_value = ".")]
+    out_dir: PathBuf,
+}
+
+/// print the completion script for `args.shell` to stdout.
+pub fn completions(args: CompletionsArgs) -> anyhow::Result<()> {
+    let mut command = Cli::command();
+    clap_complete::generate(
+        args.shell,
+        &mut command,
+        "delta-tree",
+        &mut std::io::stdout(),
+    );
+    Ok(())
+}
+
+/// write the man pages of the command and its subcommands.
+pub fn man(args: ManArgs) -> anyhow::Result<()> {
+    let command = Cli::command();
+    std::fs::create_dir_all(&args.out_dir)?;
+    for subcommand in command.get_subcommands() {
+        let name = format!("{}-{}", command.get_name(), subcommand.get_name());
+        write_man(&args.out_dir, &name, subcommand.clone().name(name.as_str()))?;
+    }
+    let name = command.get_name().to_string();
+    write_man(&args.out_dir, &name, command)
+}
+
+fn write_man(dir: &Path, name: &str, command: clap::Command) -> anyhow::Result<()> {
+    let path = dir.join(format!("{}.1", name));
+    let mut out = std::fs::File::create(&path)?;
+    clap_mangen::Man::new(command).render(&mut out)?;
+    eprintln!("wrote {}", path.display());
+    Ok(())
+}
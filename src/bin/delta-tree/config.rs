@@ -0,0 +1,61 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// named tables, read from `~/.config/delta-tree/config.toml` or the file given by `--config`:
+///
+/// ```toml
+/// [tables.prod_events]
+/// uri = "s3://lake/events"
+/// where = ["date>=2024-01-01"]
+///
+/// [tables.prod_events.storage]
+/// AWS_REGION = "eu-central-1"
+/// AWS_PROFILE = "prod"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    tables: HashMap<String, TableConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TableConfig {
+    pub uri: String,
+    /// object store options, overridden by those given on the command line.
+    #[serde(default)]
+    pub storage: HashMap<String, String>,
+    /// partition predicates like `date>=2024-01-01`, applied whenever the table is loaded.
+    #[serde(default, rename = "where")]
+    pub filters: Vec<String>,
+}
+
+impl Config {
+    /// the config at `path`, or the default one if it exists.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Config> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path().filter(|p| p.exists()) {
+                Some(path) => path,
+                None => return Ok(Config::default()),
+            },
+        };
+        let content = std::fs::read_to_string(&path)
+            .map_err(|err| anyhow::anyhow!("can't read {}: {}", path.display(), err))?;
+        toml::from_str(&content)
+            .map_err(|err| anyhow::anyhow!("invalid config {}: {}", path.display(), err))
+    }
+
+    pub fn table(&self, name: &str) -> Option<&TableConfig> {
+        self.tables.get(name)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/delta-tree/config.toml`, falling back to `~/.config`.
+fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("delta-tree").join("config.toml"))
+}
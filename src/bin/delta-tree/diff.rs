@@ -0,0 +1,92 @@
+use crate::output::Style;
+use crate::{Context, Format};
+use clap::Args;
+use deltalake::DeltaDataTypeVersion;
+use deltatree::tree::diff::TreeDiff;
+use serde::Serialize;
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// the older version, e.g. `v10`.
+    #[clap(long, parse(try_from_str = crate::parse_version))]
+    from: DeltaDataTypeVersion,
+    /// the newer version, the latest if not given.
+    #[clap(long, parse(try_from_str = crate::parse_version))]
+    to: Option<DeltaDataTypeVersion>,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+#[derive(Serialize)]
+struct DiffReport<'a> {
+    from: DeltaDataTypeVersion,
+    to: DeltaDataTypeVersion,
+    #[serde(flatten)]
+    diff: &'a TreeDiff,
+}
+
+pub async fn run(args: DiffArgs, ctx: &Context) -> anyhow::Result<()> {
+    // the table is loaded once and moved forward, replaying only the commits in between.
+    let table = ctx.table(&args.table).await?;
+    let mut delta_table = table.open(Some(args.from)).await?;
+    let old = crate::progress::sized_tree(&delta_table, &table.filters);
+    match args.to {
+        Some(version) => delta_table.load_version(version).await?,
+        None => delta_table.update_incremental().await?,
+    }
+    let new = crate::progress::sized_tree(&delta_table, &table.filters);
+    let diff = TreeDiff::new(&old, &new);
+    let report = DiffReport {
+        from: args.from,
+        to: delta_table.version,
+        diff: &diff,
+    };
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        Format::Text => print_text(&report, ctx.style),
+    }
+    Ok(())
+}
+
+fn print_text(report: &DiffReport, style: Style) {
+    println!("version {} -> {}", report.from, report.to);
+    for file in &report.diff.added {
+        println!("+ {}", file);
+    }
+    for file in &report.diff.removed {
+        println!("- {}", file);
+    }
+    if report.diff.is_empty() {
+        println!("no changes.");
+        return;
+    }
+    println!();
+    println!("{:>8} {:>8} {:>14}  partition", "added", "removed", "bytes");
+    for partition in &report.diff.partitions {
+        println!(
+            "{} {:>8} {}  {}",
+            style.files(
+                partition.added_files,
+                format!("{:>8}", style.count(partition.added_files))
+            ),
+            style.count(partition.removed_files),
+            style.delta(
+                partition.byte_delta(),
+                format!("{:>14}", style.byte_delta(partition.byte_delta()))
+            ),
+            partition.path
+        );
+    }
+    println!(
+        "{:>8} {:>8} {}  total",
+        style.count(report.diff.added.len()),
+        style.count(report.diff.removed.len()),
+        style.delta(
+            report.diff.byte_delta(),
+            format!("{:>14}", style.byte_delta(report.diff.byte_delta()))
+        )
+    );
+}
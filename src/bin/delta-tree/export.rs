@@ -0,0 +1,119 @@
+use crate::Context;
+use clap::{ArgEnum, Args};
+use deltatree::tree::DeltaTree;
+use deltatree::tree::{canonical, manifest, serialize};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// the file to write to, e.g. `listing.dtree`, `manifest.csv` or `layout.db`.
+    #[clap(short, long)]
+    output: PathBuf,
+    #[clap(long, arg_enum, default_value = "tree")]
+    format: ExportFormat,
+    /// files below this size count as small in the views of the `duckdb` format, e.g. `32MiB`.
+    #[clap(long, default_value = "32MiB", parse(try_from_str = crate::parse_size))]
+    small_file_size: u64,
+}
+
+/// what `export` writes.
+#[derive(ArgEnum, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ExportFormat {
+    /// the tree, to be read by `query`.
+    Tree,
+    /// the manifest of the files: a row per file with its partition values, name, size,
+    /// compression and modification time.
+    Csv,
+    /// the manifest as a parquet file, with partition columns typed as in the table schema.
+    Parquet,
+    /// the parquet manifest and next to it, with the extension `.sql`, a script creating DuckDB
+    /// views over it: stats per partition, small files and compaction candidates.
+    Duckdb,
+    /// the manifest as an arrow IPC file, typed like `parquet`, to memory-map without decoding.
+    #[cfg(feature = "arrow")]
+    Arrow,
+    /// the manifest in the arrow IPC stream format.
+    #[cfg(feature = "arrow")]
+    ArrowStream,
+    /// the manifest as a sqlite database with a `partitions` and a `files` table, to query the
+    /// layout with SQL.
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+pub async fn run(args: ExportArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let mut bytes = vec![];
+    let files = match args.format {
+        ExportFormat::Tree => {
+            let tree = DeltaTree::load_filtered(&delta_table, &table.filters);
+            serialize::write_tree(&tree, delta_table.version, &mut bytes);
+            tree.file_iter(&[]).count()
+        }
+        ExportFormat::Csv => {
+            let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+            manifest::write_csv(&tree, &mut bytes)?;
+            tree.file_iter(&[]).count()
+        }
+        ExportFormat::Parquet | ExportFormat::Duckdb => {
+            let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+            let types = delta_table
+                .schema()
+                .map(canonical::partition_types)
+                .unwrap_or_default();
+            bytes = manifest::to_parquet(&tree, &types)?;
+            if args.format == ExportFormat::Duckdb {
+                let script = args.output.with_extension("sql");
+                let views = manifest::duckdb_views(
+                    &args.output.to_string_lossy(),
+                    &manifest::partition_columns(&tree),
+                    args.small_file_size,
+                );
+                std::fs::write(&script, views)?;
+                eprintln!("wrote the DuckDB views to {}", script.display());
+            }
+            tree.file_iter(&[]).count()
+        }
+        #[cfg(feature = "arrow")]
+        ExportFormat::Arrow | ExportFormat::ArrowStream => {
+            let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+            let types = delta_table
+                .schema()
+                .map(canonical::partition_types)
+                .unwrap_or_default();
+            if args.format == ExportFormat::Arrow {
+                manifest::write_ipc_file(&tree, &types, &mut bytes)?;
+            } else {
+                manifest::write_ipc_stream(&tree, &types, &mut bytes)?;
+            }
+            tree.file_iter(&[]).count()
+        }
+        #[cfg(feature = "sqlite")]
+        ExportFormat::Sqlite => {
+            let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+            let types = delta_table
+                .schema()
+                .map(canonical::partition_types)
+                .unwrap_or_default();
+            let mut connection = rusqlite::Connection::open(&args.output)?;
+            manifest::write_sqlite(&tree, &types, &mut connection)?;
+            tree.file_iter(&[]).count()
+        }
+    };
+    // the sqlite database is written in place, all other formats are built in memory.
+    if !bytes.is_empty() {
+        std::fs::write(&args.output, &bytes)?;
+    }
+    eprintln!(
+        "exported version {} of {} ({} files, {} bytes) to {}",
+        delta_table.version,
+        args.table,
+        files,
+        std::fs::metadata(&args.output)?.len(),
+        args.output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use crate::output::FileRecord;
+use crate::{Context, ListFormat};
+use clap::Args;
+use deltatree::tree::glob::PartitionGlob;
+use deltatree::tree::PartitionValue;
+use std::collections::BTreeMap;
+use std::io::{BufWriter, Write};
+
+#[derive(Args)]
+pub struct FindArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// partition glob like `year=2024/month=0*`, `**` matches any number of directories.
+    glob: PartitionGlob,
+    /// print the matching partitions with their file counts and bytes instead of the files.
+    #[clap(long)]
+    partitions: bool,
+    /// how the files are printed, `--partitions` are always printed as text.
+    #[clap(long, arg_enum, default_value = "text")]
+    format: ListFormat,
+}
+
+pub async fn run(args: FindArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+    let style = ctx.style;
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for (dir, files) in args.glob.leaves(&tree) {
+        if args.partitions {
+            writeln!(
+                out,
+                "{} {:>14}  {}",
+                style.files(files.len(), format!("{:>8}", style.count(files.len()))),
+                style.bytes(files.iter().map(|f| f.size).sum()),
+                dir
+            )?;
+        } else if args.format == ListFormat::Ndjson {
+            let values = PartitionValue::from_dir(&dir);
+            let partition_values: BTreeMap<_, _> = values
+                .iter()
+                .map(|p| (p.key.as_str(), p.value.as_deref()))
+                .collect();
+            for file in files {
+                FileRecord {
+                    path: format!("{}{}", dir, file.file.name()),
+                    partition_values: &partition_values,
+                    size: Some(file.size),
+                    modification_time: Some(file.modification_time),
+                }
+                .write(&mut out)?;
+            }
+        } else {
+            for file in files {
+                writeln!(out, "{}{}", dir, file.file.name())?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use crate::Context;
+use clap::Args;
+use std::io::Write;
+
+#[derive(Args)]
+pub struct FlamegraphArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// weigh the partitions by their number of files instead of their bytes.
+    #[clap(long)]
+    files: bool,
+}
+
+pub async fn run(args: FlamegraphArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    if args.files {
+        tree.write_collapsed_stacks(&mut out, |_| 1)?;
+    } else {
+        tree.write_collapsed_stacks(&mut out, |f| f.size)?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use clap::Args;
+use deltatree::tree::generate::{Layout, PartitionColumn};
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// a partition column with its cardinality, e.g. `date:365`, outermost first. may be
+    /// repeated.
+    #[clap(long = "column", value_name = "NAME:CARDINALITY")]
+    columns: Vec<PartitionColumn>,
+    /// the number of files.
+    #[clap(long, default_value = "10000")]
+    files: usize,
+    /// how unevenly files are spread over partitions, `0` for evenly.
+    #[clap(long, default_value = "0")]
+    skew: f64,
+    /// the mean file size, e.g. `128MiB`.
+    #[clap(long, default_value = "128MiB", parse(try_from_str = crate::parse_size))]
+    file_size: u64,
+    #[clap(long, default_value = "0")]
+    seed: u64,
+    /// write a dummy delta table log to this directory instead of printing the file listing.
+    #[clap(long)]
+    log: Option<PathBuf>,
+}
+
+pub fn run(args: GenerateArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(args.skew >= 0.0, "--skew must not be negative");
+    let layout = Layout::new(args.columns, args.files)
+        .skew(args.skew)
+        .file_size(args.file_size)
+        .seed(args.seed);
+    match args.log {
+        Some(dir) => {
+            layout.write_delta_log(&dir)?;
+            eprintln!(
+                "wrote {} files in {} partitions to {}",
+                layout.files,
+                layout.partitions(),
+                dir.display()
+            );
+        }
+        None => {
+            let stdout = std::io::stdout();
+            let mut out = std::io::BufWriter::new(stdout.lock());
+            for path in layout.paths() {
+                writeln!(out, "{}", path)?;
+            }
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use crate::output::Style;
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::history::{self, CommitSummary};
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// how many of the latest commits to show.
+    #[clap(short = 'n', long, default_value = "20")]
+    limit: usize,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+pub async fn run(args: HistoryArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let commits = history::history(
+        &delta_table.table_uri,
+        delta_table.version,
+        args.limit,
+        &table.storage,
+    )
+    .await?;
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&commits)?),
+        Format::Text => print_text(&commits, ctx.style),
+    }
+    Ok(())
+}
+
+fn print_text(commits: &[CommitSummary], style: Style) {
+    println!(
+        "{:>8}  {:<19}  {:<16} {:>8} {:>8} {:>14}",
+        "version", "timestamp", "operation", "added", "removed", "bytes"
+    );
+    for commit in commits {
+        let timestamp = commit
+            .timestamp
+            .and_then(|ms| chrono::NaiveDateTime::from_timestamp_opt(ms.div_euclid(1000), 0))
+            .map_or_else(String::new, |t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+        let delta = commit.added_bytes as i64 - commit.removed_bytes as i64;
+        println!(
+            "{:>8}  {:<19}  {:<16} {:>8} {:>8} {}",
+            commit.version,
+            timestamp,
+            commit.operation.as_deref().unwrap_or(""),
+            style.count(commit.added_files),
+            style.count(commit.removed_files),
+            style.delta(delta, format!("{:>14}", style.byte_delta(delta)))
+        );
+    }
+}
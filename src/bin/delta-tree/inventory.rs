@@ -0,0 +1,107 @@
+use crate::output::Style;
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::inventory;
+use deltatree::tree::stats::TableStats;
+use deltatree::tree::verify::ConsistencyReport;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Args)]
+pub struct InventoryArgs {
+    /// the `manifest.json` of an S3 Inventory report of the table's bucket.
+    manifest: String,
+    /// URI of the delta table in the inventoried bucket, e.g. `s3://bucket/tables/events`.
+    table: String,
+    /// compare the inventory to the table's log: orphan files, missing files and files whose
+    /// size differs, instead of the layout. files written after the inventory show as missing.
+    #[clap(long)]
+    orphans: bool,
+    /// files below this size count as small, e.g. `32MiB`.
+    #[clap(long, default_value = "32MiB", parse(try_from_str = crate::parse_size))]
+    small_file_size: u64,
+    /// how many of the smallest and largest partitions to show.
+    #[clap(long, default_value = "5")]
+    top: usize,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+pub async fn run(args: InventoryArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let (bucket, prefix) = table
+        .uri
+        .split_once("://")
+        .and_then(|(_, path)| path.split_once('/'))
+        .ok_or_else(|| anyhow::anyhow!("expected a table below a bucket, got {}", table.uri))?;
+    let prefix = format!("{}/", prefix.trim_end_matches('/'));
+
+    let spinner = crate::progress::spinner(format!("reading the inventory {}", args.manifest));
+    let (manifest, objects) = inventory::load(&args.manifest, &table.storage).await?;
+    spinner.finish_with_message(format!(
+        "{} objects in the inventory of {}",
+        objects.len(),
+        manifest.source_bucket
+    ));
+    anyhow::ensure!(
+        manifest.source_bucket == bucket,
+        "the inventory lists the bucket {}, not {}",
+        manifest.source_bucket,
+        bucket
+    );
+
+    if args.orphans {
+        let delta_table = table.open(None).await?;
+        let active: HashMap<String, u64> = delta_table
+            .get_active_add_actions()
+            .iter()
+            .map(|add| (add.path.clone(), add.size.max(0) as u64))
+            .collect();
+        let removed: HashSet<String> = delta_table
+            .get_tombstones()
+            .iter()
+            .map(|remove| remove.path.clone())
+            .collect();
+        let listed = inventory::table_objects(&objects, &prefix)
+            .map(|(path, object)| (path.to_string(), Some(object.size)));
+        let report = ConsistencyReport::new(&active, &removed, listed, None);
+        match args.format {
+            Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            Format::Text => print_consistency(&report, ctx.style),
+        }
+    } else {
+        let tree = inventory::table_tree(&objects, &prefix);
+        let stats = TableStats::new(&tree, args.small_file_size, args.top);
+        match args.format {
+            Format::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+            Format::Text => crate::stats::print_text(&stats, ctx.style),
+        }
+    }
+    Ok(())
+}
+
+fn print_consistency(report: &ConsistencyReport, style: Style) {
+    println!(
+        "{} files in the log, {} data files in the inventory",
+        style.count(report.files),
+        style.count(report.listed)
+    );
+    for partition in &report.missing {
+        for file in &partition.files {
+            println!("missing: {}{}", partition.partition, file);
+        }
+    }
+    for orphan in &report.orphans {
+        println!("orphan: {}", orphan);
+    }
+    for mismatch in &report.size_mismatches {
+        println!(
+            "size mismatch: {} ({} in the log, {} in the inventory)",
+            mismatch.path,
+            style.bytes(mismatch.expected),
+            style.bytes(mismatch.actual)
+        );
+    }
+    if report.is_ok() {
+        println!("consistent.");
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::listing;
+use deltatree::tree::stats::TableStats;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct ListingArgs {
+    /// a captured listing of a table, e.g. from `find`, `ls -R` or `aws s3 ls --recursive`,
+    /// `-` for stdin. only the latter has sizes, files of the others count as empty.
+    #[clap(default_value = "-")]
+    listing: PathBuf,
+    /// print the partitions with their file counts down to this depth instead of the stats.
+    #[clap(long)]
+    depth: Option<usize>,
+    /// files below this size count as small, e.g. `32MiB`.
+    #[clap(long, default_value = "32MiB", parse(try_from_str = crate::parse_size))]
+    small_file_size: u64,
+    /// how many of the smallest and largest partitions to show.
+    #[clap(long, default_value = "5")]
+    top: usize,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+pub fn run(args: ListingArgs, ctx: &Context) -> anyhow::Result<()> {
+    let files = if args.listing == Path::new("-") {
+        let stdin = std::io::stdin();
+        listing::parse_listing(stdin.lock())?
+    } else {
+        listing::parse_listing(BufReader::new(File::open(&args.listing)?))?
+    };
+    let tree = listing::listing_tree(&files);
+    if let Some(depth) = args.depth {
+        print!("{:.*}", depth, tree);
+        return Ok(());
+    }
+    let stats = TableStats::new(&tree, args.small_file_size, args.top);
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+        Format::Text => crate::stats::print_text(&stats, ctx.style),
+    }
+    Ok(())
+}
@@ -0,0 +1,354 @@
+extern crate anyhow;
+extern crate deltalake;
+
+mod bench;
+mod clustering;
+mod compaction;
+mod compare;
+mod completions;
+mod config;
+mod diff;
+mod export;
+mod find;
+mod flamegraph;
+mod generate;
+mod history;
+mod inventory;
+mod listing;
+mod memory;
+mod output;
+mod progress;
+mod query;
+mod report;
+mod schema;
+mod stats;
+mod vacuum;
+mod verify;
+mod watch;
+
+use clap::{ArgEnum, Parser, Subcommand};
+use config::Config;
+use deltalake::{DeltaDataTypeVersion, DeltaTable};
+use deltatree::tree::history::{scan_replay, ReplayProgress};
+use deltatree::tree::predicate::PartitionPredicate;
+use deltatree::tree::storage::{RetryPolicy, RetryStats, StorageOptions};
+use output::Style;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub use deltatree::tree::sized::parse_size;
+
+/// explore the partitions and files of delta tables.
+#[derive(Parser)]
+#[clap(name = "delta-tree", version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+    /// without a subcommand, estimate the memory of the table's tree.
+    #[clap(flatten)]
+    memory: memory::MemoryArgs,
+    /// object store option, e.g. `AWS_REGION=eu-central-1`. may be repeated.
+    #[clap(long = "storage-option", value_name = "KEY=VALUE", global = true)]
+    storage_options: Vec<String>,
+    /// give up on object store requests taking longer than this, e.g. `30s`.
+    #[clap(long, global = true, parse(try_from_str = parse_duration))]
+    timeout: Option<Duration>,
+    /// retry object store requests failing with throttling or server errors up to this many
+    /// times, backing off exponentially.
+    #[clap(long, global = true, default_value = "0")]
+    retries: u32,
+    /// the config file with named tables, `~/.config/delta-tree/config.toml` by default.
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+    /// sizes in KiB, MiB, GiB, ... and counts grouped by thousands.
+    #[clap(long, global = true)]
+    human: bool,
+    /// don't highlight problematic partitions. colors are also disabled by `NO_COLOR`.
+    #[clap(long, global = true)]
+    no_color: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// estimate the memory of a table's tree compared to its plain file list.
+    Memory(memory::MemoryArgs),
+    /// files added and removed between two versions of a table.
+    Diff(diff::DiffArgs),
+    /// poll a table for new versions and summarize the changes of each.
+    Watch(watch::WatchArgs),
+    /// a health report: files, bytes, partition cardinalities and small files.
+    Stats(stats::StatsArgs),
+    /// write a table's tree to a file, to be queried without replaying the log.
+    Export(export::ExportArgs),
+    /// list the files of an exported tree.
+    Query(query::QueryArgs),
+    /// the latest commits with their operations and added / removed files.
+    History(history::HistoryArgs),
+    /// the schema, partition columns and properties of a table.
+    Schema(schema::SchemaArgs),
+    /// the layout of a table, or its orphan files, from an S3 Inventory report instead of
+    /// listing its bucket.
+    Inventory(inventory::InventoryArgs),
+    /// the layout of a table from a captured listing of its files, e.g. the output of `find`
+    /// or `aws s3 ls --recursive`.
+    Listing(listing::ListingArgs),
+    /// partition layouts, cardinalities and file sizes of two tables side by side.
+    Compare(compare::CompareArgs),
+    /// a static HTML page with the layout, partition sizes, small files and history of a
+    /// table, to share with people who don't use the CLI.
+    Report(report::ReportArgs),
+    /// check that all files of a table exist in storage.
+    Verify(verify::VerifyArgs),
+    /// the files a vacuum would delete and the bytes it would reclaim, without deleting.
+    VacuumPlan(vacuum::VacuumPlanArgs),
+    /// which partitions need compaction and how their small files could be grouped.
+    CompactPlan(compaction::CompactPlanArgs),
+    /// how the files of each partition are spread over the clusters in their names.
+    Clusters(clustering::ClustersArgs),
+    /// the files or partitions matching a glob over partition directories.
+    Find(find::FindArgs),
+    /// the bytes of each partition as collapsed stacks, e.g. for `inferno-flamegraph` or
+    /// speedscope.
+    Flamegraph(flamegraph::FlamegraphArgs),
+    /// time loading, building, querying and listing a table's tree, e.g. to track
+    /// regressions.
+    Bench(bench::BenchArgs),
+    /// a synthetic file listing or dummy delta log, e.g. for benchmarks.
+    Generate(generate::GenerateArgs),
+    /// print a shell completion script.
+    Completions(completions::CompletionsArgs),
+    /// write man pages.
+    Man(completions::ManArgs),
+}
+
+/// how reports are printed.
+#[derive(ArgEnum, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+/// how file listings are printed.
+#[derive(ArgEnum, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ListFormat {
+    /// a path per line.
+    Text,
+    /// a JSON object per line and file, written as the files are listed.
+    Ndjson,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let ctx = Context {
+        storage: cli.storage_options,
+        timeout: cli.timeout,
+        retries: cli.retries,
+        retry_stats: RetryStats::new(),
+        style: Style::new(cli.human, cli.no_color),
+        config: Config::load(cli.config.as_deref())?,
+    };
+    let result = match cli.command {
+        Some(Command::Memory(args)) => memory::run(args, &ctx).await,
+        Some(Command::Diff(args)) => diff::run(args, &ctx).await,
+        Some(Command::Watch(args)) => watch::run(args, &ctx).await,
+        Some(Command::Stats(args)) => stats::run(args, &ctx).await,
+        Some(Command::Export(args)) => export::run(args, &ctx).await,
+        Some(Command::Query(args)) => query::run(args),
+        Some(Command::History(args)) => history::run(args, &ctx).await,
+        Some(Command::Schema(args)) => schema::run(args, &ctx).await,
+        Some(Command::Inventory(args)) => inventory::run(args, &ctx).await,
+        Some(Command::Listing(args)) => listing::run(args, &ctx),
+        Some(Command::Compare(args)) => compare::run(args, &ctx).await,
+        Some(Command::Report(args)) => report::run(args, &ctx).await,
+        Some(Command::Verify(args)) => verify::run(args, &ctx).await,
+        Some(Command::VacuumPlan(args)) => vacuum::run(args, &ctx).await,
+        Some(Command::CompactPlan(args)) => compaction::run(args, &ctx).await,
+        Some(Command::Clusters(args)) => clustering::run(args, &ctx).await,
+        Some(Command::Find(args)) => find::run(args, &ctx).await,
+        Some(Command::Flamegraph(args)) => flamegraph::run(args, &ctx).await,
+        Some(Command::Bench(args)) => bench::run(args, &ctx).await,
+        Some(Command::Generate(args)) => generate::run(args),
+        Some(Command::Completions(args)) => completions::completions(args),
+        Some(Command::Man(args)) => completions::man(args),
+        None => memory::run(cli.memory, &ctx).await,
+    };
+    if ctx.retry_stats.retries() > 0 {
+        eprintln!(
+            "retried {} object store requests, {} failed nonetheless",
+            ctx.retry_stats.retries(),
+            ctx.retry_stats.exhausted()
+        );
+    }
+    result
+}
+
+/// the global options shared by all subcommands.
+pub struct Context {
+    /// `KEY=VALUE` object store options from the command line.
+    storage: Vec<String>,
+    timeout: Option<Duration>,
+    retries: u32,
+    retry_stats: RetryStats,
+    pub style: Style,
+    config: Config,
+}
+
+/// a table to be opened, with the settings from the config file if it names one.
+pub struct Table {
+    pub uri: String,
+    pub storage: StorageOptions,
+    /// partition predicates applied when building the table's tree.
+    pub filters: Vec<PartitionPredicate>,
+}
+
+impl Context {
+    /// resolve `table`: the name of a table in the config file, a path / URI, or with a
+    /// catalog feature enabled a `catalog.schema.table` name.
+    pub async fn table(&self, table: &str) -> anyhow::Result<Table> {
+        let mut table = self.resolve(table).await?;
+        if let Some(timeout) = self.timeout {
+            table.storage = table.storage.timeout(timeout);
+        }
+        if self.retries > 0 {
+            table.storage = table
+                .storage
+                .retries(RetryPolicy::new(self.retries))
+                .retry_stats(&self.retry_stats);
+        }
+        Ok(table)
+    }
+
+    async fn resolve(&self, table: &str) -> anyhow::Result<Table> {
+        match self.config.table(table) {
+            Some(config) => {
+                let storage = config
+                    .storage
+                    .iter()
+                    .fold(StorageOptions::new(), |storage, (key, value)| {
+                        storage.option(key, value)
+                    });
+                let filters = config
+                    .filters
+                    .iter()
+                    .map(|filter| filter.parse().map_err(anyhow::Error::msg))
+                    .collect::<anyhow::Result<_>>()?;
+                Ok(Table {
+                    uri: config.uri.clone(),
+                    storage: with_storage_options(storage, &self.storage)?,
+                    filters,
+                })
+            }
+            None => Ok(Table {
+                uri: resolve_table(table).await?,
+                storage: with_storage_options(StorageOptions::new(), &self.storage)?,
+                filters: vec![],
+            }),
+        }
+    }
+}
+
+impl Table {
+    /// open the table in the given or its latest version. the commits on top of the last
+    /// checkpoint are counted first, delta-rs replays them without reporting progress.
+    pub async fn open(&self, version: Option<DeltaDataTypeVersion>) -> anyhow::Result<DeltaTable> {
+        let spinner = progress::spinner(format!("reading the log of {}", self.uri));
+        let replay = scan_replay(&self.uri, version, &self.storage, |replay| {
+            spinner.set_message(format!(
+                "reading the log of {}: {} commits with {} actions {}",
+                self.uri,
+                replay.commits,
+                replay.actions,
+                replayed_from(replay)
+            ))
+        })
+        .await?;
+        spinner.set_message(format!(
+            "replaying the log of {}: {} commits with {} actions {}",
+            self.uri,
+            replay.commits,
+            replay.actions,
+            replayed_from(&replay)
+        ));
+        let delta_table = match version {
+            Some(version) => {
+                self.storage
+                    .open_table_with_version(&self.uri, version)
+                    .await?
+            }
+            None => self.storage.open_table(&self.uri).await?,
+        };
+        spinner.finish_with_message(format!(
+            "loaded version {} of {}: {} files, {} tombstones, {} commits replayed {}",
+            delta_table.version,
+            self.uri,
+            delta_table.get_files().len(),
+            delta_table.get_tombstones().len(),
+            replay.commits,
+            replayed_from(&replay)
+        ));
+        Ok(delta_table)
+    }
+}
+
+/// where a replay starts, for progress messages.
+fn replayed_from(replay: &ReplayProgress) -> String {
+    match replay.checkpoint {
+        Some(version) => format!("after the checkpoint of version {}", version),
+        None => "from version 0".to_string(),
+    }
+}
+
+/// a table version as `15` or `v15`.
+pub fn parse_version(version: &str) -> Result<DeltaDataTypeVersion, String> {
+    version
+        .strip_prefix('v')
+        .unwrap_or(version)
+        .parse()
+        .map_err(|_| format!("invalid table version '{}'", version))
+}
+
+/// a duration as a number with a unit, `ms`, `s`, `m` or `h`.
+pub fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let (value, unit) = duration.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", duration))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value * 60)),
+        "h" => Ok(Duration::from_secs(value * 60 * 60)),
+        _ => Err(format!(
+            "invalid duration '{}', expected e.g. 30s",
+            duration
+        )),
+    }
+}
+
+/// add object store settings given as `KEY=VALUE`.
+pub fn with_storage_options(
+    storage: StorageOptions,
+    options: &[String],
+) -> anyhow::Result<StorageOptions> {
+    storage.parse_options(options).map_err(anyhow::Error::msg)
+}
+
+/// with a catalog feature enabled, `catalog.schema.table` names that aren't local paths are
+/// resolved to the table's location first.
+#[cfg(any(feature = "glue", feature = "unity"))]
+async fn resolve_table(table: &str) -> anyhow::Result<String> {
+    use deltatree::tree::catalog::Catalog;
+    if table.contains(&['/', '\\', ':'][..]) || std::path::Path::new(table).exists() {
+        return Ok(table.to_string());
+    }
+    Ok(Catalog::from_env()?.location(&table.parse()?).await?)
+}
+
+#[cfg(not(any(feature = "glue", feature = "unity")))]
+async fn resolve_table(table: &str) -> anyhow::Result<String> {
+    Ok(table.to_string())
+}
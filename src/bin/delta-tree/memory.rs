@@ -0,0 +1,75 @@
+use crate::Context;
+use clap::Args;
+use deltatree::tree::memory::RepresentationReport;
+use deltatree::tree::DeltaTree;
+use std::time::Instant;
+
+#[derive(Args)]
+pub struct MemoryArgs {
+    /// path or URI of the delta table.
+    table: Option<String>,
+    /// object store options as `KEY=VALUE`, e.g. `AWS_REGION=eu-central-1`.
+    storage_options: Vec<String>,
+}
+
+pub async fn run(args: MemoryArgs, ctx: &Context) -> anyhow::Result<()> {
+    let style = ctx.style;
+    if let Some(table_path) = &args.table {
+        println!("reading delta table: {:?}", table_path);
+        let start_load = Instant::now();
+        let mut table = ctx.table(table_path).await?;
+        table.storage = crate::with_storage_options(table.storage, &args.storage_options)?;
+        let delta_table = table.open(None).await?;
+        let file_memory = estimate_file_memory(&delta_table);
+        println!(
+            "delta file memory: {} (time: {:?})",
+            style.bytes(file_memory as u64),
+            start_load.elapsed()
+        );
+        let start_tree = Instant::now();
+        let mut delta_tree = DeltaTree::new(&delta_table);
+        delta_tree.shrink_to_fit();
+        let tree_time = start_tree.elapsed();
+        let report = RepresentationReport::from_tree(&delta_tree, file_memory);
+        println!(
+            "delta tree memory: {} (time: {:?}, leaves: {})",
+            style.bytes(report.tree as u64),
+            tree_time,
+            if cfg!(feature = "smallvec") {
+                "SmallVec<[_; 4]>"
+            } else {
+                "Vec<_>"
+            }
+        );
+        println!("relative tree size: {} %", 100 * report.tree / file_memory);
+        println!(
+            "packed tree memory: {} (relative: {} %)",
+            style.bytes(report.packed_tree as u64),
+            100 * report.packed_tree / file_memory
+        );
+        println!(
+            "partition value memory: {} (front-coded: {})",
+            style.bytes(report.partition_values as u64),
+            style.bytes(report.front_coded_partition_values as u64)
+        );
+        if let Some(compact) = report.compact_tree {
+            println!(
+                "compact tree memory: {} (relative: {} %)",
+                style.bytes(compact as u64),
+                100 * compact / file_memory
+            );
+        }
+        Ok(())
+    } else {
+        println!("no file argument given.");
+        Ok(())
+    }
+}
+
+fn estimate_file_memory(delta_table: &deltalake::DeltaTable) -> usize {
+    delta_table
+        .get_files()
+        .iter()
+        .map(|f| f.capacity())
+        .fold(0, |a, b| a + b)
+}
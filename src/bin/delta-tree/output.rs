@@ -0,0 +1,129 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// partitions with more files than this are highlighted in red, above `MANY_FILES` in yellow.
+const TOO_MANY_FILES: usize = 10_000;
+const MANY_FILES: usize = 1_000;
+
+const RED: &str = "31";
+const YELLOW: &str = "33";
+const GREEN: &str = "32";
+
+/// how sizes and counts of the text reports are rendered.
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    human: bool,
+    color: bool,
+}
+
+impl Style {
+    /// colors are only used on a terminal, and neither with `--no-color` nor with `NO_COLOR`
+    /// set (https://no-color.org).
+    pub fn new(human: bool, no_color: bool) -> Style {
+        let color = !no_color
+            && std::env::var_os("NO_COLOR").unwrap_or_default().is_empty()
+            && console::Term::stdout().is_term();
+        Style { human, color }
+    }
+
+    /// a size in bytes, with `--human` in KiB, MiB, ...
+    pub fn bytes(&self, bytes: u64) -> String {
+        if !self.human {
+            return bytes.to_string();
+        }
+        const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} B", bytes)
+        } else {
+            format!("{:.1} {}", value, UNITS[unit])
+        }
+    }
+
+    /// a change in bytes, always signed.
+    pub fn byte_delta(&self, delta: i64) -> String {
+        let sign = if delta < 0 { "-" } else { "+" };
+        format!("{}{}", sign, self.bytes(delta.unsigned_abs()))
+    }
+
+    /// a count, with `--human` in groups of thousands (`12,345`).
+    pub fn count(&self, count: usize) -> String {
+        let digits = count.to_string();
+        if !self.human {
+            return digits;
+        }
+        let head = (digits.len() - 1) % 3 + 1;
+        let mut grouped = digits[..head].to_string();
+        for group in digits.as_bytes()[head..].chunks(3) {
+            grouped.push(',');
+            grouped.push_str(std::str::from_utf8(group).unwrap());
+        }
+        grouped
+    }
+
+    /// `text`, e.g. a padded file count, highlighted if a partition with `files` files has
+    /// too many of them.
+    pub fn files(&self, files: usize, text: String) -> String {
+        if files > TOO_MANY_FILES {
+            self.paint(RED, text)
+        } else if files > MANY_FILES {
+            self.paint(YELLOW, text)
+        } else {
+            text
+        }
+    }
+
+    /// `text` in red if `problem`, e.g. a small file ratio above a threshold.
+    pub fn warn_if(&self, problem: bool, text: String) -> String {
+        if problem {
+            self.paint(RED, text)
+        } else {
+            text
+        }
+    }
+
+    /// `text` in green for growth and red for shrinkage.
+    pub fn delta(&self, delta: i64, text: String) -> String {
+        match delta {
+            d if d > 0 => self.paint(GREEN, text),
+            d if d < 0 => self.paint(RED, text),
+            _ => text,
+        }
+    }
+
+    fn paint(&self, color: &str, text: String) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", color, text)
+        } else {
+            text
+        }
+    }
+}
+
+/// a file of a listing, printed as a line of newline-delimited JSON by `--format ndjson`.
+#[derive(Serialize)]
+pub struct FileRecord<'a> {
+    pub path: String,
+    /// the values of the partition directories of the path, `null` for the null partition.
+    pub partition_values: &'a BTreeMap<&'a str, Option<&'a str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// milliseconds since the epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modification_time: Option<i64>,
+}
+
+impl<'a> FileRecord<'a> {
+    /// write the record as a single line to `out`.
+    pub fn write<W: Write>(&self, out: &mut W) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut *out, self)?;
+        out.write_all(b"\n")?;
+        Ok(())
+    }
+}
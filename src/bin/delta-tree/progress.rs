@@ -0,0 +1,39 @@
+use deltalake::DeltaTable;
+use deltatree::tree::predicate::{self, PartitionPredicate};
+use deltatree::tree::sized::SizedDeltaFile;
+use deltatree::tree::DeltaTree;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Duration;
+
+/// a spinner on stderr for work of unknown length, like replaying a table's log. hidden if
+/// stderr is not a terminal.
+pub fn spinner(message: String) -> ProgressBar {
+    let spinner = ProgressBar::new_spinner().with_message(message);
+    spinner.set_style(
+        ProgressStyle::with_template("{spinner} {msg} ({elapsed})")
+            .expect("valid progress template"),
+    );
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+/// build the sized tree of the files of `delta_table` matching `filters`, counting the files
+/// parsed so far.
+pub fn sized_tree(
+    delta_table: &DeltaTable,
+    filters: &[PartitionPredicate],
+) -> DeltaTree<SizedDeltaFile> {
+    let adds = delta_table.get_active_add_actions();
+    let bar = ProgressBar::new(adds.len() as u64).with_message("parsing files");
+    bar.set_style(
+        ProgressStyle::with_template("{msg} {wide_bar} {pos}/{len} ({elapsed})")
+            .expect("valid progress template"),
+    );
+    let adds = adds
+        .iter()
+        .inspect(|_| bar.inc(1))
+        .filter(|add| predicate::path_matches(&add.path, filters));
+    let tree = DeltaTree::from_add_actions(adds).with_txns(delta_table);
+    bar.finish_and_clear();
+    tree
+}
@@ -0,0 +1,60 @@
+use crate::output::FileRecord;
+use crate::ListFormat;
+use clap::Args;
+use deltatree::tree::predicate::PartitionPredicate;
+use deltatree::tree::{serialize, DeltaTree, FxBuildHasher};
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct QueryArgs {
+    /// a tree written by `export`.
+    listing: PathBuf,
+    /// only files in partitions matching the predicate, e.g. `date>=2021-03-01`. may be
+    /// repeated, all predicates must match.
+    #[clap(long = "where", value_name = "PREDICATE")]
+    predicates: Vec<PartitionPredicate>,
+    /// print the number of matching files only.
+    #[clap(long)]
+    count: bool,
+    /// separate the paths by NUL instead of newlines, e.g. for `xargs -0`. only for the text
+    /// format.
+    #[clap(long, short = '0')]
+    null: bool,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: ListFormat,
+}
+
+pub fn run(args: QueryArgs) -> anyhow::Result<()> {
+    let bytes = std::fs::read(&args.listing)?;
+    let (tree, _version): (DeltaTree<_, FxBuildHasher>, _) = serialize::read_tree(&bytes)
+        .ok_or_else(|| anyhow::anyhow!("{} is not an exported tree", args.listing.display()))?;
+    if args.count {
+        println!("{}", tree.file_iter(&args.predicates).count());
+    } else {
+        let stdout = std::io::stdout();
+        let mut out = std::io::BufWriter::new(stdout.lock());
+        match args.format {
+            ListFormat::Text => {
+                let delimiter = if args.null { "\0" } else { "\n" };
+                tree.write_files(&mut out, delimiter, &args.predicates)?;
+            }
+            ListFormat::Ndjson => tree.try_for_each_path(&args.predicates, |path| {
+                let partition_values = path
+                    .partitions()
+                    .iter()
+                    .map(|(name, value)| (*name, value.as_deref()))
+                    .collect();
+                FileRecord {
+                    path: path.to_string(),
+                    partition_values: &partition_values,
+                    size: None,
+                    modification_time: None,
+                }
+                .write(&mut out)
+            })?,
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
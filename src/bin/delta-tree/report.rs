@@ -0,0 +1,47 @@
+use crate::Context;
+use clap::Args;
+use deltatree::tree::stats::TableStats;
+use deltatree::tree::{clustering, history, html};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// the file to write the page to, e.g. `report.html`.
+    #[clap(short, long)]
+    output: PathBuf,
+    /// files below this size count as small, e.g. `32MiB`.
+    #[clap(long, default_value = "32MiB", parse(try_from_str = crate::parse_size))]
+    small_file_size: u64,
+    /// how many of the smallest and largest partitions to show.
+    #[clap(long, default_value = "10")]
+    top: usize,
+    /// how many of the latest commits to show.
+    #[clap(long, default_value = "20")]
+    history: usize,
+}
+
+pub async fn run(args: ReportArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+    let mut stats = TableStats::new(&tree, args.small_file_size, args.top);
+    stats.clustering_columns =
+        clustering::clustering_columns(&table.uri, delta_table.version, &table.storage).await?;
+    let commits = history::history(
+        &delta_table.table_uri,
+        delta_table.version,
+        args.history,
+        &table.storage,
+    )
+    .await?;
+
+    let title = format!("{} version {}", args.table, delta_table.version);
+    std::fs::write(
+        &args.output,
+        html::html_report(&title, &tree, &stats, &commits),
+    )?;
+    eprintln!("wrote the report of {} to {}", title, args.output.display());
+    Ok(())
+}
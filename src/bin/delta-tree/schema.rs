@@ -0,0 +1,68 @@
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::clustering;
+use deltatree::tree::schema::TableSchema;
+
+#[derive(Args)]
+pub struct SchemaArgs {
+    /// path or URI of the delta table.
+    table: String,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+pub async fn run(args: SchemaArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let mut schema = TableSchema::new(&delta_table)?;
+    schema.clustering_columns =
+        clustering::clustering_columns(&table.uri, delta_table.version, &table.storage).await?;
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&schema)?),
+        Format::Text => print_text(&schema),
+    }
+    Ok(())
+}
+
+fn print_text(schema: &TableSchema) {
+    if let Some(name) = &schema.name {
+        println!("name: {}", name);
+    }
+    if let Some(description) = &schema.description {
+        println!("description: {}", description);
+    }
+    let width = schema
+        .columns
+        .iter()
+        .map(|c| c.name.len())
+        .max()
+        .unwrap_or(0);
+    println!("columns:");
+    for column in &schema.columns {
+        println!(
+            "  {:<width$}  {}{}{}",
+            column.name,
+            column.data_type,
+            if column.nullable { "" } else { " not null" },
+            if schema.partition_columns.contains(&column.name) {
+                " (partition)"
+            } else {
+                ""
+            },
+            width = width
+        );
+    }
+    println!("partition columns: {}", schema.partition_columns.join(", "));
+    match &schema.clustering_columns {
+        Some(columns) if columns.is_empty() => {}
+        Some(columns) => println!("clustering columns: {}", columns.join(", ")),
+        None => println!("clustering columns: unknown"),
+    }
+    if !schema.properties.is_empty() {
+        println!("properties:");
+        for (key, value) in &schema.properties {
+            println!("  {} = {}", key, value.as_deref().unwrap_or("null"));
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::output::Style;
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::clustering;
+use deltatree::tree::stats::{PartitionSize, TableStats};
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// files below this size count as small, e.g. `32MiB`.
+    #[clap(long, default_value = "32MiB", parse(try_from_str = crate::parse_size))]
+    small_file_size: u64,
+    /// how many of the smallest and largest partitions to show.
+    #[clap(long, default_value = "5")]
+    top: usize,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+pub async fn run(args: StatsArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let tree = crate::progress::sized_tree(&delta_table, &table.filters);
+    let mut stats = TableStats::new(&tree, args.small_file_size, args.top);
+    stats.clustering_columns =
+        clustering::clustering_columns(&table.uri, delta_table.version, &table.storage).await?;
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+        Format::Text => print_text(&stats, ctx.style),
+    }
+    Ok(())
+}
+
+/// a small file ratio above this is highlighted.
+const SMALL_FILE_WARNING: f64 = 0.5;
+
+pub fn print_text(stats: &TableStats, style: Style) {
+    println!("files:      {}", style.count(stats.files));
+    println!("bytes:      {}", style.bytes(stats.bytes));
+    println!("partitions: {}", style.count(stats.partitions));
+    let columns: Vec<String> = stats
+        .columns
+        .iter()
+        .zip(&stats.cardinalities)
+        .map(|(column, cardinality)| format!("{} ({})", column, style.count(*cardinality)))
+        .collect();
+    println!("columns:    {}", columns.join(", "));
+    match &stats.clustering_columns {
+        Some(columns) if columns.is_empty() => {}
+        Some(columns) => println!("clustered:  {}", columns.join(", ")),
+        None => println!("clustered:  unknown"),
+    }
+    println!(
+        "small files: {} of {} below {} ({})",
+        style.count(stats.small_files),
+        style.count(stats.files),
+        style.bytes(stats.small_file_threshold),
+        style.warn_if(
+            stats.small_file_ratio > SMALL_FILE_WARNING,
+            format!("{:.1} %", 100.0 * stats.small_file_ratio)
+        )
+    );
+    print_partitions("smallest partitions:", &stats.smallest, style);
+    print_partitions("largest partitions:", &stats.largest, style);
+}
+
+fn print_partitions(title: &str, partitions: &[PartitionSize], style: Style) {
+    println!();
+    println!("{}", title);
+    for partition in partitions {
+        println!(
+            "{:>14} {}  {}",
+            style.bytes(partition.bytes),
+            style.files(
+                partition.files,
+                format!("{:>8}", style.count(partition.files))
+            ),
+            partition.path
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use crate::output::Style;
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::predicate;
+use deltatree::tree::vacuum::VacuumPlan;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Args)]
+pub struct VacuumPlanArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// files removed longer ago than this are deleted, e.g. `168h`.
+    #[clap(long, default_value = "168h", parse(try_from_str = crate::parse_duration))]
+    retention: Duration,
+    /// also list every file to be deleted.
+    #[clap(long)]
+    files: bool,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+/// only plans: nothing is deleted.
+pub async fn run(args: VacuumPlanArgs, ctx: &Context) -> anyhow::Result<()> {
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let cutoff = now.saturating_sub(args.retention).as_millis() as i64;
+    let tombstones = delta_table
+        .get_tombstones()
+        .iter()
+        .filter(|remove| predicate::path_matches(&remove.path, &table.filters));
+    let adds = delta_table
+        .get_active_add_actions()
+        .iter()
+        .filter(|add| predicate::path_matches(&add.path, &table.filters));
+    let plan = VacuumPlan::new(tombstones, adds, cutoff);
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&plan)?),
+        Format::Text => print_text(&plan, args.files, ctx.style),
+    }
+    Ok(())
+}
+
+fn print_text(plan: &VacuumPlan, files: bool, style: Style) {
+    if plan.files.is_empty() {
+        println!("no files to delete.");
+        return;
+    }
+    if files {
+        for file in &plan.files {
+            println!("{}", file);
+        }
+        println!();
+    }
+    println!("{:>8} {:>14}  partition", "files", "bytes");
+    for partition in &plan.partitions {
+        let emptied = plan.emptied.binary_search(&partition.path).is_ok();
+        println!(
+            "{:>8} {:>14}  {}{}",
+            style.count(partition.files),
+            style.bytes(partition.bytes),
+            partition.path,
+            if emptied { " (emptied)" } else { "" }
+        );
+    }
+    println!(
+        "{:>8} {:>14}  total, {} partitions emptied",
+        style.count(plan.files.len()),
+        style.bytes(plan.bytes),
+        style.count(plan.emptied.len())
+    );
+}
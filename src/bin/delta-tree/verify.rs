@@ -0,0 +1,63 @@
+use crate::output::Style;
+use crate::{Context, Format};
+use clap::Args;
+use deltatree::tree::verify::{self, VerifyReport};
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// how many files to probe at the same time.
+    #[clap(long, default_value = "32")]
+    concurrency: usize,
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+/// fails if any file is missing, so it can be used as a check in scripts.
+pub async fn run(args: VerifyArgs, ctx: &Context) -> anyhow::Result<()> {
+    anyhow::ensure!(args.concurrency > 0, "--concurrency must be positive");
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let files = crate::progress::sized_tree(&delta_table, &table.filters).files();
+    let spinner = crate::progress::spinner(format!("probing {} files", files.len()));
+    let report = verify::verify(
+        &table.uri,
+        files.into_iter(),
+        args.concurrency,
+        &table.storage,
+    )
+    .await?;
+    spinner.finish_and_clear();
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        Format::Text => print_text(&report, ctx.style),
+    }
+    anyhow::ensure!(
+        report.is_ok(),
+        "{} of {} files missing",
+        report.missing_files(),
+        report.files
+    );
+    Ok(())
+}
+
+fn print_text(report: &VerifyReport, style: Style) {
+    for partition in &report.missing {
+        println!(
+            "{}  {}",
+            style.warn_if(
+                true,
+                format!("{:>8} missing", style.count(partition.files.len()))
+            ),
+            partition.partition
+        );
+        for file in &partition.files {
+            println!("           {}{}", partition.partition, file);
+        }
+    }
+    if report.is_ok() {
+        println!("all {} files exist.", style.count(report.files));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{Context, Format};
+use clap::Args;
+use deltalake::{DeltaDataTypeVersion, DeltaTable};
+use deltatree::tree::history::commit_actions;
+use deltatree::tree::predicate::{self, PartitionPredicate};
+use deltatree::tree::snapshot::newer_commits;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// path or URI of the delta table.
+    table: String,
+    /// how often to check for new versions, e.g. `500ms`, `30s`, `5m`.
+    #[clap(long, default_value = "30s", parse(try_from_str = crate::parse_duration))]
+    interval: Duration,
+    /// `json` prints one object per line.
+    #[clap(long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+#[derive(Serialize)]
+struct VersionSummary {
+    version: DeltaDataTypeVersion,
+    files: usize,
+    added_files: usize,
+    removed_files: usize,
+    added_bytes: u64,
+    removed_bytes: u64,
+    /// the application transactions committed with the version.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    txns: BTreeMap<String, DeltaDataTypeVersion>,
+}
+
+impl VersionSummary {
+    /// summarize the commit file of `version`, counting only the files matching `filters`.
+    /// `files` is the number of such files in the previous version.
+    fn of_commit(
+        version: DeltaDataTypeVersion,
+        commit: &[u8],
+        filters: &[PartitionPredicate],
+        files: usize,
+    ) -> anyhow::Result<VersionSummary> {
+        let mut summary = VersionSummary {
+            version,
+            files,
+            added_files: 0,
+            removed_files: 0,
+            added_bytes: 0,
+            removed_bytes: 0,
+            txns: BTreeMap::new(),
+        };
+        for action in commit_actions(commit) {
+            let action = action?;
+            if let Some(add) = action.get("add").filter(|add| matches(add, filters)) {
+                summary.added_files += 1;
+                summary.added_bytes += add["size"].as_u64().unwrap_or(0);
+            } else if let Some(remove) = action
+                .get("remove")
+                .filter(|remove| matches(remove, filters))
+            {
+                summary.removed_files += 1;
+                summary.removed_bytes += remove["size"].as_u64().unwrap_or(0);
+            } else if let Some(app_id) = action["txn"]["appId"].as_str() {
+                if let Some(version) = action["txn"]["version"].as_i64() {
+                    summary.txns.insert(app_id.to_string(), version);
+                }
+            }
+        }
+        summary.files = (summary.files + summary.added_files).saturating_sub(summary.removed_files);
+        Ok(summary)
+    }
+
+    fn byte_delta(&self) -> i64 {
+        self.added_bytes as i64 - self.removed_bytes as i64
+    }
+}
+
+/// whether the path of the add or remove action `file` matches `filters`.
+fn matches(file: &Value, filters: &[PartitionPredicate]) -> bool {
+    match file["path"].as_str() {
+        Some(path) => predicate::path_matches(path, filters),
+        None => false,
+    }
+}
+
+/// the files of `delta_table` matching `filters`.
+fn matching_files(delta_table: &DeltaTable, filters: &[PartitionPredicate]) -> usize {
+    delta_table
+        .get_files()
+        .iter()
+        .filter(|path| predicate::path_matches(path, filters))
+        .count()
+}
+
+pub async fn run(args: WatchArgs, ctx: &Context) -> anyhow::Result<()> {
+    let style = ctx.style;
+    let table = ctx.table(&args.table).await?;
+    let delta_table = table.open(None).await?;
+    let mut version = delta_table.version;
+    let mut files = matching_files(&delta_table, &table.filters);
+    eprintln!(
+        "watching {} from version {} ({} files)",
+        args.table, version, files
+    );
+    let backend = table.storage.backend(&table.uri)?;
+    loop {
+        tokio::time::sleep(args.interval).await;
+        // read only the new commit files, each gets its own summary.
+        let commits = table
+            .storage
+            .retry(|| newer_commits(backend.as_ref(), &table.uri, version))
+            .await?;
+        let commits = match commits {
+            Some(commits) => commits,
+            None => {
+                let delta_table = table.open(None).await?;
+                eprintln!(
+                    "the commits following version {} were cleaned up, continuing from version {}",
+                    version, delta_table.version
+                );
+                version = delta_table.version;
+                files = matching_files(&delta_table, &table.filters);
+                continue;
+            }
+        };
+        for commit in commits {
+            version += 1;
+            let summary = VersionSummary::of_commit(version, &commit, &table.filters, files)?;
+            files = summary.files;
+            match args.format {
+                Format::Json => println!("{}", serde_json::to_string(&summary)?),
+                Format::Text => {
+                    let txns: String = summary
+                        .txns
+                        .iter()
+                        .map(|(app_id, version)| format!(", txn {} {}", app_id, version))
+                        .collect();
+                    let delta = summary.byte_delta();
+                    println!(
+                        "version {}: +{} -{} files, net {}, {} files total{}",
+                        summary.version,
+                        style.count(summary.added_files),
+                        style.count(summary.removed_files),
+                        style.delta(delta, style.byte_delta(delta)),
+                        style.count(summary.files),
+                        txns
+                    )
+                }
+            }
+        }
+    }
+}
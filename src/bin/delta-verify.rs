@@ -0,0 +1,89 @@
+extern crate anyhow;
+extern crate deltalake;
+
+use clap::{ArgEnum, Parser};
+use deltatree::tree::consistency;
+use deltatree::tree::storage::StorageOptions;
+use deltatree::tree::verify::ConsistencyReport;
+
+/// compare the files of a delta table's log to a listing of its storage: files missing in
+/// storage, orphan files not referenced by the log, files whose size differs, and change data
+/// files and deletion vectors the log doesn't refer to. exits with status 1 if any are found,
+/// for scheduled integrity checks.
+#[derive(Parser)]
+#[clap(name = "delta-verify", version)]
+struct Cli {
+    /// path or URI of the delta table.
+    table: String,
+    /// object store option, e.g. `AWS_REGION=eu-central-1`. may be repeated.
+    #[clap(long = "storage-option", value_name = "KEY=VALUE")]
+    storage_options: Vec<String>,
+    /// how many commits to read at the same time.
+    #[clap(long, default_value = "32")]
+    concurrency: usize,
+    #[clap(long, arg_enum, default_value = "json")]
+    format: Format,
+}
+
+#[derive(ArgEnum, Debug, PartialEq, Eq, Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let storage = StorageOptions::new()
+        .parse_options(&cli.storage_options)
+        .map_err(anyhow::Error::msg)?;
+
+    anyhow::ensure!(cli.concurrency > 0, "--concurrency must be positive");
+    let delta_table = storage.open_table(&cli.table).await?;
+    let report = consistency::consistency_check(&delta_table, cli.concurrency, &storage).await?;
+
+    match cli.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        Format::Text => print_text(&report),
+    }
+    if !report.is_ok() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn print_text(report: &ConsistencyReport) {
+    println!(
+        "{} files in the log, {} data files in storage",
+        report.files, report.listed
+    );
+    for partition in &report.missing {
+        for file in &partition.files {
+            println!("missing: {}{}", partition.partition, file);
+        }
+    }
+    for orphan in &report.orphans {
+        println!("orphan: {}", orphan);
+    }
+    for mismatch in &report.size_mismatches {
+        println!(
+            "size mismatch: {} ({} in the log, {} in storage)",
+            mismatch.path, mismatch.expected, mismatch.actual
+        );
+    }
+    for path in &report.unreferenced_change_data {
+        println!("unreferenced change data: {}", path);
+    }
+    for path in &report.unreferenced_deletion_vectors {
+        println!("unreferenced deletion vector: {}", path);
+    }
+    if !report.unknown_references.is_empty() {
+        println!(
+            "{} change data files and deletion vectors of commits already cleaned up, not checked",
+            report.unknown_references.len()
+        );
+    }
+    if report.is_ok() {
+        println!("consistent.");
+    }
+}
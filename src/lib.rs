@@ -1 +1,4 @@
+// the tests pass `&vec![..]` to `from_paths`, which accepts the vector itself as well.
+#![cfg_attr(test, allow(clippy::needless_borrows_for_generic_args))]
+
 pub mod tree;
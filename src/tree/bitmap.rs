@@ -0,0 +1,175 @@
+use super::{DeltaTree, FxBuildHasher, ParquetDeltaFile, TreeNode};
+use roaring::RoaringBitmap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// a dense mapping of files to `u32` ids. ids are stable for the lifetime of the mapping, so
+/// bitmaps built from different versions of a table with the same `FileIds` can be combined.
+#[derive(Debug, Default)]
+pub struct FileIds {
+    ids: HashMap<ParquetDeltaFile, u32, FxBuildHasher>,
+    files: Vec<ParquetDeltaFile>,
+}
+
+impl FileIds {
+    pub fn new() -> FileIds {
+        FileIds::default()
+    }
+
+    /// the id of `file`, assigning the next free one to files not seen before.
+    pub fn id(&mut self, file: &ParquetDeltaFile) -> u32 {
+        let files = &mut self.files;
+        *self.ids.entry(*file).or_insert_with(|| {
+            files.push(*file);
+            (files.len() - 1) as u32
+        })
+    }
+
+    pub fn file(&self, id: u32) -> Option<&ParquetDeltaFile> {
+        self.files.get(id as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// per partition column and value, the ids of the files in that partition. filters on
+/// multiple columns become intersections, the changes between two versions differences.
+#[derive(Debug, Default)]
+pub struct PartitionBitmaps {
+    all: RoaringBitmap,
+    columns:
+        HashMap<String, HashMap<Option<Arc<str>>, RoaringBitmap, FxBuildHasher>, FxBuildHasher>,
+}
+
+impl PartitionBitmaps {
+    pub fn new<F: AsRef<ParquetDeltaFile>, S>(
+        tree: &DeltaTree<F, S>,
+        ids: &mut FileIds,
+    ) -> PartitionBitmaps {
+        let mut bitmaps = PartitionBitmaps::default();
+        bitmaps.all = bitmaps.add_subtree(&tree.root, ids);
+        bitmaps
+    }
+
+    /// all files of the table.
+    pub fn files(&self) -> &RoaringBitmap {
+        &self.all
+    }
+
+    /// the files with `value` in partition `column`, `None` if there's no such column.
+    pub fn matching(&self, column: &str, value: Option<&str>) -> Option<RoaringBitmap> {
+        self.columns.get(column).map(|values| {
+            values
+                .get(&value.map(Arc::from))
+                .cloned()
+                .unwrap_or_default()
+        })
+    }
+
+    /// the files matching all `(column, value)` predicates, `None` if any of the columns is
+    /// not a partition column.
+    pub fn matching_all(&self, predicates: &[(&str, Option<&str>)]) -> Option<RoaringBitmap> {
+        predicates
+            .iter()
+            .try_fold(self.all.clone(), |mut agg, (column, value)| {
+                agg &= &self.matching(column, *value)?;
+                Some(agg)
+            })
+    }
+
+    fn add_subtree<F: AsRef<ParquetDeltaFile>, S>(
+        &mut self,
+        node: &TreeNode<F, S>,
+        ids: &mut FileIds,
+    ) -> RoaringBitmap {
+        match node {
+            TreeNode::FileEntries { files } => files.iter().map(|f| ids.id(f.as_ref())).collect(),
+            TreeNode::Partition { name, values } => {
+                let mut all = RoaringBitmap::new();
+                for (value, child) in values.iter() {
+                    let files = self.add_subtree(child, ids);
+                    all |= &files;
+                    let column = self.columns.entry(name.clone()).or_default();
+                    *column.entry(value.clone()).or_default() |= &files;
+                }
+                all
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+    use pretty_assertions::assert_eq;
+
+    fn path(date: &str, country: &str, idx: u128) -> String {
+        fixtures::path(&format!("date={}/country={}/", date, country), idx)
+    }
+
+    fn paths() -> Vec<String> {
+        vec![
+            path("2021-03-01", "de", 0),
+            path("2021-03-01", "fr", 1),
+            path("2021-03-02", "de", 2),
+            path("2021-03-02", "de", 3),
+            path("2021-03-02", "fr", 4),
+        ]
+    }
+
+    fn file_names(bitmap: &RoaringBitmap, ids: &FileIds) -> Vec<String> {
+        let mut names: Vec<_> = bitmap
+            .iter()
+            .map(|id| ids.file(id).unwrap().uuid().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn intersect_partition_columns() {
+        let mut ids = FileIds::new();
+        let bitmaps = PartitionBitmaps::new(&DeltaTree::from_paths(&paths()), &mut ids);
+        assert_eq!(ids.len(), 5);
+        assert_eq!(bitmaps.files().len(), 5);
+        assert_eq!(bitmaps.matching("country", Some("de")).unwrap().len(), 3);
+        assert_eq!(bitmaps.matching("country", Some("it")).unwrap().len(), 0);
+        assert_eq!(bitmaps.matching("year", Some("2021")), None);
+        let matching = bitmaps
+            .matching_all(&[("date", Some("2021-03-02")), ("country", Some("de"))])
+            .unwrap();
+        assert_eq!(
+            file_names(&matching, &ids),
+            vec![
+                uuid::Uuid::from_u128(2).to_string(),
+                uuid::Uuid::from_u128(3).to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_versions() {
+        let mut ids = FileIds::new();
+        let old = PartitionBitmaps::new(&DeltaTree::from_paths(&paths()), &mut ids);
+        let mut new_paths = paths();
+        new_paths.remove(1);
+        new_paths.push(path("2021-03-03", "fr", 5));
+        let new = PartitionBitmaps::new(&DeltaTree::from_paths(&new_paths), &mut ids);
+        assert_eq!(ids.len(), 6);
+        assert_eq!(
+            file_names(&(new.files() - old.files()), &ids),
+            vec![uuid::Uuid::from_u128(5).to_string()]
+        );
+        assert_eq!(
+            file_names(&(old.files() - new.files()), &ids),
+            vec![uuid::Uuid::from_u128(1).to_string()]
+        );
+    }
+}
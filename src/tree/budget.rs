@@ -0,0 +1,250 @@
+use super::codec::SparkFileNameCodec;
+use super::serialize::{read_node, write_node};
+use super::{
+    file_list_heap_size, files_in_subtree, partition_value, value_heap_size, DeltaTree,
+    FxBuildHasher, ParquetDeltaFile, TreeNode,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// the zstd level used for cold subtrees. serialized trees compress well, higher levels mostly
+/// cost time.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// a `DeltaTree` keeping the subtrees below the first partition column as zstd compressed
+/// blobs, inflated on access. inflated subtrees stay cached as long as compressed and
+/// inflated subtrees together fit in the memory budget, the least recently used ones are
+/// dropped first. an unpartitioned table consists of a single subtree under the `None` key.
+#[derive(Debug)]
+pub struct BudgetedDeltaTree {
+    pub prefix: String,
+    column: Option<String>,
+    subtrees: HashMap<Option<Arc<str>>, Subtree, FxBuildHasher>,
+    /// the directories in storage of files with changed partition values, see
+    /// `DeltaTree::raw_dirs`.
+    raw_dirs: HashMap<ParquetDeltaFile, String>,
+    /// whether files are listed by partition value, see `DeltaTree::ordered`.
+    ordered: bool,
+    budget: usize,
+    compressed_bytes: usize,
+    inflated_bytes: usize,
+    clock: u64,
+    inflations: u64,
+}
+
+#[derive(Debug)]
+struct Subtree {
+    blob: Vec<u8>,
+    inflated: Option<TreeNode>,
+    /// estimated heap size of the inflated subtree.
+    size: usize,
+    last_access: u64,
+}
+
+impl BudgetedDeltaTree {
+    /// compress all subtrees of `tree`. `budget` is the number of bytes the compressed and the
+    /// cached inflated subtrees may occupy together.
+    pub fn new<S>(tree: DeltaTree<ParquetDeltaFile, S>, budget: usize) -> BudgetedDeltaTree {
+        let (column, nodes) = match tree.root {
+            TreeNode::Partition { name, values } => (Some(name), values.into_iter().collect()),
+            TreeNode::FileEntries { files } => {
+                (None, vec![(None, TreeNode::FileEntries { files })])
+            }
+        };
+        let mut subtrees = HashMap::default();
+        let mut compressed_bytes = 0;
+        for (value, node) in nodes {
+            let mut bytes = vec![];
+            write_node(&node, &mut bytes);
+            let blob = zstd::encode_all(&bytes[..], COMPRESSION_LEVEL)
+                .expect("compressing in memory can't fail");
+            compressed_bytes += blob.capacity();
+            let subtree = Subtree {
+                blob,
+                inflated: None,
+                size: node_size(&node),
+                last_access: 0,
+            };
+            subtrees.insert(value, subtree);
+        }
+        BudgetedDeltaTree {
+            prefix: tree.prefix,
+            column,
+            subtrees,
+            raw_dirs: tree.raw_dirs,
+            ordered: tree.ordered,
+            budget,
+            compressed_bytes,
+            inflated_bytes: 0,
+            clock: 0,
+            inflations: 0,
+        }
+    }
+
+    /// the partition column the subtrees are split by, `None` for unpartitioned tables.
+    pub fn column(&self) -> Option<&str> {
+        self.column.as_deref()
+    }
+
+    /// the subtree for `value` of the first partition column, inflated if necessary.
+    pub fn subtree(&mut self, value: Option<&str>) -> Option<&TreeNode> {
+        let key = value.map(Arc::from);
+        self.clock += 1;
+        let clock = self.clock;
+        let subtree = self.subtrees.get_mut(&key)?;
+        subtree.last_access = clock;
+        if subtree.inflated.is_none() {
+            subtree.inflated = Some(inflate(&subtree.blob));
+            self.inflated_bytes += subtree.size;
+            self.inflations += 1;
+            self.evict(&key);
+        }
+        self.subtrees[&key].inflated.as_ref()
+    }
+
+    /// all files, relative to `prefix`. cold subtrees are inflated only temporarily.
+    pub fn files(&self) -> Vec<String> {
+        let mut result = vec![];
+        let mut subtrees: Vec<_> = self.subtrees.iter().collect();
+        if self.ordered {
+            subtrees.sort_by_key(|(value, _)| *value);
+        }
+        for (value, subtree) in subtrees {
+            let prefix = match &self.column {
+                Some(column) => format!("{}={}/", column, partition_value(value)),
+                None => String::new(),
+            };
+            let (raw_dirs, ordered) = (&self.raw_dirs, self.ordered);
+            let files = match &subtree.inflated {
+                Some(node) => {
+                    files_in_subtree(&prefix, node, raw_dirs, ordered, &SparkFileNameCodec)
+                }
+                None => {
+                    let node = inflate(&subtree.blob);
+                    files_in_subtree(&prefix, &node, raw_dirs, ordered, &SparkFileNameCodec)
+                }
+            };
+            result.extend(files);
+        }
+        result
+    }
+
+    /// bytes currently occupied by compressed and inflated subtrees.
+    pub fn memory_usage(&self) -> usize {
+        self.compressed_bytes + self.inflated_bytes
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// the number of times a subtree had to be inflated.
+    pub fn inflations(&self) -> u64 {
+        self.inflations
+    }
+
+    /// drop the least recently used inflated subtrees other than `keep` until the budget is met.
+    fn evict(&mut self, keep: &Option<Arc<str>>) {
+        while self.memory_usage() > self.budget {
+            let coldest = self
+                .subtrees
+                .iter()
+                .filter(|(key, subtree)| *key != keep && subtree.inflated.is_some())
+                .min_by_key(|(_, subtree)| subtree.last_access)
+                .map(|(key, _)| key.clone());
+            match coldest {
+                Some(key) => {
+                    let subtree = self.subtrees.get_mut(&key).unwrap();
+                    subtree.inflated = None;
+                    self.inflated_bytes -= subtree.size;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+fn inflate(blob: &[u8]) -> TreeNode {
+    let bytes = zstd::decode_all(blob).expect("corrupt compressed subtree");
+    read_node(&bytes).expect("corrupt serialized subtree")
+}
+
+/// estimated heap size of a subtree, along the lines of the `delta-tree` memory estimator.
+fn node_size<S>(node: &TreeNode<ParquetDeltaFile, S>) -> usize {
+    match node {
+        TreeNode::FileEntries { files } => file_list_heap_size(files),
+        TreeNode::Partition { name, values } => values.iter().fold(
+            name.capacity()
+                + std::mem::size_of::<(Option<Arc<str>>, TreeNode)>() * values.capacity(),
+            |agg, (key, value)| agg + value_heap_size(key) + node_size(value),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    fn paths() -> Vec<String> {
+        (0..40)
+            .map(|idx| {
+                let dir = format!("date=2021-03-{:02}/hour={:02}/", idx % 4 + 1, idx % 6);
+                path(&dir, idx)
+            })
+            .collect()
+    }
+
+    fn sorted(mut files: Vec<String>) -> Vec<String> {
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn budgeted_tree_files() {
+        let paths = paths();
+        let tree = DeltaTree::from_paths(&paths);
+        let budgeted = BudgetedDeltaTree::new(tree, 0);
+        assert_eq!(budgeted.column(), Some("date"));
+        assert_eq!(sorted(budgeted.files()), sorted(paths));
+        assert_eq!(budgeted.inflations(), 0);
+    }
+
+    #[test]
+    fn subtrees_are_inflated_on_demand() {
+        let tree = DeltaTree::from_paths(&paths());
+        let expected = match &tree.root {
+            TreeNode::Partition { values, .. } => {
+                let mut bytes = vec![];
+                write_node(&values[&Some(Arc::<str>::from("2021-03-02"))], &mut bytes);
+                read_node::<FxBuildHasher>(&bytes).unwrap()
+            }
+            _ => panic!("expected a partition"),
+        };
+        let mut budgeted = BudgetedDeltaTree::new(tree, usize::MAX);
+        let compressed = budgeted.memory_usage();
+        assert_eq!(budgeted.subtree(Some("2021-03-02")), Some(&expected));
+        assert_eq!(budgeted.subtree(Some("2021-03-02")), Some(&expected));
+        assert_eq!(budgeted.inflations(), 1);
+        assert!(budgeted.memory_usage() > compressed);
+        assert_eq!(budgeted.subtree(Some("2021-04-01")), None);
+    }
+
+    #[test]
+    fn least_recently_used_subtrees_are_evicted() {
+        let tree = DeltaTree::from_paths(&paths());
+        let mut budgeted = BudgetedDeltaTree::new(tree, 0);
+        budgeted.subtree(Some("2021-03-01"));
+        budgeted.subtree(Some("2021-03-02"));
+        budgeted.subtree(Some("2021-03-01"));
+        assert_eq!(budgeted.inflations(), 3);
+
+        let tree = DeltaTree::from_paths(&paths());
+        let mut budgeted = BudgetedDeltaTree::new(tree, usize::MAX);
+        budgeted.subtree(Some("2021-03-01"));
+        budgeted.subtree(Some("2021-03-02"));
+        budgeted.subtree(Some("2021-03-01"));
+        assert_eq!(budgeted.inflations(), 2);
+    }
+}
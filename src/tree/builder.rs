@@ -0,0 +1,202 @@
+use super::canonical::PartitionType;
+use super::codec::{FileNameCodec, SparkFileNameCodec};
+use super::options::DeltaTreeOptions;
+use super::{
+    parse_path, prefix_of, DeltaTree, FileList, ParquetDeltaFile, PartitionPath, TreeNode,
+};
+use std::collections::HashMap;
+
+/// builds a tree from paths pushed one at a time, e.g. while replaying the log or reading a
+/// listing from the network. each path is parsed and inserted right away, so unlike
+/// `from_paths` the paths never have to be held in memory all at once.
+pub struct DeltaTreeBuilder<C = SparkFileNameCodec> {
+    codec: C,
+    options: DeltaTreeOptions,
+    root: Option<TreeNode>,
+    /// the prefix of the first path, which all other paths have to share.
+    prefix: Option<String>,
+    raw_dirs: HashMap<ParquetDeltaFile, String>,
+    files: usize,
+}
+
+impl DeltaTreeBuilder {
+    pub fn new() -> DeltaTreeBuilder {
+        DeltaTreeBuilder::with_codec(SparkFileNameCodec)
+    }
+}
+
+impl Default for DeltaTreeBuilder {
+    fn default() -> DeltaTreeBuilder {
+        DeltaTreeBuilder::new()
+    }
+}
+
+impl<C: FileNameCodec> DeltaTreeBuilder<C> {
+    /// a builder for files that don't follow spark's naming scheme, see
+    /// `DeltaTree::from_paths_with_codec`.
+    pub fn with_codec(codec: C) -> DeltaTreeBuilder<C> {
+        DeltaTreeBuilder {
+            codec,
+            options: DeltaTreeOptions::default(),
+            root: None,
+            prefix: None,
+            raw_dirs: HashMap::new(),
+            files: 0,
+        }
+    }
+
+    /// canonicalize the values of the partition columns given in `types`, see
+    /// `DeltaTree::from_paths_canonical`.
+    pub fn canonical(self, types: HashMap<String, PartitionType>) -> DeltaTreeBuilder<C> {
+        let options = self.options.clone().partition_types(types);
+        DeltaTreeBuilder { options, ..self }
+    }
+
+    /// build with `options`, see `DeltaTree::from_paths_with_options`.
+    pub fn options(self, options: DeltaTreeOptions) -> DeltaTreeBuilder<C> {
+        let options = options.for_build().into_owned();
+        DeltaTreeBuilder { options, ..self }
+    }
+
+    /// parse `path` and add its file to the tree. panics like `from_paths` if the path
+    /// doesn't share the prefix or the partition columns of the paths pushed before, unless
+    /// the options are lenient, which skip paths that can't be parsed or have another prefix.
+    pub fn push(&mut self, path: &str) {
+        let parsed = match parse_path(path, &self.options, &self.codec) {
+            Ok(parsed) => parsed,
+            Err(_) if self.options.is_lenient() => return,
+            Err(err) => panic!("{}", err),
+        };
+        let (scheme, dirs, partitions, file, raw_dir) = parsed;
+        let prefix = prefix_of(scheme, &dirs);
+        match &self.prefix {
+            Some(p) if p != &prefix && self.options.is_lenient() => return,
+            Some(p) => assert_eq!(p, &prefix, "unexpected prefix in '{}'", path),
+            None => self.prefix = Some(prefix),
+        }
+        let root = self.root.get_or_insert_with(|| empty_node(&partitions));
+        insert(root, partitions, file, path, &self.options);
+        if let Some(raw_dir) = raw_dir {
+            self.raw_dirs.insert(file, raw_dir);
+        }
+        self.files += 1;
+    }
+
+    /// the number of files pushed so far.
+    pub fn len(&self) -> usize {
+        self.files
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files == 0
+    }
+
+    /// the tree of all paths pushed, the same as building it from them at once.
+    pub fn finish(self) -> DeltaTree {
+        let mut root = self.root.unwrap_or_else(|| empty_node(&[]));
+        sort_files(&mut root);
+        DeltaTree {
+            root,
+            prefix: self.prefix.unwrap_or_default(),
+            txns: HashMap::new(),
+            canonical: self.options.is_canonical(),
+            raw_dirs: self.raw_dirs,
+            ordered: self.options.is_ordered(),
+        }
+    }
+}
+
+impl<'a, C: FileNameCodec> Extend<&'a str> for DeltaTreeBuilder<C> {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, paths: I) {
+        for path in paths {
+            self.push(path);
+        }
+    }
+}
+
+/// the node for the first file below the `partitions`, without the file.
+pub(super) fn empty_node(partitions: &[PartitionPath]) -> TreeNode {
+    match partitions.first() {
+        Some(partition) => TreeNode::Partition {
+            name: partition.key.to_string(),
+            values: Default::default(),
+        },
+        None => TreeNode::FileEntries {
+            files: FileList::new(),
+        },
+    }
+}
+
+fn insert(
+    node: &mut TreeNode,
+    partitions: Vec<PartitionPath>,
+    file: ParquetDeltaFile,
+    path: &str,
+    options: &DeltaTreeOptions,
+) {
+    let mut node = node;
+    for (level, partition) in partitions.iter().enumerate() {
+        node = match node {
+            TreeNode::Partition { name, values } => {
+                assert_eq!(name, partition.key, "unexpected partition in '{}'", path);
+                let value = partition.value.as_deref().map(|v| options.value(v));
+                values
+                    .entry(value)
+                    .or_insert_with(|| empty_node(&partitions[level + 1..]))
+            }
+            TreeNode::FileEntries { .. } => panic!("unexpected partition depth of '{}'", path),
+        };
+    }
+    match node {
+        TreeNode::FileEntries { files } => files.push(file),
+        TreeNode::Partition { .. } => panic!("unexpected partition depth of '{}'", path),
+    }
+}
+
+/// order the files of each leaf like `build` does.
+fn sort_files(node: &mut TreeNode) {
+    match node {
+        TreeNode::FileEntries { files } => files.sort(),
+        TreeNode::Partition { values, .. } => values.values_mut().for_each(sort_files),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn build_incrementally() {
+        let paths = vec![
+            path("s3://bucket/table/a=2/b=7/", 3),
+            path("s3://bucket/table/a=1/b=7/", 2),
+            path("s3://bucket/table/a=1/b=7/", 1),
+            path("s3://bucket/table/a=1/b=__HIVE_DEFAULT_PARTITION__/", 4),
+        ];
+        let mut builder = DeltaTreeBuilder::new();
+        for path in &paths {
+            builder.push(path);
+        }
+        assert_eq!(builder.len(), 4);
+        assert_eq!(builder.finish(), DeltaTree::from_paths(&paths));
+
+        let flat = vec![path("", 2), path("", 1)];
+        let mut builder = DeltaTreeBuilder::default();
+        builder.extend(flat.iter().map(String::as_str));
+        assert_eq!(builder.finish(), DeltaTree::from_paths(&flat));
+        assert_eq!(
+            DeltaTreeBuilder::new().finish(),
+            DeltaTree::from_paths(&Vec::<String>::new())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected partition")]
+    fn reject_other_partitions() {
+        let mut builder = DeltaTreeBuilder::new();
+        builder.push(&path("a=1/", 1));
+        builder.push(&path("b=1/", 2));
+    }
+}
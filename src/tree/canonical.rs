@@ -0,0 +1,403 @@
+use super::options::unescape;
+use super::{partition_value, DeltaTree, ParquetDeltaFile, TreeNode, NULL_PARTITION_VALUE};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+/// decimals with a larger exponent are kept as they are rather than padded with zeros.
+const MAX_EXPONENT: u32 = 1000;
+
+/// the data type of a partition column, used to bring differently formatted partition values
+/// (`day=2024-1-5` vs `day=2024-01-05`, `x=1.0` vs `x=1`) into a single canonical form.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PartitionType {
+    String,
+    Integer,
+    Decimal,
+    Boolean,
+    Date,
+    Timestamp,
+}
+
+impl PartitionType {
+    /// map the name of a delta primitive type (`long`, `date`, `decimal(10,2)`, ...).
+    pub fn from_delta_type(name: &str) -> PartitionType {
+        match name {
+            "byte" | "short" | "integer" | "long" => PartitionType::Integer,
+            "float" | "double" => PartitionType::Decimal,
+            "boolean" => PartitionType::Boolean,
+            "date" => PartitionType::Date,
+            "timestamp" => PartitionType::Timestamp,
+            _ if name.starts_with("decimal") => PartitionType::Decimal,
+            _ => PartitionType::String,
+        }
+    }
+
+    /// the canonical representation of `value`. values that can't be interpreted as this type
+    /// are kept as they are.
+    pub fn canonicalize<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let canonical = match self {
+            PartitionType::String => None,
+            PartitionType::Integer => value.parse::<i64>().ok().map(|i| i.to_string()),
+            PartitionType::Decimal => canonical_decimal(value),
+            PartitionType::Boolean => match value.to_ascii_lowercase().as_str() {
+                "true" => Some("true".to_string()),
+                "false" => Some("false".to_string()),
+                _ => None,
+            },
+            PartitionType::Date => canonical_date(value),
+            PartitionType::Timestamp => canonical_timestamp(value),
+        };
+        match canonical {
+            Some(c) if c != value => Cow::Owned(c),
+            _ => Cow::Borrowed(value),
+        }
+    }
+}
+
+/// the partition types of all primitive columns in a delta schema.
+pub fn partition_types(schema: &deltalake::Schema) -> HashMap<String, PartitionType> {
+    schema
+        .get_fields()
+        .iter()
+        .filter_map(|field| match field.get_type() {
+            deltalake::SchemaDataType::primitive(name) => Some((
+                field.get_name().to_string(),
+                PartitionType::from_delta_type(name),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// strip leading zeros of the integral and trailing zeros of the fractional part. values in
+/// scientific notation have their point shifted by the exponent, digit by digit, so they
+/// keep their precision.
+fn canonical_decimal(value: &str) -> Option<String> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (mantissa, exponent) = match digits.find(&['e', 'E'][..]) {
+        Some(idx) => (&digits[..idx], digits[idx + 1..].parse::<i32>().ok()?),
+        None => (digits, 0),
+    };
+    let (integral, fraction) = match mantissa.find('.') {
+        Some(idx) => (&mantissa[..idx], &mantissa[idx + 1..]),
+        None => (mantissa, ""),
+    };
+    let is_numeric = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if integral.is_empty() && fraction.is_empty() || !is_numeric(integral) || !is_numeric(fraction)
+    {
+        return None;
+    }
+    if exponent.unsigned_abs() > MAX_EXPONENT {
+        return None;
+    }
+    let digits = format!("{}{}", integral, fraction);
+    let point = integral.len() as i64 + exponent as i64;
+    let (integral, fraction) = if point <= 0 {
+        (String::new(), "0".repeat(-point as usize) + &digits)
+    } else if point as usize >= digits.len() {
+        (
+            digits.clone() + &"0".repeat(point as usize - digits.len()),
+            String::new(),
+        )
+    } else {
+        let (integral, fraction) = digits.split_at(point as usize);
+        (integral.to_string(), fraction.to_string())
+    };
+    let integral = integral.trim_start_matches('0');
+    let fraction = fraction.trim_end_matches('0');
+    let mut canonical = String::with_capacity(value.len());
+    if negative && !(integral.is_empty() && fraction.is_empty()) {
+        canonical.push('-');
+    }
+    canonical.push_str(if integral.is_empty() { "0" } else { integral });
+    if !fraction.is_empty() {
+        canonical.push('.');
+        canonical.push_str(fraction);
+    }
+    Some(canonical)
+}
+
+/// zero-pad `yyyy-m-d` dates.
+fn canonical_date(value: &str) -> Option<String> {
+    let parts = numeric_parts(value, '-')?;
+    match parts.as_slice() {
+        [year, month, day] => Some(format!("{:04}-{:02}-{:02}", year, month, day)),
+        _ => None,
+    }
+}
+
+/// zero-pad date and time of `yyyy-m-d h:m:s[.fff]` (or `T` separated) timestamps and drop
+/// trailing zeros of the fractional seconds.
+fn canonical_timestamp(value: &str) -> Option<String> {
+    let idx = value.find(&[' ', 'T'][..])?;
+    let date = canonical_date(&value[..idx])?;
+    let time = &value[idx + 1..];
+    let (time, fraction) = match time.find('.') {
+        Some(idx) => (&time[..idx], time[idx + 1..].trim_end_matches('0')),
+        None => (time, ""),
+    };
+    if !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let parts = numeric_parts(time, ':')?;
+    let time = match parts.as_slice() {
+        [hour, minute] => format!("{:02}:{:02}:00", hour, minute),
+        [hour, minute, second] => format!("{:02}:{:02}:{:02}", hour, minute, second),
+        _ => return None,
+    };
+    if fraction.is_empty() {
+        Some(format!("{} {}", date, time))
+    } else {
+        Some(format!("{} {}.{}", date, time, fraction))
+    }
+}
+
+fn numeric_parts(value: &str, separator: char) -> Option<Vec<u32>> {
+    value
+        .split(separator)
+        .map(|part| {
+            if !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()) {
+                part.parse().ok()
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+impl<F: AsRef<ParquetDeltaFile>, S: BuildHasher> DeltaTree<F, S> {
+    /// bring the tree into its canonical form: the files of each leaf sorted, percent-escaped
+    /// partition values decoded and `__HIVE_DEFAULT_PARTITION__` values turned into null,
+    /// merging the children whose values become the same. trees of the same files compare
+    /// equal regardless of their order, this also makes them equal regardless of how values
+    /// were written. the tree becomes `canonical`, files whose values changed keep their
+    /// directories in `raw_dirs`.
+    pub fn canonicalize(&mut self) {
+        canonicalize_node(&mut self.root, "", "", &mut self.raw_dirs);
+        self.canonical = true;
+    }
+}
+
+/// canonicalize `node`, which is in the directory `raw` in storage and `dir` once its values
+/// are canonical, recording the raw directories of files where the two differ.
+fn canonicalize_node<F: AsRef<ParquetDeltaFile>, S: BuildHasher>(
+    node: &mut TreeNode<F, S>,
+    raw: &str,
+    dir: &str,
+    raw_dirs: &mut HashMap<ParquetDeltaFile, String>,
+) {
+    match node {
+        TreeNode::FileEntries { files } => {
+            files.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+            if raw != dir {
+                for file in files.iter() {
+                    // files of trees built canonical already know their directory.
+                    raw_dirs
+                        .entry(*file.as_ref())
+                        .or_insert_with(|| raw.to_string());
+                }
+            }
+        }
+        TreeNode::Partition { name, values } => {
+            let children: Vec<_> = values.drain().collect();
+            for (value, mut child) in children {
+                let child_raw = format!("{}{}={}/", raw, name, partition_value(&value));
+                let value = value.and_then(|v| match unescape(&v) {
+                    Cow::Borrowed(NULL_PARTITION_VALUE) => None,
+                    Cow::Borrowed(_) => Some(v),
+                    Cow::Owned(decoded) if decoded == NULL_PARTITION_VALUE => None,
+                    Cow::Owned(decoded) => Some(Arc::from(decoded)),
+                });
+                let child_dir = format!("{}{}={}/", dir, name, partition_value(&value));
+                // the directories are only known before merging.
+                canonicalize_node(&mut child, &child_raw, &child_dir, raw_dirs);
+                match values.get_mut(&value) {
+                    Some(existing) => merge(existing, child),
+                    None => {
+                        values.insert(value, child);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// move the files of `other` into `node`, keeping the leaves sorted. panics if they have other
+/// partition columns.
+fn merge<F: AsRef<ParquetDeltaFile>, S: BuildHasher>(
+    node: &mut TreeNode<F, S>,
+    other: TreeNode<F, S>,
+) {
+    match (node, other) {
+        (TreeNode::FileEntries { files }, TreeNode::FileEntries { files: other }) => {
+            files.extend(other);
+            files.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        }
+        (
+            TreeNode::Partition { name, values },
+            TreeNode::Partition {
+                name: other_name,
+                values: other_values,
+            },
+        ) if *name == other_name => {
+            for (value, child) in other_values {
+                match values.get_mut(&value) {
+                    Some(existing) => merge(existing, child),
+                    None => {
+                        values.insert(value, child);
+                    }
+                }
+            }
+        }
+        _ => panic!("unable to merge partitions of different columns"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PartitionType::*;
+    use super::*;
+    use crate::tree::fixtures::path;
+    use crate::tree::PartitionValue;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn canonical_integers() {
+        assert_eq!(Integer.canonicalize("007"), "7");
+        assert_eq!(Integer.canonicalize("+12"), "12");
+        assert_eq!(Integer.canonicalize("-3"), "-3");
+        assert_eq!(Integer.canonicalize("abc"), "abc");
+    }
+
+    #[test]
+    fn canonical_decimals() {
+        assert_eq!(Decimal.canonicalize("1.0"), "1");
+        assert_eq!(Decimal.canonicalize("1"), "1");
+        assert_eq!(Decimal.canonicalize("01.50"), "1.5");
+        assert_eq!(Decimal.canonicalize(".5"), "0.5");
+        assert_eq!(Decimal.canonicalize("-0.0"), "0");
+        assert_eq!(Decimal.canonicalize("1e3"), "1000");
+        assert_eq!(Decimal.canonicalize("1.0E10"), "10000000000");
+        assert_eq!(Decimal.canonicalize("-1.5e-3"), "-0.0015");
+        assert_eq!(
+            Decimal.canonicalize("1.2345678901234567891E3"),
+            "1234.5678901234567891"
+        );
+        assert_eq!(Decimal.canonicalize("1e100000"), "1e100000");
+        assert_eq!(
+            Decimal.canonicalize("12345678901234567890.10"),
+            "12345678901234567890.1"
+        );
+        assert_eq!(Decimal.canonicalize("NaN"), "NaN");
+    }
+
+    #[test]
+    fn canonical_dates() {
+        assert_eq!(Date.canonicalize("2024-1-5"), "2024-01-05");
+        assert_eq!(Date.canonicalize("2024-01-05"), "2024-01-05");
+        assert_eq!(Date.canonicalize("2024-01"), "2024-01");
+        assert_eq!(
+            Timestamp.canonicalize("2024-1-5 1:2:3"),
+            "2024-01-05 01:02:03"
+        );
+        assert_eq!(
+            Timestamp.canonicalize("2024-01-05T01:02"),
+            "2024-01-05 01:02:00"
+        );
+        assert_eq!(
+            Timestamp.canonicalize("2024-01-05 01:02:03.500000"),
+            "2024-01-05 01:02:03.5"
+        );
+        assert_eq!(
+            Timestamp.canonicalize("2024-01-05 01:02:03.000"),
+            "2024-01-05 01:02:03"
+        );
+    }
+
+    #[test]
+    fn canonical_value_is_borrowed_when_unchanged() {
+        assert!(matches!(Date.canonicalize("2024-01-05"), Cow::Borrowed(_)));
+        assert!(matches!(String.canonicalize("1.0"), Cow::Borrowed(_)));
+        assert_eq!(Boolean.canonicalize("TRUE"), "true");
+    }
+
+    #[test]
+    fn delta_type_names() {
+        assert_eq!(PartitionType::from_delta_type("long"), Integer);
+        assert_eq!(PartitionType::from_delta_type("decimal(10,2)"), Decimal);
+        assert_eq!(PartitionType::from_delta_type("string"), String);
+    }
+
+    #[test]
+    fn canonical_tree() {
+        let mut escaped = DeltaTree::from_paths(vec![
+            path("ts=10%3A00/h=1/", 3),
+            path("ts=10:00/h=1/", 2),
+            path("ts=__HIVE_DEFAULT_PARTITION__/h=1/", 1),
+            path("ts=__HIVE_DEFAULT_PARTITION__/h=1/", 4),
+        ]);
+        let mut plain = DeltaTree::from_paths(vec![
+            path("ts=10:00/h=1/", 2),
+            path("ts=10:00/h=1/", 3),
+            path("ts=__HIVE_DEFAULT_PARTITION__/h=1/", 4),
+            path("ts=__HIVE_DEFAULT_PARTITION__/h=1/", 1),
+        ]);
+        assert_ne!(escaped, plain);
+        escaped.canonicalize();
+        plain.canonicalize();
+        assert_eq!(escaped, plain);
+        let mut partitions: Vec<_> = escaped
+            .partitions()
+            .into_iter()
+            .map(|(values, files)| (values, files.len()))
+            .collect();
+        partitions.sort();
+        assert_eq!(
+            partitions,
+            vec![
+                (
+                    vec![
+                        PartitionValue::new("ts", None),
+                        PartitionValue::new("h", Some("1"))
+                    ],
+                    2
+                ),
+                (
+                    vec![
+                        PartitionValue::new("ts", Some("10:00")),
+                        PartitionValue::new("h", Some("1"))
+                    ],
+                    2
+                ),
+            ]
+        );
+        assert!(escaped.canonical);
+        assert_eq!(
+            escaped.get(&[("ts", "10:00"), ("h", "1")]).map(<[_]>::len),
+            Some(2)
+        );
+        assert!(escaped.validate().is_valid());
+
+        fn reverse(node: &mut TreeNode) {
+            match node {
+                TreeNode::FileEntries { files } => files.reverse(),
+                TreeNode::Partition { values, .. } => values.values_mut().for_each(reverse),
+            }
+        }
+        let hash = |tree: &DeltaTree| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            std::hash::Hash::hash(tree, &mut hasher);
+            std::hash::Hasher::finish(&hasher)
+        };
+        reverse(&mut plain.root);
+        let leaf = [("ts", "10:00"), ("h", "1")];
+        assert_ne!(plain.get(&leaf), escaped.get(&leaf));
+        assert_eq!(plain, escaped);
+        assert_eq!(hash(&plain), hash(&escaped));
+    }
+}
@@ -0,0 +1,189 @@
+use super::DeltaTree;
+use std::fmt;
+use std::str::FromStr;
+
+/// a logical table name, `catalog.schema.table` or `schema.table`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TableName {
+    pub catalog: Option<String>,
+    pub schema: String,
+    pub table: String,
+}
+
+impl FromStr for TableName {
+    type Err = CatalogError;
+
+    fn from_str(s: &str) -> Result<TableName, CatalogError> {
+        let parts: Vec<&str> = s.split('.').collect();
+        if parts.iter().any(|p| p.is_empty()) {
+            return Err(CatalogError::InvalidName(s.to_string()));
+        }
+        match parts.as_slice() {
+            [schema, table] => Ok(TableName {
+                catalog: None,
+                schema: schema.to_string(),
+                table: table.to_string(),
+            }),
+            [catalog, schema, table] => Ok(TableName {
+                catalog: Some(catalog.to_string()),
+                schema: schema.to_string(),
+                table: table.to_string(),
+            }),
+            _ => Err(CatalogError::InvalidName(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for TableName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(catalog) = &self.catalog {
+            write!(f, "{}.", catalog)?;
+        }
+        write!(f, "{}.{}", self.schema, self.table)
+    }
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    InvalidName(String),
+    /// the table doesn't exist or has no storage location.
+    NotFound(String),
+    /// the catalog couldn't be reached or rejected the request.
+    Request(String),
+    /// no catalog is configured, see `Catalog::from_env`.
+    NotConfigured,
+    Delta(deltalake::DeltaTableError),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CatalogError::InvalidName(name) => write!(f, "invalid table name '{}'", name),
+            CatalogError::NotFound(name) => write!(f, "no location for table '{}'", name),
+            CatalogError::Request(msg) => write!(f, "catalog request failed: {}", msg),
+            CatalogError::NotConfigured => write!(
+                f,
+                "no catalog configured, set DELTA_TREE_CATALOG to 'glue' or 'unity'"
+            ),
+            CatalogError::Delta(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+impl From<deltalake::DeltaTableError> for CatalogError {
+    fn from(err: deltalake::DeltaTableError) -> CatalogError {
+        CatalogError::Delta(err)
+    }
+}
+
+/// resolves logical table names to storage locations.
+#[derive(Debug, Clone)]
+pub enum Catalog {
+    /// the AWS Glue data catalog of the default credentials. the catalog part of a table name
+    /// is the catalog id, i.e. the AWS account, defaulting to the caller's account.
+    #[cfg(feature = "glue")]
+    Glue,
+    /// a Databricks Unity Catalog, reached via its REST API.
+    #[cfg(feature = "unity")]
+    Unity { host: String, token: String },
+}
+
+impl Catalog {
+    /// the catalog named by `DELTA_TREE_CATALOG` (`glue` or `unity`). Unity Catalog also
+    /// needs `DATABRICKS_HOST` and `DATABRICKS_TOKEN`.
+    pub fn from_env() -> Result<Catalog, CatalogError> {
+        match std::env::var("DELTA_TREE_CATALOG").as_deref() {
+            #[cfg(feature = "glue")]
+            Ok("glue") => Ok(Catalog::Glue),
+            #[cfg(feature = "unity")]
+            Ok("unity") => match (
+                std::env::var("DATABRICKS_HOST"),
+                std::env::var("DATABRICKS_TOKEN"),
+            ) {
+                (Ok(host), Ok(token)) => Ok(Catalog::Unity { host, token }),
+                _ => Err(CatalogError::NotConfigured),
+            },
+            _ => Err(CatalogError::NotConfigured),
+        }
+    }
+
+    /// the storage location of table `name`.
+    pub async fn location(&self, name: &TableName) -> Result<String, CatalogError> {
+        match self {
+            #[cfg(feature = "glue")]
+            Catalog::Glue => glue_location(name).await,
+            #[cfg(feature = "unity")]
+            Catalog::Unity { host, token } => unity_location(host, token, name).await,
+        }
+    }
+
+    /// resolve `name` and build the tree of the table stored there.
+    pub async fn open(&self, name: &str) -> Result<DeltaTree, CatalogError> {
+        let location = self.location(&name.parse()?).await?;
+        Ok(DeltaTree::open(&location).await?)
+    }
+}
+
+#[cfg(feature = "glue")]
+async fn glue_location(name: &TableName) -> Result<String, CatalogError> {
+    let config = aws_config::load_from_env().await;
+    let output = aws_sdk_glue::Client::new(&config)
+        .get_table()
+        .set_catalog_id(name.catalog.clone())
+        .database_name(&name.schema)
+        .name(&name.table)
+        .send()
+        .await
+        .map_err(|err| CatalogError::Request(err.to_string()))?;
+    output
+        .table()
+        .and_then(|table| table.storage_descriptor())
+        .and_then(|descriptor| descriptor.location())
+        .map(str::to_string)
+        .ok_or_else(|| CatalogError::NotFound(name.to_string()))
+}
+
+#[cfg(feature = "unity")]
+async fn unity_location(host: &str, token: &str, name: &TableName) -> Result<String, CatalogError> {
+    let url = format!(
+        "{}/api/2.1/unity-catalog/tables/{}",
+        host.trim_end_matches('/'),
+        name
+    );
+    let request_error = |err: reqwest::Error| CatalogError::Request(err.to_string());
+    let table: serde_json::Value = reqwest::Client::new()
+        .get(url)
+        .bearer_auth(token)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(request_error)?
+        .json()
+        .await
+        .map_err(request_error)?;
+    table["storage_location"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| CatalogError::NotFound(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_table_names() {
+        let name: TableName = "main.sales.orders".parse().unwrap();
+        assert_eq!(name.catalog.as_deref(), Some("main"));
+        assert_eq!(name.to_string(), "main.sales.orders");
+        let name: TableName = "sales.orders".parse().unwrap();
+        assert_eq!(name.catalog, None);
+        assert_eq!(name.table, "orders");
+        assert!("orders".parse::<TableName>().is_err());
+        assert!("a..b".parse::<TableName>().is_err());
+        assert!("a.b.c.d".parse::<TableName>().is_err());
+    }
+}
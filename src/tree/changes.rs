@@ -0,0 +1,235 @@
+use super::history::commit_actions;
+use deltalake::DeltaDataTypeVersion;
+use futures::stream::Stream;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// a change of a watched tree, see `TableWatcher::changes`.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreeChange {
+    /// a commit was replayed onto the tree. the paths are as in the log.
+    Commit {
+        version: DeltaDataTypeVersion,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    /// the tree was loaded from scratch, e.g. after log cleanup. the changes leading to
+    /// `version` are unknown.
+    Reloaded { version: DeltaDataTypeVersion },
+    /// the consumer fell behind and the `skipped` oldest changes were dropped, see
+    /// `Backpressure::DropOldest`.
+    Lagged { skipped: usize },
+}
+
+impl TreeChange {
+    /// the change of the commit file of `version`, one json action per line.
+    pub fn of_commit(
+        version: DeltaDataTypeVersion,
+        commit: &[u8],
+    ) -> Result<TreeChange, serde_json::Error> {
+        let mut added = vec![];
+        let mut removed = vec![];
+        for action in commit_actions(commit) {
+            let action = action?;
+            if let Some(path) = action["add"]["path"].as_str() {
+                added.push(path.to_string());
+            } else if let Some(path) = action["remove"]["path"].as_str() {
+                removed.push(path.to_string());
+            }
+        }
+        Ok(TreeChange::Commit {
+            version,
+            added,
+            removed,
+        })
+    }
+}
+
+/// what happens to new changes while the buffer of a consumer is full.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Backpressure {
+    /// drop the oldest buffered change, the consumer gets a `TreeChange::Lagged` instead.
+    DropOldest,
+    /// wait for the consumer, pausing the refreshes of the watcher meanwhile.
+    Pause,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    changes: VecDeque<TreeChange>,
+    /// changes dropped since the consumer last got a `Lagged`.
+    skipped: usize,
+    consumer: Option<Waker>,
+    producer: Option<Waker>,
+    /// whether either side is gone.
+    closed: bool,
+}
+
+/// the bounded buffer between a watcher and one consumer of its changes.
+#[derive(Debug)]
+pub(super) struct ChangeQueue {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    backpressure: Backpressure,
+}
+
+impl ChangeQueue {
+    pub(super) fn new(capacity: usize, backpressure: Backpressure) -> Arc<ChangeQueue> {
+        assert!(capacity > 0, "capacity must be positive");
+        Arc::new(ChangeQueue {
+            state: Mutex::new(QueueState::default()),
+            capacity,
+            backpressure,
+        })
+    }
+
+    /// buffer `change`, waiting for room with `Backpressure::Pause`. returns whether the
+    /// consumer is still there.
+    pub(super) async fn push(&self, change: TreeChange) -> bool {
+        let mut change = Some(change);
+        futures::future::poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            if state.closed {
+                return Poll::Ready(false);
+            }
+            if state.changes.len() >= self.capacity {
+                match self.backpressure {
+                    Backpressure::DropOldest => {
+                        state.changes.pop_front();
+                        state.skipped += 1;
+                    }
+                    Backpressure::Pause => {
+                        state.producer = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            }
+            state.changes.extend(change.take());
+            if let Some(consumer) = state.consumer.take() {
+                consumer.wake();
+            }
+            Poll::Ready(true)
+        })
+        .await
+    }
+
+    pub(super) fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    /// end the stream of the consumer once it took the buffered changes.
+    pub(super) fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        if let Some(consumer) = state.consumer.take() {
+            consumer.wake();
+        }
+    }
+}
+
+/// the changes of a watched tree as they happen, buffered up to a capacity, see
+/// `TableWatcher::changes`. ends when the watcher is dropped.
+#[derive(Debug)]
+pub struct TreeChanges {
+    queue: Arc<ChangeQueue>,
+}
+
+impl TreeChanges {
+    pub(super) fn new(queue: Arc<ChangeQueue>) -> TreeChanges {
+        TreeChanges { queue }
+    }
+}
+
+impl Stream for TreeChanges {
+    type Item = TreeChange;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<TreeChange>> {
+        let mut state = self.queue.state.lock().unwrap();
+        if state.skipped > 0 {
+            let skipped = std::mem::take(&mut state.skipped);
+            return Poll::Ready(Some(TreeChange::Lagged { skipped }));
+        }
+        if let Some(change) = state.changes.pop_front() {
+            if let Some(producer) = state.producer.take() {
+                producer.wake();
+            }
+            return Poll::Ready(Some(change));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.consumer = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for TreeChanges {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap();
+        state.closed = true;
+        if let Some(producer) = state.producer.take() {
+            producer.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{FutureExt, StreamExt};
+    use pretty_assertions::assert_eq;
+
+    fn reloaded(version: DeltaDataTypeVersion) -> TreeChange {
+        TreeChange::Reloaded { version }
+    }
+
+    #[test]
+    fn drop_oldest_changes() {
+        let queue = ChangeQueue::new(2, Backpressure::DropOldest);
+        let mut changes = TreeChanges::new(queue.clone());
+        for version in 1..=5 {
+            assert_eq!(queue.push(reloaded(version)).now_or_never(), Some(true));
+        }
+        assert_eq!(
+            changes.next().now_or_never(),
+            Some(Some(TreeChange::Lagged { skipped: 3 }))
+        );
+        assert_eq!(changes.next().now_or_never(), Some(Some(reloaded(4))));
+        assert_eq!(changes.next().now_or_never(), Some(Some(reloaded(5))));
+        assert_eq!(changes.next().now_or_never(), None);
+        queue.close();
+        assert_eq!(changes.next().now_or_never(), Some(None));
+    }
+
+    #[test]
+    fn pause_for_consumer() {
+        let queue = ChangeQueue::new(1, Backpressure::Pause);
+        let mut changes = TreeChanges::new(queue.clone());
+        assert_eq!(queue.push(reloaded(1)).now_or_never(), Some(true));
+        assert_eq!(queue.push(reloaded(2)).now_or_never(), None);
+        assert_eq!(changes.next().now_or_never(), Some(Some(reloaded(1))));
+        assert_eq!(queue.push(reloaded(2)).now_or_never(), Some(true));
+        drop(changes);
+        assert_eq!(queue.push(reloaded(3)).now_or_never(), Some(false));
+    }
+
+    #[test]
+    fn change_of_commit() {
+        let commit = br#"{"commitInfo":{"operation":"OPTIMIZE"}}
+{"remove":{"path":"a=1/part-00000.parquet","dataChange":false}}
+{"add":{"path":"a=1/part-00001.parquet","size":25,"dataChange":false}}
+"#;
+        assert_eq!(
+            TreeChange::of_commit(3, commit).unwrap(),
+            TreeChange::Commit {
+                version: 3,
+                added: vec!["a=1/part-00001.parquet".to_string()],
+                removed: vec!["a=1/part-00000.parquet".to_string()],
+            }
+        );
+    }
+}
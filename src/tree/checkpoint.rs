@@ -0,0 +1,637 @@
+use super::commit::partition_values;
+use super::sized::SizedDeltaFile;
+use super::storage::StorageOptions;
+use super::{stats, DeltaTree};
+use deltalake::action::{MetaData, Protocol, Remove, Txn};
+use deltalake::storage::StorageError;
+use deltalake::DeltaDataTypeVersion;
+use parquet::basic::Compression;
+use parquet::column::writer::get_typed_column_writer_mut;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DataType, Int32Type, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{FileWriter, RowGroupWriter, SerializedFileWriter};
+use parquet::schema::parser::parse_message_type;
+use parquet::util::cursor::InMemoryWriteableCursor;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// the columns of a checkpoint that readers of the delta protocol rely on, with one action
+/// per row.
+const SCHEMA: &str = "
+message spark_schema {
+    optional group txn {
+        optional binary appId (UTF8);
+        optional int64 version;
+        optional int64 lastUpdated;
+    }
+    optional group add {
+        optional binary path (UTF8);
+        optional group partitionValues (MAP) {
+            repeated group key_value {
+                required binary key (UTF8);
+                optional binary value (UTF8);
+            }
+        }
+        optional int64 size;
+        optional int64 modificationTime;
+        optional boolean dataChange;
+        optional binary stats (UTF8);
+    }
+    optional group remove {
+        optional binary path (UTF8);
+        optional int64 deletionTimestamp;
+        optional boolean dataChange;
+        optional boolean extendedFileMetadata;
+        optional group partitionValues (MAP) {
+            repeated group key_value {
+                required binary key (UTF8);
+                optional binary value (UTF8);
+            }
+        }
+        optional int64 size;
+    }
+    optional group metaData {
+        optional binary id (UTF8);
+        optional binary name (UTF8);
+        optional binary description (UTF8);
+        optional group format {
+            optional binary provider (UTF8);
+            optional group options (MAP) {
+                repeated group key_value {
+                    required binary key (UTF8);
+                    optional binary value (UTF8);
+                }
+            }
+        }
+        optional binary schemaString (UTF8);
+        optional group partitionColumns (LIST) {
+            repeated group list {
+                optional binary element (UTF8);
+            }
+        }
+        optional group configuration (MAP) {
+            repeated group key_value {
+                required binary key (UTF8);
+                optional binary value (UTF8);
+            }
+        }
+        optional int64 createdTime;
+    }
+    optional group protocol {
+        optional int32 minReaderVersion;
+        optional int32 minWriterVersion;
+    }
+}";
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Parquet(ParquetError),
+    Storage(StorageError),
+    /// the tree's partition values were canonicalized, they may not be those of the files'
+    /// add actions.
+    Canonical,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckpointError::Parquet(err) => write!(f, "can't write checkpoint: {}", err),
+            CheckpointError::Storage(err) => write!(f, "can't store checkpoint: {}", err),
+            CheckpointError::Canonical => {
+                write!(f, "can't checkpoint a tree with canonical partition values")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<ParquetError> for CheckpointError {
+    fn from(err: ParquetError) -> CheckpointError {
+        CheckpointError::Parquet(err)
+    }
+}
+
+impl From<StorageError> for CheckpointError {
+    fn from(err: StorageError) -> CheckpointError {
+        CheckpointError::Storage(err)
+    }
+}
+
+/// a checkpoint of a table, built from a tree of its live files rather than by replaying
+/// its log, e.g. to cut short an enormous log in a maintenance job. the tree knows nothing
+/// about file statistics, so readers of the checkpoint can't skip files by their stats.
+#[derive(Debug, Default)]
+pub struct Checkpoint {
+    rows: usize,
+    txn: TxnColumns,
+    add: AddColumns,
+    remove: RemoveColumns,
+    metadata: MetaDataColumns,
+    protocol: ProtocolColumns,
+}
+
+impl Checkpoint {
+    /// a checkpoint of a table with `protocol` and `metadata`, still without any files.
+    pub fn new(protocol: &Protocol, metadata: &MetaData) -> Checkpoint {
+        let mut checkpoint = Checkpoint::default();
+        checkpoint.push(Row::Protocol(protocol));
+        checkpoint.push(Row::MetaData(metadata));
+        checkpoint
+    }
+
+    /// the live files of `tree`, as of the version of the checkpoint. canonical trees are
+    /// refused, the paths and partition values of their add actions would be made up.
+    pub fn files<S>(
+        mut self,
+        tree: &DeltaTree<SizedDeltaFile, S>,
+    ) -> Result<Checkpoint, CheckpointError> {
+        if tree.canonical {
+            return Err(CheckpointError::Canonical);
+        }
+        for (dir, files) in stats::leaves(tree) {
+            for file in files {
+                let path = format!("{}{}", dir, file.file.name());
+                self.push(Row::Add(&path, file));
+            }
+        }
+        Ok(self)
+    }
+
+    /// the latest version of each application transaction.
+    pub fn txns<'a>(mut self, txns: impl IntoIterator<Item = &'a Txn>) -> Checkpoint {
+        txns.into_iter().for_each(|txn| self.push(Row::Txn(txn)));
+        self
+    }
+
+    /// the tombstones that haven't expired yet, so vacuum keeps their files until they do.
+    pub fn tombstones<'a>(mut self, removes: impl IntoIterator<Item = &'a Remove>) -> Checkpoint {
+        removes
+            .into_iter()
+            .for_each(|remove| self.push(Row::Remove(remove)));
+        self
+    }
+
+    /// the number of actions in the checkpoint.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// the checkpoint as a single row group of a parquet file.
+    pub fn to_parquet(&self) -> Result<Vec<u8>, ParquetError> {
+        let schema = Arc::new(parse_message_type(SCHEMA)?);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let output = InMemoryWriteableCursor::default();
+        let mut writer = SerializedFileWriter::new(output.clone(), schema, Arc::new(properties))?;
+        let mut row_group = writer.next_row_group()?;
+        let rg = &mut row_group;
+        write_column::<ByteArrayType>(rg, &self.txn.app_id)?;
+        write_column::<Int64Type>(rg, &self.txn.version)?;
+        write_column::<Int64Type>(rg, &self.txn.last_updated)?;
+        write_column::<ByteArrayType>(rg, &self.add.path)?;
+        write_map(rg, &self.add.partition_values)?;
+        write_column::<Int64Type>(rg, &self.add.size)?;
+        write_column::<Int64Type>(rg, &self.add.modification_time)?;
+        write_column::<BoolType>(rg, &self.add.data_change)?;
+        write_column::<ByteArrayType>(rg, &self.add.stats)?;
+        write_column::<ByteArrayType>(rg, &self.remove.path)?;
+        write_column::<Int64Type>(rg, &self.remove.deletion_timestamp)?;
+        write_column::<BoolType>(rg, &self.remove.data_change)?;
+        write_column::<BoolType>(rg, &self.remove.extended_file_metadata)?;
+        write_map(rg, &self.remove.partition_values)?;
+        write_column::<Int64Type>(rg, &self.remove.size)?;
+        write_column::<ByteArrayType>(rg, &self.metadata.id)?;
+        write_column::<ByteArrayType>(rg, &self.metadata.name)?;
+        write_column::<ByteArrayType>(rg, &self.metadata.description)?;
+        write_column::<ByteArrayType>(rg, &self.metadata.provider)?;
+        write_map(rg, &self.metadata.options)?;
+        write_column::<ByteArrayType>(rg, &self.metadata.schema_string)?;
+        write_column::<ByteArrayType>(rg, &self.metadata.partition_columns)?;
+        write_map(rg, &self.metadata.configuration)?;
+        write_column::<Int64Type>(rg, &self.metadata.created_time)?;
+        write_column::<Int32Type>(rg, &self.protocol.min_reader_version)?;
+        write_column::<Int32Type>(rg, &self.protocol.min_writer_version)?;
+        writer.close_row_group(row_group)?;
+        writer.close()?;
+        Ok(output.data())
+    }
+
+    /// store the checkpoint as `version` of the table at `table_uri` and point
+    /// `_last_checkpoint` at it. the commit of `version` must already exist.
+    pub async fn write(
+        &self,
+        table_uri: &str,
+        version: DeltaDataTypeVersion,
+        options: &StorageOptions,
+    ) -> Result<(), CheckpointError> {
+        let bytes = self.to_parquet()?;
+        let backend = options.backend(table_uri)?;
+        let log = backend.join_path(table_uri, "_delta_log");
+        let path = format!("{:020}.checkpoint.parquet", version);
+        options
+            .guard(backend.put_obj(&backend.join_path(&log, &path), &bytes))
+            .await?;
+        let last = format!(r#"{{"version":{},"size":{}}}"#, version, self.rows);
+        options
+            .guard(backend.put_obj(
+                &backend.join_path(&log, "_last_checkpoint"),
+                last.as_bytes(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    fn push(&mut self, row: Row) {
+        self.rows += 1;
+        self.txn.push(match row {
+            Row::Txn(txn) => Some(txn),
+            _ => None,
+        });
+        self.add.push(match row {
+            Row::Add(path, file) => Some((path, file)),
+            _ => None,
+        });
+        self.remove.push(match row {
+            Row::Remove(remove) => Some(remove),
+            _ => None,
+        });
+        self.metadata.push(match row {
+            Row::MetaData(metadata) => Some(metadata),
+            _ => None,
+        });
+        self.protocol.push(match row {
+            Row::Protocol(protocol) => Some(protocol),
+            _ => None,
+        });
+    }
+}
+
+/// the action of a row of the checkpoint.
+#[derive(Clone, Copy)]
+enum Row<'a> {
+    Txn(&'a Txn),
+    Add(&'a str, &'a SizedDeltaFile),
+    Remove(&'a Remove),
+    MetaData(&'a MetaData),
+    Protocol(&'a Protocol),
+}
+
+/// a leaf column with the definition and repetition level of each of its entries. the
+/// entries without a value are null at their definition level.
+#[derive(Debug, PartialEq)]
+struct Column<T> {
+    values: Vec<T>,
+    def: Vec<i16>,
+    rep: Vec<i16>,
+}
+
+impl<T> Default for Column<T> {
+    fn default() -> Column<T> {
+        Column {
+            values: vec![],
+            def: vec![],
+            rep: vec![],
+        }
+    }
+}
+
+impl<T> Column<T> {
+    fn level(&mut self, def: i16, rep: i16) {
+        self.def.push(def);
+        self.rep.push(rep);
+    }
+
+    fn value(&mut self, value: T, def: i16, rep: i16) {
+        self.values.push(value);
+        self.level(def, rep);
+    }
+
+    /// a row in which the action of the column is missing.
+    fn null(&mut self) {
+        self.level(0, 0);
+    }
+
+    /// a field directly below the action of the column.
+    fn field(&mut self, value: Option<T>) {
+        match value {
+            Some(value) => self.value(value, 2, 0),
+            None => self.level(1, 0),
+        }
+    }
+}
+
+/// the keys and values of a map of strings, like the partition values of a file.
+#[derive(Debug, Default, PartialEq)]
+struct MapColumn {
+    keys: Column<ByteArray>,
+    values: Column<ByteArray>,
+}
+
+impl MapColumn {
+    fn level(&mut self, def: i16) {
+        self.keys.level(def, 0);
+        self.values.level(def, 0);
+    }
+
+    /// the entries of a map present at definition level `def`, sorted by key.
+    fn entries(&mut self, map: &HashMap<String, Option<String>>, def: i16) {
+        if map.is_empty() {
+            return self.level(def);
+        }
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort();
+        for (i, (key, value)) in entries.into_iter().enumerate() {
+            let rep = if i == 0 { 0 } else { 1 };
+            self.keys.value(ByteArray::from(key.as_str()), def + 1, rep);
+            match value {
+                Some(value) => self
+                    .values
+                    .value(ByteArray::from(value.as_str()), def + 2, rep),
+                None => self.values.level(def + 1, rep),
+            }
+        }
+    }
+}
+
+fn string(value: &str) -> Option<ByteArray> {
+    Some(ByteArray::from(value))
+}
+
+#[derive(Debug, Default)]
+struct TxnColumns {
+    app_id: Column<ByteArray>,
+    version: Column<i64>,
+    last_updated: Column<i64>,
+}
+
+impl TxnColumns {
+    fn push(&mut self, txn: Option<&Txn>) {
+        match txn {
+            Some(txn) => {
+                self.app_id.field(string(&txn.app_id));
+                self.version.field(Some(txn.version));
+                self.last_updated.field(txn.last_updated);
+            }
+            None => {
+                self.app_id.null();
+                self.version.null();
+                self.last_updated.null();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct AddColumns {
+    path: Column<ByteArray>,
+    partition_values: MapColumn,
+    size: Column<i64>,
+    modification_time: Column<i64>,
+    data_change: Column<bool>,
+    stats: Column<ByteArray>,
+}
+
+impl AddColumns {
+    fn push(&mut self, add: Option<(&str, &SizedDeltaFile)>) {
+        match add {
+            Some((path, file)) => {
+                self.path.field(string(path));
+                self.partition_values.entries(&partition_values(path), 2);
+                self.size.field(Some(file.size as i64));
+                self.modification_time.field(Some(file.modification_time));
+                self.data_change.field(Some(false));
+                self.stats.field(None);
+            }
+            None => {
+                self.path.null();
+                self.partition_values.level(0);
+                self.size.null();
+                self.modification_time.null();
+                self.data_change.null();
+                self.stats.null();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RemoveColumns {
+    path: Column<ByteArray>,
+    deletion_timestamp: Column<i64>,
+    data_change: Column<bool>,
+    extended_file_metadata: Column<bool>,
+    partition_values: MapColumn,
+    size: Column<i64>,
+}
+
+impl RemoveColumns {
+    fn push(&mut self, remove: Option<&Remove>) {
+        match remove {
+            Some(remove) => {
+                self.path.field(string(&remove.path));
+                self.deletion_timestamp.field(remove.deletion_timestamp);
+                self.data_change.field(Some(remove.data_change));
+                self.extended_file_metadata
+                    .field(remove.extended_file_metadata);
+                match &remove.partition_values {
+                    Some(values) => self.partition_values.entries(values, 2),
+                    None => self.partition_values.level(1),
+                }
+                self.size.field(remove.size);
+            }
+            None => {
+                self.path.null();
+                self.deletion_timestamp.null();
+                self.data_change.null();
+                self.extended_file_metadata.null();
+                self.partition_values.level(0);
+                self.size.null();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MetaDataColumns {
+    id: Column<ByteArray>,
+    name: Column<ByteArray>,
+    description: Column<ByteArray>,
+    provider: Column<ByteArray>,
+    options: MapColumn,
+    schema_string: Column<ByteArray>,
+    partition_columns: Column<ByteArray>,
+    configuration: MapColumn,
+    created_time: Column<i64>,
+}
+
+impl MetaDataColumns {
+    fn push(&mut self, metadata: Option<&MetaData>) {
+        match metadata {
+            Some(metadata) => {
+                self.id.field(string(&metadata.id));
+                self.name.field(metadata.name.as_deref().and_then(string));
+                self.description
+                    .field(metadata.description.as_deref().and_then(string));
+                // delta tables are always parquet, written without format options.
+                self.provider.value(ByteArray::from("parquet"), 3, 0);
+                self.options.level(3);
+                self.schema_string.field(string(&metadata.schema_string));
+                if metadata.partition_columns.is_empty() {
+                    self.partition_columns.level(2, 0);
+                }
+                for (i, column) in metadata.partition_columns.iter().enumerate() {
+                    let rep = if i == 0 { 0 } else { 1 };
+                    self.partition_columns
+                        .value(ByteArray::from(column.as_str()), 4, rep);
+                }
+                self.configuration.entries(&metadata.configuration, 2);
+                self.created_time.field(metadata.created_time);
+            }
+            None => {
+                self.id.null();
+                self.name.null();
+                self.description.null();
+                self.provider.null();
+                self.options.level(0);
+                self.schema_string.null();
+                self.partition_columns.null();
+                self.configuration.level(0);
+                self.created_time.null();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProtocolColumns {
+    min_reader_version: Column<i32>,
+    min_writer_version: Column<i32>,
+}
+
+impl ProtocolColumns {
+    fn push(&mut self, protocol: Option<&Protocol>) {
+        match protocol {
+            Some(protocol) => {
+                self.min_reader_version
+                    .field(Some(protocol.min_reader_version));
+                self.min_writer_version
+                    .field(Some(protocol.min_writer_version));
+            }
+            None => {
+                self.min_reader_version.null();
+                self.min_writer_version.null();
+            }
+        }
+    }
+}
+
+/// write `column` as the next leaf column of the row group, in the order of `SCHEMA`.
+fn write_column<T: DataType>(
+    row_group: &mut Box<dyn RowGroupWriter>,
+    column: &Column<T::T>,
+) -> Result<(), ParquetError> {
+    let mut writer = row_group
+        .next_column()?
+        .expect("a column for each leaf of the schema");
+    get_typed_column_writer_mut::<T>(&mut writer).write_batch(
+        &column.values,
+        Some(&column.def),
+        Some(&column.rep),
+    )?;
+    row_group.close_column(writer)
+}
+
+fn write_map(row_group: &mut Box<dyn RowGroupWriter>, map: &MapColumn) -> Result<(), ParquetError> {
+    write_column::<ByteArrayType>(row_group, &map.keys)?;
+    write_column::<ByteArrayType>(row_group, &map.values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn tree(paths: &[&str]) -> DeltaTree<SizedDeltaFile> {
+        let entries = paths.iter().map(|path| (path.to_string(), 100)).collect();
+        DeltaTree::from_entries(entries, |file, size| SizedDeltaFile {
+            file,
+            size,
+            modification_time: 7,
+        })
+    }
+
+    #[test]
+    fn levels_of_a_checkpoint() {
+        let protocol = Protocol {
+            min_reader_version: 1,
+            min_writer_version: 2,
+        };
+        let metadata = MetaData {
+            id: "table".to_string(),
+            partition_columns: vec!["a".to_string(), "b".to_string()],
+            ..Default::default()
+        };
+        let txn = Txn {
+            app_id: "compactor".to_string(),
+            version: 3,
+            last_updated: None,
+        };
+        let checkpoint = Checkpoint::new(&protocol, &metadata)
+            .txns(std::iter::once(&txn))
+            .files(&tree(&[
+                "a=1/b=__HIVE_DEFAULT_PARTITION__/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+            ]))
+            .unwrap();
+        assert_eq!(checkpoint.rows(), 4);
+
+        // protocol, metadata, txn, add
+        assert_eq!(checkpoint.protocol.min_writer_version.values, vec![2]);
+        assert_eq!(checkpoint.protocol.min_writer_version.def, vec![2, 0, 0, 0]);
+        assert_eq!(
+            checkpoint.metadata.partition_columns.def,
+            vec![0, 4, 4, 0, 0]
+        );
+        assert_eq!(
+            checkpoint.metadata.partition_columns.rep,
+            vec![0, 0, 1, 0, 0]
+        );
+        assert_eq!(checkpoint.metadata.configuration.keys.def, vec![0, 2, 0, 0]);
+        assert_eq!(checkpoint.txn.last_updated.def, vec![0, 0, 1, 0]);
+        assert_eq!(checkpoint.add.size.values, vec![100]);
+        assert_eq!(
+            checkpoint.add.partition_values.keys.values,
+            vec![ByteArray::from("a"), ByteArray::from("b")]
+        );
+        assert_eq!(
+            checkpoint.add.partition_values.keys.def,
+            vec![0, 0, 0, 3, 3]
+        );
+        assert_eq!(
+            checkpoint.add.partition_values.keys.rep,
+            vec![0, 0, 0, 0, 1]
+        );
+        assert_eq!(
+            checkpoint.add.partition_values.values.def,
+            vec![0, 0, 0, 4, 3]
+        );
+        assert_eq!(checkpoint.add.stats.def, vec![0, 0, 0, 1]);
+        assert_eq!(checkpoint.remove.path.def, vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn canonical_trees_are_refused() {
+        let protocol = Protocol {
+            min_reader_version: 1,
+            min_writer_version: 2,
+        };
+        let mut tree =
+            tree(&["a=%31/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet"]);
+        tree.canonicalize();
+        let checkpoint = Checkpoint::new(&protocol, &MetaData::default()).files(&tree);
+        assert!(matches!(checkpoint, Err(CheckpointError::Canonical)));
+    }
+}
@@ -0,0 +1,365 @@
+use super::history::commit_actions;
+use super::sized::SizedDeltaFile;
+use super::snapshot::last_checkpoint;
+use super::stats;
+use super::storage::StorageOptions;
+use super::DeltaTree;
+use deltalake::storage::StorageError;
+use deltalake::{DeltaDataTypeVersion, DeltaTableError};
+use parquet::file::reader::FileReader;
+use parquet::file::serialized_reader::SerializedFileReader;
+use parquet::record::{Field, Row};
+use parquet::util::cursor::SliceableCursor;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// the domain of the `domainMetadata` action holding the columns of a clustered table.
+const CLUSTERING_DOMAIN: &str = "delta.clustering";
+
+/// how the files of each leaf partition are spread over the clusters in their names
+/// (`c000`), e.g. to check that a Z-order or clustered write produced even file groups.
+#[derive(Debug, Default, PartialEq, Clone, Serialize)]
+pub struct ClusteringReport {
+    pub files: usize,
+    /// files without a cluster in their name.
+    pub unclustered_files: usize,
+    /// the leaf partitions, and those with at least one clustered file.
+    pub partitions: usize,
+    pub clustered_partitions: usize,
+    /// the clustered partitions, sorted by path.
+    pub details: Vec<PartitionClusters>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct PartitionClusters {
+    /// the directory of the partition, e.g. `date=2021-03-01/`.
+    pub path: String,
+    pub files: usize,
+    pub unclustered_files: usize,
+    /// the distinct clusters of the partition, ascending.
+    pub clusters: Vec<ClusterFiles>,
+    /// the files of the largest cluster over the mean files per cluster, 1.0 if the files
+    /// are spread evenly.
+    pub skew: f64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct ClusterFiles {
+    pub cluster: u8,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl ClusteringReport {
+    pub fn new<S>(tree: &DeltaTree<SizedDeltaFile, S>) -> ClusteringReport {
+        let mut report = ClusteringReport::default();
+        for (path, files) in stats::leaves(tree) {
+            report.files += files.len();
+            report.partitions += 1;
+            let mut clusters: BTreeMap<u8, ClusterFiles> = BTreeMap::new();
+            let mut unclustered_files = 0;
+            for file in files {
+                match file.file.cluster() {
+                    Some(cluster) => {
+                        let entry = clusters.entry(cluster).or_insert(ClusterFiles {
+                            cluster,
+                            files: 0,
+                            bytes: 0,
+                        });
+                        entry.files += 1;
+                        entry.bytes += file.size;
+                    }
+                    None => unclustered_files += 1,
+                }
+            }
+            report.unclustered_files += unclustered_files;
+            if clusters.is_empty() {
+                continue;
+            }
+            let clusters: Vec<ClusterFiles> = clusters.into_values().collect();
+            let clustered = files.len() - unclustered_files;
+            let largest = clusters.iter().map(|c| c.files).max().unwrap_or(0);
+            report.details.push(PartitionClusters {
+                path,
+                files: files.len(),
+                unclustered_files,
+                skew: largest as f64 * clusters.len() as f64 / clustered as f64,
+                clusters,
+            });
+        }
+        report.clustered_partitions = report.details.len();
+        report.details.sort_by(|a, b| a.path.cmp(&b.path));
+        report
+    }
+}
+
+/// what a single commit says about liquid clustering.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CommitClustering {
+    /// the clustering columns set by the commit, empty if it removed the clustering.
+    columns: Option<Vec<String>>,
+    /// whether the protocol set by the commit supports clustering.
+    protocol: Option<bool>,
+}
+
+impl CommitClustering {
+    fn parse(commit: &[u8]) -> Result<CommitClustering, serde_json::Error> {
+        let mut clustering = CommitClustering::default();
+        for action in commit_actions(commit) {
+            let action = action?;
+            if let Some(protocol) = action.get("protocol") {
+                let features = protocol["writerFeatures"].as_array();
+                let clustering_feature = features
+                    .into_iter()
+                    .flatten()
+                    .any(|f| f.as_str() == Some("clustering"));
+                clustering.protocol = Some(clustering_feature);
+            } else if let Some(domain) = action.get("domainMetadata") {
+                if domain["domain"].as_str() != Some(CLUSTERING_DOMAIN) {
+                    continue;
+                }
+                if domain["removed"].as_bool().unwrap_or(false) {
+                    clustering.columns = Some(vec![]);
+                    continue;
+                }
+                let configuration = domain["configuration"].as_str().unwrap_or("{}");
+                clustering.columns = Some(clustering_configuration(configuration)?);
+            }
+        }
+        Ok(clustering)
+    }
+
+    /// the clustering of a checkpoint, or of one part of a multi-part checkpoint. checkpoints
+    /// only keep the domains that weren't removed.
+    fn from_checkpoint(checkpoint: Vec<u8>) -> Result<CommitClustering, DeltaTableError> {
+        let reader = SerializedFileReader::new(SliceableCursor::new(checkpoint))?;
+        let mut clustering = CommitClustering::default();
+        for row in reader.get_row_iter(None)? {
+            for (column, field) in row.get_column_iter() {
+                match (column.as_str(), field) {
+                    ("protocol", Field::Group(protocol)) => {
+                        clustering.protocol = Some(writer_feature(protocol, "clustering"));
+                    }
+                    ("domainMetadata", Field::Group(domain)) => {
+                        if string_field(domain, "domain") != Some(CLUSTERING_DOMAIN) {
+                            continue;
+                        }
+                        let configuration = string_field(domain, "configuration").unwrap_or("{}");
+                        clustering.columns = Some(clustering_configuration(configuration)?);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(clustering)
+    }
+}
+
+/// the clustering columns of the configuration of a `delta.clustering` domain.
+fn clustering_configuration(configuration: &str) -> Result<Vec<String>, serde_json::Error> {
+    let configuration: Value = serde_json::from_str(configuration)?;
+    let columns = configuration["clusteringColumns"]
+        .as_array()
+        .map(|columns| columns.iter().map(column_name).collect());
+    Ok(columns.unwrap_or_default())
+}
+
+fn string_field<'a>(row: &'a Row, name: &str) -> Option<&'a str> {
+    row.get_column_iter()
+        .find_map(|(column, field)| match field {
+            Field::Str(value) if column == name => Some(value.as_str()),
+            _ => None,
+        })
+}
+
+/// whether the protocol of a checkpoint row lists `feature` among its writer features.
+fn writer_feature(protocol: &Row, feature: &str) -> bool {
+    protocol
+        .get_column_iter()
+        .any(|(column, field)| match field {
+            Field::ListInternal(features) if column == "writerFeatures" => features
+                .elements()
+                .iter()
+                .any(|f| matches!(f, Field::Str(f) if f == feature)),
+            _ => false,
+        })
+}
+
+/// a clustering column is given as the path of field names to a possibly nested column.
+fn column_name(path: &Value) -> String {
+    match path.as_array() {
+        Some(fields) => fields
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("."),
+        None => path.as_str().unwrap_or_default().to_string(),
+    }
+}
+
+/// the liquid clustering columns of the table at `table_uri` as of `version`, empty if it isn't
+/// clustered, or `None` if log cleanup already removed the commits that would tell. delta-rs
+/// doesn't keep domain metadata, so this walks the commits back from `version` until the latest
+/// clustering change or a protocol without the clustering feature, and then reads the latest
+/// checkpoint if it's no newer than `version`. with column mapping, the columns are physical
+/// names.
+pub async fn clustering_columns(
+    table_uri: &str,
+    version: DeltaDataTypeVersion,
+    options: &StorageOptions,
+) -> Result<Option<Vec<String>>, DeltaTableError> {
+    let backend = options.backend(table_uri)?;
+    let log_uri = backend.join_path(table_uri, "_delta_log");
+    let checkpoint = options
+        .guard(last_checkpoint(backend.as_ref(), &log_uri))
+        .await?
+        .filter(|checkpoint| checkpoint.version <= version);
+    let oldest = checkpoint
+        .as_ref()
+        .map_or(0, |checkpoint| checkpoint.version + 1);
+    for version in (oldest..=version).rev() {
+        let commit_uri = backend.join_path(&log_uri, &format!("{:020}.json", version));
+        let commit = match options.guard(backend.get_obj(&commit_uri)).await {
+            Ok(commit) => commit,
+            Err(StorageError::NotFound) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let clustering = CommitClustering::parse(&commit)?;
+        if let Some(columns) = clustering.columns {
+            return Ok(Some(columns));
+        }
+        if clustering.protocol == Some(false) {
+            return Ok(Some(vec![]));
+        }
+    }
+    let checkpoint = match checkpoint {
+        Some(checkpoint) => checkpoint,
+        None => return Ok(Some(vec![])),
+    };
+    let mut columns = vec![];
+    for part in checkpoint.files() {
+        let part_uri = backend.join_path(&log_uri, &part);
+        let part = match options.guard(backend.get_obj(&part_uri)).await {
+            Ok(part) => part,
+            Err(StorageError::NotFound) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(part_columns) = CommitClustering::from_checkpoint(part)?.columns {
+            columns = part_columns;
+        }
+    }
+    Ok(Some(columns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::sized_tree;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn clusters_of_partitions() {
+        let entries = [
+            ("d=1/", 1, Some(0)),
+            ("d=1/", 2, Some(0)),
+            ("d=1/", 3, Some(0)),
+            ("d=1/", 4, Some(1)),
+            ("d=1/", 5, None),
+            ("d=2/", 6, None),
+        ]
+        .iter()
+        .map(|(dir, id, cluster)| {
+            let cluster = cluster.map_or(String::new(), |c| format!(".c{:03}", c));
+            let name = format!(
+                "{}part-00000-{}{}.snappy.parquet",
+                dir,
+                uuid::Uuid::from_u128(*id),
+                cluster
+            );
+            (name, 10)
+        })
+        .collect();
+        let tree = sized_tree(entries);
+        let report = ClusteringReport::new(&tree);
+        assert_eq!((report.files, report.unclustered_files), (6, 2));
+        assert_eq!((report.partitions, report.clustered_partitions), (2, 1));
+        let partition = &report.details[0];
+        assert_eq!(partition.path, "d=1/");
+        assert_eq!(partition.unclustered_files, 1);
+        assert_eq!(
+            partition.clusters,
+            vec![
+                ClusterFiles {
+                    cluster: 0,
+                    files: 3,
+                    bytes: 30
+                },
+                ClusterFiles {
+                    cluster: 1,
+                    files: 1,
+                    bytes: 10
+                },
+            ]
+        );
+        assert_eq!(partition.skew, 1.5);
+    }
+
+    #[test]
+    fn clustering_of_commits() {
+        let commit = br#"{"commitInfo":{"operation":"CREATE TABLE"}}
+{"protocol":{"minReaderVersion":3,"minWriterVersion":7,"readerFeatures":[],"writerFeatures":["clustering","domainMetadata"]}}
+{"domainMetadata":{"domain":"delta.rowTracking","configuration":"{}","removed":false}}
+{"domainMetadata":{"domain":"delta.clustering","configuration":"{\"clusteringColumns\":[[\"date\"],[\"device\",\"id\"]],\"domainName\":\"delta.clustering\"}","removed":false}}
+"#;
+        assert_eq!(
+            CommitClustering::parse(commit).unwrap(),
+            CommitClustering {
+                columns: Some(vec!["date".to_string(), "device.id".to_string()]),
+                protocol: Some(true),
+            }
+        );
+        let removed = br#"{"domainMetadata":{"domain":"delta.clustering","configuration":"","removed":true}}"#;
+        assert_eq!(
+            CommitClustering::parse(removed).unwrap().columns,
+            Some(vec![])
+        );
+        let legacy = br#"{"protocol":{"minReaderVersion":1,"minWriterVersion":2}}"#;
+        assert_eq!(
+            CommitClustering::parse(legacy).unwrap(),
+            CommitClustering {
+                columns: None,
+                protocol: Some(false),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn clustering_of_history() {
+        let dir =
+            std::env::temp_dir().join(format!("delta-tree-clustering-{}", std::process::id()));
+        let log = dir.join("_delta_log");
+        std::fs::create_dir_all(&log).unwrap();
+        let commit = |version: i64, commit: &str| {
+            std::fs::write(log.join(format!("{:020}.json", version)), commit).unwrap();
+        };
+        commit(
+            0,
+            r#"{"protocol":{"minReaderVersion":3,"minWriterVersion":7,"writerFeatures":["clustering"]}}
+{"domainMetadata":{"domain":"delta.clustering","configuration":"{\"clusteringColumns\":[[\"date\"]]}","removed":false}}"#,
+        );
+        commit(1, r#"{"commitInfo":{"operation":"WRITE"}}"#);
+        commit(
+            2,
+            r#"{"protocol":{"minReaderVersion":3,"minWriterVersion":7,"writerFeatures":["clustering","deletionVectors"]}}"#,
+        );
+        let uri = dir.to_str().unwrap();
+        let options = StorageOptions::new();
+        let columns = clustering_columns(uri, 2, &options).await.unwrap();
+        assert_eq!(columns, Some(vec!["date".to_string()]));
+
+        std::fs::remove_file(log.join(format!("{:020}.json", 0))).unwrap();
+        assert_eq!(clustering_columns(uri, 2, &options).await.unwrap(), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,369 @@
+use super::{CompressionType, FileKind, NameLayout, ParquetDeltaFile};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::convert::TryFrom;
+use std::fmt;
+use uuid::Uuid;
+
+/// translates between file names and their compact `ParquetDeltaFile` representation.
+///
+/// the default `SparkFileNameCodec` understands the names written by spark; writers with other
+/// naming conventions can supply their own codec to `DeltaTree::from_paths_with_codec` and
+/// `DeltaTree::files_with_codec`. `encode` must reproduce the exact name passed to `decode`.
+pub trait FileNameCodec {
+    fn decode(&self, name: &str) -> Option<ParquetDeltaFile>;
+    fn encode(&self, file: &ParquetDeltaFile) -> String;
+}
+
+/// file names of the form `part-00007-<uuid>.c000.snappy.parquet`, including the variants
+/// described by `NameLayout` and change data files named `cdc-00007-<uuid>...`. names are
+/// parsed by hand, falling back to `RegexFileNameCodec` for anything unusual.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SparkFileNameCodec;
+
+/// the same names as `SparkFileNameCodec`, parsed using a regular expression only. this is
+/// the reference implementation, it's considerably slower for large tables.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RegexFileNameCodec;
+
+lazy_static! {
+    static ref FILENAME_REGEX: Regex = Regex::new(
+        "^(?P<kind>part|cdc)-(?P<part>\\d{5})-(tid-(?P<tid>\\d+)-)?\
+                (?P<uuid>[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-\
+                [0-9a-fA-F]{4}-[0-9a-fA-F]{12})(-(?P<task>\\d+)-(?P<attempt>\\d+))?\
+                ((?P<sep>[.-])c(?P<c>\\d{3}))?\
+                (\\.(?P<compression>(snappy|gzip|none)))?\\.parquet$"
+    )
+    .unwrap();
+}
+
+impl FileNameCodec for SparkFileNameCodec {
+    fn decode(&self, name: &str) -> Option<ParquetDeltaFile> {
+        parse_name(name.as_bytes()).or_else(|| RegexFileNameCodec.decode(name))
+    }
+
+    fn encode(&self, file: &ParquetDeltaFile) -> String {
+        RegexFileNameCodec.encode(file)
+    }
+}
+
+impl FileNameCodec for RegexFileNameCodec {
+    fn decode(&self, name: &str) -> Option<ParquetDeltaFile> {
+        let caps = FILENAME_REGEX.captures(name)?;
+        let partition = caps["part"]
+            .parse::<u32>()
+            .unwrap_or_else(|_err| <u32>::max_value());
+        let uuid = Uuid::parse_str(&caps["uuid"]).unwrap();
+        let cluster = match caps.name("c") {
+            Some(c) => Some(c.as_str().parse().ok()?),
+            None => None,
+        };
+        let compression = caps
+            .name("compression")
+            .map(|compression| CompressionType::from_str(compression.as_str()));
+        let separator = caps.name("sep").map(|sep| sep.as_str());
+        let layout = match (caps.name("tid"), caps.name("task"), caps.name("attempt")) {
+            (Some(tid), Some(task), Some(attempt)) if separator != Some(".") => NameLayout::Task {
+                tid: tid.as_str().parse().ok()?,
+                task: task.as_str().parse().ok()?,
+                attempt: attempt.as_str().parse().ok()?,
+            },
+            (None, None, None) if separator == Some("-") => NameLayout::Dashed,
+            (None, None, None) => NameLayout::Dotted,
+            _ => return None,
+        };
+
+        let kind = match &caps["kind"] {
+            "cdc" => FileKind::ChangeData,
+            _ => FileKind::Data,
+        };
+
+        Some(ParquetDeltaFile {
+            partition,
+            uuid,
+            cluster,
+            compression,
+            layout,
+            kind,
+        })
+    }
+
+    fn encode(&self, file: &ParquetDeltaFile) -> String {
+        file.to_string()
+    }
+}
+
+/// the spark name of the file, written piecewise to the formatter without allocating.
+impl fmt::Display for ParquetDeltaFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            FileKind::ChangeData => "cdc",
+            _ => "part",
+        };
+        write!(f, "{}-{:05}-", kind, self.partition)?;
+        let separator = match self.layout {
+            NameLayout::Dotted => {
+                write!(f, "{}", self.uuid)?;
+                '.'
+            }
+            NameLayout::Dashed => {
+                write!(f, "{}", self.uuid)?;
+                '-'
+            }
+            NameLayout::Task { tid, task, attempt } => {
+                write!(f, "tid-{}-{}-{}-{}", tid, self.uuid, task, attempt)?;
+                '-'
+            }
+        };
+        if let Some(cluster) = self.cluster {
+            write!(f, "{}c{:03}", separator, cluster)?;
+        }
+        if let Some(compression) = self.compression {
+            write!(f, ".{}", compression.to_string())?;
+        }
+        f.write_str(".parquet")
+    }
+}
+
+/// a cursor over the bytes of a file name.
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    fn literal(&mut self, literal: &[u8]) -> bool {
+        let matches = self.rest().starts_with(literal);
+        if matches {
+            self.pos += literal.len();
+        }
+        matches
+    }
+
+    /// exactly `count` digits, or as many as there are if `count` is `None`.
+    fn number(&mut self, count: Option<usize>) -> Option<u64> {
+        let digits = self
+            .rest()
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        let digits = match count {
+            Some(count) if digits >= count => count,
+            None if digits > 0 && digits <= 19 => digits,
+            _ => return None,
+        };
+        let value = self.rest()[..digits]
+            .iter()
+            .fold(0u64, |acc, b| acc * 10 + u64::from(b - b'0'));
+        self.pos += digits;
+        Some(value)
+    }
+
+    fn uuid(&mut self) -> Option<Uuid> {
+        let bytes = self.rest().get(..36)?;
+        let mut value = 0u128;
+        for (idx, &b) in bytes.iter().enumerate() {
+            if idx == 8 || idx == 13 || idx == 18 || idx == 23 {
+                if b != b'-' {
+                    return None;
+                }
+                continue;
+            }
+            let digit = match b {
+                b'0'..=b'9' => b - b'0',
+                b'a'..=b'f' => b - b'a' + 10,
+                b'A'..=b'F' => b - b'A' + 10,
+                _ => return None,
+            };
+            value = value << 4 | u128::from(digit);
+        }
+        self.pos += 36;
+        Some(Uuid::from_u128(value))
+    }
+}
+
+/// hand-rolled equivalent of `FILENAME_REGEX`. returns `None` for anything it doesn't
+/// understand completely, including trailing characters after `.parquet`.
+fn parse_name(name: &[u8]) -> Option<ParquetDeltaFile> {
+    let mut scanner = Scanner {
+        bytes: name,
+        pos: 0,
+    };
+    let kind = if scanner.literal(b"part-") {
+        FileKind::Data
+    } else if scanner.literal(b"cdc-") {
+        FileKind::ChangeData
+    } else {
+        return None;
+    };
+    let partition = scanner.number(Some(5))? as u32;
+    if !scanner.literal(b"-") {
+        return None;
+    }
+    let tid = if scanner.literal(b"tid-") {
+        let tid = scanner.number(None)?;
+        if !scanner.literal(b"-") {
+            return None;
+        }
+        Some(tid)
+    } else {
+        None
+    };
+    let uuid = scanner.uuid()?;
+    let layout = match tid {
+        Some(tid) => {
+            if !scanner.literal(b"-") {
+                return None;
+            }
+            let task = scanner.number(None)?;
+            if !scanner.literal(b"-") {
+                return None;
+            }
+            let attempt = scanner.number(None)?;
+            NameLayout::Task {
+                tid,
+                task: u32::try_from(task).ok()?,
+                attempt: u32::try_from(attempt).ok()?,
+            }
+        }
+        None => NameLayout::Dotted,
+    };
+    let (cluster, layout) = match (scanner.peek(), scanner.bytes.get(scanner.pos + 1)) {
+        (Some(separator @ b'.'), Some(b'c')) | (Some(separator @ b'-'), Some(b'c')) => {
+            scanner.pos += 2;
+            let cluster = u8::try_from(scanner.number(Some(3))?).ok()?;
+            let layout = match (layout, separator) {
+                (NameLayout::Task { .. }, b'.') => return None,
+                (NameLayout::Dotted, b'-') => NameLayout::Dashed,
+                (layout, _) => layout,
+            };
+            (Some(cluster), layout)
+        }
+        _ => (None, layout),
+    };
+    let compression = if scanner.literal(b".snappy") {
+        Some(CompressionType::SNAPPY)
+    } else if scanner.literal(b".gzip") {
+        Some(CompressionType::GZIP)
+    } else if scanner.literal(b".none") {
+        Some(CompressionType::NONE)
+    } else {
+        None
+    };
+    if !scanner.literal(b".parquet") || scanner.peek().is_some() {
+        return None;
+    }
+    Some(ParquetDeltaFile {
+        partition,
+        uuid,
+        cluster,
+        compression,
+        layout,
+        kind,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_regex_filename() {
+        let name = "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.c003.snappy.parquet";
+        let caps = FILENAME_REGEX.captures(name).unwrap();
+        assert_eq!(&caps["part"], "00009");
+        assert_eq!(&caps["uuid"], "477077ae-1429-4633-b07a-0c0cb75caf55");
+        assert_eq!(&caps["c"], "003");
+        assert_eq!(&caps["compression"], "snappy");
+    }
+
+    #[test]
+    fn spark_codec_layouts_round_trip() {
+        let names = [
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.c003.snappy.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55-c003.snappy.parquet",
+            "part-00000-tid-3166393358236446939-477077ae-1429-4633-b07a-0c0cb75caf55-20-1-c000.gzip.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.snappy.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55-c003.parquet",
+            "part-00000-tid-3166393358236446939-477077ae-1429-4633-b07a-0c0cb75caf55-20-1.snappy.parquet",
+            "cdc-00000-477077ae-1429-4633-b07a-0c0cb75caf55.c000.snappy.parquet",
+        ];
+        for name in names.iter() {
+            let file = SparkFileNameCodec.decode(name).unwrap();
+            assert_eq!(&SparkFileNameCodec.encode(&file), name);
+        }
+
+        let file = SparkFileNameCodec.decode(names[2]).unwrap();
+        assert_eq!(
+            file.layout(),
+            NameLayout::Task {
+                tid: 3166393358236446939,
+                task: 20,
+                attempt: 1
+            }
+        );
+        assert_eq!(file.partition(), 0);
+        assert_eq!(file.compression(), Some(CompressionType::GZIP));
+    }
+
+    #[test]
+    fn hand_rolled_parser_matches_regex() {
+        let names = [
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.c003.snappy.parquet",
+            "part-00009-477077AE-1429-4633-B07A-0C0CB75CAF55.c003.snappy.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55-c003.none.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.gzip.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.parquet",
+            "part-00000-tid-3166393358236446939-477077ae-1429-4633-b07a-0c0cb75caf55-20-1-c000.snappy.parquet",
+            "part-00000-tid-3166393358236446939-477077ae-1429-4633-b07a-0c0cb75caf55-20-1.parquet",
+        ];
+        for name in names.iter() {
+            assert!(parse_name(name.as_bytes()).is_some(), "{}", name);
+            assert_eq!(
+                parse_name(name.as_bytes()),
+                RegexFileNameCodec.decode(name),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn hand_rolled_parser_rejects_odd_names() {
+        let names = [
+            "part-0009-477077ae-1429-4633-b07a-0c0cb75caf55.c003.snappy.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf5.c003.snappy.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.c03.snappy.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.c003.lz4.parquet",
+            "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.c003.snappy.parquet.crc",
+            "part-00000-tid-3-477077ae-1429-4633-b07a-0c0cb75caf55-20-1.c000.snappy.parquet",
+        ];
+        for name in names.iter() {
+            assert_eq!(parse_name(name.as_bytes()), None, "{}", name);
+        }
+        // neither does the regex fallback accept names with a suffix it couldn't encode.
+        assert_eq!(
+            SparkFileNameCodec
+                .decode("part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.c003.snappy.parquet.crc"),
+            None
+        );
+    }
+
+    #[test]
+    fn spark_codec_rejects_foreign_names() {
+        assert_eq!(SparkFileNameCodec.decode("data.csv"), None);
+        assert_eq!(
+            SparkFileNameCodec.decode("477077ae-1429-4633-b07a-0c0cb75caf55.parquet"),
+            None
+        );
+    }
+}
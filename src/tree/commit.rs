@@ -0,0 +1,321 @@
+use super::diff::TreeDiff;
+use super::options::unescape;
+use super::sized::SizedDeltaFile;
+use super::{stats, DeltaTree, PartitionValue};
+use deltalake::action::{Action, Add, Remove, Txn};
+use deltalake::{DeltaDataTypeVersion, DeltaTable, DeltaTableError, DeltaTransactionError};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// the remove and add actions of a commit, e.g. turning one tree of a table into another.
+/// with an application transaction, committing is idempotent: a commit whose application id
+/// already reached its version is skipped, so a layout job can safely be retried.
+#[derive(Debug, Clone)]
+pub struct CommitBuilder {
+    removes: Vec<Remove>,
+    adds: Vec<Add>,
+    txn: Option<(String, DeltaDataTypeVersion)>,
+    read_version: Option<DeltaDataTypeVersion>,
+    data_change: bool,
+    timestamp: i64,
+}
+
+#[derive(Debug)]
+pub enum CommitError {
+    Transaction(DeltaTransactionError),
+    /// a file removed by the commit was already removed by `version` of the table, after the
+    /// version the commit was planned on.
+    Conflict {
+        path: String,
+        version: DeltaDataTypeVersion,
+    },
+}
+
+impl fmt::Display for CommitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommitError::Transaction(err) => write!(f, "can't commit: {}", err),
+            CommitError::Conflict { path, version } => {
+                write!(f, "{} was already removed by version {}", path, version)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommitError {}
+
+impl From<DeltaTransactionError> for CommitError {
+    fn from(err: DeltaTransactionError) -> CommitError {
+        CommitError::Transaction(err)
+    }
+}
+
+impl From<DeltaTableError> for CommitError {
+    fn from(err: DeltaTableError) -> CommitError {
+        CommitError::Transaction(err.into())
+    }
+}
+
+impl Default for CommitBuilder {
+    fn default() -> CommitBuilder {
+        CommitBuilder::new()
+    }
+}
+
+impl CommitBuilder {
+    /// an empty commit. unless changed with `data_change`, it only rearranges data.
+    pub fn new() -> CommitBuilder {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        CommitBuilder {
+            removes: vec![],
+            adds: vec![],
+            txn: None,
+            read_version: None,
+            data_change: false,
+            timestamp,
+        }
+    }
+
+    /// the commit replacing the files only in `old` by the files only in `new`.
+    pub fn from_trees<S>(
+        old: &DeltaTree<SizedDeltaFile, S>,
+        new: &DeltaTree<SizedDeltaFile, S>,
+    ) -> CommitBuilder
+    where
+        S: std::hash::BuildHasher,
+    {
+        CommitBuilder::from_diff(&TreeDiff::new(old, new), old, new)
+    }
+
+    /// the commit of `diff` between `old` and `new`, which provide the sizes of the files.
+    pub fn from_diff<S>(
+        diff: &TreeDiff,
+        old: &DeltaTree<SizedDeltaFile, S>,
+        new: &DeltaTree<SizedDeltaFile, S>,
+    ) -> CommitBuilder {
+        let old_files = files_by_path(old);
+        let new_files = files_by_path(new);
+        let mut commit = CommitBuilder::new();
+        for path in &diff.removed {
+            let file = old_files
+                .get(path.as_str())
+                .unwrap_or_else(|| panic!("removed file '{}' not in the old tree", path));
+            commit = commit.remove(path, file.size);
+        }
+        for path in &diff.added {
+            let file = new_files
+                .get(path.as_str())
+                .unwrap_or_else(|| panic!("added file '{}' not in the new tree", path));
+            commit = commit.add(path, file.size, file.modification_time);
+        }
+        commit
+    }
+
+    /// remove the file at `path`, relative to the table root.
+    pub fn remove(mut self, path: &str, size: u64) -> CommitBuilder {
+        self.removes.push(Remove {
+            path: path.to_string(),
+            deletion_timestamp: Some(self.timestamp),
+            data_change: self.data_change,
+            extended_file_metadata: Some(true),
+            partition_values: Some(partition_values(path)),
+            size: Some(size as i64),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// add the file at `path`, relative to the table root.
+    pub fn add(mut self, path: &str, size: u64, modification_time: i64) -> CommitBuilder {
+        self.adds.push(Add {
+            path: path.to_string(),
+            size: size as i64,
+            partition_values: partition_values(path),
+            modification_time,
+            data_change: self.data_change,
+            ..Default::default()
+        });
+        self
+    }
+
+    /// whether the commit changes the data of the table rather than just its layout, for
+    /// all files added or removed so far and later.
+    pub fn data_change(mut self, data_change: bool) -> CommitBuilder {
+        self.data_change = data_change;
+        self.removes
+            .iter_mut()
+            .for_each(|r| r.data_change = data_change);
+        self.adds
+            .iter_mut()
+            .for_each(|a| a.data_change = data_change);
+        self
+    }
+
+    /// record `version` of the application `app_id` with the commit, and skip it if the
+    /// table already has that version or a later one of the application.
+    pub fn txn(self, app_id: &str, version: DeltaDataTypeVersion) -> CommitBuilder {
+        CommitBuilder {
+            txn: Some((app_id.to_string(), version)),
+            ..self
+        }
+    }
+
+    /// the version of the table the commit was planned on. committing then fails with a
+    /// conflict if a later commit removed one of the files this one removes, and commits
+    /// exactly the version following the one checked.
+    pub fn read_version(self, version: DeltaDataTypeVersion) -> CommitBuilder {
+        CommitBuilder {
+            read_version: Some(version),
+            ..self
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.removes.is_empty() && self.adds.is_empty()
+    }
+
+    /// the actions of the commit: the application transaction, removes, then adds.
+    pub fn actions(&self) -> Vec<Action> {
+        let txn = self.txn.iter().map(|(app_id, version)| {
+            Action::txn(Txn {
+                app_id: app_id.clone(),
+                version: *version,
+                last_updated: Some(self.timestamp),
+            })
+        });
+        txn.chain(self.removes.iter().cloned().map(Action::remove))
+            .chain(self.adds.iter().cloned().map(Action::add))
+            .collect()
+    }
+
+    /// commit to `delta_table`, brought up to date first. `None` if there was nothing to
+    /// commit or the application transaction was already committed.
+    pub async fn commit(
+        self,
+        delta_table: &mut DeltaTable,
+    ) -> Result<Option<DeltaDataTypeVersion>, CommitError> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        delta_table.update().await?;
+        if let Some((app_id, version)) = &self.txn {
+            let committed = delta_table.get_app_transaction_version().get(app_id);
+            if matches!(committed, Some(committed) if committed >= version) {
+                return Ok(None);
+            }
+        }
+        let read_version = match self.read_version {
+            Some(read_version) => read_version,
+            None => {
+                let mut transaction = delta_table.create_transaction(None);
+                transaction.add_actions(self.actions());
+                return Ok(Some(transaction.commit(None, None).await?));
+            }
+        };
+        if delta_table.version != read_version {
+            let active: HashSet<&str> = delta_table
+                .get_active_add_actions()
+                .iter()
+                .map(|add| add.path.as_str())
+                .collect();
+            if let Some(remove) = self
+                .removes
+                .iter()
+                .find(|r| !active.contains(r.path.as_str()))
+            {
+                return Err(CommitError::Conflict {
+                    path: remove.path.clone(),
+                    version: delta_table.version,
+                });
+            }
+        }
+        let version = delta_table.version + 1;
+        let mut transaction = delta_table.create_transaction(None);
+        transaction.add_actions(self.actions());
+        Ok(Some(transaction.commit_version(version, None, None).await?))
+    }
+}
+
+/// the partition values encoded in the directories of `path`, e.g. `date=2021-03-01/`, with
+/// the characters spark escapes in directory names decoded.
+pub fn partition_values(path: &str) -> HashMap<String, Option<String>> {
+    PartitionValue::from_dir(path)
+        .into_iter()
+        .map(|p| {
+            let value = p.value.map(|value| unescape(&value).into_owned());
+            (p.key, value)
+        })
+        .collect()
+}
+
+fn files_by_path<S>(tree: &DeltaTree<SizedDeltaFile, S>) -> HashMap<String, &SizedDeltaFile> {
+    stats::leaves(tree)
+        .into_iter()
+        .flat_map(|(dir, files)| {
+            files
+                .iter()
+                .map(move |f| (format!("{}{}", dir, f.file.name()), f))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::{path, sized_tree};
+    use pretty_assertions::assert_eq;
+
+    fn tree(files: &[(&str, u128, u64)]) -> DeltaTree<SizedDeltaFile> {
+        let entries = files
+            .iter()
+            .map(|(dir, id, size)| (path(dir, *id), *size))
+            .collect();
+        sized_tree(entries).map_files(|file| SizedDeltaFile {
+            modification_time: 7,
+            ..file
+        })
+    }
+
+    #[test]
+    fn commit_of_two_trees() {
+        let old = tree(&[("d=1/", 1, 10), ("d=1/", 2, 20), ("d=2/", 3, 30)]);
+        let new = tree(&[("d=1/", 4, 30), ("d=2/", 3, 30)]);
+        let commit = CommitBuilder::from_trees(&old, &new).txn("compactor", 3);
+        let actions = commit.actions();
+        assert_eq!(actions.len(), 4);
+        match &actions[0] {
+            Action::txn(txn) => assert_eq!((txn.app_id.as_str(), txn.version), ("compactor", 3)),
+            action => panic!("expected a txn, got {:?}", action),
+        }
+        let removed: Vec<_> = actions[1..3]
+            .iter()
+            .map(|action| match action {
+                Action::remove(remove) => (remove.size, remove.data_change),
+                action => panic!("expected a remove, got {:?}", action),
+            })
+            .collect();
+        assert_eq!(removed, vec![(Some(10), false), (Some(20), false)]);
+        match &actions[3] {
+            Action::add(add) => {
+                assert!(add.path.starts_with("d=1/part-00000-"));
+                assert_eq!((add.size, add.modification_time), (30, 7));
+                assert_eq!(add.partition_values, partition_values("d=1/"));
+            }
+            action => panic!("expected an add, got {:?}", action),
+        }
+    }
+
+    #[test]
+    fn values_of_partitions() {
+        let values = partition_values("a=1/b=__HIVE_DEFAULT_PARTITION__/part-00000.parquet");
+        assert_eq!(values.len(), 2);
+        assert_eq!(values["a"], Some("1".to_string()));
+        assert_eq!(values["b"], None);
+        let escaped = partition_values("ts=2021-03-01 10%3A00%3A00/path=a%2Fb/part-00000.parquet");
+        assert_eq!(escaped["ts"], Some("2021-03-01 10:00:00".to_string()));
+        assert_eq!(escaped["path"], Some("a/b".to_string()));
+    }
+}
@@ -0,0 +1,464 @@
+use super::codec::{FileNameCodec, SparkFileNameCodec};
+use super::{raw_dir, DeltaTree, ParquetDeltaFile, TreeNode, NULL_PARTITION_VALUE};
+use std::collections::{HashMap, VecDeque};
+
+/// an experimental, immutable alternative to `DeltaTree`. the tree structure is serialized
+/// level by level into a LOUDS bit vector (every node writes a `1` per child, followed by a
+/// `0`), children are found via rank / select on that vector instead of following pointers.
+/// partition values, column names and files live in flat arrays indexed by node rank, so the
+/// whole tree consists of a handful of allocations, independent of the number of partitions.
+#[derive(Debug)]
+pub struct CompactDeltaTree {
+    pub prefix: String,
+    louds: BitVector,
+    /// whether a node is a leaf holding files.
+    leaves: BitVector,
+    /// the partition value of every node but the root, in level order. siblings are sorted.
+    label_bytes: Vec<u8>,
+    label_offsets: Vec<u32>,
+    nulls: BitVector,
+    /// the partition column of every inner node, as an index into `columns`.
+    columns: Vec<String>,
+    node_columns: Vec<u16>,
+    /// the files of every leaf, `files[file_offsets[leaf]..file_offsets[leaf + 1]]`.
+    files: Vec<ParquetDeltaFile>,
+    file_offsets: Vec<u32>,
+    /// the directories in storage of files with changed partition values, see
+    /// `DeltaTree::raw_dirs`.
+    raw_dirs: HashMap<ParquetDeltaFile, String>,
+}
+
+impl CompactDeltaTree {
+    pub fn new(delta_table: &deltalake::DeltaTable) -> CompactDeltaTree {
+        CompactDeltaTree::from_tree(&DeltaTree::new(delta_table))
+    }
+
+    pub fn from_paths<I>(input_files: I) -> CompactDeltaTree
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        CompactDeltaTree::from_tree(&DeltaTree::from_paths(input_files))
+    }
+
+    pub fn from_tree<F: AsRef<ParquetDeltaFile>, S>(tree: &DeltaTree<F, S>) -> CompactDeltaTree {
+        let mut louds = BitVectorBuilder::default();
+        let mut leaves = BitVectorBuilder::default();
+        let mut nulls = BitVectorBuilder::default();
+        let mut compact = CompactDeltaTree {
+            prefix: tree.prefix.clone(),
+            louds: BitVector::default(),
+            leaves: BitVector::default(),
+            label_bytes: vec![],
+            label_offsets: vec![0],
+            nulls: BitVector::default(),
+            columns: vec![],
+            node_columns: vec![],
+            files: vec![],
+            file_offsets: vec![0],
+            raw_dirs: tree.raw_dirs.clone(),
+        };
+        let mut queue = VecDeque::new();
+        queue.push_back(&tree.root);
+        while let Some(node) = queue.pop_front() {
+            match node {
+                TreeNode::FileEntries { files } => {
+                    leaves.push(true);
+                    compact.files.extend(files.iter().map(|f| *f.as_ref()));
+                    compact.file_offsets.push(compact.files.len() as u32);
+                }
+                TreeNode::Partition { name, values } => {
+                    leaves.push(false);
+                    let column = match compact.columns.iter().position(|c| c == name) {
+                        Some(idx) => idx,
+                        None => {
+                            compact.columns.push(name.clone());
+                            compact.columns.len() - 1
+                        }
+                    };
+                    compact.node_columns.push(column as u16);
+                    let mut children: Vec<_> = values.iter().collect();
+                    children.sort_by_key(|(value, _)| *value);
+                    for (value, child) in children {
+                        louds.push(true);
+                        nulls.push(value.is_none());
+                        if let Some(value) = value {
+                            compact.label_bytes.extend_from_slice(value.as_bytes());
+                        }
+                        compact.label_offsets.push(compact.label_bytes.len() as u32);
+                        queue.push_back(child);
+                    }
+                }
+            }
+            louds.push(false);
+        }
+        compact.louds = louds.build();
+        compact.leaves = leaves.build();
+        compact.nulls = nulls.build();
+        compact.label_bytes.shrink_to_fit();
+        compact.label_offsets.shrink_to_fit();
+        compact.node_columns.shrink_to_fit();
+        compact.files.shrink_to_fit();
+        compact.file_offsets.shrink_to_fit();
+        compact
+    }
+
+    /// all files, relative to `prefix`.
+    pub fn files(&self) -> Vec<String> {
+        self.files_with_codec(&SparkFileNameCodec)
+    }
+
+    pub fn files_with_codec<C: FileNameCodec>(&self, codec: &C) -> Vec<String> {
+        let mut result = Vec::with_capacity(self.files.len());
+        self.collect_files(0, "", codec, &mut result);
+        result
+    }
+
+    /// all files including the common prefix.
+    pub fn files_with_prefix(&self) -> Vec<String> {
+        self.files()
+            .into_iter()
+            .map(|f| format!("{}{}", self.prefix, f))
+            .collect()
+    }
+
+    /// the files below the partition described by `values`, one value per level starting at
+    /// the root. `None` if there's no such partition.
+    pub fn partition_files(&self, values: &[Option<&str>]) -> Option<Vec<ParquetDeltaFile>> {
+        let mut node = 0;
+        for value in values {
+            node = self.child(node, *value)?;
+        }
+        let mut result = vec![];
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if self.leaves.get(node) {
+                result.extend_from_slice(self.leaf_files(node));
+            } else {
+                stack.extend(self.children(node));
+            }
+        }
+        Some(result)
+    }
+
+    /// bytes allocated on the heap.
+    pub fn heap_size(&self) -> usize {
+        self.prefix.capacity()
+            + self.louds.heap_size()
+            + self.leaves.heap_size()
+            + self.nulls.heap_size()
+            + self.label_bytes.capacity()
+            + std::mem::size_of::<u32>() * self.label_offsets.capacity()
+            + self.columns.iter().map(|c| c.capacity()).sum::<usize>()
+            + std::mem::size_of::<u16>() * self.node_columns.capacity()
+            + std::mem::size_of::<ParquetDeltaFile>() * self.files.capacity()
+            + std::mem::size_of::<u32>() * self.file_offsets.capacity()
+            + self.raw_dirs.values().map(|d| d.capacity()).sum::<usize>()
+    }
+
+    fn collect_files<C: FileNameCodec>(
+        &self,
+        node: usize,
+        prefix: &str,
+        codec: &C,
+        result: &mut Vec<String>,
+    ) {
+        if self.leaves.get(node) {
+            result.extend(
+                self.leaf_files(node)
+                    .iter()
+                    .map(|f| format!("{}{}", raw_dir(&self.raw_dirs, f, prefix), codec.encode(f))),
+            );
+        } else {
+            let name = &self.columns[self.node_columns[self.leaves.rank0(node)] as usize];
+            for child in self.children(node) {
+                let sub_prefix = format!(
+                    "{}{}={}/",
+                    prefix,
+                    name,
+                    self.label(child).unwrap_or(NULL_PARTITION_VALUE)
+                );
+                self.collect_files(child, &sub_prefix, codec, result);
+            }
+        }
+    }
+
+    /// the children of `node`, in level order node numbers. the root is node 0, its `k`th
+    /// child node `k + 1` and so on.
+    fn children(&self, node: usize) -> std::ops::Range<usize> {
+        let start = if node == 0 {
+            0
+        } else {
+            self.louds.select0(node - 1) + 1
+        };
+        let end = self.louds.select0(node);
+        let first = self.louds.rank1(start) + 1;
+        first..first + (end - start)
+    }
+
+    /// binary search the sorted siblings.
+    fn child(&self, node: usize, value: Option<&str>) -> Option<usize> {
+        let children = self.children(node);
+        let (mut low, mut high) = (children.start, children.end);
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.label(mid).cmp(&value) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    fn label(&self, node: usize) -> Option<&str> {
+        if self.nulls.get(node - 1) {
+            return None;
+        }
+        let start = self.label_offsets[node - 1] as usize;
+        let end = self.label_offsets[node] as usize;
+        Some(std::str::from_utf8(&self.label_bytes[start..end]).unwrap())
+    }
+
+    fn leaf_files(&self, node: usize) -> &[ParquetDeltaFile] {
+        let leaf = self.leaves.rank1(node);
+        &self.files[self.file_offsets[leaf] as usize..self.file_offsets[leaf + 1] as usize]
+    }
+}
+
+/// number of 64 bit words covered by a single rank sample.
+const WORDS_PER_BLOCK: usize = 8;
+
+/// an immutable bit vector with sampled ranks: the number of ones in front of every block of
+/// 512 bits, rank and select scan at most a single block.
+#[derive(Debug, Default)]
+struct BitVector {
+    len: usize,
+    words: Vec<u64>,
+    ranks: Vec<u32>,
+}
+
+impl BitVector {
+    fn get(&self, pos: usize) -> bool {
+        assert!(pos < self.len, "bit {} out of bounds ({})", pos, self.len);
+        self.words[pos / 64] & (1 << (pos % 64)) != 0
+    }
+
+    /// the number of ones in front of `pos`.
+    fn rank1(&self, pos: usize) -> usize {
+        let word = pos / 64;
+        let block = word / WORDS_PER_BLOCK;
+        let mut rank = self.ranks[block] as usize;
+        for w in &self.words[block * WORDS_PER_BLOCK..word] {
+            rank += w.count_ones() as usize;
+        }
+        let bit = pos % 64;
+        if bit != 0 {
+            rank += (self.words[word] & ((1 << bit) - 1)).count_ones() as usize;
+        }
+        rank
+    }
+
+    fn rank0(&self, pos: usize) -> usize {
+        pos - self.rank1(pos)
+    }
+
+    /// the position of the `k`th zero, starting at 0.
+    fn select0(&self, k: usize) -> usize {
+        let zeros_before = |block: usize| block * WORDS_PER_BLOCK * 64 - self.ranks[block] as usize;
+        // the last block with at most `k` zeros in front of it.
+        let (mut low, mut high) = (0, self.ranks.len());
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if zeros_before(mid) <= k {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        let mut remaining = k - zeros_before(low);
+        for (idx, word) in self.words.iter().enumerate().skip(low * WORDS_PER_BLOCK) {
+            let zeros = word.count_zeros() as usize;
+            if remaining < zeros {
+                let mut word = !word;
+                for _ in 0..remaining {
+                    word &= word - 1;
+                }
+                let pos = idx * 64 + word.trailing_zeros() as usize;
+                assert!(pos < self.len, "select0({}) out of bounds", k);
+                return pos;
+            }
+            remaining -= zeros;
+        }
+        panic!("select0({}) out of bounds", k)
+    }
+
+    fn heap_size(&self) -> usize {
+        std::mem::size_of::<u64>() * self.words.capacity()
+            + std::mem::size_of::<u32>() * self.ranks.capacity()
+    }
+}
+
+#[derive(Debug, Default)]
+struct BitVectorBuilder {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl BitVectorBuilder {
+    fn push(&mut self, bit: bool) {
+        let offset = self.len % 64;
+        if offset == 0 {
+            self.words.push(0);
+        }
+        if bit {
+            *self.words.last_mut().unwrap() |= 1 << offset;
+        }
+        self.len += 1;
+    }
+
+    fn build(mut self) -> BitVector {
+        // an extra word keeps `rank1(len)` in bounds.
+        self.words.push(0);
+        self.words.shrink_to_fit();
+        let ranks = self
+            .words
+            .chunks(WORDS_PER_BLOCK)
+            .scan(0, |rank, block| {
+                let before = *rank;
+                *rank += block.iter().map(|w| w.count_ones()).sum::<u32>();
+                Some(before)
+            })
+            .collect();
+        BitVector {
+            len: self.len,
+            words: self.words,
+            ranks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::canonical::PartitionType;
+    use crate::tree::fixtures;
+    use pretty_assertions::assert_eq;
+
+    fn path(date: &str, country: &str, idx: u128) -> String {
+        fixtures::path(&format!("date={}/country={}/", date, country), idx)
+    }
+
+    fn sorted(mut files: Vec<String>) -> Vec<String> {
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn compact_tree_files() {
+        let paths = vec![
+            path("2021-03-01", "de", 0),
+            path("2021-03-01", NULL_PARTITION_VALUE, 1),
+            path("2021-03-02", "de", 2),
+            path("2021-03-02", "de", 3),
+            path("2021-03-02", "fr", 4),
+        ];
+        let compact = CompactDeltaTree::from_paths(&paths);
+        assert_eq!(sorted(compact.files()), sorted(paths));
+        assert_eq!(
+            compact
+                .partition_files(&[Some("2021-03-02"), Some("de")])
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            compact
+                .partition_files(&[Some("2021-03-02")])
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            compact
+                .partition_files(&[Some("2021-03-01"), None])
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(compact.partition_files(&[Some("2021-03-03")]), None);
+        assert_eq!(compact.partition_files(&[]).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn compact_tree_spanning_many_blocks() {
+        let paths: Vec<String> = (0..3000)
+            .map(|idx| {
+                path(
+                    &format!("2021-{:02}-{:02}", idx % 12 + 1, idx % 28 + 1),
+                    &format!("c{}", idx % 97),
+                    idx as u128,
+                )
+            })
+            .collect();
+        let compact = CompactDeltaTree::from_paths(&paths);
+        assert_eq!(sorted(compact.files()), sorted(paths.clone()));
+        assert_eq!(
+            compact
+                .partition_files(&[Some("2021-01-01"), Some("c0")])
+                .unwrap()
+                .len(),
+            paths
+                .iter()
+                .filter(|p| p.starts_with("date=2021-01-01/country=c0/"))
+                .count()
+        );
+    }
+
+    #[test]
+    fn canonical_compact_tree() {
+        let paths = vec![path("2021-3-1", "de", 0), path("2021-03-01", "de", 1)];
+        let types = vec![("date".to_string(), PartitionType::Date)]
+            .into_iter()
+            .collect();
+        let tree = DeltaTree::from_paths_canonical(&paths, &types);
+        let compact = CompactDeltaTree::from_tree(&tree);
+        assert_eq!(sorted(compact.files()), sorted(paths));
+        assert_eq!(
+            compact
+                .partition_files(&[Some("2021-03-01"), Some("de")])
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn unpartitioned_compact_tree() {
+        let paths = vec![fixtures::path("", 7)];
+        let compact = CompactDeltaTree::from_paths(&paths);
+        assert_eq!(compact.files(), paths);
+        assert_eq!(compact.partition_files(&[]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn bit_vector_rank_select() {
+        let mut builder = BitVectorBuilder::default();
+        let bits: Vec<bool> = (0..2000).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+        bits.iter().for_each(|b| builder.push(*b));
+        let vector = builder.build();
+        let mut ones = 0;
+        let mut zeros = 0;
+        for (pos, bit) in bits.iter().enumerate() {
+            assert_eq!(vector.get(pos), *bit);
+            assert_eq!(vector.rank1(pos), ones);
+            if *bit {
+                ones += 1;
+            } else {
+                assert_eq!(vector.select0(zeros), pos);
+                zeros += 1;
+            }
+        }
+        assert_eq!(vector.rank1(bits.len()), ones);
+    }
+}
@@ -0,0 +1,116 @@
+use super::sized::SizedDeltaFile;
+use super::stats;
+use super::DeltaTree;
+use serde::Serialize;
+
+/// how the small files of a table could be rewritten into files of about `target_size` bytes.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct CompactionPlan {
+    pub target_size: u64,
+    /// the files to be rewritten, their bytes, and the number of files written instead.
+    pub files: usize,
+    pub bytes: u64,
+    pub output_files: usize,
+    /// the leaf partitions needing compaction, sorted by path.
+    pub partitions: Vec<PartitionCompaction>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PartitionCompaction {
+    /// the directory of the partition, e.g. `date=2021-03-01/`.
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+    /// the files to be merged, one output file per group.
+    pub groups: Vec<FileGroup>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct FileGroup {
+    /// file names within the partition, largest first.
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+impl CompactionPlan {
+    /// group the files smaller than `target_size` of each partition into as few groups of at
+    /// most `target_size` bytes as the first fit decreasing heuristic finds. a group of a
+    /// single file would just rewrite it, so such groups are dropped.
+    pub fn new<S>(tree: &DeltaTree<SizedDeltaFile, S>, target_size: u64) -> CompactionPlan {
+        assert!(target_size > 0, "target size must be positive");
+        let mut plan = CompactionPlan {
+            target_size,
+            ..Default::default()
+        };
+        for (path, files) in stats::leaves(tree) {
+            let mut small: Vec<&SizedDeltaFile> =
+                files.iter().filter(|f| f.size < target_size).collect();
+            small.sort_by_key(|f| std::cmp::Reverse(f.size));
+            let mut groups: Vec<FileGroup> = vec![];
+            for file in small {
+                match groups
+                    .iter_mut()
+                    .find(|g| g.bytes + file.size <= target_size)
+                {
+                    Some(group) => {
+                        group.files.push(file.file.name());
+                        group.bytes += file.size;
+                    }
+                    None => groups.push(FileGroup {
+                        files: vec![file.file.name()],
+                        bytes: file.size,
+                    }),
+                }
+            }
+            groups.retain(|g| g.files.len() > 1);
+            if groups.is_empty() {
+                continue;
+            }
+            let partition = PartitionCompaction {
+                path,
+                files: groups.iter().map(|g| g.files.len()).sum(),
+                bytes: groups.iter().map(|g| g.bytes).sum(),
+                groups,
+            };
+            plan.files += partition.files;
+            plan.bytes += partition.bytes;
+            plan.output_files += partition.groups.len();
+            plan.partitions.push(partition);
+        }
+        plan.partitions.sort_by(|a, b| a.path.cmp(&b.path));
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::{path, sized_tree};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn plan_compaction() {
+        let entries = [
+            ("d=1/", 1, 60),
+            ("d=1/", 2, 50),
+            ("d=1/", 3, 40),
+            ("d=1/", 4, 30),
+            ("d=1/", 5, 200),
+            ("d=2/", 6, 10),
+            ("d=3/", 7, 10),
+            ("d=3/", 8, 20),
+        ]
+        .iter()
+        .map(|(dir, id, size)| (path(dir, *id), *size))
+        .collect();
+        let tree = sized_tree(entries);
+        let plan = CompactionPlan::new(&tree, 100);
+        let paths: Vec<_> = plan.partitions.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(paths, vec!["d=1/", "d=3/"]);
+        let sizes: Vec<_> = plan.partitions[0].groups.iter().map(|g| g.bytes).collect();
+        assert_eq!(sizes, vec![100, 80]);
+        assert_eq!(plan.files, 6);
+        assert_eq!(plan.bytes, 210);
+        assert_eq!(plan.output_files, 3);
+    }
+}
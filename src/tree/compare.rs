@@ -0,0 +1,176 @@
+use super::sized::SizedDeltaFile;
+use super::stats::{self, PartitionSize, TableStats};
+use super::DeltaTree;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// two tables side by side, e.g. a table and its backfill copy.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct TableComparison {
+    pub left: TableStats,
+    pub right: TableStats,
+    pub left_sizes: FileSizes,
+    pub right_sizes: FileSizes,
+    /// the leaf partitions whose files or bytes differ, including those of only one table.
+    pub partitions: Vec<PartitionComparison>,
+}
+
+/// the distribution of file sizes in bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize)]
+pub struct FileSizes {
+    pub min: u64,
+    pub p10: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PartitionComparison {
+    pub path: String,
+    pub left: Option<PartitionSize>,
+    pub right: Option<PartitionSize>,
+}
+
+impl TableComparison {
+    pub fn new<S>(
+        left: &DeltaTree<SizedDeltaFile, S>,
+        right: &DeltaTree<SizedDeltaFile, S>,
+        small_file_threshold: u64,
+        top: usize,
+    ) -> TableComparison {
+        let left_partitions = partition_sizes(left);
+        let mut right_partitions = partition_sizes(right);
+        let mut partitions = vec![];
+        for (path, l) in left_partitions {
+            let r = right_partitions.remove(&path);
+            if r.as_ref() != Some(&l) {
+                partitions.push(PartitionComparison {
+                    path,
+                    left: Some(l),
+                    right: r,
+                });
+            }
+        }
+        partitions.extend(
+            right_partitions
+                .into_iter()
+                .map(|(path, r)| PartitionComparison {
+                    path,
+                    left: None,
+                    right: Some(r),
+                }),
+        );
+        partitions.sort_by(|a, b| a.path.cmp(&b.path));
+
+        TableComparison {
+            left: TableStats::new(left, small_file_threshold, top),
+            right: TableStats::new(right, small_file_threshold, top),
+            left_sizes: FileSizes::new(left),
+            right_sizes: FileSizes::new(right),
+            partitions,
+        }
+    }
+
+    /// whether both tables have the same partitions with the same number of files and bytes.
+    pub fn is_same_layout(&self) -> bool {
+        self.partitions.is_empty()
+    }
+}
+
+impl FileSizes {
+    pub fn new<S>(tree: &DeltaTree<SizedDeltaFile, S>) -> FileSizes {
+        let mut sizes: Vec<u64> = stats::leaves(tree)
+            .iter()
+            .flat_map(|(_, files)| files.iter().map(|f| f.size))
+            .collect();
+        if sizes.is_empty() {
+            return FileSizes::default();
+        }
+        sizes.sort_unstable();
+        let quantile = |q: usize| sizes[(sizes.len() - 1) * q / 100];
+        FileSizes {
+            min: sizes[0],
+            p10: quantile(10),
+            p50: quantile(50),
+            p90: quantile(90),
+            max: sizes[sizes.len() - 1],
+        }
+    }
+}
+
+fn partition_sizes<S>(tree: &DeltaTree<SizedDeltaFile, S>) -> BTreeMap<String, PartitionSize> {
+    stats::leaves(tree)
+        .into_iter()
+        .map(|(path, files)| {
+            let size = PartitionSize {
+                path: path.clone(),
+                files: files.len(),
+                bytes: files.iter().map(|f| f.size).sum(),
+            };
+            (path, size)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::{path, sized_tree};
+    use pretty_assertions::assert_eq;
+
+    fn tree(entries: &[(&str, u128, u64)]) -> DeltaTree<SizedDeltaFile> {
+        let entries = entries
+            .iter()
+            .map(|(dir, id, size)| (path(dir, *id), *size))
+            .collect();
+        sized_tree(entries)
+    }
+
+    #[test]
+    fn compare_tables() {
+        let left = tree(&[("d=1/", 1, 10), ("d=1/", 2, 20), ("d=2/", 3, 30)]);
+        let right = tree(&[("d=1/", 4, 30), ("d=2/", 5, 30), ("d=3/", 6, 40)]);
+        let comparison = TableComparison::new(&left, &right, 25, 1);
+        assert_eq!(comparison.left.files, 3);
+        assert_eq!(comparison.right.bytes, 100);
+        assert_eq!(
+            comparison.partitions,
+            vec![
+                PartitionComparison {
+                    path: "d=1/".to_string(),
+                    left: Some(PartitionSize {
+                        path: "d=1/".to_string(),
+                        files: 2,
+                        bytes: 30
+                    }),
+                    right: Some(PartitionSize {
+                        path: "d=1/".to_string(),
+                        files: 1,
+                        bytes: 30
+                    }),
+                },
+                PartitionComparison {
+                    path: "d=3/".to_string(),
+                    left: None,
+                    right: Some(PartitionSize {
+                        path: "d=3/".to_string(),
+                        files: 1,
+                        bytes: 40
+                    }),
+                },
+            ]
+        );
+        assert!(!comparison.is_same_layout());
+        assert_eq!(
+            comparison.left_sizes,
+            FileSizes {
+                min: 10,
+                p10: 10,
+                p50: 20,
+                p90: 20,
+                max: 30
+            }
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use super::history::commit_actions;
+use super::snapshot::last_checkpoint;
+use super::storage::StorageOptions;
+use super::verify::{check_consistency, ConsistencyReport};
+use super::FileKind;
+use deltalake::storage::StorageError;
+use deltalake::{DeltaDataTypeVersion, DeltaTable, DeltaTableError};
+use futures::StreamExt;
+use parquet::errors::ParquetError;
+use parquet::file::reader::FileReader;
+use parquet::file::serialized_reader::SerializedFileReader;
+use parquet::record::{Field, Row};
+use parquet::util::cursor::SliceableCursor;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+
+/// the characters of the Z85 encoding of the uuids in deletion vector descriptors.
+const Z85: &[u8] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+/// the change data files and deletion vectors the log of a table refers to, relative to the
+/// table root unless stored elsewhere, see `log_references`.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct References {
+    pub files: HashSet<String>,
+    /// whether every commit was read. checkpoints don't keep change data files, so they're
+    /// only known from the commits.
+    pub change_data_known: bool,
+    /// whether the deletion vectors of the active and removed files are known, from the
+    /// commits or from the latest checkpoint.
+    pub deletion_vectors_known: bool,
+}
+
+impl References {
+    /// whether the log refers to `path`, `None` if a commit already removed by log cleanup may
+    /// have referred to it.
+    pub fn referenced(&self, path: &str) -> Option<bool> {
+        let known = match FileKind::of(path) {
+            FileKind::ChangeData => self.change_data_known,
+            FileKind::DeletionVector => self.deletion_vectors_known,
+            FileKind::Data => true,
+        };
+        match (self.files.contains(path), known) {
+            (true, _) => Some(true),
+            (false, true) => Some(false),
+            (false, false) => None,
+        }
+    }
+}
+
+/// the change data files and deletion vectors a commit refers to, relative to the table root
+/// unless stored elsewhere. inline deletion vectors have no file.
+pub fn referenced_files(commit: &[u8]) -> Result<Vec<String>, serde_json::Error> {
+    let mut files = vec![];
+    for action in commit_actions(commit) {
+        let action = action?;
+        if let Some(path) = action["cdc"]["path"].as_str() {
+            files.push(path.to_string());
+        }
+        let file = action.get("add").or_else(|| action.get("remove"));
+        let descriptor = file.and_then(|file| file.get("deletionVector"));
+        if let Some(path) = descriptor.and_then(deletion_vector_path) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// the path of the file of a deletion vector descriptor: for storage type `u` a directory
+/// prefix followed by the Z85 encoded uuid of the file, for `p` the absolute path.
+fn deletion_vector_path(descriptor: &Value) -> Option<String> {
+    let path = descriptor["pathOrInlineDv"].as_str()?;
+    match descriptor["storageType"].as_str()? {
+        "u" if path.len() >= 20 && path.is_char_boundary(path.len() - 20) => {
+            let (prefix, encoded) = path.split_at(path.len() - 20);
+            let bytes = z85_decode(encoded)?;
+            let uuid = uuid::Uuid::from_bytes(<[u8; 16]>::try_from(bytes.as_slice()).ok()?);
+            let name = format!("deletion_vector_{}.bin", uuid);
+            Some(match prefix {
+                "" => name,
+                prefix => format!("{}/{}", prefix, name),
+            })
+        }
+        "p" => Some(path.to_string()),
+        _ => None,
+    }
+}
+
+/// decode Z85, five characters to four big endian bytes.
+fn z85_decode(encoded: &str) -> Option<Vec<u8>> {
+    let chunks = encoded.as_bytes().chunks_exact(5);
+    if !chunks.remainder().is_empty() {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(chunks.len() * 4);
+    for chunk in chunks {
+        let mut value: u64 = 0;
+        for c in chunk {
+            value = value * 85 + Z85.iter().position(|z| z == c)? as u64;
+        }
+        bytes.extend_from_slice(&u32::try_from(value).ok()?.to_be_bytes());
+    }
+    Some(bytes)
+}
+
+/// the deletion vectors of the add and remove actions of a checkpoint, or of one part of a
+/// multi-part checkpoint.
+fn checkpoint_references(checkpoint: Vec<u8>) -> Result<Vec<String>, ParquetError> {
+    let reader = SerializedFileReader::new(SliceableCursor::new(checkpoint))?;
+    let mut files = vec![];
+    for row in reader.get_row_iter(None)? {
+        for (column, field) in row.get_column_iter() {
+            let file = match (column.as_str(), field) {
+                ("add", Field::Group(file)) | ("remove", Field::Group(file)) => file,
+                _ => continue,
+            };
+            let descriptor = file
+                .get_column_iter()
+                .find_map(|(column, field)| match field {
+                    Field::Group(descriptor) if column == "deletionVector" => Some(descriptor),
+                    _ => None,
+                });
+            if let Some(path) = descriptor.and_then(|d| deletion_vector_path(&descriptor_value(d)))
+            {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// the string fields of the deletion vector descriptor of a checkpoint row, as in a commit.
+fn descriptor_value(descriptor: &Row) -> Value {
+    Value::Object(
+        descriptor
+            .get_column_iter()
+            .filter_map(|(column, field)| match field {
+                Field::Str(value) => Some((column.clone(), Value::String(value.clone()))),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+/// the files referred to by the log of the table at `table_uri` as of `version`, see
+/// `referenced_files`. reads the commits back to the first one removed by log cleanup, with at
+/// most `concurrency` requests in flight, and then the latest checkpoint for the deletion
+/// vectors of the files it still knows about. each read is retried on its own.
+pub async fn log_references(
+    table_uri: &str,
+    version: DeltaDataTypeVersion,
+    concurrency: usize,
+    options: &StorageOptions,
+) -> Result<References, DeltaTableError> {
+    assert!(concurrency > 0, "concurrency must be positive");
+    let backend = options.backend(table_uri)?;
+    let backend = &backend;
+    let log_uri = &backend.join_path(table_uri, "_delta_log");
+    let mut references = References {
+        change_data_known: true,
+        deletion_vectors_known: true,
+        ..Default::default()
+    };
+    let mut commits = futures::stream::iter((0..=version).rev())
+        .map(|version| async move {
+            let commit_uri = backend.join_path(log_uri, &format!("{:020}.json", version));
+            (
+                version,
+                options.retry(|| backend.get_obj(&commit_uri)).await,
+            )
+        })
+        .buffer_unordered(concurrency);
+    // the commits arrive out of order, a missing one is the newest removed by log cleanup only
+    // once all newer ones have been read.
+    let mut read: BTreeMap<DeltaDataTypeVersion, Vec<String>> = BTreeMap::new();
+    let mut cleaned_up: Option<DeltaDataTypeVersion> = None;
+    let mut read_after_cleanup = 0;
+    while let Some((commit_version, commit)) = commits.next().await {
+        match commit {
+            Ok(commit) => {
+                if matches!(cleaned_up, Some(cleaned_up) if commit_version > cleaned_up) {
+                    read_after_cleanup += 1;
+                }
+                read.insert(commit_version, referenced_files(&commit)?);
+            }
+            Err(StorageError::NotFound) => {
+                if !matches!(cleaned_up, Some(cleaned_up) if cleaned_up > commit_version) {
+                    cleaned_up = Some(commit_version);
+                    read_after_cleanup = read.range(commit_version + 1..).count();
+                }
+            }
+            Err(err) => return Err(err.into()),
+        }
+        let newer_read = match cleaned_up {
+            Some(cleaned_up) => read_after_cleanup as DeltaDataTypeVersion == version - cleaned_up,
+            None => false,
+        };
+        if newer_read {
+            break;
+        }
+    }
+    let newer = cleaned_up.map_or(0, |cleaned_up| cleaned_up + 1);
+    for (_, files) in read.range(newer..) {
+        references.files.extend(files.iter().cloned());
+    }
+    let cleaned_up = match cleaned_up {
+        Some(cleaned_up) => cleaned_up,
+        None => return Ok(references),
+    };
+    references.change_data_known = false;
+    let checkpoint = options
+        .retry(|| last_checkpoint(backend.as_ref(), log_uri))
+        .await?
+        .filter(|checkpoint| (cleaned_up..=version).contains(&checkpoint.version));
+    let checkpoint = match checkpoint {
+        Some(checkpoint) => checkpoint,
+        None => {
+            references.deletion_vectors_known = false;
+            return Ok(references);
+        }
+    };
+    for part in checkpoint.files() {
+        let part_uri = backend.join_path(log_uri, &part);
+        match options.retry(|| backend.get_obj(&part_uri)).await {
+            Ok(part) => references.files.extend(checkpoint_references(part)?),
+            Err(StorageError::NotFound) => references.deletion_vectors_known = false,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(references)
+}
+
+/// compare `delta_table` to a listing of its storage location, including the change data files
+/// and deletion vectors its log refers to, see `ConsistencyReport::new`. lists the location
+/// once and reads every commit still in the log, with at most `concurrency` requests in
+/// flight.
+pub async fn consistency_check(
+    delta_table: &DeltaTable,
+    concurrency: usize,
+    options: &StorageOptions,
+) -> Result<ConsistencyReport, DeltaTableError> {
+    let table_uri = delta_table.table_uri.as_str();
+    let references = log_references(table_uri, delta_table.version, concurrency, options).await?;
+    let active: HashMap<String, u64> = delta_table
+        .get_active_add_actions()
+        .iter()
+        .map(|add| (add.path.clone(), add.size.max(0) as u64))
+        .collect();
+    let removed: HashSet<String> = delta_table
+        .get_tombstones()
+        .iter()
+        .map(|remove| remove.path.clone())
+        .collect();
+    let report =
+        check_consistency(table_uri, &active, &removed, Some(&references), options).await?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const DV: &str = "ab/deletion_vector_9f2b4e10-7d2a-4c8e-8f5b-2a1c3d4e5f60.bin";
+
+    #[test]
+    fn references_of_commit() {
+        let commit = br#"{"cdc":{"path":"_change_data/d=1/cdc-00000.c000.snappy.parquet","partitionValues":{"d":"1"},"size":3,"dataChange":false}}
+{"add":{"path":"d=1/a.parquet","size":1,"deletionVector":{"storageType":"u","pathOrInlineDv":"abPdrUFEjv{JK6s7NjX/u(","offset":1,"sizeInBytes":36,"cardinality":2}}}
+{"remove":{"path":"d=1/b.parquet","deletionVector":{"storageType":"i","pathOrInlineDv":"wi5b=000010000siXQKl0rr91000f55c8Xg0@@D72lkbi5=-{L","sizeInBytes":40,"cardinality":6}}}
+"#;
+        assert_eq!(
+            referenced_files(commit).unwrap(),
+            vec!["_change_data/d=1/cdc-00000.c000.snappy.parquet", DV]
+        );
+        assert_eq!(
+            z85_decode("HelloWorld"),
+            Some(vec![0x86, 0x4f, 0xd2, 0x6f, 0xb5, 0x59, 0xf7, 0x5b])
+        );
+    }
+
+    #[test]
+    fn references_in_report() {
+        let active: HashMap<String, u64> =
+            std::iter::once(("d=1/a.parquet".to_string(), 1)).collect();
+        let listed = [
+            "d=1/a.parquet".to_string(),
+            "_change_data/d=1/cdc-00000.c000.snappy.parquet".to_string(),
+            "_change_data/d=1/cdc-00001.c000.snappy.parquet".to_string(),
+            DV.to_string(),
+        ];
+        let mut references = References {
+            files: std::iter::once(listed[1].clone()).collect(),
+            change_data_known: true,
+            deletion_vectors_known: true,
+        };
+        let report = |references: &References| {
+            let listed = listed.iter().map(|path| (path.clone(), Some(1)));
+            ConsistencyReport::new(&active, &HashSet::new(), listed, Some(references))
+        };
+        let known = report(&references);
+        assert_eq!(known.unreferenced_change_data, vec![listed[2].clone()]);
+        assert_eq!(known.unreferenced_deletion_vectors, vec![DV.to_string()]);
+        assert!(known.unknown_references.is_empty());
+        assert!(!known.is_ok());
+
+        references.change_data_known = false;
+        references.files.insert(DV.to_string());
+        let cleaned_up = report(&references);
+        assert!(cleaned_up.unreferenced_change_data.is_empty());
+        assert!(cleaned_up.unreferenced_deletion_vectors.is_empty());
+        assert_eq!(cleaned_up.unknown_references, vec![listed[2].clone()]);
+        assert!(cleaned_up.is_ok());
+    }
+
+    #[tokio::test]
+    async fn references_of_log_after_cleanup() {
+        let dir =
+            std::env::temp_dir().join(format!("delta-tree-references-{}", std::process::id()));
+        let log = dir.join("_delta_log");
+        std::fs::create_dir_all(&log).unwrap();
+        let cdc = |version: i64| format!("_change_data/cdc-{:05}.c000.snappy.parquet", version);
+        // commit 1 was removed by log cleanup, commit 0 is left over and mustn't be read.
+        for version in [0, 2, 3, 4, 5] {
+            let commit = format!(r#"{{"cdc":{{"path":"{}","size":3}}}}"#, cdc(version));
+            std::fs::write(log.join(format!("{:020}.json", version)), commit).unwrap();
+        }
+        let uri = dir.to_str().unwrap();
+        let references = log_references(uri, 4, 2, &StorageOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            references,
+            References {
+                files: [2, 3, 4].iter().map(|&version| cdc(version)).collect(),
+                change_data_known: false,
+                deletion_vectors_known: false,
+            }
+        );
+
+        std::fs::write(log.join(format!("{:020}.json", 1)), "").unwrap();
+        let references = log_references(uri, 5, 3, &StorageOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(references.files.len(), 5);
+        assert!(references.change_data_known);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+use super::{children, partition_value, Child, DeltaTree, PartitionValue, TreeNode};
+
+/// a position in a tree that moves down into a partition value, back up and across the
+/// values of the same partition, e.g. to browse a table interactively. the values of each
+/// partition are visited in order, `null` first.
+pub struct TreeCursor<'a, F, S> {
+    root: &'a TreeNode<F, S>,
+    /// the partitions above the current node, outermost first.
+    stack: Vec<Level<'a, F, S>>,
+}
+
+struct Level<'a, F, S> {
+    name: &'a str,
+    /// the values of the partition, sorted.
+    values: Vec<Child<'a, F, S>>,
+    /// the value leading to the current node.
+    idx: usize,
+}
+
+impl<F, S> DeltaTree<F, S> {
+    /// a cursor at the root of the tree.
+    pub fn cursor(&self) -> TreeCursor<'_, F, S> {
+        TreeCursor {
+            root: &self.root,
+            stack: vec![],
+        }
+    }
+}
+
+impl<'a, F, S> TreeCursor<'a, F, S> {
+    pub fn node(&self) -> &'a TreeNode<F, S> {
+        match self.stack.last() {
+            Some(level) => level.values[level.idx].1,
+            None => self.root,
+        }
+    }
+
+    /// the partitions above the current node.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// the partition values leading to the current node, outermost first.
+    pub fn path(&self) -> Vec<PartitionValue> {
+        self.stack
+            .iter()
+            .map(|level| PartitionValue::new(level.name, level.values[level.idx].0.as_deref()))
+            .collect()
+    }
+
+    /// the directory of the current node, e.g. `date=2021-03-01/`, empty at the root.
+    pub fn dir(&self) -> String {
+        self.path().iter().map(|p| format!("{}/", p)).collect()
+    }
+
+    /// the column of the current node, `None` at a leaf.
+    pub fn column(&self) -> Option<&'a str> {
+        match self.node() {
+            TreeNode::Partition { name, .. } => Some(name),
+            TreeNode::FileEntries { .. } => None,
+        }
+    }
+
+    /// the values of the current partition, sorted. empty at a leaf.
+    pub fn values(&self) -> Vec<Option<&'a str>> {
+        match self.node() {
+            TreeNode::Partition { values, .. } => {
+                let mut values: Vec<_> = values.keys().map(Option::as_deref).collect();
+                values.sort();
+                values
+            }
+            TreeNode::FileEntries { .. } => vec![],
+        }
+    }
+
+    /// the files of the current node, `None` unless it's a leaf.
+    pub fn files(&self) -> Option<&'a [F]> {
+        match self.node() {
+            TreeNode::FileEntries { files } => Some(&files[..]),
+            TreeNode::Partition { .. } => None,
+        }
+    }
+
+    /// move down into `value` of the current partition. stays put and returns false if the
+    /// current node is a leaf or doesn't have the value.
+    pub fn down(&mut self, value: Option<&str>) -> bool {
+        self.descend(|values| {
+            values
+                .binary_search_by(|(v, _)| v.as_deref().cmp(&value))
+                .ok()
+        })
+    }
+
+    /// move down into the first value of the current partition, false at a leaf.
+    pub fn first_child(&mut self) -> bool {
+        self.descend(|values| if values.is_empty() { None } else { Some(0) })
+    }
+
+    /// move up to the parent partition, false at the root.
+    pub fn up(&mut self) -> bool {
+        self.stack.pop().is_some()
+    }
+
+    /// move to the next value of the parent partition, false at the last value or the root.
+    pub fn next_sibling(&mut self) -> bool {
+        match self.stack.last_mut() {
+            Some(level) if level.idx + 1 < level.values.len() => {
+                level.idx += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// move to the previous value of the parent partition, false at the first value or the
+    /// root.
+    pub fn prev_sibling(&mut self) -> bool {
+        match self.stack.last_mut() {
+            Some(level) if level.idx > 0 => {
+                level.idx -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn descend<P>(&mut self, position: P) -> bool
+    where
+        P: FnOnce(&[Child<'a, F, S>]) -> Option<usize>,
+    {
+        if let TreeNode::Partition { name, values } = self.node() {
+            let values = children(values, true);
+            if let Some(idx) = position(&values) {
+                self.stack.push(Level { name, values, idx });
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl<'a, F, S> std::fmt::Debug for TreeCursor<'a, F, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let path: Vec<String> = self
+            .stack
+            .iter()
+            .map(|level| {
+                let value = partition_value(level.values[level.idx].0);
+                format!("{}={}", level.name, value)
+            })
+            .collect();
+        f.debug_struct("TreeCursor").field("path", &path).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn navigate_a_tree() {
+        let tree = DeltaTree::from_paths(&vec![
+            path("a=1/b=7/", 1),
+            path("a=1/b=__HIVE_DEFAULT_PARTITION__/", 2),
+            path("a=2/b=7/", 3),
+            path("a=2/b=7/", 4),
+        ]);
+        let mut cursor = tree.cursor();
+        assert_eq!(cursor.column(), Some("a"));
+        assert_eq!(cursor.values(), vec![Some("1"), Some("2")]);
+        assert!(!cursor.up());
+        assert!(!cursor.next_sibling());
+
+        assert!(cursor.first_child());
+        assert_eq!(cursor.values(), vec![None, Some("7")]);
+        assert!(cursor.first_child());
+        assert_eq!(cursor.dir(), "a=1/b=__HIVE_DEFAULT_PARTITION__/");
+        assert_eq!(cursor.files().map(<[_]>::len), Some(1));
+        assert!(!cursor.first_child());
+        assert!(!cursor.prev_sibling());
+        assert!(cursor.next_sibling());
+        assert_eq!(
+            cursor.path(),
+            vec![
+                PartitionValue::new("a", Some("1")),
+                PartitionValue::new("b", Some("7"))
+            ]
+        );
+        assert!(!cursor.next_sibling());
+
+        assert!(cursor.up());
+        assert!(cursor.next_sibling());
+        assert!(!cursor.down(Some("8")));
+        assert!(cursor.down(Some("7")));
+        assert_eq!(cursor.depth(), 2);
+        assert_eq!(cursor.dir(), "a=2/b=7/");
+        assert_eq!(cursor.files().map(<[_]>::len), Some(2));
+        assert_eq!(cursor.column(), None);
+
+        assert!(cursor.up() && cursor.up());
+        assert!(std::ptr::eq(cursor.node(), &tree.root));
+    }
+}
@@ -0,0 +1,238 @@
+use super::codec::{FileNameCodec, SparkFileNameCodec};
+use super::sized::SizedDeltaFile;
+use super::{partition_value, DeltaTree, ParquetDeltaFile, TreeNode};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+/// the changes between two trees of the same table, e.g. two versions of it.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct TreeDiff {
+    /// paths of the files only in the newer tree, sorted.
+    pub added: Vec<String>,
+    /// paths of the files only in the older tree, sorted.
+    pub removed: Vec<String>,
+    /// the leaf partitions with added or removed files, sorted by path.
+    pub partitions: Vec<PartitionDiff>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PartitionDiff {
+    /// the directory of the partition, e.g. `date=2021-03-01/hour=00/`.
+    pub path: String,
+    pub added_files: usize,
+    pub removed_files: usize,
+    pub added_bytes: u64,
+    pub removed_bytes: u64,
+}
+
+impl PartitionDiff {
+    /// the net change in bytes of the partition.
+    pub fn byte_delta(&self) -> i64 {
+        self.added_bytes as i64 - self.removed_bytes as i64
+    }
+}
+
+impl TreeDiff {
+    /// compare two trees. subtrees present in only one of them count as added or removed as a
+    /// whole, subtrees in both are compared leaf by leaf.
+    pub fn new<S: BuildHasher>(
+        old: &DeltaTree<SizedDeltaFile, S>,
+        new: &DeltaTree<SizedDeltaFile, S>,
+    ) -> TreeDiff {
+        let mut diff = TreeDiff::default();
+        diff_node("", Some(&old.root), Some(&new.root), &mut diff);
+        diff.added.sort();
+        diff.removed.sort();
+        diff.partitions.sort_by(|a, b| a.path.cmp(&b.path));
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn added_bytes(&self) -> u64 {
+        self.partitions.iter().map(|p| p.added_bytes).sum()
+    }
+
+    pub fn removed_bytes(&self) -> u64 {
+        self.partitions.iter().map(|p| p.removed_bytes).sum()
+    }
+
+    /// the net change in bytes of the table.
+    pub fn byte_delta(&self) -> i64 {
+        self.added_bytes() as i64 - self.removed_bytes() as i64
+    }
+}
+
+fn diff_node<S: BuildHasher>(
+    dir: &str,
+    old: Option<&TreeNode<SizedDeltaFile, S>>,
+    new: Option<&TreeNode<SizedDeltaFile, S>>,
+    diff: &mut TreeDiff,
+) {
+    match (old, new) {
+        (
+            Some(TreeNode::Partition {
+                name: old_name,
+                values: old_values,
+            }),
+            Some(TreeNode::Partition {
+                name: new_name,
+                values: new_values,
+            }),
+        ) if old_name == new_name => {
+            let values: BTreeSet<&Option<Arc<str>>> =
+                old_values.keys().chain(new_values.keys()).collect();
+            for value in values {
+                let child_dir = format!("{}{}={}/", dir, old_name, partition_value(value));
+                diff_node(
+                    &child_dir,
+                    old_values.get(value),
+                    new_values.get(value),
+                    diff,
+                );
+            }
+        }
+        (
+            Some(TreeNode::FileEntries { files: old }),
+            Some(TreeNode::FileEntries { files: new }),
+        ) => diff_leaf(dir, old, new, diff),
+        // the partitioning changed or one side is missing, match the leaves by their path.
+        (old, new) => {
+            let mut old_leaves = BTreeMap::new();
+            let mut new_leaves = BTreeMap::new();
+            if let Some(old) = old {
+                collect_leaves(dir.to_string(), old, &mut old_leaves);
+            }
+            if let Some(new) = new {
+                collect_leaves(dir.to_string(), new, &mut new_leaves);
+            }
+            let dirs: BTreeSet<&String> = old_leaves.keys().chain(new_leaves.keys()).collect();
+            for dir in dirs {
+                let old = old_leaves.get(dir).copied().unwrap_or_default();
+                let new = new_leaves.get(dir).copied().unwrap_or_default();
+                diff_leaf(dir, old, new, diff);
+            }
+        }
+    }
+}
+
+fn collect_leaves<'a, S>(
+    dir: String,
+    node: &'a TreeNode<SizedDeltaFile, S>,
+    leaves: &mut BTreeMap<String, &'a [SizedDeltaFile]>,
+) {
+    match node {
+        TreeNode::FileEntries { files } => {
+            leaves.insert(dir, files);
+        }
+        TreeNode::Partition { name, values } => {
+            for (value, child) in values.iter() {
+                let child_dir = format!("{}{}={}/", dir, name, partition_value(value));
+                collect_leaves(child_dir, child, leaves);
+            }
+        }
+    }
+}
+
+fn diff_leaf(dir: &str, old: &[SizedDeltaFile], new: &[SizedDeltaFile], diff: &mut TreeDiff) {
+    let old_files: HashSet<&ParquetDeltaFile> = old.iter().map(|f| &f.file).collect();
+    let new_files: HashSet<&ParquetDeltaFile> = new.iter().map(|f| &f.file).collect();
+    let mut partition = PartitionDiff {
+        path: dir.to_string(),
+        added_files: 0,
+        removed_files: 0,
+        added_bytes: 0,
+        removed_bytes: 0,
+    };
+    for file in new.iter().filter(|f| !old_files.contains(&f.file)) {
+        partition.added_files += 1;
+        partition.added_bytes += file.size;
+        diff.added
+            .push(format!("{}{}", dir, SparkFileNameCodec.encode(&file.file)));
+    }
+    for file in old.iter().filter(|f| !new_files.contains(&f.file)) {
+        partition.removed_files += 1;
+        partition.removed_bytes += file.size;
+        diff.removed
+            .push(format!("{}{}", dir, SparkFileNameCodec.encode(&file.file)));
+    }
+    if partition.added_files > 0 || partition.removed_files > 0 {
+        diff.partitions.push(partition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::sized_tree;
+    use pretty_assertions::assert_eq;
+
+    const A: &str = "part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet";
+    const B: &str = "part-00000-00000000-0000-0000-0000-000000000002.c000.snappy.parquet";
+    const C: &str = "part-00000-00000000-0000-0000-0000-000000000003.c000.snappy.parquet";
+
+    fn tree(files: &[(&str, u64)]) -> DeltaTree<SizedDeltaFile> {
+        let entries = files
+            .iter()
+            .map(|(path, size)| (path.to_string(), *size))
+            .collect();
+        sized_tree(entries)
+    }
+
+    #[test]
+    fn diff_between_versions() {
+        let old = tree(&[
+            (&format!("d=1/{}", A), 10),
+            (&format!("d=1/{}", B), 20),
+            (&format!("d=2/{}", C), 30),
+        ]);
+        let new = tree(&[
+            (&format!("d=1/{}", A), 10),
+            (&format!("d=1/{}", C), 5),
+            (&format!("d=3/{}", B), 7),
+        ]);
+        let diff = TreeDiff::new(&old, &new);
+        assert_eq!(diff.added, vec![format!("d=1/{}", C), format!("d=3/{}", B)]);
+        assert_eq!(
+            diff.removed,
+            vec![format!("d=1/{}", B), format!("d=2/{}", C)]
+        );
+        let deltas: Vec<_> = diff
+            .partitions
+            .iter()
+            .map(|p| {
+                (
+                    p.path.as_str(),
+                    p.added_files,
+                    p.removed_files,
+                    p.byte_delta(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            deltas,
+            vec![("d=1/", 1, 1, -15), ("d=2/", 0, 1, -30), ("d=3/", 1, 0, 7)]
+        );
+        assert_eq!((diff.added_bytes(), diff.removed_bytes()), (12, 50));
+        assert_eq!(diff.byte_delta(), -38);
+        assert!(TreeDiff::new(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn diff_with_changed_partitioning() {
+        let old = tree(&[(&format!("d=1/{}", A), 10)]);
+        let new = tree(&[(&format!("d=1/h=0/{}", A), 10)]);
+        let diff = TreeDiff::new(&old, &new);
+        assert_eq!(diff.added, vec![format!("d=1/h=0/{}", A)]);
+        assert_eq!(diff.removed, vec![format!("d=1/{}", A)]);
+
+        let new = tree(&[(&format!("e=1/{}", A), 10), (&format!("e=2/{}", B), 1)]);
+        let diff = TreeDiff::new(&old, &new);
+        assert_eq!(diff.partitions.len(), 3);
+        assert_eq!((diff.added_bytes(), diff.removed_bytes()), (11, 10));
+    }
+}
@@ -0,0 +1,193 @@
+use super::{partition_value, DeltaTree, TreeNode};
+use std::fmt::{self, Write};
+
+/// an indented tree of the partitions with their file counts, e.g.
+///
+/// ```text
+/// 5 files
+/// date=2021-03-01/ 3 files
+///   country=de/ 2 files
+///   country=fr/ 1 files
+/// date=2021-03-02/ 2 files
+///   country=de/ 2 files
+/// ```
+///
+/// the precision limits the levels shown, `{:.1}` only lists the dates. values are sorted,
+/// `null` first.
+impl<F, S> fmt::Display for DeltaTree<F, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let files = file_count(&self.root);
+        if self.prefix.is_empty() {
+            writeln!(f, "{} files", files)?;
+        } else {
+            writeln!(f, "{} {} files", self.prefix, files)?;
+        }
+        let depth = f.precision().unwrap_or(usize::MAX);
+        write_node(f, &self.root, 0, depth)
+    }
+}
+
+fn write_node<F, S>(
+    f: &mut fmt::Formatter,
+    node: &TreeNode<F, S>,
+    level: usize,
+    depth: usize,
+) -> fmt::Result {
+    if level >= depth {
+        return Ok(());
+    }
+    if let TreeNode::Partition { name, values } = node {
+        let mut values: Vec<_> = values.iter().collect();
+        values.sort_by_key(|(value, _)| *value);
+        for (value, child) in values {
+            let indent = "  ".repeat(level);
+            let value = partition_value(value);
+            let files = file_count(child);
+            writeln!(f, "{}{}={}/ {} files", indent, name, value, files)?;
+            write_node(f, child, level + 1, depth)?;
+        }
+    }
+    Ok(())
+}
+
+impl<F, S> DeltaTree<F, S> {
+    /// the partitions down to `depth` levels as a mermaid flowchart with the file counts, to
+    /// paste into markdown, e.g.
+    ///
+    /// ```text
+    /// graph LR
+    ///   n0["5 files"]
+    ///   n0 --> n1["date=2021-03-01<br/>3 files"]
+    ///   n1 --> n2["country=de<br/>2 files"]
+    /// ```
+    pub fn to_mermaid(&self, depth: usize) -> String {
+        let root = if self.prefix.is_empty() {
+            format!("{} files", file_count(&self.root))
+        } else {
+            format!("{}<br/>{} files", self.prefix, file_count(&self.root))
+        };
+        let mut mermaid = format!("graph LR\n  n0[\"{}\"]\n", mermaid_label(&root));
+        mermaid_node(&mut mermaid, &self.root, 0, &mut 0, depth);
+        mermaid
+    }
+}
+
+fn mermaid_node<F, S>(
+    mermaid: &mut String,
+    node: &TreeNode<F, S>,
+    id: usize,
+    last_id: &mut usize,
+    depth: usize,
+) {
+    if depth == 0 {
+        return;
+    }
+    if let TreeNode::Partition { name, values } = node {
+        let mut values: Vec<_> = values.iter().collect();
+        values.sort_by_key(|(value, _)| *value);
+        for (value, child) in values {
+            *last_id += 1;
+            let child_id = *last_id;
+            let label = format!(
+                "{}={}<br/>{} files",
+                name,
+                partition_value(value),
+                file_count(child)
+            );
+            // writing to a `String` can't fail.
+            let _ = writeln!(
+                mermaid,
+                "  n{} --> n{}[\"{}\"]",
+                id,
+                child_id,
+                mermaid_label(&label)
+            );
+            mermaid_node(mermaid, child, child_id, last_id, depth - 1);
+        }
+    }
+}
+
+/// `label` with the quotes mermaid doesn't allow in a quoted label replaced by entities.
+fn mermaid_label(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
+
+fn file_count<F, S>(node: &TreeNode<F, S>) -> usize {
+    match node {
+        TreeNode::FileEntries { files } => files.len(),
+        TreeNode::Partition { values, .. } => values.values().map(file_count).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn display_partitions() {
+        let files: Vec<String> = [
+            ("date=2021-03-02/country=de/", 1),
+            ("date=2021-03-01/country=fr/", 2),
+            ("date=2021-03-01/country=de/", 3),
+            ("date=2021-03-01/country=de/", 4),
+            ("date=2021-03-02/country=__HIVE_DEFAULT_PARTITION__/", 5),
+        ]
+        .iter()
+        .map(|(dir, id)| path(dir, *id))
+        .collect();
+        let tree = DeltaTree::from_paths(&files);
+        assert_eq!(
+            tree.to_string(),
+            "5 files
+date=2021-03-01/ 3 files
+  country=de/ 2 files
+  country=fr/ 1 files
+date=2021-03-02/ 2 files
+  country=__HIVE_DEFAULT_PARTITION__/ 1 files
+  country=de/ 1 files
+"
+        );
+        assert_eq!(
+            format!("{:.1}", tree),
+            "5 files
+date=2021-03-01/ 3 files
+date=2021-03-02/ 2 files
+"
+        );
+        assert_eq!(
+            DeltaTree::from_paths(&Vec::<String>::new()).to_string(),
+            "0 files\n"
+        );
+    }
+
+    #[test]
+    fn mermaid_partitions() {
+        let files: Vec<String> = [
+            ("date=2021-03-02/country=de/", 1),
+            ("date=2021-03-01/country=fr/", 2),
+            ("date=2021-03-01/country=\"de\"/", 3),
+        ]
+        .iter()
+        .map(|(dir, id)| path(&format!("s3://bucket/t/{}", dir), *id))
+        .collect();
+        let tree = DeltaTree::from_paths(&files);
+        assert_eq!(
+            tree.to_mermaid(2),
+            r#"graph LR
+  n0["s3://bucket/t/<br/>3 files"]
+  n0 --> n1["date=2021-03-01<br/>2 files"]
+  n1 --> n2["country=#quot;de#quot;<br/>1 files"]
+  n1 --> n3["country=fr<br/>1 files"]
+  n0 --> n4["date=2021-03-02<br/>1 files"]
+  n4 --> n5["country=de<br/>1 files"]
+"#
+        );
+        assert_eq!(
+            tree.to_mermaid(1).lines().count(),
+            4,
+            "only the dates below the root"
+        );
+    }
+}
@@ -0,0 +1,22 @@
+//! fixtures shared by the tests of the tree's modules.
+
+use super::sized::SizedDeltaFile;
+use super::DeltaTree;
+
+/// the path of a data file below `dir`, e.g. `a=1/`, told apart from its neighbours by `id`.
+pub fn path(dir: &str, id: u128) -> String {
+    format!(
+        "{}part-00000-{}.c000.snappy.parquet",
+        dir,
+        uuid::Uuid::from_u128(id)
+    )
+}
+
+/// the tree of files given by their path and size, without modification times.
+pub fn sized_tree(entries: Vec<(String, u64)>) -> DeltaTree<SizedDeltaFile> {
+    DeltaTree::from_entries(entries, |file, size| SizedDeltaFile {
+        file,
+        size,
+        modification_time: 0,
+    })
+}
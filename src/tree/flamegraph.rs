@@ -0,0 +1,107 @@
+use super::{partition_value, DeltaTree, TreeNode};
+use std::io::{self, Write};
+
+/// the frame of an unpartitioned table's files.
+const ROOT_FRAME: &str = "/";
+
+impl<F, S> DeltaTree<F, S> {
+    /// write the weights of the leaf partitions in the collapsed-stack format of flamegraph
+    /// tools like inferno or speedscope, one `a=1;b=7 <weight>` line per leaf, sorted. the
+    /// weight of a leaf is the sum of `weight` over its files, e.g. the size for the bytes of
+    /// a sized tree, or 1 to count files. leaves without weight are left out, `;` in values is
+    /// written as `%3B`.
+    pub fn write_collapsed_stacks<W, G>(&self, out: &mut W, weight: G) -> io::Result<()>
+    where
+        W: Write,
+        G: Fn(&F) -> u64,
+    {
+        let mut stacks = vec![];
+        collect(&self.root, &mut vec![], &weight, &mut stacks);
+        stacks.sort();
+        for (stack, weight) in stacks {
+            writeln!(out, "{} {}", stack, weight)?;
+        }
+        Ok(())
+    }
+}
+
+fn collect<F, S, G: Fn(&F) -> u64>(
+    node: &TreeNode<F, S>,
+    frames: &mut Vec<String>,
+    weight: &G,
+    stacks: &mut Vec<(String, u64)>,
+) {
+    match node {
+        TreeNode::FileEntries { files } => {
+            let total: u64 = files.iter().map(weight).sum();
+            if total > 0 {
+                let stack = if frames.is_empty() {
+                    ROOT_FRAME.to_string()
+                } else {
+                    frames.join(";")
+                };
+                stacks.push((stack, total));
+            }
+        }
+        TreeNode::Partition { name, values } => {
+            for (value, child) in values {
+                let frame = format!("{}={}", name, partition_value(value));
+                frames.push(frame.replace(';', "%3B"));
+                collect(child, frames, weight, stacks);
+                frames.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::{self, path};
+    use crate::tree::sized::SizedDeltaFile;
+    use pretty_assertions::assert_eq;
+
+    fn sized_tree(files: &[(&str, u64)]) -> DeltaTree<SizedDeltaFile> {
+        let entries = files
+            .iter()
+            .enumerate()
+            .map(|(id, (dir, size))| (path(dir, id as u128), *size))
+            .collect();
+        fixtures::sized_tree(entries)
+    }
+
+    #[test]
+    fn collapsed_stacks() {
+        let tree = sized_tree(&[
+            ("a=2/b=x;y/", 5),
+            ("a=1/b=7/", 100),
+            ("a=1/b=7/", 20),
+            ("a=1/b=__HIVE_DEFAULT_PARTITION__/", 3),
+            ("a=1/b=8/", 0),
+        ]);
+        let mut out = vec![];
+        tree.write_collapsed_stacks(&mut out, |f| f.size).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a=1;b=7 120\na=1;b=__HIVE_DEFAULT_PARTITION__ 3\na=2;b=x%3By 5\n"
+        );
+
+        let mut out = vec![];
+        tree.write_collapsed_stacks(&mut out, |_| 1).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap().lines().collect::<Vec<_>>(),
+            vec![
+                "a=1;b=7 2",
+                "a=1;b=8 1",
+                "a=1;b=__HIVE_DEFAULT_PARTITION__ 1",
+                "a=2;b=x%3By 1"
+            ]
+        );
+
+        let mut out = vec![];
+        sized_tree(&[("", 7), ("", 8)])
+            .write_collapsed_stacks(&mut out, |f| f.size)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "/ 15\n");
+    }
+}
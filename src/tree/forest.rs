@@ -0,0 +1,524 @@
+use super::intern::Interner;
+use super::options::DeltaTreeOptions;
+use super::storage::StorageOptions;
+use super::{DeltaTree, TreeNode};
+use deltalake::{DeltaDataTypeVersion, DeltaTableError};
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+/// trees of many tables, e.g. all tables of a lakehouse served by a single listing service.
+/// tables are registered under a name and can be refreshed individually or all at once, a
+/// tree is only rebuilt if its table has a new version. the partition values of all trees
+/// are interned, the forest keeps each distinct value once no matter how many tables or
+/// partitions share it.
+#[derive(Debug, Default)]
+pub struct DeltaForest {
+    tables: BTreeMap<String, ForestTable>,
+    interner: Interner,
+}
+
+#[derive(Debug)]
+pub struct ForestTable {
+    pub uri: String,
+    pub options: StorageOptions,
+    pub version: deltalake::DeltaDataTypeVersion,
+    pub tree: DeltaTree,
+    /// the partition columns, outermost first.
+    pub columns: Vec<String>,
+}
+
+/// aggregate numbers over all trees of a forest.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ForestStats {
+    pub tables: usize,
+    pub files: usize,
+    /// leaf partitions, i.e. directories containing files.
+    pub partitions: usize,
+    /// partition values over all tables and levels.
+    pub partition_values: usize,
+    /// distinct partition column names and values over all tables.
+    pub distinct_strings: usize,
+    /// bytes of all partition values, and of the distinct values the trees share.
+    pub partition_value_bytes: usize,
+    pub interned_bytes: usize,
+}
+
+/// how many tables `load_many` and `refresh_many` open at once, and how long each may take.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LoadLimits {
+    pub concurrency: usize,
+    /// for opening a table and building its tree, unlimited if `None`.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for LoadLimits {
+    fn default() -> LoadLimits {
+        LoadLimits {
+            concurrency: 8,
+            timeout: None,
+        }
+    }
+}
+
+/// why a table of a batch wasn't loaded.
+#[derive(Debug)]
+pub enum ForestError {
+    Table(DeltaTableError),
+    /// the table took longer than the timeout of the `LoadLimits`.
+    Timeout(Duration),
+}
+
+impl fmt::Display for ForestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ForestError::Table(err) => write!(f, "{}", err),
+            ForestError::Timeout(timeout) => write!(f, "timed out after {:?}", timeout),
+        }
+    }
+}
+
+impl std::error::Error for ForestError {}
+
+/// the tables of a batch by name, with whether each tree changed or why it couldn't be
+/// loaded. a failed table keeps its previous tree, if any.
+pub type BatchResult = BTreeMap<String, Result<bool, ForestError>>;
+
+/// a table to open for a batch, along with the version of its tree if it has one.
+struct Pending {
+    name: String,
+    uri: String,
+    options: StorageOptions,
+    version: Option<DeltaDataTypeVersion>,
+}
+
+impl DeltaForest {
+    pub fn new() -> DeltaForest {
+        DeltaForest::default()
+    }
+
+    /// open the table at `uri` and register its tree as `name`, replacing any previous table
+    /// of that name.
+    pub async fn load(&mut self, name: &str, uri: &str) -> Result<(), deltalake::DeltaTableError> {
+        self.load_with_options(name, uri, StorageOptions::default())
+            .await
+    }
+
+    /// like `load`, the `options` are kept for refreshing the table.
+    pub async fn load_with_options(
+        &mut self,
+        name: &str,
+        uri: &str,
+        options: StorageOptions,
+    ) -> Result<(), deltalake::DeltaTableError> {
+        let delta_table = options.open_table(uri).await?;
+        let tree = DeltaTree::new_with_options(&delta_table, &self.tree_options());
+        self.register(name, uri, options, delta_table.version, tree);
+        Ok(())
+    }
+
+    /// register an already built tree, its partition values are replaced by the shared ones.
+    pub fn insert(
+        &mut self,
+        name: &str,
+        uri: &str,
+        version: deltalake::DeltaDataTypeVersion,
+        tree: DeltaTree,
+    ) {
+        self.register(name, uri, StorageOptions::default(), version, tree);
+    }
+
+    fn register(
+        &mut self,
+        name: &str,
+        uri: &str,
+        options: StorageOptions,
+        version: deltalake::DeltaDataTypeVersion,
+        mut tree: DeltaTree,
+    ) {
+        intern_node(&mut tree.root, &self.interner);
+        let mut columns = vec![];
+        collect_columns(&tree.root, &mut columns);
+        let table = ForestTable {
+            uri: uri.to_string(),
+            options,
+            version,
+            tree,
+            columns,
+        };
+        self.tables.insert(name.to_string(), table);
+        // drop the values only the replaced tree referred to.
+        self.interner.purge();
+    }
+
+    /// build trees sharing the partition values of the forest.
+    fn tree_options(&self) -> DeltaTreeOptions {
+        DeltaTreeOptions::new().interner(self.interner.clone())
+    }
+
+    /// reload table `name` and rebuild its tree if there's a new version. returns whether the
+    /// tree changed, `false` for unknown tables.
+    pub async fn refresh(&mut self, name: &str) -> Result<bool, deltalake::DeltaTableError> {
+        let (uri, options, version) = match self.tables.get(name) {
+            Some(table) => (table.uri.clone(), table.options.clone(), table.version),
+            None => return Ok(false),
+        };
+        let delta_table = options.open_table(&uri).await?;
+        if delta_table.version == version {
+            return Ok(false);
+        }
+        let tree = DeltaTree::new_with_options(&delta_table, &self.tree_options());
+        self.register(name, &uri, options, delta_table.version, tree);
+        Ok(true)
+    }
+
+    /// refresh all tables, returning the names of those that changed.
+    pub async fn refresh_all(&mut self) -> Result<Vec<String>, deltalake::DeltaTableError> {
+        let names: Vec<String> = self.tables.keys().cloned().collect();
+        let mut changed = vec![];
+        for name in names {
+            if self.refresh(&name).await? {
+                changed.push(name);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// open the tables given by name, uri and storage options concurrently and register their
+    /// trees like `load_with_options`, within `limits`. a table failing or timing out doesn't
+    /// affect the others.
+    pub async fn load_many(
+        &mut self,
+        tables: impl IntoIterator<Item = (String, String, StorageOptions)>,
+        limits: LoadLimits,
+    ) -> BatchResult {
+        let pending = tables
+            .into_iter()
+            .map(|(name, uri, options)| Pending {
+                name,
+                uri,
+                options,
+                version: None,
+            })
+            .collect();
+        self.open_many(pending, limits).await
+    }
+
+    /// like `refresh_all`, refreshing the tables concurrently within `limits` and reporting
+    /// the outcome of each instead of stopping at the first error.
+    pub async fn refresh_many(&mut self, limits: LoadLimits) -> BatchResult {
+        let pending = self
+            .tables
+            .iter()
+            .map(|(name, table)| Pending {
+                name: name.clone(),
+                uri: table.uri.clone(),
+                options: table.options.clone(),
+                version: Some(table.version),
+            })
+            .collect();
+        self.open_many(pending, limits).await
+    }
+
+    async fn open_many(&mut self, pending: Vec<Pending>, limits: LoadLimits) -> BatchResult {
+        let tree_options = self.tree_options();
+        self.open_many_with(pending, limits, |table| {
+            let (uri, options) = (table.uri.clone(), table.options.clone());
+            open_newer(uri, options, table.version, tree_options.clone())
+        })
+        .await
+    }
+
+    /// like `open_many`, opening each table with `open`.
+    async fn open_many_with<O, F>(
+        &mut self,
+        pending: Vec<Pending>,
+        limits: LoadLimits,
+        open: O,
+    ) -> BatchResult
+    where
+        O: Fn(&Pending) -> F,
+        F: Future<Output = Result<Option<(DeltaDataTypeVersion, DeltaTree)>, DeltaTableError>>,
+    {
+        assert!(limits.concurrency > 0, "concurrency must be positive");
+        let opened: Vec<_> = futures::stream::iter(pending)
+            .map(|table| {
+                let opened = open(&table);
+                async move {
+                    let result = within(limits.timeout, opened).await;
+                    (table, result)
+                }
+            })
+            .buffer_unordered(limits.concurrency)
+            .collect()
+            .await;
+        let mut results = BatchResult::new();
+        for (table, result) in opened {
+            let Pending {
+                name, uri, options, ..
+            } = table;
+            let result = result.map(|opened| match opened {
+                Some((version, tree)) => {
+                    self.register(&name, &uri, options, version, tree);
+                    true
+                }
+                None => false,
+            });
+            results.insert(name, result);
+        }
+        results
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ForestTable> {
+        self.tables.get(name)
+    }
+
+    /// unregister table `name`, returning its tree.
+    pub fn remove(&mut self, name: &str) -> Option<DeltaTree> {
+        // the values only the removed tree refers to are purged once it's dropped, see
+        // `stats` and `register`.
+        self.tables.remove(name).map(|table| table.tree)
+    }
+
+    /// the registered tables, ordered by name.
+    pub fn tables(&self) -> impl Iterator<Item = (&str, &ForestTable)> {
+        self.tables
+            .iter()
+            .map(|(name, table)| (name.as_str(), table))
+    }
+
+    /// the tables partitioned by `column`.
+    pub fn tables_with_column<'a>(&'a self, column: &'a str) -> impl Iterator<Item = &'a str> {
+        self.tables
+            .iter()
+            .filter(move |(_, table)| table.columns.iter().any(|c| c == column))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// the numbers of all trees, after dropping the interned values of trees that are gone.
+    pub fn stats(&self) -> ForestStats {
+        self.interner.purge();
+        let mut stats = ForestStats {
+            tables: self.tables.len(),
+            interned_bytes: self.interner.bytes(),
+            ..ForestStats::default()
+        };
+        let mut distinct = HashSet::new();
+        for table in self.tables.values() {
+            collect_stats(&table.tree.root, &mut stats, &mut distinct);
+        }
+        stats.distinct_strings = distinct.len();
+        stats
+    }
+}
+
+/// open the table at `uri` and build its tree with `tree_options`, unless it's still at the
+/// `version` of its tree.
+async fn open_newer(
+    uri: String,
+    options: StorageOptions,
+    version: Option<DeltaDataTypeVersion>,
+    tree_options: DeltaTreeOptions,
+) -> Result<Option<(DeltaDataTypeVersion, DeltaTree)>, DeltaTableError> {
+    let delta_table = options.open_table(&uri).await?;
+    if Some(delta_table.version) == version {
+        return Ok(None);
+    }
+    let tree = DeltaTree::new_with_options(&delta_table, &tree_options);
+    Ok(Some((delta_table.version, tree)))
+}
+
+/// await `future`, giving up after `timeout`.
+async fn within<T>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = Result<T, DeltaTableError>>,
+) -> Result<T, ForestError> {
+    let result = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .map_err(|_| ForestError::Timeout(timeout))?,
+        None => future.await,
+    };
+    result.map_err(ForestError::Table)
+}
+
+/// replace the partition values below `node` by their shared copies in `interner`.
+fn intern_node(node: &mut TreeNode, interner: &Interner) {
+    if let TreeNode::Partition { values, .. } = node {
+        let children: Vec<_> = values.drain().collect();
+        for (value, mut child) in children {
+            intern_node(&mut child, interner);
+            values.insert(value.map(|v| interner.intern(&v)), child);
+        }
+    }
+}
+
+/// the partition columns below `node`, outermost first.
+fn collect_columns(node: &TreeNode, columns: &mut Vec<String>) {
+    if let TreeNode::Partition { name, values } = node {
+        if !columns.contains(name) {
+            columns.push(name.clone());
+        }
+        for child in values.values() {
+            collect_columns(child, columns);
+        }
+    }
+}
+
+fn collect_stats<'a>(node: &'a TreeNode, stats: &mut ForestStats, distinct: &mut HashSet<&'a str>) {
+    match node {
+        TreeNode::FileEntries { files } => {
+            stats.partitions += 1;
+            stats.files += files.len();
+        }
+        TreeNode::Partition { name, values } => {
+            distinct.insert(name);
+            for (value, child) in values.iter() {
+                stats.partition_values += 1;
+                stats.partition_value_bytes += value.as_ref().map_or(0, |v| v.len());
+                if let Some(value) = value {
+                    distinct.insert(value);
+                }
+                collect_stats(child, stats, distinct);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+
+    fn tree(partitions: &[&str]) -> DeltaTree {
+        let paths: Vec<String> = partitions
+            .iter()
+            .enumerate()
+            .map(|(idx, p)| path(&format!("{}/", p), idx as u128))
+            .collect();
+        DeltaTree::from_paths(&paths)
+    }
+
+    #[test]
+    fn forest_stats() {
+        let mut forest = DeltaForest::new();
+        forest.insert(
+            "events",
+            "s3://lake/events",
+            3,
+            tree(&["date=2021-03-01/hour=1", "date=2021-03-01/hour=2"]),
+        );
+        forest.insert(
+            "clicks",
+            "s3://lake/clicks",
+            7,
+            tree(&["date=2021-03-01", "date=2021-03-02", "date=2021-03-02"]),
+        );
+        assert_eq!(
+            forest.stats(),
+            ForestStats {
+                tables: 2,
+                files: 5,
+                partitions: 4,
+                partition_values: 5,
+                distinct_strings: 6,
+                partition_value_bytes: 32,
+                interned_bytes: 22,
+            }
+        );
+        let value = |table: &str, value: &str| match &forest.get(table).unwrap().tree.root {
+            TreeNode::Partition { values, .. } => values
+                .keys()
+                .flatten()
+                .find(|v| &***v == value)
+                .unwrap()
+                .clone(),
+            TreeNode::FileEntries { .. } => panic!("expected a partition"),
+        };
+        assert!(Arc::ptr_eq(
+            &value("events", "2021-03-01"),
+            &value("clicks", "2021-03-01")
+        ));
+        assert_eq!(forest.get("clicks").unwrap().version, 7);
+        assert_eq!(
+            forest.tables().map(|(name, _)| name).collect::<Vec<_>>(),
+            vec!["clicks", "events"]
+        );
+        assert_eq!(
+            forest.tables_with_column("hour").collect::<Vec<_>>(),
+            vec!["events"]
+        );
+
+        forest.remove("events");
+        assert_eq!(forest.stats().distinct_strings, 3);
+        assert_eq!(forest.stats().interned_bytes, 20);
+        assert!(forest.get("events").is_none());
+    }
+
+    #[tokio::test]
+    async fn batch_within_limits() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let pending = ["a", "b", "c", "d", "e", "slow"]
+            .iter()
+            .map(|name| Pending {
+                name: name.to_string(),
+                uri: format!("s3://lake/{}", name),
+                options: StorageOptions::new(),
+                version: None,
+            })
+            .collect();
+        let limits = LoadLimits {
+            concurrency: 2,
+            timeout: Some(Duration::from_millis(200)),
+        };
+        let mut forest = DeltaForest::new();
+        let results = forest
+            .open_many_with(pending, limits, |table| {
+                let (running, peak) = (running.clone(), peak.clone());
+                let delay = match table.name.as_str() {
+                    "slow" => Duration::from_secs(3600),
+                    _ => Duration::from_millis(10),
+                };
+                async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(delay).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(Some((1, tree(&["d=1"]))))
+                }
+            })
+            .await;
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert!(matches!(results["slow"], Err(ForestError::Timeout(_))));
+        assert!(results
+            .iter()
+            .filter(|(name, _)| *name != "slow")
+            .all(|(_, result)| matches!(result, Ok(true))));
+        assert_eq!(forest.tables().count(), 5);
+        assert!(forest.get("slow").is_none());
+    }
+
+    #[test]
+    fn batch_outcomes() {
+        let mut forest = DeltaForest::new();
+        let results = futures::executor::block_on(forest.load_many(vec![], LoadLimits::default()));
+        assert!(results.is_empty());
+        let failed = futures::executor::block_on(within(None, async {
+            Err::<(), _>(DeltaTableError::from(
+                deltalake::storage::StorageError::NotFound,
+            ))
+        }));
+        assert!(matches!(failed, Err(ForestError::Table(_))));
+        assert_eq!(
+            ForestError::Timeout(Duration::from_secs(5)).to_string(),
+            "timed out after 5s"
+        );
+    }
+}
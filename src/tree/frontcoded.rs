@@ -0,0 +1,288 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+/// number of keys sharing a fully stored head key. larger buckets compress better, but need
+/// more decoding per lookup.
+const BUCKET_SIZE: usize = 16;
+
+/// a sorted set of partition values, front-coded in buckets: the first key of each bucket is
+/// stored in full, the following ones as the length of the prefix shared with their
+/// predecessor plus the remaining suffix. values of a partition column tend to share long
+/// prefixes (`2021-03-01`, `2021-03-02`, ...), which are stored only once per bucket.
+/// the `null` value sorts first.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct FrontCodedKeys {
+    has_null: bool,
+    len: usize,
+    bytes: Vec<u8>,
+    /// start of each bucket in `bytes`.
+    buckets: Vec<u32>,
+}
+
+impl FrontCodedKeys {
+    /// `keys` must be sorted and free of duplicates.
+    pub fn from_sorted<'a, I: IntoIterator<Item = Option<&'a str>>>(keys: I) -> FrontCodedKeys {
+        let mut store = FrontCodedKeys::default();
+        let mut previous: Option<&str> = None;
+        for key in keys {
+            let key = match key {
+                None => {
+                    assert!(store.len == 0, "null key must be the first key");
+                    store.has_null = true;
+                    store.len += 1;
+                    continue;
+                }
+                Some(key) => key,
+            };
+            if let Some(previous) = previous {
+                assert!(previous < key, "keys must be sorted and unique");
+            }
+            let idx = store.len - store.has_null as usize;
+            if idx == store.buckets.len() * BUCKET_SIZE {
+                store.buckets.push(store.bytes.len() as u32);
+                write_varint(&mut store.bytes, key.len());
+                store.bytes.extend_from_slice(key.as_bytes());
+            } else {
+                let shared = shared_prefix(previous.unwrap(), key);
+                write_varint(&mut store.bytes, shared);
+                write_varint(&mut store.bytes, key.len() - shared);
+                store.bytes.extend_from_slice(&key.as_bytes()[shared..]);
+            }
+            previous = Some(key);
+            store.len += 1;
+        }
+        store.bytes.shrink_to_fit();
+        store.buckets.shrink_to_fit();
+        store
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// the key at `idx`, in sort order.
+    pub fn get(&self, idx: usize) -> Option<Option<String>> {
+        if idx >= self.len {
+            return None;
+        }
+        if self.has_null && idx == 0 {
+            return Some(None);
+        }
+        let idx = idx - self.has_null as usize;
+        self.bucket(idx / BUCKET_SIZE)
+            .nth(idx % BUCKET_SIZE)
+            .map(|key| Some(String::from_utf8(key).unwrap()))
+    }
+
+    /// the index of `key`, found by a binary search over the bucket heads followed by a scan
+    /// through a single bucket.
+    pub fn position(&self, key: Option<&str>) -> Option<usize> {
+        let key = match key {
+            None if self.has_null => return Some(0),
+            None => return None,
+            Some(key) => key.as_bytes(),
+        };
+        let bucket = match self
+            .buckets
+            .binary_search_by(|&offset| self.head(offset as usize).cmp(key))
+        {
+            Ok(bucket) => return Some(self.has_null as usize + bucket * BUCKET_SIZE),
+            Err(0) => return None,
+            Err(insert) => insert - 1,
+        };
+        for (idx, candidate) in self.bucket(bucket).enumerate().skip(1) {
+            match candidate.as_slice().cmp(key) {
+                Ordering::Less => continue,
+                Ordering::Equal => {
+                    return Some(self.has_null as usize + bucket * BUCKET_SIZE + idx)
+                }
+                Ordering::Greater => return None,
+            }
+        }
+        None
+    }
+
+    /// all keys, in sort order.
+    pub fn iter(&self) -> impl Iterator<Item = Option<String>> + '_ {
+        let null = if self.has_null { Some(None) } else { None };
+        null.into_iter().chain(
+            (0..self.buckets.len())
+                .flat_map(move |bucket| self.bucket(bucket))
+                .map(|key| Some(String::from_utf8(key).unwrap())),
+        )
+    }
+
+    /// bytes allocated on the heap.
+    pub fn heap_size(&self) -> usize {
+        self.bytes.capacity() + std::mem::size_of::<u32>() * self.buckets.capacity()
+    }
+
+    fn head(&self, offset: usize) -> &[u8] {
+        let (len, start) = read_varint(&self.bytes, offset);
+        &self.bytes[start..start + len]
+    }
+
+    /// decode the keys of a single bucket.
+    fn bucket(&self, bucket: usize) -> impl Iterator<Item = Vec<u8>> + '_ {
+        let mut offset = self.buckets[bucket] as usize;
+        let end = self
+            .buckets
+            .get(bucket + 1)
+            .map_or(self.bytes.len(), |&o| o as usize);
+        let mut key: Vec<u8> = vec![];
+        let mut first = true;
+        std::iter::from_fn(move || {
+            if offset >= end {
+                return None;
+            }
+            let shared = if first {
+                first = false;
+                0
+            } else {
+                let (shared, next) = read_varint(&self.bytes, offset);
+                offset = next;
+                shared
+            };
+            let (suffix, next) = read_varint(&self.bytes, offset);
+            key.truncate(shared);
+            key.extend_from_slice(&self.bytes[next..next + suffix]);
+            offset = next + suffix;
+            Some(key.clone())
+        })
+    }
+}
+
+/// the children of a partition node, keyed by a `FrontCodedKeys` instead of a hash map.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FrontCodedMap<V> {
+    keys: FrontCodedKeys,
+    values: Vec<V>,
+}
+
+impl<V> FrontCodedMap<V> {
+    pub fn from_map<S: BuildHasher>(map: HashMap<Option<String>, V, S>) -> FrontCodedMap<V> {
+        let mut entries: Vec<_> = map.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        FrontCodedMap {
+            keys: FrontCodedKeys::from_sorted(entries.iter().map(|(key, _)| key.as_deref())),
+            values: entries.into_iter().map(|(_, value)| value).collect(),
+        }
+    }
+
+    pub fn get(&self, key: Option<&str>) -> Option<&V> {
+        self.keys.position(key).map(|idx| &self.values[idx])
+    }
+
+    pub fn keys(&self) -> &FrontCodedKeys {
+        &self.keys
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Option<String>, &V)> + '_ {
+        self.keys.iter().zip(self.values.iter())
+    }
+}
+
+fn shared_prefix(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count()
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// the decoded value and the offset behind it.
+fn read_varint(bytes: &[u8], mut offset: usize) -> (usize, usize) {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[offset];
+        offset += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return (value, offset);
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn dates(n: usize) -> Vec<Option<String>> {
+        (0..n)
+            .map(|i| Some(format!("2021-{:02}-{:02}", i / 28 + 1, i % 28 + 1)))
+            .collect()
+    }
+
+    #[test]
+    fn front_coded_keys_round_trip() {
+        let mut keys = vec![None];
+        keys.extend(dates(100));
+        let store = FrontCodedKeys::from_sorted(keys.iter().map(|k| k.as_deref()));
+        assert_eq!(store.len(), keys.len());
+        assert_eq!(store.iter().collect::<Vec<_>>(), keys);
+        for (idx, key) in keys.iter().enumerate() {
+            assert_eq!(store.get(idx).as_ref(), Some(key));
+            assert_eq!(store.position(key.as_deref()), Some(idx));
+        }
+        assert_eq!(store.get(keys.len()), None);
+        assert!(store.heap_size() < keys.iter().flatten().map(|k| k.len()).sum::<usize>() / 2);
+    }
+
+    #[test]
+    fn missing_keys_are_not_found() {
+        let keys = dates(40);
+        let store = FrontCodedKeys::from_sorted(keys.iter().map(|k| k.as_deref()));
+        assert_eq!(store.position(None), None);
+        assert_eq!(store.position(Some("2020-01-01")), None);
+        assert_eq!(store.position(Some("2021-01-015")), None);
+        assert_eq!(store.position(Some("2021-02-30")), None);
+        assert_eq!(store.position(Some("2099-01-01")), None);
+        assert_eq!(store.position(Some("")), None);
+    }
+
+    #[test]
+    fn multi_byte_prefixes() {
+        let keys = ["ab", "aé", "aéb", "aê", "b"];
+        let store = FrontCodedKeys::from_sorted(keys.iter().map(|k| Some(*k)));
+        assert_eq!(
+            store.iter().collect::<Vec<_>>(),
+            keys.iter().map(|k| Some(k.to_string())).collect::<Vec<_>>()
+        );
+        assert_eq!(store.position(Some("aê")), Some(3));
+    }
+
+    #[test]
+    fn front_coded_map_lookup() {
+        let mut map: HashMap<Option<String>, usize> = HashMap::new();
+        map.insert(None, 0);
+        for (idx, key) in dates(50).into_iter().enumerate() {
+            map.insert(key, idx + 1);
+        }
+        let front_coded = FrontCodedMap::from_map(map.clone());
+        assert_eq!(front_coded.len(), map.len());
+        for (key, value) in map.iter() {
+            assert_eq!(front_coded.get(key.as_deref()), Some(value));
+        }
+        assert_eq!(front_coded.get(Some("1999-01-01")), None);
+    }
+}
@@ -0,0 +1,301 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// a partition column of a generated table, with values `0` up to `cardinality - 1`,
+/// zero-padded to equal width.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PartitionColumn {
+    pub name: String,
+    pub cardinality: usize,
+}
+
+/// parse `name:cardinality`, e.g. `date:365`.
+impl FromStr for PartitionColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PartitionColumn, String> {
+        let (name, cardinality) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected name:cardinality, got '{}'", s))?;
+        let cardinality = cardinality
+            .parse()
+            .ok()
+            .filter(|&c| c > 0)
+            .ok_or_else(|| format!("invalid cardinality in '{}'", s))?;
+        if name.is_empty() {
+            return Err(format!("missing column name in '{}'", s));
+        }
+        Ok(PartitionColumn {
+            name: name.to_string(),
+            cardinality,
+        })
+    }
+}
+
+/// the shape of a synthetic table: its partition columns and how many files are spread over
+/// the partitions. the same layout and seed always generate the same files.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Layout {
+    pub columns: Vec<PartitionColumn>,
+    pub files: usize,
+    /// the exponent of a zipf distribution of files over partitions: with `0` all partitions
+    /// get about the same number of files, larger values pile them up in the first ones.
+    pub skew: f64,
+    /// the mean file size in bytes, actual sizes vary between half and one and a half of it.
+    pub file_size: u64,
+    pub seed: u64,
+}
+
+/// a file of a generated table.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GeneratedFile {
+    pub path: String,
+    pub partition_values: Vec<(String, String)>,
+    pub size: u64,
+}
+
+impl Layout {
+    pub fn new(columns: Vec<PartitionColumn>, files: usize) -> Layout {
+        Layout {
+            columns,
+            files,
+            skew: 0.0,
+            file_size: 128 << 20,
+            seed: 0,
+        }
+    }
+
+    pub fn skew(self, skew: f64) -> Layout {
+        Layout { skew, ..self }
+    }
+
+    pub fn file_size(self, file_size: u64) -> Layout {
+        Layout { file_size, ..self }
+    }
+
+    pub fn seed(self, seed: u64) -> Layout {
+        Layout { seed, ..self }
+    }
+
+    /// the number of partitions, i.e. the product of all cardinalities.
+    pub fn partitions(&self) -> usize {
+        self.columns.iter().map(|c| c.cardinality).product()
+    }
+
+    /// generate the files, grouped by partition.
+    pub fn generate(&self) -> Vec<GeneratedFile> {
+        assert!(self.skew >= 0.0, "skew must not be negative");
+        let mut rng = SplitMix64(self.seed);
+        let partitions = self.partitions();
+        let mut cumulative = Vec::with_capacity(partitions);
+        let mut total = 0.0;
+        for rank in 0..partitions {
+            total += 1.0 / ((rank + 1) as f64).powf(self.skew);
+            cumulative.push(total);
+        }
+        let mut counts = vec![0; partitions];
+        for _ in 0..self.files {
+            let x = rng.next_f64() * total;
+            let partition = cumulative.partition_point(|&c| c <= x).min(partitions - 1);
+            counts[partition] += 1;
+        }
+
+        let mut files = Vec::with_capacity(self.files);
+        for (partition, &count) in counts.iter().enumerate() {
+            let partition_values = self.partition_values(partition);
+            let dir: String = partition_values
+                .iter()
+                .map(|(column, value)| format!("{}={}/", column, value))
+                .collect();
+            for part in 0..count {
+                let uuid = Uuid::from_u128((rng.next() as u128) << 64 | rng.next() as u128);
+                let spread = rng.next() % (self.file_size + 1);
+                files.push(GeneratedFile {
+                    path: format!("{}part-{:05}-{}.c000.snappy.parquet", dir, part, uuid),
+                    partition_values: partition_values.clone(),
+                    size: self.file_size / 2 + spread,
+                });
+            }
+        }
+        files
+    }
+
+    /// the paths of the generated files.
+    pub fn paths(&self) -> Vec<String> {
+        self.generate().into_iter().map(|f| f.path).collect()
+    }
+
+    /// write a delta log with a single commit adding all generated files to `table_dir`. the
+    /// data files themselves are not written.
+    pub fn write_delta_log(&self, table_dir: &Path) -> std::io::Result<()> {
+        let log_dir = table_dir.join("_delta_log");
+        std::fs::create_dir_all(&log_dir)?;
+        let commit = std::fs::File::create(log_dir.join(format!("{:020}.json", 0)))?;
+        let mut commit = std::io::BufWriter::new(commit);
+        let mut line = |action: &Action| -> std::io::Result<()> {
+            serde_json::to_writer(&mut commit, action)?;
+            commit.write_all(b"\n")
+        };
+        line(&Action::Protocol {
+            min_reader_version: 1,
+            min_writer_version: 2,
+        })?;
+        line(&Action::MetaData {
+            id: Uuid::from_u128(self.seed as u128).to_string(),
+            format: Format {
+                provider: "parquet",
+                options: BTreeMap::new(),
+            },
+            schema_string: self.schema_string(),
+            partition_columns: self.columns.iter().map(|c| c.name.clone()).collect(),
+            configuration: BTreeMap::new(),
+            created_time: 0,
+        })?;
+        for file in self.generate() {
+            line(&Action::Add {
+                path: file.path,
+                partition_values: file.partition_values.into_iter().collect(),
+                size: file.size,
+                modification_time: 0,
+                data_change: true,
+            })?;
+        }
+        commit.flush()
+    }
+
+    /// the values of the partition with the given index, the last column varying fastest.
+    fn partition_values(&self, mut partition: usize) -> Vec<(String, String)> {
+        let mut values = vec![];
+        for column in self.columns.iter().rev() {
+            let width = (column.cardinality - 1).to_string().len();
+            let value = format!("{:0width$}", partition % column.cardinality, width = width);
+            values.push((column.name.clone(), value));
+            partition /= column.cardinality;
+        }
+        values.reverse();
+        values
+    }
+
+    /// string partition columns and a single `value` column for the data.
+    fn schema_string(&self) -> String {
+        let fields: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .map(|name| (name, "string"))
+            .chain(std::iter::once(("value", "long")))
+            .map(|(name, data_type)| {
+                format!(
+                    r#"{{"name":"{}","type":"{}","nullable":true,"metadata":{{}}}}"#,
+                    name, data_type
+                )
+            })
+            .collect();
+        format!(r#"{{"type":"struct","fields":[{}]}}"#, fields.join(","))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Action {
+    #[serde(rename_all = "camelCase")]
+    Protocol {
+        min_reader_version: i32,
+        min_writer_version: i32,
+    },
+    #[serde(rename_all = "camelCase")]
+    MetaData {
+        id: String,
+        format: Format,
+        schema_string: String,
+        partition_columns: Vec<String>,
+        configuration: BTreeMap<String, String>,
+        created_time: i64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Add {
+        path: String,
+        partition_values: BTreeMap<String, String>,
+        size: u64,
+        modification_time: i64,
+        data_change: bool,
+    },
+}
+
+#[derive(Serialize)]
+struct Format {
+    provider: &'static str,
+    options: BTreeMap<String, String>,
+}
+
+/// a small, seedable generator, so layouts don't depend on an rng crate's stream stability.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::DeltaTree;
+    use pretty_assertions::assert_eq;
+
+    fn columns() -> Vec<PartitionColumn> {
+        vec!["date:12".parse().unwrap(), "country:3".parse().unwrap()]
+    }
+
+    #[test]
+    fn generate_layout() {
+        let layout = Layout::new(columns(), 1000).seed(7);
+        let files = layout.generate();
+        assert_eq!(layout.partitions(), 36);
+        assert_eq!(files.len(), 1000);
+        assert_eq!(layout.paths(), layout.paths());
+        assert!(files[0].path.starts_with("date=00/country=0/part-00000-"));
+        assert!(files
+            .iter()
+            .all(|f| f.size >= 64 << 20 && f.size <= 192 << 20));
+        let tree = DeltaTree::from_paths(&layout.paths());
+        assert_eq!(tree.files().len(), 1000);
+    }
+
+    #[test]
+    fn skewed_layout() {
+        let files = Layout::new(columns(), 1000).skew(2.0).generate();
+        let first = files
+            .iter()
+            .filter(|f| f.path.starts_with("date=00/country=0/"))
+            .count();
+        assert!(first > 500, "{} files in the first partition", first);
+    }
+
+    #[test]
+    fn parse_partition_column() {
+        assert_eq!(
+            "date:365".parse(),
+            Ok(PartitionColumn {
+                name: "date".to_string(),
+                cardinality: 365
+            })
+        );
+        assert!("date".parse::<PartitionColumn>().is_err());
+        assert!("date:0".parse::<PartitionColumn>().is_err());
+        assert!(":3".parse::<PartitionColumn>().is_err());
+    }
+}
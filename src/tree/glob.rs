@@ -0,0 +1,99 @@
+use super::sized::SizedDeltaFile;
+use super::stats;
+use super::DeltaTree;
+use std::str::FromStr;
+
+/// a glob over partition directories like `year=2024/month=0*`. each `/`-separated segment is
+/// matched against one partition directory, with `*` matching any characters and `?` a single
+/// one; a `**` segment matches any number of directories. a pattern matches all partitions
+/// below the directories it matches, so `year=2024` selects the whole year.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PartitionGlob {
+    segments: Vec<String>,
+}
+
+impl FromStr for PartitionGlob {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PartitionGlob, String> {
+        let segments: Vec<String> = s
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect();
+        if segments.is_empty() {
+            return Err(format!("empty partition glob '{}'", s));
+        }
+        Ok(PartitionGlob { segments })
+    }
+}
+
+impl PartitionGlob {
+    /// whether the partition directory `dir`, e.g. `year=2024/month=03/`, is selected.
+    pub fn matches(&self, dir: &str) -> bool {
+        let dirs: Vec<&str> = dir.split('/').filter(|d| !d.is_empty()).collect();
+        matches_dirs(&self.segments, &dirs)
+    }
+
+    /// the leaf partitions of `tree` selected by the glob, with their files.
+    pub fn leaves<'a, S>(
+        &self,
+        tree: &'a DeltaTree<SizedDeltaFile, S>,
+    ) -> Vec<(String, &'a [SizedDeltaFile])> {
+        stats::leaves(tree)
+            .into_iter()
+            .filter(|(dir, _)| self.matches(dir))
+            .collect()
+    }
+}
+
+fn matches_dirs(segments: &[String], dirs: &[&str]) -> bool {
+    match (segments.split_first(), dirs.split_first()) {
+        (None, _) => true,
+        (Some((segment, rest)), _) if segment == "**" => {
+            (0..=dirs.len()).any(|skip| matches_dirs(rest, &dirs[skip..]))
+        }
+        (Some(_), None) => false,
+        (Some((segment, rest)), Some((dir, dirs))) => {
+            wildcard(segment.as_bytes(), dir.as_bytes()) && matches_dirs(rest, dirs)
+        }
+    }
+}
+
+fn wildcard(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| wildcard(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && wildcard(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && wildcard(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn glob_matches() {
+        let glob: PartitionGlob = "year=2024/month=0*".parse().unwrap();
+        assert!(glob.matches("year=2024/month=03/day=01/"));
+        assert!(!glob.matches("year=2024/month=10/day=01/"));
+        assert!(!glob.matches("year=2023/month=03/"));
+        assert!(!glob.matches("year=2024/"));
+
+        let glob: PartitionGlob = "**/day=?1".parse().unwrap();
+        assert!(glob.matches("year=2024/month=03/day=01/"));
+        assert!(glob.matches("day=21/"));
+        assert!(!glob.matches("year=2024/month=03/day=02/"));
+
+        assert!("year=*"
+            .parse::<PartitionGlob>()
+            .unwrap()
+            .matches("year=2024/"));
+        assert_eq!(
+            "/".parse::<PartitionGlob>(),
+            Err("empty partition glob '/'".to_string())
+        );
+    }
+}
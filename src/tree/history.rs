@@ -0,0 +1,204 @@
+use super::snapshot::last_checkpoint;
+use super::storage::StorageOptions;
+use deltalake::storage::StorageError;
+use deltalake::{DeltaDataTypeVersion, DeltaTableError};
+use serde::Serialize;
+use serde_json::Value;
+
+/// the actions of a commit file, one json object per line. blank lines are skipped.
+pub fn commit_actions(
+    commit: &[u8],
+) -> impl Iterator<Item = Result<Value, serde_json::Error>> + '_ {
+    commit
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+        .map(serde_json::from_slice)
+}
+
+/// what a single commit of the delta log did.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct CommitSummary {
+    pub version: DeltaDataTypeVersion,
+    /// milliseconds since the epoch, from the commit info.
+    pub timestamp: Option<i64>,
+    /// e.g. `WRITE`, `MERGE` or `OPTIMIZE`, from the commit info.
+    pub operation: Option<String>,
+    pub added_files: usize,
+    pub removed_files: usize,
+    pub added_bytes: u64,
+    /// removes only carry a size if the writer recorded it.
+    pub removed_bytes: u64,
+}
+
+impl CommitSummary {
+    /// summarize the commit file of `version`, one json action per line.
+    pub fn parse(
+        version: DeltaDataTypeVersion,
+        commit: &[u8],
+    ) -> Result<CommitSummary, serde_json::Error> {
+        let mut summary = CommitSummary {
+            version,
+            ..CommitSummary::default()
+        };
+        for action in commit_actions(commit) {
+            let action = action?;
+            if let Some(add) = action.get("add") {
+                summary.added_files += 1;
+                summary.added_bytes += add["size"].as_u64().unwrap_or(0);
+            } else if let Some(remove) = action.get("remove") {
+                summary.removed_files += 1;
+                summary.removed_bytes += remove["size"].as_u64().unwrap_or(0);
+            } else if let Some(info) = action.get("commitInfo") {
+                summary.timestamp = info["timestamp"].as_i64();
+                summary.operation = info["operation"].as_str().map(str::to_string);
+            }
+        }
+        Ok(summary)
+    }
+}
+
+/// the commits of the table at `table_uri` up to `version`, newest first and at most
+/// `limit`. stops early at commits already removed by log cleanup.
+pub async fn history(
+    table_uri: &str,
+    version: DeltaDataTypeVersion,
+    limit: usize,
+    options: &StorageOptions,
+) -> Result<Vec<CommitSummary>, DeltaTableError> {
+    let backend = options.backend(table_uri)?;
+    let log_uri = backend.join_path(table_uri, "_delta_log");
+    let mut commits = vec![];
+    for version in (0..=version).rev().take(limit) {
+        let commit_uri = backend.join_path(&log_uri, &format!("{:020}.json", version));
+        let commit = match options.guard(backend.get_obj(&commit_uri)).await {
+            Ok(commit) => commit,
+            Err(StorageError::NotFound) => break,
+            Err(err) => return Err(err.into()),
+        };
+        commits.push(CommitSummary::parse(version, &commit)?);
+    }
+    Ok(commits)
+}
+
+/// how much of the log a load of a table replays on top of its last checkpoint, see
+/// `scan_replay`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ReplayProgress {
+    /// the version of the checkpoint the replay starts from, `None` if it starts at version 0.
+    pub checkpoint: Option<DeltaDataTypeVersion>,
+    /// the commits read so far.
+    pub commits: usize,
+    /// the actions of those commits.
+    pub actions: usize,
+}
+
+/// read the commits of the table at `table_uri` that a load of `version`, or of the latest
+/// version, replays on top of the last checkpoint, one at a time, and call `progress` after
+/// each. delta-rs replays the log in a single call without reporting progress, this tells how
+/// far the log goes beyond the checkpoint.
+pub async fn scan_replay<P>(
+    table_uri: &str,
+    version: Option<DeltaDataTypeVersion>,
+    options: &StorageOptions,
+    mut progress: P,
+) -> Result<ReplayProgress, DeltaTableError>
+where
+    P: FnMut(&ReplayProgress),
+{
+    let backend = options.backend(table_uri)?;
+    let log_uri = backend.join_path(table_uri, "_delta_log");
+    let checkpoint = options
+        .retry(|| last_checkpoint(backend.as_ref(), &log_uri))
+        .await?;
+    let checkpoint = match (checkpoint, version) {
+        (Some(checkpoint), Some(version)) if checkpoint.version > version => None,
+        (checkpoint, _) => checkpoint.map(|checkpoint| checkpoint.version),
+    };
+    let mut replay = ReplayProgress {
+        checkpoint,
+        ..ReplayProgress::default()
+    };
+    progress(&replay);
+    let mut next = checkpoint.map_or(0, |checkpoint| checkpoint + 1);
+    while !matches!(version, Some(version) if next > version) {
+        let commit_uri = backend.join_path(&log_uri, &format!("{:020}.json", next));
+        let commit = match options.retry(|| backend.get_obj(&commit_uri)).await {
+            Ok(commit) => commit,
+            Err(StorageError::NotFound) => break,
+            Err(err) => return Err(err.into()),
+        };
+        replay.commits += 1;
+        replay.actions += commit_actions(&commit).count();
+        progress(&replay);
+        next += 1;
+    }
+    Ok(replay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_commit() {
+        let commit = br#"{"commitInfo":{"timestamp":1614600000000,"operation":"OPTIMIZE"}}
+{"remove":{"path":"a=1/part-00000.parquet","deletionTimestamp":1614600000000,"dataChange":false,"size":10}}
+{"remove":{"path":"a=1/part-00001.parquet","deletionTimestamp":1614600000000,"dataChange":false}}
+{"add":{"path":"a=1/part-00002.parquet","size":25,"partitionValues":{"a":"1"},"modificationTime":1614600000000,"dataChange":false}}
+"#;
+        assert_eq!(
+            CommitSummary::parse(3, commit).unwrap(),
+            CommitSummary {
+                version: 3,
+                timestamp: Some(1614600000000),
+                operation: Some("OPTIMIZE".to_string()),
+                added_files: 1,
+                removed_files: 2,
+                added_bytes: 25,
+                removed_bytes: 10,
+            }
+        );
+        assert!(CommitSummary::parse(4, b"{\"add\":").is_err());
+    }
+
+    #[tokio::test]
+    async fn scan_replay_from_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("delta-tree-replay-{}", std::process::id()));
+        let log = dir.join("_delta_log");
+        std::fs::create_dir_all(&log).unwrap();
+        for version in 0..4 {
+            let commit = "{\"commitInfo\":{}}\n{\"add\":{}}\n".repeat(version + 1);
+            std::fs::write(log.join(format!("{:020}.json", version)), commit).unwrap();
+        }
+        let uri = dir.to_str().unwrap();
+        let options = StorageOptions::new();
+        let mut reported = vec![];
+        let replay = scan_replay(uri, None, &options, |p| reported.push(p.commits))
+            .await
+            .unwrap();
+        assert_eq!(
+            replay,
+            ReplayProgress {
+                checkpoint: None,
+                commits: 4,
+                actions: 20,
+            }
+        );
+        assert_eq!(reported, vec![0, 1, 2, 3, 4]);
+
+        std::fs::write(log.join("_last_checkpoint"), r#"{"version":1,"size":3}"#).unwrap();
+        let replay = scan_replay(uri, Some(2), &options, |_| {}).await.unwrap();
+        assert_eq!(
+            replay,
+            ReplayProgress {
+                checkpoint: Some(1),
+                commits: 1,
+                actions: 6,
+            }
+        );
+        let replay = scan_replay(uri, Some(0), &options, |_| {}).await.unwrap();
+        assert_eq!((replay.checkpoint, replay.commits), (None, 1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
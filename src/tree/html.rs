@@ -0,0 +1,275 @@
+use super::history::CommitSummary;
+use super::sized::{subtree_size, SizedDeltaFile};
+use super::stats::{self, PartitionSize, TableStats};
+use super::{partition_value, DeltaTree, TreeNode};
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// the partition levels drawn in the treemap, deeper partitions are part of their parent's box.
+const TREEMAP_DEPTH: usize = 3;
+/// the children drawn per partition, the smaller ones are combined into a single box.
+const TREEMAP_CHILDREN: usize = 50;
+/// leaf partitions with a larger share of small files are highlighted.
+const SMALL_FILE_WARNING: f64 = 0.5;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:2em}\
+td,th{padding:2px 10px;text-align:right;border-bottom:1px solid #ddd}\
+td:first-child,th:first-child{text-align:left}\
+.warn{color:#b00}\
+.treemap{display:flex;height:480px;border:1px solid #888;margin-bottom:2em}\
+.treemap div{display:flex;box-sizing:border-box;border:1px solid #fff;min-width:0;min-height:0;\
+overflow:hidden;font-size:11px;background:#8ab4d8}\
+.treemap div.small{background:#e8a0a0}\
+.row{flex-direction:row}.col{flex-direction:column}";
+
+/// a self-contained HTML page on a table for people who don't use the CLI: a treemap of the
+/// bytes per partition, the smallest and largest partitions, the partitions with mostly small
+/// files and the latest `commits`. `stats` are those of `tree`.
+pub fn html_report<S>(
+    title: &str,
+    tree: &DeltaTree<SizedDeltaFile, S>,
+    stats: &TableStats,
+    commits: &[CommitSummary],
+) -> String {
+    let mut html = String::new();
+    let title = escape(title);
+    // writing to a `String` can't fail.
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>{}</style>\n</head>\n<body>\n<h1>{}</h1>",
+        title, STYLE, title
+    );
+    let _ = writeln!(
+        html,
+        "<p>{} files, {} in {} partitions. partition columns: {}</p>",
+        stats.files,
+        human_bytes(stats.bytes),
+        stats.partitions,
+        escape(&stats.columns.join(", "))
+    );
+
+    html.push_str("<h2>layout</h2>\n<div class=\"treemap row\">");
+    treemap(&mut html, &tree.root, "", 0, stats.small_file_threshold);
+    html.push_str("</div>\n");
+
+    html.push_str("<h2>largest partitions</h2>\n");
+    partition_table(&mut html, &stats.largest);
+    html.push_str("<h2>smallest partitions</h2>\n");
+    partition_table(&mut html, &stats.smallest);
+
+    let _ = writeln!(
+        html,
+        "<h2>small files</h2>\n<p{}>{} of {} files are smaller than {} ({:.1} %)</p>",
+        if stats.small_file_ratio > SMALL_FILE_WARNING {
+            " class=\"warn\""
+        } else {
+            ""
+        },
+        stats.small_files,
+        stats.files,
+        human_bytes(stats.small_file_threshold),
+        100.0 * stats.small_file_ratio
+    );
+    let warnings: Vec<(String, usize, usize)> = stats::leaves(tree)
+        .into_iter()
+        .map(|(dir, files)| {
+            let small = files
+                .iter()
+                .filter(|f| f.size < stats.small_file_threshold)
+                .count();
+            (dir, small, files.len())
+        })
+        .filter(|&(_, small, files)| small > 1 && small as f64 > SMALL_FILE_WARNING * files as f64)
+        .collect();
+    if !warnings.is_empty() {
+        html.push_str("<table>\n<tr><th>partition</th><th>small files</th><th>files</th></tr>\n");
+        for (dir, small, files) in warnings {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"warn\">{}</td><td>{}</td></tr>",
+                escape(&dir),
+                small,
+                files
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str(
+        "<h2>history</h2>\n<table>\n<tr><th>version</th><th>timestamp</th>\
+        <th>operation</th><th>added</th><th>removed</th><th>added bytes</th>\
+        <th>removed bytes</th></tr>\n",
+    );
+    for commit in commits {
+        let timestamp = commit
+            .timestamp
+            .and_then(|ms| chrono::NaiveDateTime::from_timestamp_opt(ms.div_euclid(1000), 0))
+            .map_or_else(String::new, |t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            commit.version,
+            timestamp,
+            escape(commit.operation.as_deref().unwrap_or("")),
+            commit.added_files,
+            commit.removed_files,
+            human_bytes(commit.added_bytes),
+            human_bytes(commit.removed_bytes)
+        );
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// the boxes of the children of `node`, sized by their bytes and nested in alternating
+/// directions.
+fn treemap<S>(
+    html: &mut String,
+    node: &TreeNode<SizedDeltaFile, S>,
+    dir: &str,
+    depth: usize,
+    small_file_threshold: u64,
+) {
+    let (name, values) = match node {
+        TreeNode::Partition { name, values } if depth < TREEMAP_DEPTH => (name, values),
+        _ => return,
+    };
+    let mut children: Vec<_> = values
+        .iter()
+        .map(|(value, child)| (value, child, subtree_size(child)))
+        .collect();
+    children.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(b.0)));
+    let others: u64 = children.iter().skip(TREEMAP_CHILDREN).map(|c| c.2).sum();
+    let direction = ["col", "row"][depth % 2];
+    for (value, child, bytes) in children.into_iter().take(TREEMAP_CHILDREN) {
+        let child_dir = format!("{}{}={}/", dir, name, partition_value(value));
+        let (files, small) = file_counts(child, small_file_threshold);
+        let class = if small as f64 > SMALL_FILE_WARNING * files as f64 {
+            " small"
+        } else {
+            ""
+        };
+        let _ = write!(
+            html,
+            "<div class=\"{}{}\" style=\"flex:{} 1 0\" title=\"{}: {}, {} files\">{}",
+            direction,
+            class,
+            bytes.max(1),
+            escape(&child_dir),
+            human_bytes(bytes),
+            files,
+            escape(&format!("{}={}", name, partition_value(value)))
+        );
+        treemap(html, child, &child_dir, depth + 1, small_file_threshold);
+        html.push_str("</div>");
+    }
+    if others > 0 {
+        let _ = write!(
+            html,
+            "<div class=\"{}\" style=\"flex:{} 1 0\" title=\"{}: {} in other partitions\">…</div>",
+            direction,
+            others,
+            escape(dir),
+            human_bytes(others)
+        );
+    }
+}
+
+/// the files below `node` and how many of them are smaller than `small_file_threshold`.
+fn file_counts<S>(node: &TreeNode<SizedDeltaFile, S>, small_file_threshold: u64) -> (usize, usize) {
+    match node {
+        TreeNode::FileEntries { files } => (
+            files.len(),
+            files
+                .iter()
+                .filter(|f| f.size < small_file_threshold)
+                .count(),
+        ),
+        TreeNode::Partition { values, .. } => values
+            .values()
+            .map(|child| file_counts(child, small_file_threshold))
+            .fold((0, 0), |agg, c| (agg.0 + c.0, agg.1 + c.1)),
+    }
+}
+
+fn partition_table(html: &mut String, partitions: &[PartitionSize]) {
+    html.push_str("<table>\n<tr><th>partition</th><th>files</th><th>bytes</th></tr>\n");
+    for partition in partitions {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&partition.path),
+            partition.files,
+            human_bytes(partition.bytes)
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+/// `bytes` in KiB, MiB, ... with one decimal.
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(&['<', '>', '&', '"', '\''][..]) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 16);
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::{path, sized_tree};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn report_sections() {
+        let entries: Vec<(String, u64)> = (0..20u128)
+            .map(|id| {
+                let dir = if id < 10 { "d=a<b/" } else { "d=c/" };
+                (path(dir, id), if id < 10 { 1000 } else { 1 << 30 })
+            })
+            .collect();
+        let tree = sized_tree(entries);
+        let stats = TableStats::new(&tree, 1 << 20, 5);
+        let commits = vec![CommitSummary {
+            version: 3,
+            operation: Some("WRITE".to_string()),
+            added_files: 20,
+            ..CommitSummary::default()
+        }];
+        let html = html_report("s3://bucket/table", &tree, &stats, &commits);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>s3://bucket/table</title>"));
+        assert!(html.contains("title=\"d=c/: 10.0 GiB, 10 files\""));
+        assert!(html.contains("<tr><td>d=a&lt;b/</td><td class=\"warn\">10</td><td>10</td></tr>"));
+        assert!(html.contains("<td>3</td><td></td><td>WRITE</td><td>20</td>"));
+        assert_eq!(html.matches("<table>").count(), 4);
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// hands out the same `Arc<str>` for equal strings, so the trees built with it share their
+/// partition values instead of keeping a copy per partition, see
+/// `DeltaTreeOptions::interner`. clones share the strings, e.g. all trees of a `DeltaForest`
+/// use a single interner.
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    strings: Arc<Mutex<HashSet<Arc<str>>>>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /// the shared copy of `s`, added if it's new.
+    pub fn intern(&self, s: &str) -> Arc<str> {
+        let mut strings = self.strings.lock().unwrap();
+        match strings.get(s) {
+            Some(interned) => interned.clone(),
+            None => {
+                let interned: Arc<str> = Arc::from(s);
+                strings.insert(interned.clone());
+                interned
+            }
+        }
+    }
+
+    /// the number of distinct strings.
+    pub fn len(&self) -> usize {
+        self.strings.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// the bytes of all distinct strings.
+    pub fn bytes(&self) -> usize {
+        self.strings.lock().unwrap().iter().map(|s| s.len()).sum()
+    }
+
+    /// drop the strings no tree refers to anymore, e.g. after replacing or dropping trees.
+    pub fn purge(&self) {
+        self.strings
+            .lock()
+            .unwrap()
+            .retain(|s| Arc::strong_count(s) > 1);
+    }
+}
+
+/// interners are the same if they share their strings.
+impl PartialEq for Interner {
+    fn eq(&self, other: &Interner) -> bool {
+        Arc::ptr_eq(&self.strings, &other.strings)
+    }
+}
+
+impl Eq for Interner {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn interned_strings_are_shared() {
+        let interner = Interner::new();
+        let a = interner.intern("2021-03-01");
+        let b = interner.clone().intern("2021-03-01");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(interner.len(), 1);
+        assert_eq!(interner.bytes(), 10);
+
+        drop(interner.intern("2021-03-02"));
+        interner.purge();
+        assert_eq!(interner.len(), 1);
+        drop((a, b));
+        interner.purge();
+        assert!(interner.is_empty());
+    }
+}
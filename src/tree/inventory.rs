@@ -0,0 +1,365 @@
+use super::manifest::epoch_micros;
+use super::options::{unescape, DeltaTreeOptions};
+use super::sized::SizedDeltaFile;
+use super::storage::StorageOptions;
+use super::verify::is_data_file;
+use super::{build, DeltaTree, FileKind, SparkFileNameCodec};
+use deltalake::storage::StorageError;
+use flate2::read::GzDecoder;
+use parquet::errors::ParquetError;
+use parquet::file::reader::FileReader;
+use parquet::file::serialized_reader::SerializedFileReader;
+use parquet::record::Field;
+use parquet::util::cursor::SliceableCursor;
+use serde_json::Value;
+use std::fmt;
+use std::io::{self, BufRead, BufReader};
+
+/// an object listed in an S3 Inventory report.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InventoryObject {
+    /// the key of the object in its bucket.
+    pub key: String,
+    pub size: u64,
+    /// milliseconds since the epoch.
+    pub last_modified: i64,
+}
+
+/// the format of the data files of an inventory report. ORC isn't supported.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InventoryFormat {
+    Csv,
+    Parquet,
+}
+
+/// the `manifest.json` of an S3 Inventory report, listing its data files.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InventoryManifest {
+    /// the bucket whose objects are listed.
+    pub source_bucket: String,
+    /// the bucket the report is written to.
+    pub destination_bucket: String,
+    pub format: InventoryFormat,
+    /// the columns of CSV files, e.g. `Bucket`, `Key`, `Size`, `LastModifiedDate`. parquet
+    /// files have their own schema.
+    pub columns: Vec<String>,
+    /// the keys of the data files in the destination bucket.
+    pub files: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum InventoryError {
+    /// the manifest isn't valid JSON or lacks a field.
+    Manifest(String),
+    /// a row of a CSV file, by line number, lacks a column or has an invalid value.
+    Row {
+        line: usize,
+        message: String,
+    },
+    Io(io::Error),
+    Parquet(ParquetError),
+    Storage(StorageError),
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InventoryError::Manifest(msg) => write!(f, "invalid inventory manifest: {}", msg),
+            InventoryError::Row { line, message } => {
+                write!(f, "invalid inventory row {}: {}", line, message)
+            }
+            InventoryError::Io(err) => write!(f, "{}", err),
+            InventoryError::Parquet(err) => write!(f, "{}", err),
+            InventoryError::Storage(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for InventoryError {}
+
+impl From<io::Error> for InventoryError {
+    fn from(err: io::Error) -> InventoryError {
+        InventoryError::Io(err)
+    }
+}
+
+impl From<ParquetError> for InventoryError {
+    fn from(err: ParquetError) -> InventoryError {
+        InventoryError::Parquet(err)
+    }
+}
+
+impl From<StorageError> for InventoryError {
+    fn from(err: StorageError) -> InventoryError {
+        InventoryError::Storage(err)
+    }
+}
+
+impl InventoryManifest {
+    pub fn parse(json: &[u8]) -> Result<InventoryManifest, InventoryError> {
+        let manifest: Value = serde_json::from_slice(json)
+            .map_err(|err| InventoryError::Manifest(err.to_string()))?;
+        let field = |name: &str| {
+            manifest[name]
+                .as_str()
+                .ok_or_else(|| InventoryError::Manifest(format!("no {}", name)))
+        };
+        let format = match field("fileFormat")? {
+            "CSV" => InventoryFormat::Csv,
+            "Parquet" => InventoryFormat::Parquet,
+            other => {
+                return Err(InventoryError::Manifest(format!(
+                    "unsupported file format {}",
+                    other
+                )))
+            }
+        };
+        let columns = match format {
+            InventoryFormat::Csv => field("fileSchema")?
+                .split(',')
+                .map(|column| column.trim().to_string())
+                .collect(),
+            InventoryFormat::Parquet => vec![],
+        };
+        let files = manifest["files"]
+            .as_array()
+            .ok_or_else(|| InventoryError::Manifest("no files".to_string()))?
+            .iter()
+            .map(|file| {
+                file["key"]
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| InventoryError::Manifest("a file without key".to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        let destination = field("destinationBucket")?;
+        Ok(InventoryManifest {
+            source_bucket: field("sourceBucket")?.to_string(),
+            destination_bucket: destination
+                .strip_prefix("arn:aws:s3:::")
+                .unwrap_or(destination)
+                .to_string(),
+            format,
+            columns,
+            files,
+        })
+    }
+}
+
+/// the objects of an inventory CSV file, which has no header, with the `columns` of its
+/// manifest. keys are URL-encoded in the report. rows without a size, i.e. delete markers,
+/// are skipped.
+pub fn read_csv<R: BufRead>(
+    reader: R,
+    columns: &[String],
+) -> Result<Vec<InventoryObject>, InventoryError> {
+    let column = |name: &str| {
+        columns
+            .iter()
+            .position(|c| c == name)
+            .ok_or_else(|| InventoryError::Manifest(format!("no {} column", name)))
+    };
+    let (key_column, size_column, modified_column) =
+        (column("Key")?, column("Size")?, column("LastModifiedDate")?);
+    let mut objects = vec![];
+    for (idx, row) in reader.lines().enumerate() {
+        let row = row?;
+        if row.is_empty() {
+            continue;
+        }
+        let invalid = |message: String| InventoryError::Row {
+            line: idx + 1,
+            message,
+        };
+        let fields = csv_fields(&row);
+        let value = |column: usize| {
+            fields
+                .get(column)
+                .map(String::as_str)
+                .ok_or_else(|| invalid(format!("expected {} columns", columns.len())))
+        };
+        let (key, size, last_modified) = (
+            value(key_column)?,
+            value(size_column)?,
+            value(modified_column)?,
+        );
+        if size.is_empty() {
+            continue;
+        }
+        objects.push(InventoryObject {
+            key: unescape(&key.replace('+', " ")).into_owned(),
+            size: size
+                .parse()
+                .map_err(|_| invalid(format!("invalid size '{}'", size)))?,
+            last_modified: epoch_millis(last_modified)
+                .ok_or_else(|| invalid(format!("invalid modification date '{}'", last_modified)))?,
+        });
+    }
+    Ok(objects)
+}
+
+/// the fields of a CSV row, unquoted.
+fn csv_fields(row: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// the milliseconds since the epoch of an ISO 8601 UTC `timestamp`, e.g.
+/// `2021-03-01T12:00:00.000Z`.
+fn epoch_millis(timestamp: &str) -> Option<i64> {
+    let timestamp = timestamp.trim_end_matches('Z').replacen('T', " ", 1);
+    epoch_micros(&timestamp).map(|micros| micros.div_euclid(1000))
+}
+
+/// the objects of an inventory parquet file. rows without a size, i.e. delete markers, are
+/// skipped.
+pub fn read_parquet(bytes: Vec<u8>) -> Result<Vec<InventoryObject>, InventoryError> {
+    let reader = SerializedFileReader::new(SliceableCursor::new(bytes))?;
+    let mut objects = vec![];
+    for row in reader.get_row_iter(None)? {
+        let (mut key, mut size, mut last_modified) = (None, None, None);
+        for (name, field) in row.get_column_iter() {
+            match (name.as_str(), field) {
+                ("key", Field::Str(value)) => key = Some(value.clone()),
+                ("size", Field::Long(value)) => size = Some(*value.max(&0) as u64),
+                ("last_modified_date", Field::TimestampMillis(value)) => {
+                    last_modified = Some(*value as i64)
+                }
+                _ => {}
+            }
+        }
+        if let (Some(key), Some(size), Some(last_modified)) = (key, size, last_modified) {
+            objects.push(InventoryObject {
+                key,
+                size,
+                last_modified,
+            });
+        }
+    }
+    Ok(objects)
+}
+
+/// read the S3 Inventory report with the `manifest.json` at `manifest_uri`, e.g.
+/// `s3://inventory/bucket/config/2021-03-01T01-00Z/manifest.json`, and the objects listed in
+/// all of its data files.
+pub async fn load(
+    manifest_uri: &str,
+    options: &StorageOptions,
+) -> Result<(InventoryManifest, Vec<InventoryObject>), InventoryError> {
+    let backend = options.backend(manifest_uri)?;
+    let manifest = InventoryManifest::parse(&options.guard(backend.get_obj(manifest_uri)).await?)?;
+    let mut objects = vec![];
+    for key in &manifest.files {
+        let uri = format!("s3://{}/{}", manifest.destination_bucket, key);
+        let bytes = options.guard(backend.get_obj(&uri)).await?;
+        match manifest.format {
+            InventoryFormat::Csv if key.ends_with(".gz") => objects.extend(read_csv(
+                BufReader::new(GzDecoder::new(&bytes[..])),
+                &manifest.columns,
+            )?),
+            InventoryFormat::Csv => objects.extend(read_csv(&bytes[..], &manifest.columns)?),
+            InventoryFormat::Parquet => objects.extend(read_parquet(bytes)?),
+        }
+    }
+    Ok((manifest, objects))
+}
+
+/// the objects below the key `prefix` of a table, e.g. `tables/events/`, with their paths
+/// relative to it, like the paths of add actions.
+pub fn table_objects<'a>(
+    objects: &'a [InventoryObject],
+    prefix: &'a str,
+) -> impl Iterator<Item = (&'a str, &'a InventoryObject)> + 'a {
+    objects
+        .iter()
+        .filter_map(move |object| Some((object.key.strip_prefix(prefix)?, object)))
+}
+
+/// the tree of the data files below the key `prefix` of a table, see `table_objects`, sized
+/// by the inventory. without a log, all data files are in the tree, including those removed
+/// but not yet vacuumed. paths that don't parse as data files are skipped.
+pub fn table_tree(objects: &[InventoryObject], prefix: &str) -> DeltaTree<SizedDeltaFile> {
+    let entries = table_objects(objects, prefix)
+        .filter(|(path, _)| is_data_file(path) && FileKind::of(path) == FileKind::Data)
+        .map(|(path, object)| (path, (object.size, object.last_modified)));
+    build(
+        entries,
+        &DeltaTreeOptions::new().lenient(true),
+        &SparkFileNameCodec,
+        |file, (size, modification_time)| SizedDeltaFile {
+            file,
+            size,
+            modification_time,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn read_inventory_csv() {
+        let manifest = InventoryManifest::parse(
+            br#"{
+                "sourceBucket": "data",
+                "destinationBucket": "arn:aws:s3:::inventory",
+                "fileFormat": "CSV",
+                "fileSchema": "Bucket, Key, Size, LastModifiedDate, IsDeleteMarker",
+                "files": [{"key": "data/config/data/a.csv.gz", "size": 100}]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.destination_bucket, "inventory");
+        assert_eq!(manifest.files, vec!["data/config/data/a.csv.gz"]);
+
+        let csv = r#""data","t/d%3D1/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet","100","2021-03-01T12:00:00.123Z","false"
+"data","t/_delta_log/00000000000000000000.json","10","2021-03-01T12:00:00.000Z","false"
+"data","t/d%3D1/part-00001-00000000-0000-0000-0000-000000000002.c000.snappy.parquet","","2021-03-01T12:00:00.000Z","true"
+"data","t/d%3D2/notes+on+it.txt","5","1970-01-01T00:00:01.000Z","false"
+"data","other/x.parquet","1","2021-03-01T12:00:00.000Z","false"
+"#;
+        let objects = read_csv(csv.as_bytes(), &manifest.columns).unwrap();
+        assert_eq!(objects.len(), 4, "without the delete marker");
+        assert_eq!(
+            objects[3],
+            InventoryObject {
+                key: "other/x.parquet".to_string(),
+                size: 1,
+                last_modified: 1614600000000,
+            }
+        );
+        assert_eq!(objects[2].key, "t/d=2/notes on it.txt");
+        assert_eq!(objects[0].last_modified, 1614600000123);
+
+        let paths: Vec<_> = table_objects(&objects, "t/").map(|(p, _)| p).collect();
+        assert_eq!(paths.len(), 3);
+        let tree = table_tree(&objects, "t/");
+        assert_eq!(
+            tree.files(),
+            vec!["d=1/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet"]
+        );
+
+        let err = read_csv(
+            &b"\"data\",\"k\",\"x\",\"2021-03-01T12:00:00Z\""[..],
+            &manifest.columns,
+        );
+        assert!(matches!(err, Err(InventoryError::Row { line: 1, .. })));
+    }
+}
@@ -0,0 +1,72 @@
+use super::codec::{FileNameCodec, SparkFileNameCodec};
+use super::predicate::PartitionPredicate;
+use super::{children, partition_value, raw_dir, ParquetDeltaFile, TreeNode};
+use std::collections::HashMap;
+
+/// lazily renders the paths of a tree's files, depth first. subtrees whose partition value
+/// doesn't satisfy the predicates on their column are skipped without being visited.
+pub struct FileIter<'a, F, S> {
+    predicates: &'a [PartitionPredicate],
+    /// the directories in storage of files with changed partition values, see
+    /// `DeltaTree::raw_dirs`.
+    raw_dirs: &'a HashMap<ParquetDeltaFile, String>,
+    /// whether children are visited by value, see `DeltaTree::ordered`.
+    ordered: bool,
+    /// partition nodes still to visit, with the directory they're in.
+    stack: Vec<(String, &'a TreeNode<F, S>)>,
+    /// the leaf currently being rendered.
+    leaf: Option<(String, std::slice::Iter<'a, F>)>,
+}
+
+impl<'a, F, S> FileIter<'a, F, S> {
+    pub(crate) fn new(
+        root: &'a TreeNode<F, S>,
+        raw_dirs: &'a HashMap<ParquetDeltaFile, String>,
+        ordered: bool,
+        predicates: &'a [PartitionPredicate],
+    ) -> FileIter<'a, F, S> {
+        FileIter {
+            predicates,
+            raw_dirs,
+            ordered,
+            stack: vec![(String::new(), root)],
+            leaf: None,
+        }
+    }
+}
+
+impl<'a, F: AsRef<ParquetDeltaFile>, S> Iterator for FileIter<'a, F, S> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if let Some((dir, files)) = &mut self.leaf {
+                if let Some(file) = files.next() {
+                    let file = file.as_ref();
+                    let dir = raw_dir(self.raw_dirs, file, dir);
+                    return Some(format!("{}{}", dir, SparkFileNameCodec.encode(file)));
+                }
+                self.leaf = None;
+            }
+            let (dir, node) = self.stack.pop()?;
+            match node {
+                TreeNode::FileEntries { files } => self.leaf = Some((dir, files.iter())),
+                TreeNode::Partition { name, values } => {
+                    let predicates = self.predicates;
+                    let children = children(values, self.ordered);
+                    let matching = children.into_iter().filter(|(value, _)| {
+                        predicates
+                            .iter()
+                            .filter(|p| p.column() == name)
+                            .all(|p| p.matches(value.as_deref()))
+                    });
+                    // the stack is popped from the back, push the first child last.
+                    for (value, child) in matching.rev() {
+                        let child_dir = format!("{}{}={}/", dir, name, partition_value(value));
+                        self.stack.push((child_dir, child));
+                    }
+                }
+            }
+        }
+    }
+}
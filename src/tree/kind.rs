@@ -0,0 +1,111 @@
+use super::{DeltaTree, FileKind};
+use serde::Serialize;
+
+/// the files below a table root, e.g. from a storage listing, split by their kind. change
+/// data files keep `_change_data/` as the prefix of their tree. deletion vectors aren't
+/// parquet files and may sit below random prefixes, so they're kept as plain paths.
+#[derive(Debug)]
+pub struct FilesByKind {
+    pub data: DeltaTree,
+    pub change_data: DeltaTree,
+    pub deletion_vectors: Vec<String>,
+}
+
+/// the number of files of each kind.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize)]
+pub struct KindCounts {
+    pub data: usize,
+    pub change_data: usize,
+    pub deletion_vectors: usize,
+}
+
+impl KindCounts {
+    pub fn add(&mut self, kind: FileKind) {
+        match kind {
+            FileKind::Data => self.data += 1,
+            FileKind::ChangeData => self.change_data += 1,
+            FileKind::DeletionVector => self.deletion_vectors += 1,
+        }
+    }
+}
+
+impl FilesByKind {
+    /// route each of `paths`, relative to the table root, by `FileKind::of`.
+    pub fn from_paths<I>(paths: I) -> FilesByKind
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut data = vec![];
+        let mut change_data = vec![];
+        let mut deletion_vectors = vec![];
+        for path in paths {
+            let path = path.as_ref();
+            match FileKind::of(path) {
+                FileKind::Data => data.push(path.to_string()),
+                FileKind::ChangeData => change_data.push(path.to_string()),
+                FileKind::DeletionVector => deletion_vectors.push(path.to_string()),
+            }
+        }
+        FilesByKind {
+            data: DeltaTree::from_paths(&data),
+            change_data: DeltaTree::from_paths(&change_data),
+            deletion_vectors,
+        }
+    }
+
+    /// the paths of the files of `kind`, as they were passed in.
+    pub fn files(&self, kind: FileKind) -> Box<dyn Iterator<Item = String> + '_> {
+        match kind {
+            FileKind::Data => Box::new(prefixed(&self.data)),
+            FileKind::ChangeData => Box::new(prefixed(&self.change_data)),
+            FileKind::DeletionVector => Box::new(self.deletion_vectors.iter().cloned()),
+        }
+    }
+
+    pub fn counts(&self) -> KindCounts {
+        KindCounts {
+            data: self.data.file_iter(&[]).count(),
+            change_data: self.change_data.file_iter(&[]).count(),
+            deletion_vectors: self.deletion_vectors.len(),
+        }
+    }
+}
+
+fn prefixed(tree: &DeltaTree) -> impl Iterator<Item = String> + '_ {
+    tree.file_iter(&[])
+        .map(move |path| format!("{}{}", tree.prefix, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn route_files_by_kind() {
+        let uuid = "477077ae-1429-4633-b07a-0c0cb75caf55";
+        let paths = vec![
+            format!("d=1/part-00000-{}.c000.snappy.parquet", uuid),
+            format!("d=2/part-00001-{}.c000.snappy.parquet", uuid),
+            format!("_change_data/d=1/cdc-00000-{}.c000.snappy.parquet", uuid),
+            format!("deletion_vector_{}.bin", uuid),
+            format!("ab/deletion_vector_{}.bin", uuid),
+        ];
+        let files = FilesByKind::from_paths(&paths);
+        assert_eq!(
+            files.counts(),
+            KindCounts {
+                data: 2,
+                change_data: 1,
+                deletion_vectors: 2
+            }
+        );
+        let change_data: Vec<_> = files.files(FileKind::ChangeData).collect();
+        assert_eq!(change_data, vec![paths[2].clone()]);
+        assert_eq!(files.change_data.prefix, "_change_data/");
+        let mut data: Vec<_> = files.files(FileKind::Data).collect();
+        data.sort();
+        assert_eq!(data, paths[..2].to_vec());
+    }
+}
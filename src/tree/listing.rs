@@ -0,0 +1,150 @@
+use super::manifest::epoch_micros;
+use super::options::DeltaTreeOptions;
+use super::sized::SizedDeltaFile;
+use super::verify::is_data_file;
+use super::{build, DeltaTree, FileKind, SparkFileNameCodec};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::io::{self, BufRead};
+
+lazy_static! {
+    // a line of `aws s3 ls --recursive`: date, time, size and key.
+    static ref S3_LS_LINE: Regex =
+        Regex::new("^(\\d{4}-\\d{2}-\\d{2} \\d{2}:\\d{2}:\\d{2}) +(\\d+) (.+)$").unwrap();
+}
+
+/// a file of a captured listing, with its size and modification time if the listing has them.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListedFile {
+    pub path: String,
+    pub size: Option<u64>,
+    /// milliseconds since the epoch.
+    pub modification_time: Option<i64>,
+}
+
+/// the files of a captured listing: a path per line as printed by `find`, the lines of
+/// `aws s3 ls --recursive` with the date, time, size and key of each object, or the output
+/// of `ls -R` with a `dir:` line before the names in each directory. the times of
+/// `aws s3 ls` are local to where it ran, they're taken as UTC. empty lines are skipped.
+pub fn parse_listing<R: BufRead>(reader: R) -> io::Result<Vec<ListedFile>> {
+    let mut files = vec![];
+    // the directory of the names that follow, once an `ls -R` header was seen.
+    let mut dir: Option<String> = None;
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_suffix(':') {
+            let header = header.trim_start_matches("./").trim_start_matches('.');
+            dir = Some(match header {
+                "" => String::new(),
+                header => format!("{}/", header.trim_end_matches('/')),
+            });
+            continue;
+        }
+        let file = match (&dir, S3_LS_LINE.captures(line)) {
+            (Some(dir), _) => ListedFile {
+                path: format!("{}{}", dir, line),
+                size: None,
+                modification_time: None,
+            },
+            (None, Some(captures)) => ListedFile {
+                path: captures[3].to_string(),
+                size: captures[2].parse().ok(),
+                modification_time: epoch_micros(&captures[1]).map(|micros| micros / 1000),
+            },
+            (None, None) => ListedFile {
+                path: line.trim_start_matches("./").to_string(),
+                size: None,
+                modification_time: None,
+            },
+        };
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// the tree of the data files of a listing. hidden files like the log, directories and paths
+/// that don't parse as data files or don't share the prefix of the first data file are
+/// skipped. files without a size or modification time in the listing have 0.
+pub fn listing_tree(files: &[ListedFile]) -> DeltaTree<SizedDeltaFile> {
+    let entries = files
+        .iter()
+        .filter(|f| is_data_file(&f.path) && FileKind::of(&f.path) == FileKind::Data)
+        .map(|f| (f.path.as_str(), (f.size, f.modification_time)));
+    build(
+        entries,
+        &DeltaTreeOptions::new().lenient(true),
+        &SparkFileNameCodec,
+        |file, (size, modification_time)| SizedDeltaFile {
+            file,
+            size: size.unwrap_or(0),
+            modification_time: modification_time.unwrap_or(0),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const FILE_1: &str = "part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet";
+    const FILE_2: &str = "part-00000-00000000-0000-0000-0000-000000000002.c000.snappy.parquet";
+
+    #[test]
+    fn parse_listings() {
+        let find = format!(
+            "./_delta_log/00000000000000000000.json\n./d=1/{}\n\n./d=2/{}\n./d=2\n",
+            FILE_1, FILE_2
+        );
+        let files = parse_listing(find.as_bytes()).unwrap();
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[1].path, format!("d=1/{}", FILE_1));
+        let mut paths = listing_tree(&files).files();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![format!("d=1/{}", FILE_1), format!("d=2/{}", FILE_2)]
+        );
+
+        let s3 = format!(
+            "2021-03-01 12:00:00       1234 t/d=1/{}\n2021-03-01 12:00:01         10 t/_delta_log/00000000000000000000.json\n",
+            FILE_1
+        );
+        let files = parse_listing(s3.as_bytes()).unwrap();
+        assert_eq!(
+            files[0],
+            ListedFile {
+                path: format!("t/d=1/{}", FILE_1),
+                size: Some(1234),
+                modification_time: Some(1614600000000),
+            }
+        );
+        let tree = listing_tree(&files);
+        assert_eq!(tree.prefix, "t/");
+        assert_eq!(tree.partitions()[0].1[0].size, 1234);
+
+        let ls = format!(
+            ".:\n_delta_log\nd=1\n\n./_delta_log:\n00000000000000000000.json\n\n./d=1:\n{}\n",
+            FILE_1
+        );
+        let files = parse_listing(ls.as_bytes()).unwrap();
+        let paths: Vec<_> = files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "_delta_log".to_string(),
+                "d=1".to_string(),
+                "_delta_log/00000000000000000000.json".to_string(),
+                format!("d=1/{}", FILE_1),
+            ]
+        );
+        assert_eq!(
+            listing_tree(&files).files(),
+            vec![format!("d=1/{}", FILE_1)]
+        );
+    }
+}
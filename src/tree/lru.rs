@@ -0,0 +1,259 @@
+use super::codec::{FileNameCodec, SparkFileNameCodec};
+use super::{partition_value, DeltaTree, FileList, FxBuildHasher, ParquetDeltaFile, TreeNode};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// reloads the files of a single leaf, e.g. from the table's snapshot.
+pub trait LeafLoader {
+    /// the files directly in `dir`, a directory path as it appears in the log
+    /// (`date=2021-03-01/hour=00/`, including the table's prefix if any).
+    fn load(&self, dir: &str) -> Vec<ParquetDeltaFile>;
+}
+
+impl LeafLoader for Vec<String> {
+    fn load(&self, dir: &str) -> Vec<ParquetDeltaFile> {
+        let mut files: Vec<_> = self
+            .iter()
+            .filter_map(|path| path.strip_prefix(dir))
+            .filter(|name| !name.contains('/'))
+            .filter_map(|name| SparkFileNameCodec.decode(name))
+            .collect();
+        files.sort();
+        files
+    }
+}
+
+impl LeafLoader for deltalake::DeltaTable {
+    fn load(&self, dir: &str) -> Vec<ParquetDeltaFile> {
+        self.get_files().load(dir)
+    }
+}
+
+/// hit / miss counters of the leaf cache.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// a `DeltaTree` whose leaf file lists can be evicted: at most `capacity` files are kept in
+/// memory, the least recently used leaves are dropped first and reloaded via a `LeafLoader`
+/// when touched again. the partition structure and the number of files per leaf always stay
+/// in memory, so partition level questions never hit the loader.
+///
+/// reloading matches the leaf directory literally, trees built from paths with redundant
+/// segments or canonicalized partition values need a loader aware of that.
+#[derive(Debug)]
+pub struct LruDeltaTree<L> {
+    pub prefix: String,
+    root: Node,
+    leaves: Vec<Leaf>,
+    loader: L,
+    capacity: usize,
+    resident: usize,
+    clock: u64,
+    /// resident leaves by last access.
+    lru: BTreeMap<u64, usize>,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+enum Node {
+    Partition(HashMap<Option<Arc<str>>, Node, FxBuildHasher>),
+    Leaf(usize),
+}
+
+#[derive(Debug)]
+struct Leaf {
+    dir: String,
+    len: usize,
+    files: Option<FileList<ParquetDeltaFile>>,
+    last_access: u64,
+}
+
+impl<L: LeafLoader> LruDeltaTree<L> {
+    pub fn new<S>(
+        tree: DeltaTree<ParquetDeltaFile, S>,
+        loader: L,
+        capacity: usize,
+    ) -> LruDeltaTree<L> {
+        let mut leaves = vec![];
+        let root = convert(tree.root, tree.prefix.clone(), &mut leaves);
+        let mut lru_tree = LruDeltaTree {
+            prefix: tree.prefix,
+            root,
+            lru: (0..leaves.len()).map(|idx| (idx as u64, idx)).collect(),
+            clock: leaves.len() as u64,
+            resident: leaves.iter().map(|l| l.len).sum(),
+            leaves,
+            loader,
+            capacity,
+            stats: CacheStats::default(),
+        };
+        for (idx, leaf) in lru_tree.leaves.iter_mut().enumerate() {
+            leaf.last_access = idx as u64;
+        }
+        lru_tree.evict(None);
+        lru_tree
+    }
+
+    /// the files of the leaf at `values`, one partition value per level. reloaded if the
+    /// leaf was evicted, `None` if there's no such leaf.
+    pub fn files(&mut self, values: &[Option<&str>]) -> Option<&[ParquetDeltaFile]> {
+        let idx = match self.find(values)? {
+            Node::Leaf(idx) => *idx,
+            Node::Partition(_) => return None,
+        };
+        self.clock += 1;
+        let leaf = &mut self.leaves[idx];
+        self.lru.remove(&leaf.last_access);
+        leaf.last_access = self.clock;
+        self.lru.insert(self.clock, idx);
+        if leaf.files.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+            let files: FileList<_> = self.loader.load(&leaf.dir).into_iter().collect();
+            leaf.len = files.len();
+            leaf.files = Some(files);
+            self.resident += leaf.len;
+            self.evict(Some(idx));
+        }
+        self.leaves[idx].files.as_deref()
+    }
+
+    /// the number of files below the partition at `values`, without loading any leaves.
+    pub fn file_count(&self, values: &[Option<&str>]) -> Option<usize> {
+        fn count(node: &Node, leaves: &[Leaf]) -> usize {
+            match node {
+                Node::Leaf(idx) => leaves[*idx].len,
+                Node::Partition(values) => values.values().map(|n| count(n, leaves)).sum(),
+            }
+        }
+        self.find(values).map(|node| count(node, &self.leaves))
+    }
+
+    /// the number of files currently in memory.
+    pub fn resident_files(&self) -> usize {
+        self.resident
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    fn find(&self, values: &[Option<&str>]) -> Option<&Node> {
+        let mut node = &self.root;
+        for value in values {
+            node = match node {
+                Node::Partition(values) => values.get(&value.map(Arc::from))?,
+                Node::Leaf(_) => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// drop the least recently used leaves other than `keep` until at most `capacity` files
+    /// are resident.
+    fn evict(&mut self, keep: Option<usize>) {
+        let mut kept = vec![];
+        while self.resident > self.capacity {
+            let (access, idx) = match self.lru.iter().next() {
+                Some((access, idx)) => (*access, *idx),
+                None => break,
+            };
+            self.lru.remove(&access);
+            if Some(idx) == keep {
+                kept.push((access, idx));
+                continue;
+            }
+            let leaf = &mut self.leaves[idx];
+            leaf.files = None;
+            self.resident -= leaf.len;
+            self.stats.evictions += 1;
+        }
+        self.lru.extend(kept);
+    }
+}
+
+fn convert<S>(node: TreeNode<ParquetDeltaFile, S>, dir: String, leaves: &mut Vec<Leaf>) -> Node {
+    match node {
+        TreeNode::FileEntries { files } => {
+            leaves.push(Leaf {
+                dir,
+                len: files.len(),
+                files: Some(files),
+                last_access: 0,
+            });
+            Node::Leaf(leaves.len() - 1)
+        }
+        TreeNode::Partition { name, values } => {
+            let values = values
+                .into_iter()
+                .map(|(value, child)| {
+                    let child_dir = format!("{}{}={}/", dir, name, partition_value(&value));
+                    (value, convert(child, child_dir, leaves))
+                })
+                .collect();
+            Node::Partition(values)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    fn paths() -> Vec<String> {
+        (0..12)
+            .map(|idx| {
+                let dir = format!("s3://bucket/table/date=2021-03-{:02}/", idx % 3 + 1);
+                path(&dir, idx)
+            })
+            .collect()
+    }
+
+    fn leaf_files(day: u8) -> Vec<ParquetDeltaFile> {
+        let mut files: Vec<_> = paths()
+            .iter()
+            .filter(|p| p.contains(&format!("date=2021-03-{:02}/", day)))
+            .map(|p| ParquetDeltaFile::from_string(p.rsplit('/').next().unwrap()))
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn evicted_leaves_are_reloaded() {
+        let mut tree = LruDeltaTree::new(DeltaTree::from_paths(&paths()), paths(), 4);
+        assert_eq!(tree.resident_files(), 4);
+        assert_eq!(tree.stats().evictions, 2);
+
+        let day = |d: u8| format!("2021-03-{:02}", d);
+        assert_eq!(tree.files(&[Some(&day(1))]).unwrap(), &leaf_files(1)[..]);
+        let before = tree.stats();
+        for d in &[1, 2, 1] {
+            assert_eq!(tree.files(&[Some(&day(*d))]).unwrap(), &leaf_files(*d)[..]);
+            assert_eq!(tree.resident_files(), 4);
+        }
+        let after = tree.stats();
+        assert_eq!(after.hits - before.hits, 1);
+        assert_eq!(after.misses - before.misses, 2);
+        assert_eq!(after.evictions - before.evictions, 2);
+        assert_eq!(tree.files(&[Some("2021-03-04")]), None);
+        assert_eq!(tree.files(&[]), None);
+    }
+
+    #[test]
+    fn file_counts_do_not_load_leaves() {
+        let tree = LruDeltaTree::new(DeltaTree::from_paths(&paths()), paths(), 0);
+        assert_eq!(tree.resident_files(), 0);
+        assert_eq!(tree.file_count(&[]), Some(12));
+        assert_eq!(tree.file_count(&[Some("2021-03-02")]), Some(4));
+        assert_eq!(tree.file_count(&[Some("2021-03-04")]), None);
+        assert_eq!(tree.stats().misses, 0);
+    }
+}
@@ -0,0 +1,792 @@
+use super::canonical::PartitionType;
+use super::sized::SizedDeltaFile;
+use super::{CompressionType, DeltaTree, PartitionValue, TreeNode};
+#[cfg(feature = "arrow")]
+use arrow::array::{
+    ArrayRef, BooleanArray, Date32Array, Int64Array, StringArray, TimestampMicrosecondArray,
+    TimestampMillisecondArray,
+};
+#[cfg(feature = "arrow")]
+use arrow::datatypes::{Field, Schema};
+#[cfg(feature = "arrow")]
+use arrow::error::ArrowError;
+#[cfg(feature = "arrow")]
+use arrow::record_batch::RecordBatch;
+use parquet::basic::Compression;
+use parquet::column::writer::get_typed_column_writer_mut;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DataType, Int32Type, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{FileWriter, RowGroupWriter, SerializedFileWriter};
+use parquet::schema::parser::parse_message_type;
+use parquet::util::cursor::InMemoryWriteableCursor;
+#[cfg(feature = "sqlite")]
+use rusqlite::types::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// the columns of the manifest following the partition columns.
+pub const FILE_COLUMNS: [&str; 4] = ["file", "size", "compression", "modification_time"];
+
+/// the partition columns of `tree`, outermost first, taken from its first path.
+pub fn partition_columns<F, S>(tree: &DeltaTree<F, S>) -> Vec<String> {
+    let mut columns = vec![];
+    let mut node = &tree.root;
+    while let TreeNode::Partition { name, values } = node {
+        columns.push(name.clone());
+        match values.values().next() {
+            Some(child) => node = child,
+            None => break,
+        }
+    }
+    columns
+}
+
+/// the leaves of `tree` sorted by their partition values, `null` first.
+pub fn sorted_partitions<S>(
+    tree: &DeltaTree<SizedDeltaFile, S>,
+) -> Vec<(Vec<PartitionValue>, &[SizedDeltaFile])> {
+    let mut partitions = tree.partitions();
+    partitions.sort_by(|a, b| a.0.cmp(&b.0));
+    partitions
+}
+
+/// the modification time of `file` in RFC 3339, e.g. `2021-03-01T12:00:00.000Z`.
+pub fn modification_time(file: &SizedDeltaFile) -> String {
+    let millis = file.modification_time;
+    chrono::NaiveDateTime::from_timestamp_opt(
+        millis.div_euclid(1000),
+        (millis.rem_euclid(1000) * 1_000_000) as u32,
+    )
+    .map_or_else(String::new, |t| {
+        t.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+    })
+}
+
+/// write the manifest of `tree` as CSV: a header, then one row per file with a column for
+/// each partition column and the `FILE_COLUMNS`. `null` values are empty, the compression
+/// too if the file name has none.
+pub fn write_csv<W: Write, S>(tree: &DeltaTree<SizedDeltaFile, S>, out: &mut W) -> io::Result<()> {
+    let columns = partition_columns(tree);
+    let header: Vec<Cow<str>> = columns
+        .iter()
+        .map(|c| csv_field(c))
+        .chain(FILE_COLUMNS.iter().map(|c| Cow::Borrowed(*c)))
+        .collect();
+    writeln!(out, "{}", header.join(","))?;
+    for (values, files) in sorted_partitions(tree) {
+        let values: Vec<Cow<str>> = values
+            .iter()
+            .map(|v| v.value.as_deref().map_or(Cow::Borrowed(""), csv_field))
+            .collect();
+        for file in files {
+            for value in &values {
+                write!(out, "{},", value)?;
+            }
+            let compression = file.file.compression();
+            writeln!(
+                out,
+                "{},{},{},{}",
+                csv_field(&file.file.name()),
+                file.size,
+                compression.as_ref().map_or("", |c| c.to_string()),
+                modification_time(file)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// the values of a partition column, typed according to the partition type of the column.
+#[derive(Debug, PartialEq)]
+enum TypedColumn {
+    Integer(Vec<Option<i64>>),
+    Boolean(Vec<Option<bool>>),
+    /// days since the epoch.
+    Date(Vec<Option<i32>>),
+    /// microseconds since the epoch.
+    Timestamp(Vec<Option<i64>>),
+    String(Vec<Option<String>>),
+}
+
+impl TypedColumn {
+    /// the values of a partition column of type `data_type`. integers, booleans, dates and
+    /// timestamps are typed, timestamps as UTC, unless one of the values doesn't parse, then
+    /// the column holds the values as they are. decimals always do, a double would round them.
+    fn new(values: &[Option<&str>], data_type: PartitionType) -> TypedColumn {
+        let column = match data_type {
+            PartitionType::Integer => typed(values, |v| v.parse().ok()).map(TypedColumn::Integer),
+            PartitionType::Boolean => typed(values, |v| match v.to_ascii_lowercase().as_str() {
+                "true" => Some(true),
+                "false" => Some(false),
+                _ => None,
+            })
+            .map(TypedColumn::Boolean),
+            PartitionType::Date => typed(values, |v| {
+                let date = data_type.canonicalize(v);
+                epoch_days(&date).map(|days| days as i32)
+            })
+            .map(TypedColumn::Date),
+            PartitionType::Timestamp => typed(values, |v| {
+                let timestamp = data_type.canonicalize(v);
+                epoch_micros(&timestamp)
+            })
+            .map(TypedColumn::Timestamp),
+            PartitionType::String | PartitionType::Decimal => None,
+        };
+        column.unwrap_or_else(|| {
+            TypedColumn::String(values.iter().map(|v| v.map(str::to_string)).collect())
+        })
+    }
+
+    /// the field of the column in the parquet message type of the manifest.
+    fn parquet_field(&self, name: &str) -> String {
+        let field = match self {
+            TypedColumn::Integer(_) => "int64 {}",
+            TypedColumn::Boolean(_) => "boolean {}",
+            TypedColumn::Date(_) => "int32 {} (DATE)",
+            TypedColumn::Timestamp(_) => "int64 {} (TIMESTAMP_MICROS)",
+            TypedColumn::String(_) => "binary {} (UTF8)",
+        };
+        format!("    optional {};\n", field.replace("{}", name))
+    }
+
+    fn write_parquet(&self, row_group: &mut Box<dyn RowGroupWriter>) -> Result<(), ParquetError> {
+        match self {
+            TypedColumn::Integer(values) | TypedColumn::Timestamp(values) => {
+                write_optional::<Int64Type, _>(row_group, values, |v| *v)
+            }
+            TypedColumn::Boolean(values) => {
+                write_optional::<BoolType, _>(row_group, values, |v| *v)
+            }
+            TypedColumn::Date(values) => write_optional::<Int32Type, _>(row_group, values, |v| *v),
+            TypedColumn::String(values) => {
+                write_optional::<ByteArrayType, _>(row_group, values, |v| v.as_str().into())
+            }
+        }
+    }
+}
+
+/// `values` converted by `convert`, `None` if one of them doesn't convert.
+fn typed<T>(
+    values: &[Option<&str>],
+    convert: impl Fn(&str) -> Option<T>,
+) -> Option<Vec<Option<T>>> {
+    values
+        .iter()
+        .map(|value| match value {
+            Some(value) => convert(value).map(Some),
+            None => Some(None),
+        })
+        .collect()
+}
+
+/// the manifest of a tree column by column, a row per file in the order of
+/// `sorted_partitions`.
+struct ManifestColumns {
+    partitions: Vec<(String, TypedColumn)>,
+    names: Vec<String>,
+    sizes: Vec<i64>,
+    compressions: Vec<Option<CompressionType>>,
+    /// milliseconds since the epoch.
+    modification_times: Vec<i64>,
+}
+
+impl ManifestColumns {
+    /// the columns of the manifest of `tree`, the partition columns typed according to
+    /// `types`, see `TypedColumn::new`. columns without a type are strings.
+    fn new<S>(
+        tree: &DeltaTree<SizedDeltaFile, S>,
+        types: &HashMap<String, PartitionType>,
+    ) -> ManifestColumns {
+        let columns = partition_columns(tree);
+        let partitions = sorted_partitions(tree);
+        let mut values: Vec<Vec<Option<&str>>> = vec![vec![]; columns.len()];
+        let mut manifest = ManifestColumns {
+            partitions: vec![],
+            names: vec![],
+            sizes: vec![],
+            compressions: vec![],
+            modification_times: vec![],
+        };
+        for (partition, files) in &partitions {
+            for file in files.iter() {
+                for (column, value) in values.iter_mut().zip(partition) {
+                    column.push(value.value.as_deref());
+                }
+                manifest.names.push(file.file.name());
+                manifest.sizes.push(file.size as i64);
+                manifest.compressions.push(file.file.compression());
+                manifest.modification_times.push(file.modification_time);
+            }
+        }
+        manifest.partitions = columns
+            .into_iter()
+            .zip(&values)
+            .map(|(name, values)| {
+                let data_type = types.get(&name).copied().unwrap_or(PartitionType::String);
+                (name, TypedColumn::new(values, data_type))
+            })
+            .collect();
+        manifest
+    }
+}
+
+/// the days between 1970-01-01 and the canonical `yyyy-mm-dd` `date`.
+fn epoch_days(date: &str) -> Option<i64> {
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+/// the microseconds since the epoch of the canonical `yyyy-mm-dd hh:mm:ss[.f]` `timestamp`.
+pub(super) fn epoch_micros(timestamp: &str) -> Option<i64> {
+    let (date, time) = timestamp.split_at(timestamp.find(' ')?);
+    let (time, fraction) = match time[1..].find('.') {
+        Some(idx) => (&time[1..idx + 1], &time[idx + 2..]),
+        None => (&time[1..], ""),
+    };
+    let seconds = time.split(':').try_fold(0, |agg, part| {
+        part.parse::<i64>().ok().map(|p| agg * 60 + p)
+    })?;
+    if fraction.len() > 6 {
+        return None;
+    }
+    let micros = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<i64>().ok()? * 10i64.pow(6 - fraction.len() as u32)
+    };
+    Some((epoch_days(date)? * 86400 + seconds) * 1_000_000 + micros)
+}
+
+/// the manifest of `tree` as a parquet file with a row per file, like `write_csv`. the
+/// partition columns are typed according to `types`, see `TypedColumn::new`, columns
+/// without a type are strings. the modification time is a timestamp in milliseconds.
+pub fn to_parquet<S>(
+    tree: &DeltaTree<SizedDeltaFile, S>,
+    types: &HashMap<String, PartitionType>,
+) -> Result<Vec<u8>, ParquetError> {
+    let manifest = ManifestColumns::new(tree, types);
+    let mut schema = "message manifest {\n".to_string();
+    for (name, column) in &manifest.partitions {
+        schema.push_str(&column.parquet_field(name));
+    }
+    schema.push_str(
+        "    required binary file (UTF8);
+    required int64 size;
+    optional binary compression (UTF8);
+    required int64 modification_time (TIMESTAMP_MILLIS);
+}",
+    );
+
+    let schema = Arc::new(parse_message_type(&schema)?);
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let output = InMemoryWriteableCursor::default();
+    let mut writer = SerializedFileWriter::new(output.clone(), schema, Arc::new(properties))?;
+    let mut row_group = writer.next_row_group()?;
+    let rg = &mut row_group;
+    for (_, column) in &manifest.partitions {
+        column.write_parquet(rg)?;
+    }
+    let names: Vec<ByteArray> = manifest.names.iter().map(|n| n.as_str().into()).collect();
+    write_column::<ByteArrayType>(rg, &names, None)?;
+    write_column::<Int64Type>(rg, &manifest.sizes, None)?;
+    write_optional::<ByteArrayType, _>(rg, &manifest.compressions, |c| c.to_string().into())?;
+    write_column::<Int64Type>(rg, &manifest.modification_times, None)?;
+    writer.close_row_group(row_group)?;
+    writer.close()?;
+    Ok(output.data())
+}
+
+/// write the next column of the row group, `def` is `None` for required columns.
+fn write_column<T: DataType>(
+    row_group: &mut Box<dyn RowGroupWriter>,
+    values: &[T::T],
+    def: Option<&[i16]>,
+) -> Result<(), ParquetError> {
+    let mut writer = row_group
+        .next_column()?
+        .expect("a column for each field of the schema");
+    get_typed_column_writer_mut::<T>(&mut writer).write_batch(values, def, None)?;
+    row_group.close_column(writer)
+}
+
+/// write the next, optional column of the row group, converting its values by `convert`.
+fn write_optional<T: DataType, V>(
+    row_group: &mut Box<dyn RowGroupWriter>,
+    values: &[Option<V>],
+    convert: impl Fn(&V) -> T::T,
+) -> Result<(), ParquetError> {
+    let def: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+    let values: Vec<T::T> = values.iter().flatten().map(convert).collect();
+    write_column::<T>(row_group, &values, Some(&def))
+}
+
+/// the manifest of `tree` as an arrow record batch with a row per file, typed like `to_parquet`.
+#[cfg(feature = "arrow")]
+pub fn to_record_batch<S>(
+    tree: &DeltaTree<SizedDeltaFile, S>,
+    types: &HashMap<String, PartitionType>,
+) -> Result<RecordBatch, ArrowError> {
+    let manifest = ManifestColumns::new(tree, types);
+    let mut columns: Vec<(&str, ArrayRef, bool)> = vec![];
+    for (name, column) in &manifest.partitions {
+        let array: ArrayRef = match column {
+            TypedColumn::Integer(values) => Arc::new(Int64Array::from(values.clone())),
+            TypedColumn::Boolean(values) => Arc::new(BooleanArray::from(values.clone())),
+            TypedColumn::Date(values) => Arc::new(Date32Array::from(values.clone())),
+            TypedColumn::Timestamp(values) => {
+                Arc::new(TimestampMicrosecondArray::from(values.clone()))
+            }
+            TypedColumn::String(values) => Arc::new(StringArray::from(
+                values.iter().map(Option::as_deref).collect::<Vec<_>>(),
+            )),
+        };
+        columns.push((name, array, true));
+    }
+    let names: Vec<&str> = manifest.names.iter().map(String::as_str).collect();
+    let compressions: Vec<Option<&str>> = manifest
+        .compressions
+        .iter()
+        .map(|c| c.as_ref().map(CompressionType::to_string))
+        .collect();
+    columns.push((FILE_COLUMNS[0], Arc::new(StringArray::from(names)), false));
+    columns.push((
+        FILE_COLUMNS[1],
+        Arc::new(Int64Array::from(manifest.sizes.clone())),
+        false,
+    ));
+    columns.push((
+        FILE_COLUMNS[2],
+        Arc::new(StringArray::from(compressions)),
+        true,
+    ));
+    columns.push((
+        FILE_COLUMNS[3],
+        Arc::new(TimestampMillisecondArray::from(
+            manifest.modification_times.clone(),
+        )),
+        false,
+    ));
+
+    let fields = columns
+        .iter()
+        .map(|(name, array, nullable)| Field::new(name, array.data_type().clone(), *nullable))
+        .collect();
+    let arrays = columns.into_iter().map(|(_, array, _)| array).collect();
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+}
+
+/// write the manifest of `tree` to `out` as an arrow IPC file, which readers can memory-map
+/// instead of decoding it, see `to_record_batch`.
+#[cfg(feature = "arrow")]
+pub fn write_ipc_file<S, W: Write>(
+    tree: &DeltaTree<SizedDeltaFile, S>,
+    types: &HashMap<String, PartitionType>,
+    out: W,
+) -> Result<(), ArrowError> {
+    let batch = to_record_batch(tree, types)?;
+    let mut writer = arrow::ipc::writer::FileWriter::try_new(out, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()
+}
+
+/// write the manifest of `tree` to `out` in the arrow IPC stream format, for readers consuming
+/// a pipe, see `to_record_batch`.
+#[cfg(feature = "arrow")]
+pub fn write_ipc_stream<S, W: Write>(
+    tree: &DeltaTree<SizedDeltaFile, S>,
+    types: &HashMap<String, PartitionType>,
+    out: W,
+) -> Result<(), ArrowError> {
+    let batch = to_record_batch(tree, types)?;
+    let mut writer = arrow::ipc::writer::StreamWriter::try_new(out, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()
+}
+
+/// write the manifest of `tree` into the sqlite database of `connection`, replacing the tables
+/// of a previous export: `partitions` with a row per leaf partition, its path, a column per
+/// partition column typed according to `types`, its file count and bytes, and `files` with a
+/// row per file of a partition, like `write_csv`. the partition columns and the files'
+/// partitions and sizes are indexed.
+#[cfg(feature = "sqlite")]
+pub fn write_sqlite<S>(
+    tree: &DeltaTree<SizedDeltaFile, S>,
+    types: &HashMap<String, PartitionType>,
+    connection: &mut rusqlite::Connection,
+) -> rusqlite::Result<()> {
+    let columns = partition_columns(tree);
+    let partitions = sorted_partitions(tree);
+    let typed: Vec<(&str, Vec<Value>)> = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let values: Vec<Option<&str>> = partitions
+                .iter()
+                .map(|(values, _)| values.get(idx).and_then(|v| v.value.as_deref()))
+                .collect();
+            let data_type = types.get(name).copied().unwrap_or(PartitionType::String);
+            sqlite_column(&values, data_type)
+        })
+        .collect();
+
+    let transaction = connection.transaction()?;
+    let mut schema = "DROP TABLE IF EXISTS files;
+DROP TABLE IF EXISTS partitions;
+CREATE TABLE partitions (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL,
+"
+    .to_string();
+    for (name, (sql_type, _)) in columns.iter().zip(&typed) {
+        schema.push_str(&format!("    {} {},\n", quote_identifier(name), sql_type));
+    }
+    schema.push_str(
+        "    files INTEGER NOT NULL,
+    bytes INTEGER NOT NULL
+);
+CREATE TABLE files (
+    partition_id INTEGER NOT NULL REFERENCES partitions (id),
+    file TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    compression TEXT,
+    modification_time TEXT NOT NULL
+);",
+    );
+    transaction.execute_batch(&schema)?;
+    {
+        let placeholders = vec!["?"; columns.len() + 4].join(", ");
+        let mut insert_partition =
+            transaction.prepare(&format!("INSERT INTO partitions VALUES ({})", placeholders))?;
+        let mut insert_file = transaction.prepare("INSERT INTO files VALUES (?, ?, ?, ?, ?)")?;
+        for (row, (values, files)) in partitions.iter().enumerate() {
+            let id = row as i64 + 1;
+            let path: String = values.iter().map(|v| format!("{}/", v)).collect();
+            let bytes: u64 = files.iter().map(|f| f.size).sum();
+            let mut partition = vec![Value::Integer(id), Value::Text(path)];
+            partition.extend(typed.iter().map(|(_, values)| values[row].clone()));
+            partition.push(Value::Integer(files.len() as i64));
+            partition.push(Value::Integer(bytes as i64));
+            insert_partition.execute(rusqlite::params_from_iter(partition))?;
+            for file in files.iter() {
+                let compression = file.file.compression();
+                insert_file.execute(rusqlite::params![
+                    id,
+                    file.file.name(),
+                    file.size as i64,
+                    compression.as_ref().map(CompressionType::to_string),
+                    modification_time(file),
+                ])?;
+            }
+        }
+    }
+    // indexing after the inserts is faster than maintaining the indexes row by row.
+    let mut indexes = "CREATE INDEX files_partition ON files (partition_id);
+CREATE INDEX files_size ON files (size);
+"
+    .to_string();
+    for name in &columns {
+        indexes.push_str(&format!(
+            "CREATE INDEX {} ON partitions ({});\n",
+            quote_identifier(&format!("partitions_{}", name)),
+            quote_identifier(name)
+        ));
+    }
+    transaction.execute_batch(&indexes)?;
+    transaction.commit()
+}
+
+/// the declared type and the values of a partition column in sqlite, typed like
+/// `TypedColumn::new`. dates and timestamps are canonical text, which sqlite's date functions
+/// understand.
+#[cfg(feature = "sqlite")]
+fn sqlite_column(values: &[Option<&str>], data_type: PartitionType) -> (&'static str, Vec<Value>) {
+    let text = |value: &Option<&str>| {
+        value.map_or(Value::Null, |v| {
+            Value::Text(data_type.canonicalize(v).into_owned())
+        })
+    };
+    match TypedColumn::new(values, data_type) {
+        TypedColumn::Integer(integers) => (
+            "INTEGER",
+            integers
+                .into_iter()
+                .map(|v| v.map_or(Value::Null, Value::Integer))
+                .collect(),
+        ),
+        TypedColumn::Boolean(booleans) => (
+            "BOOLEAN",
+            booleans
+                .into_iter()
+                .map(|v| v.map_or(Value::Null, |b| Value::Integer(b as i64)))
+                .collect(),
+        ),
+        TypedColumn::Date(_) => ("DATE", values.iter().map(text).collect()),
+        TypedColumn::Timestamp(_) => ("TIMESTAMP", values.iter().map(text).collect()),
+        TypedColumn::String(strings) => (
+            "TEXT",
+            strings
+                .into_iter()
+                .map(|v| v.map_or(Value::Null, Value::Text))
+                .collect(),
+        ),
+    }
+}
+
+/// `name` as a quoted SQL identifier.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// a SQL script creating DuckDB views over the parquet manifest at `manifest`, see
+/// `to_parquet`, with the partition columns `columns`: `files`, `partitions` with the file
+/// counts and sizes per partition, `small_files` below `small_file_size` and
+/// `compaction_candidates`, the partitions with more than one small file.
+pub fn duckdb_views(manifest: &str, columns: &[String], small_file_size: u64) -> String {
+    let mut views = format!(
+        "-- views over the file manifest of a delta table, e.g. `duckdb -init manifest.sql`.
+-- the manifest path is relative to the directory duckdb is started in.
+CREATE OR REPLACE VIEW files AS
+SELECT * FROM read_parquet('{}');
+CREATE OR REPLACE VIEW partitions AS
+SELECT
+",
+        manifest.replace('\'', "''")
+    );
+    let columns: Vec<String> = columns.iter().map(|c| quote_identifier(c)).collect();
+    for column in &columns {
+        views.push_str(&format!("    {},\n", column));
+    }
+    views.push_str(&format!(
+        "    count(*) AS files,
+    sum(size) AS bytes,
+    min(size) AS min_size,
+    avg(size) AS avg_size,
+    max(size) AS max_size,
+    sum(CASE WHEN size < {} THEN 1 ELSE 0 END) AS small_files,
+    max(modification_time) AS last_modified
+FROM files",
+        small_file_size
+    ));
+    if !columns.is_empty() {
+        views.push_str(&format!("\nGROUP BY {}", columns.join(", ")));
+    }
+    views.push_str(&format!(
+        ";
+CREATE OR REPLACE VIEW small_files AS
+SELECT * FROM files WHERE size < {};
+CREATE OR REPLACE VIEW compaction_candidates AS
+SELECT * FROM partitions WHERE small_files > 1 ORDER BY small_files DESC, bytes;
+",
+        small_file_size
+    ));
+    views
+}
+
+/// `field`, quoted if it contains a delimiter, quote or line break.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn csv_manifest() {
+        let names = [
+            "part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+            "part-00001-00000000-0000-0000-0000-000000000002.c000.parquet",
+            "part-00000-00000000-0000-0000-0000-000000000003.c000.snappy.parquet",
+        ];
+        let entries = vec![
+            (format!("s3://bucket/t/a=1/b=x,y/{}", names[0]), 100),
+            (format!("s3://bucket/t/a=1/b=x,y/{}", names[1]), 20),
+            (
+                format!(
+                    "s3://bucket/t/a=__HIVE_DEFAULT_PARTITION__/b=z/{}",
+                    names[2]
+                ),
+                3,
+            ),
+        ];
+        let tree: DeltaTree<SizedDeltaFile> =
+            DeltaTree::from_entries(entries, |file, size| SizedDeltaFile {
+                file,
+                size,
+                modification_time: 1614600000123,
+            });
+        let mut out = vec![];
+        write_csv(&tree, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "a,b,file,size,compression,modification_time
+,z,{},3,snappy,2021-03-01T12:00:00.123Z
+1,\"x,y\",{},100,snappy,2021-03-01T12:00:00.123Z
+1,\"x,y\",{},20,,2021-03-01T12:00:00.123Z
+",
+                names[2], names[0], names[1]
+            )
+        );
+    }
+
+    #[test]
+    fn typed_partition_columns() {
+        assert_eq!(epoch_days("1970-01-01"), Some(0));
+        assert_eq!(epoch_days("2021-03-01"), Some(18687));
+        assert_eq!(epoch_days("1969-12-31"), Some(-1));
+        assert_eq!(epoch_days("2021-13-01"), None);
+        assert_eq!(
+            epoch_micros("2021-03-01 12:00:00.5"),
+            Some((18687 * 86400 + 12 * 3600) * 1_000_000 + 500_000)
+        );
+        assert_eq!(epoch_micros("2021-03-01"), None);
+
+        let values = [Some("2021-3-1"), None, Some("1970-01-02")];
+        let column = TypedColumn::new(&values, PartitionType::Date);
+        assert_eq!(column, TypedColumn::Date(vec![Some(18687), None, Some(1)]));
+        assert_eq!(
+            column.parquet_field("day"),
+            "    optional int32 day (DATE);\n"
+        );
+        assert_eq!(
+            TypedColumn::new(&[Some("007"), None], PartitionType::Integer),
+            TypedColumn::Integer(vec![Some(7), None])
+        );
+        let mixed = TypedColumn::new(&[Some("7"), Some("x")], PartitionType::Integer);
+        assert_eq!(
+            mixed,
+            TypedColumn::String(vec![Some("7".to_string()), Some("x".to_string())])
+        );
+        assert_eq!(mixed.parquet_field("n"), "    optional binary n (UTF8);\n");
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn arrow_manifest() {
+        use arrow::datatypes::{DataType, DateUnit, TimeUnit};
+        let entries = vec![
+            (
+                "d=2021-03-01/n=7/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet"
+                    .to_string(),
+                100,
+            ),
+            (
+                "d=__HIVE_DEFAULT_PARTITION__/n=x/part-00000-00000000-0000-0000-0000-000000000002.c000.parquet"
+                    .to_string(),
+                20,
+            ),
+        ];
+        let tree: DeltaTree<SizedDeltaFile> =
+            DeltaTree::from_entries(entries, |file, size| SizedDeltaFile {
+                file,
+                size,
+                modification_time: 1614600000123,
+            });
+        let types = vec![
+            ("d".to_string(), PartitionType::Date),
+            ("n".to_string(), PartitionType::Integer),
+        ]
+        .into_iter()
+        .collect();
+        let batch = to_record_batch(&tree, &types).unwrap();
+        let schema = batch.schema();
+        let fields: Vec<_> = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type().clone(), f.is_nullable()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("d", DataType::Date32(DateUnit::Day), true),
+                ("n", DataType::Utf8, true),
+                ("file", DataType::Utf8, false),
+                ("size", DataType::Int64, false),
+                ("compression", DataType::Utf8, true),
+                (
+                    "modification_time",
+                    DataType::Timestamp(TimeUnit::Millisecond, None),
+                    false
+                ),
+            ]
+        );
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.column(0).null_count(), 1);
+        assert_eq!(batch.column(4).null_count(), 1);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_partition_columns() {
+        let values = [Some("2021-3-1"), None];
+        assert_eq!(
+            sqlite_column(&values, PartitionType::Date),
+            (
+                "DATE",
+                vec![Value::Text("2021-03-01".to_string()), Value::Null]
+            )
+        );
+        assert_eq!(
+            sqlite_column(&[Some("TRUE"), Some("false")], PartitionType::Boolean),
+            ("BOOLEAN", vec![Value::Integer(1), Value::Integer(0)])
+        );
+        assert_eq!(
+            sqlite_column(&[Some("1.10")], PartitionType::Decimal),
+            ("TEXT", vec![Value::Text("1.10".to_string())])
+        );
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn duckdb_script() {
+        let columns = vec!["date".to_string(), "country".to_string()];
+        assert_eq!(
+            duckdb_views("out/it's.parquet", &columns, 1024),
+            "-- views over the file manifest of a delta table, e.g. `duckdb -init manifest.sql`.
+-- the manifest path is relative to the directory duckdb is started in.
+CREATE OR REPLACE VIEW files AS
+SELECT * FROM read_parquet('out/it''s.parquet');
+CREATE OR REPLACE VIEW partitions AS
+SELECT
+    \"date\",
+    \"country\",
+    count(*) AS files,
+    sum(size) AS bytes,
+    min(size) AS min_size,
+    avg(size) AS avg_size,
+    max(size) AS max_size,
+    sum(CASE WHEN size < 1024 THEN 1 ELSE 0 END) AS small_files,
+    max(modification_time) AS last_modified
+FROM files
+GROUP BY \"date\", \"country\";
+CREATE OR REPLACE VIEW small_files AS
+SELECT * FROM files WHERE size < 1024;
+CREATE OR REPLACE VIEW compaction_candidates AS
+SELECT * FROM partitions WHERE small_files > 1 ORDER BY small_files DESC, bytes;
+"
+        );
+        assert!(duckdb_views("m.parquet", &[], 1024).contains("FROM files;\n"));
+    }
+}
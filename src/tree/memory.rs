@@ -0,0 +1,134 @@
+use super::frontcoded::FrontCodedKeys;
+use super::packed::PackedFiles;
+use super::{file_list_heap_size, value_heap_size, DeltaTree, TreeNode};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// the bytes taken by the listing of a table in each representation, e.g. to decide whether
+/// the tree pays off for a table. estimates of the heap usage, not measured allocations.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct RepresentationReport {
+    pub files: usize,
+    /// the paths as a `Vec<String>` of exact capacities.
+    pub paths: usize,
+    /// the tree, after `shrink_to_fit`.
+    pub tree: usize,
+    /// the tree with leaves stored as `PackedFiles` where possible.
+    pub packed_tree: usize,
+    /// the partition values as stored in the maps of the tree, and front-coded.
+    pub partition_values: usize,
+    pub front_coded_partition_values: usize,
+    /// the `CompactDeltaTree`, if the `compact` feature is enabled.
+    pub compact_tree: Option<usize>,
+}
+
+/// compare the representations of the table listing `paths`.
+pub fn representation_report<I>(paths: I) -> RepresentationReport
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let paths: Vec<I::Item> = paths.into_iter().collect();
+    let paths_bytes = std::mem::size_of::<String>() * paths.len()
+        + paths.iter().map(|p| p.as_ref().len()).sum::<usize>();
+    let mut tree = DeltaTree::from_paths(&paths);
+    tree.shrink_to_fit();
+    RepresentationReport::from_tree(&tree, paths_bytes)
+}
+
+impl RepresentationReport {
+    /// the report of a tree already built, taking `paths` bytes as a `Vec<String>`.
+    pub fn from_tree(tree: &DeltaTree, paths: usize) -> RepresentationReport {
+        let (partition_values, front_coded_partition_values) = key_memory(&tree.root);
+        #[cfg(feature = "compact")]
+        let compact_tree = Some(super::compact::CompactDeltaTree::from_tree(tree).heap_size());
+        #[cfg(not(feature = "compact"))]
+        let compact_tree = None;
+        RepresentationReport {
+            files: tree.file_iter(&[]).count(),
+            paths,
+            tree: tree_memory(&tree.root) + tree.prefix.capacity(),
+            packed_tree: packed_memory(&tree.root) + tree.prefix.capacity(),
+            partition_values,
+            front_coded_partition_values,
+            compact_tree,
+        }
+    }
+}
+
+/// the memory of a tree: the files of the leaves, the names and the entries of the maps.
+pub fn tree_memory(tree: &TreeNode) -> usize {
+    match tree {
+        TreeNode::FileEntries { files } => file_list_heap_size(files),
+        TreeNode::Partition { name, values } => values.iter().fold(
+            map_memory(values.capacity()) + name.capacity(),
+            |agg, (key, value)| agg + value_heap_size(key) + tree_memory(value),
+        ),
+    }
+}
+
+/// like `tree_memory`, but with leaves stored as `PackedFiles` where possible.
+pub fn packed_memory(tree: &TreeNode) -> usize {
+    match tree {
+        TreeNode::FileEntries { files } => {
+            PackedFiles::pack(files).map_or_else(|| file_list_heap_size(files), |p| p.heap_size())
+        }
+        TreeNode::Partition { name, values } => values.iter().fold(
+            map_memory(values.capacity()) + name.capacity(),
+            |agg, (key, value)| agg + value_heap_size(key) + packed_memory(value),
+        ),
+    }
+}
+
+/// the table of a map of partition values with room for `capacity` entries: a slot for the key
+/// and child of each entry plus its control byte.
+fn map_memory(capacity: usize) -> usize {
+    capacity * (std::mem::size_of::<(Option<Arc<str>>, TreeNode)>() + 1)
+}
+
+/// memory of the partition values as stored in the child maps and when front-coded.
+pub fn key_memory(tree: &TreeNode) -> (usize, usize) {
+    match tree {
+        TreeNode::FileEntries { .. } => (0, 0),
+        TreeNode::Partition { values, .. } => {
+            let mut keys: Vec<Option<&str>> = values.keys().map(|k| k.as_deref()).collect();
+            keys.sort();
+            let own = (
+                values.keys().map(value_heap_size).sum(),
+                FrontCodedKeys::from_sorted(keys).heap_size(),
+            );
+            values
+                .values()
+                .map(key_memory)
+                .fold(own, |agg, m| (agg.0 + m.0, agg.1 + m.1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn report_of_representations() {
+        let paths: Vec<String> = (0..100u128)
+            .map(|id| path(&format!("date=2021-03-{:02}/", id % 10 + 1), id))
+            .collect();
+        let report = representation_report(&paths);
+        assert_eq!(report.files, 100);
+        assert_eq!(
+            report.paths,
+            100 * (std::mem::size_of::<String>() + paths[0].len())
+        );
+        assert!(report.tree < report.paths);
+        assert!(report.packed_tree <= report.tree);
+        // each value behind the reference counts of its `Arc<str>`.
+        assert_eq!(
+            report.partition_values,
+            10 * (2 * std::mem::size_of::<usize>() + "2021-03-01".len())
+        );
+        assert_eq!(report.compact_tree.is_some(), cfg!(feature = "compact"));
+    }
+}
@@ -0,0 +1,66 @@
+use super::codec::SparkFileNameCodec;
+use super::options::DeltaTreeOptions;
+use super::{build_with_metrics, DeltaTree};
+use serde::Serialize;
+
+/// how long building a tree took, e.g. for a service to record the refreshes of each table.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize)]
+pub struct BuildMetrics {
+    pub paths_parsed: usize,
+    /// nanoseconds spent parsing the paths, sorting them and building the nodes.
+    pub parse_ns: u64,
+    pub sort_ns: u64,
+    pub build_ns: u64,
+    /// partition and leaf nodes of the tree.
+    pub nodes_created: usize,
+}
+
+impl BuildMetrics {
+    pub fn total_ns(&self) -> u64 {
+        self.parse_ns + self.sort_ns + self.build_ns
+    }
+}
+
+impl DeltaTree {
+    /// like `new`, along with the metrics of the build.
+    pub fn new_with_metrics(delta_table: &deltalake::DeltaTable) -> (DeltaTree, BuildMetrics) {
+        let (tree, metrics) = DeltaTree::from_paths_with_metrics(delta_table.get_files());
+        (tree.with_txns(delta_table), metrics)
+    }
+
+    /// like `from_paths`, along with the metrics of the build.
+    pub fn from_paths_with_metrics<I>(input_files: I) -> (DeltaTree, BuildMetrics)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let input_files: Vec<I::Item> = input_files.into_iter().collect();
+        let entries = input_files.iter().map(|f| (f.as_ref(), ()));
+        build_with_metrics(
+            entries,
+            &DeltaTreeOptions::default(),
+            &SparkFileNameCodec,
+            |file, ()| file,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn metrics_of_a_build() {
+        let paths: Vec<String> = (0..6u128)
+            .map(|id| path(&format!("a={}/b={}/", id % 2, id % 3), id))
+            .collect();
+        let (tree, metrics) = DeltaTree::from_paths_with_metrics(&paths);
+        assert_eq!(tree, DeltaTree::from_paths(&paths));
+        assert_eq!(metrics.paths_parsed, 6);
+        // the root, two values of `a` with three values of `b` each.
+        assert_eq!(metrics.nodes_created, 1 + 2 + 6);
+        assert!(metrics.total_ns() >= metrics.build_ns);
+    }
+}
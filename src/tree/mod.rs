@@ -1,44 +1,351 @@
+#[cfg(feature = "roaring")]
+pub mod bitmap;
+#[cfg(feature = "zstd")]
+pub mod budget;
+pub mod builder;
+pub mod canonical;
+#[cfg(any(feature = "glue", feature = "unity"))]
+pub mod catalog;
+pub mod changes;
+#[cfg(feature = "commit")]
+pub mod checkpoint;
+pub mod clustering;
+pub mod codec;
+#[cfg(feature = "commit")]
+pub mod commit;
+#[cfg(feature = "compact")]
+pub mod compact;
+pub mod compaction;
+pub mod compare;
+pub mod consistency;
+pub mod cursor;
+pub mod diff;
+pub mod display;
+#[cfg(test)]
+mod fixtures;
+pub mod flamegraph;
+pub mod forest;
+pub mod frontcoded;
+pub mod generate;
+pub mod glob;
+pub mod history;
+pub mod html;
+pub mod intern;
+pub mod inventory;
+pub mod iter;
+pub mod kind;
+pub mod listing;
+pub mod lru;
+pub mod manifest;
+pub mod memory;
+pub mod metrics;
+#[cfg(feature = "commit")]
+pub mod optimize;
+pub mod options;
+pub mod packed;
+pub mod predicate;
+pub mod query;
+pub mod render;
+pub mod schema;
+pub mod serialize;
+pub mod sized;
+pub mod snapshot;
+pub mod stats;
+pub mod storage;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tombstones;
+pub mod vacuum;
+pub mod validate;
+pub mod verify;
+pub mod watcher;
+
+use canonical::PartitionType;
+use codec::{FileNameCodec, SparkFileNameCodec};
 use deltalake;
-use itertools::Itertools;
-use lazy_static::lazy_static;
-use regex::Regex;
+use futures::stream::Stream;
+use iter::FileIter;
+use metrics::BuildMetrics;
+use options::DeltaTreeOptions;
+use predicate::PartitionPredicate;
+use rustc_hash::FxHasher;
+use serde::Serialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Instant;
+use storage::StorageOptions;
 use uuid::Uuid;
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct DeltaTree {
-    pub root: TreeNode,
+/// the default hasher of the child maps. partition values are short strings, which FxHash
+/// handles considerably faster than std's DoS resistant SipHash.
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
+/// a delta table's files, organized along their partition values. the leaves store a
+/// `ParquetDeltaFile` per file by default, or any richer payload built on top of it. `S`
+/// is the hasher used for the maps of partition values. a tree isn't modified once built and
+/// is `Send + Sync` for the default payloads, share it between threads in an `Arc`, see
+/// `query::QueryContext`.
+#[derive(Debug)]
+pub struct DeltaTree<F = ParquetDeltaFile, S = FxBuildHasher> {
+    pub root: TreeNode<F, S>,
+    /// directories in front of the partition segments shared by all paths, e.g. the table
+    /// location if the log contains absolute paths or full URIs (`s3://bucket/table/`).
+    /// empty for the usual relative paths.
+    pub prefix: String,
+    /// the latest version of each application id of the log's transactions (`txn`), e.g. of
+    /// streaming writers with idempotent commits. empty unless built from a table.
+    pub txns: HashMap<String, deltalake::DeltaDataTypeVersion>,
+    /// whether the partition values were brought into a canonical form, see
+    /// `from_paths_canonical` and `canonicalize`. the partition values of such a tree may
+    /// differ from the directories in storage (`x=007` became `x=7`), see `raw_dirs`.
+    pub canonical: bool,
+    /// the directory in storage, relative to the prefix, of each file of a canonical tree
+    /// whose partition values were changed, e.g. `x=007/`. the paths of all other files are
+    /// rendered from their partition values.
+    pub raw_dirs: HashMap<ParquetDeltaFile, String>,
+    /// whether the files are listed in the order of their partition values, `null` first,
+    /// instead of the order of the maps, see `DeltaTreeOptions::ordered`.
+    pub ordered: bool,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum TreeNode {
+#[derive(Debug)]
+pub enum TreeNode<F = ParquetDeltaFile, S = FxBuildHasher> {
     /// a partition is a key and a map of all its values to the next lower level in the tree.
+    /// a `None` value represents `null`, which is distinct from the empty string. trees built
+    /// with an `intern::Interner` share equal values.
     Partition {
-        name: String,                      // the key / column name of the partition
-        values: HashMap<String, TreeNode>, // partition values mapped to the content
+        name: String, // the key / column name of the partition
+        values: HashMap<Option<Arc<str>>, TreeNode<F, S>, S>, // partition values mapped to the content
     },
 
     /// represent the contents of a single leaf directory: a set of parquet files.
-    FileEntries { files: Vec<ParquetDeltaFile> },
+    FileEntries { files: FileList<F> },
+}
+
+/// the files of a leaf. most leaves only contain a handful of files, with the `smallvec`
+/// feature enabled these are stored inline in the node instead of a separate allocation.
+#[cfg(feature = "smallvec")]
+pub type FileList<F> = smallvec::SmallVec<[F; 4]>;
+#[cfg(not(feature = "smallvec"))]
+pub type FileList<F> = Vec<F>;
+
+/// bytes allocated on the heap for the files of a leaf, excluding the inline part.
+pub fn file_list_heap_size<F>(files: &FileList<F>) -> usize {
+    #[cfg(feature = "smallvec")]
+    let capacity = if files.spilled() { files.capacity() } else { 0 };
+    #[cfg(not(feature = "smallvec"))]
+    let capacity = files.capacity();
+    std::mem::size_of::<F>() * capacity
+}
+
+/// bytes allocated on the heap for a partition value: the string behind the reference counts.
+/// a value shared by several partitions is counted for each of them.
+pub fn value_heap_size(value: &Option<Arc<str>>) -> usize {
+    value
+        .as_ref()
+        .map_or(0, |v| 2 * std::mem::size_of::<usize>() + v.len())
+}
+
+// implemented by hand, deriving would require `S: PartialEq` instead of `S: BuildHasher`.
+// the files of a leaf are compared regardless of their order, like the children of a
+// partition, so trees built from the same files in another order are equal.
+impl<F: AsRef<ParquetDeltaFile> + PartialEq, S: BuildHasher> PartialEq for DeltaTree<F, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root && self.prefix == other.prefix && self.txns == other.txns
+    }
+}
+
+impl<F: AsRef<ParquetDeltaFile> + Eq, S: BuildHasher> Eq for DeltaTree<F, S> {}
+
+impl<F: AsRef<ParquetDeltaFile> + PartialEq, S: BuildHasher> PartialEq for TreeNode<F, S> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                TreeNode::Partition { name, values },
+                TreeNode::Partition {
+                    name: other_name,
+                    values: other_values,
+                },
+            ) => name == other_name && values == other_values,
+            (TreeNode::FileEntries { files }, TreeNode::FileEntries { files: other_files }) => {
+                files == other_files || sorted_files(files) == sorted_files(other_files)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<F: AsRef<ParquetDeltaFile> + Eq, S: BuildHasher> Eq for TreeNode<F, S> {}
+
+impl<F: AsRef<ParquetDeltaFile> + Hash, S> Hash for DeltaTree<F, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.root.hash(state);
+        self.prefix.hash(state);
+        let mut txns: Vec<_> = self.txns.iter().collect();
+        txns.sort();
+        txns.hash(state);
+    }
+}
+
+/// consistent with `PartialEq`: the children of a partition are combined independent of
+/// their order in the map, the files of a leaf are hashed in their sorted order.
+impl<F: AsRef<ParquetDeltaFile> + Hash, S> Hash for TreeNode<F, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            TreeNode::Partition { name, values } => {
+                name.hash(state);
+                values.len().hash(state);
+                let children = values.iter().fold(0u64, |agg, child| {
+                    let mut hasher = FxHasher::default();
+                    child.hash(&mut hasher);
+                    agg.wrapping_add(hasher.finish())
+                });
+                state.write_u64(children);
+            }
+            TreeNode::FileEntries { files } => sorted_files(files).hash(state),
+        }
+    }
+}
+
+/// the files of a leaf ordered by file, files of the same name in their order in the leaf.
+fn sorted_files<F: AsRef<ParquetDeltaFile>>(files: &[F]) -> Vec<&F> {
+    let mut sorted: Vec<&F> = files.iter().collect();
+    sorted.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+    sorted
 }
 
 /// a single parquet file, represented in a compact partion / uuid / compression triple.
-/// TODO: figure out if other name components are variable, e.g. `c000`.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+/// the cluster (`c000`) and compression (`snappy`) components are optional, not all writers
+/// emit them.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct ParquetDeltaFile {
     partition: u32,
     uuid: Uuid,
-    cluster: u8,
-    compression: CompressionType,
+    cluster: Option<u8>,
+    compression: Option<CompressionType>,
+    layout: NameLayout,
+    kind: FileKind,
+}
+
+/// how the components of a file name are joined, kept to reconstruct the exact original name.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum NameLayout {
+    /// `part-00007-<uuid>.c000.snappy.parquet`
+    Dotted,
+    /// `part-00007-<uuid>-c000.snappy.parquet`, as written by spark.
+    Dashed,
+    /// `part-00007-tid-<tid>-<uuid>-<task>-<attempt>-c000.snappy.parquet`, as written by
+    /// databricks.
+    Task { tid: u64, task: u32, attempt: u32 },
+}
+
+/// what a file below the table root holds. change data files (`_change_data/cdc-...`) are
+/// parquet files like data files, deletion vectors (`deletion_vector_<uuid>.bin`) aren't and
+/// are never entries of a tree.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    Data,
+    ChangeData,
+    DeletionVector,
+}
+
+impl FileKind {
+    /// classify `path` relative to the table root by its directories and name.
+    pub fn of(path: &str) -> FileKind {
+        let mut segments = path.split(is_separator);
+        let name = segments.next_back().unwrap_or_default();
+        if name.starts_with("deletion_vector_") && name.ends_with(".bin") {
+            FileKind::DeletionVector
+        } else if segments.any(|segment| segment == CHANGE_DATA_DIR) {
+            FileKind::ChangeData
+        } else {
+            FileKind::Data
+        }
+    }
 }
 
+/// the directory below the table root holding the change data feed.
+pub const CHANGE_DATA_DIR: &str = "_change_data";
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct PartitionPath<'a> {
     key: &'a str,
-    value: &'a str,
+    value: Option<Cow<'a, str>>,
+}
+
+impl<'a> PartitionPath<'a> {
+    fn canonicalize(self, types: &HashMap<String, PartitionType>) -> PartitionPath<'a> {
+        match (types.get(self.key), self.value) {
+            (Some(data_type), Some(Cow::Borrowed(value))) => PartitionPath {
+                key: self.key,
+                value: Some(data_type.canonicalize(value)),
+            },
+            (Some(data_type), Some(Cow::Owned(value))) => PartitionPath {
+                key: self.key,
+                value: Some(Cow::Owned(data_type.canonicalize(&value).into_owned())),
+            },
+            (_, value) => PartitionPath {
+                key: self.key,
+                value,
+            },
+        }
+    }
+}
+
+/// a partition column and its value in a file's path, `None` for `null`. the owned
+/// counterpart of a directory like `date=2021-03-01`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct PartitionValue {
+    pub key: String,
+    pub value: Option<String>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+impl PartitionValue {
+    pub fn new(key: &str, value: Option<&str>) -> PartitionValue {
+        PartitionValue {
+            key: key.to_string(),
+            value: value.map(str::to_string),
+        }
+    }
+
+    /// the partition values encoded in the directories of `path`, outermost first.
+    pub fn from_dir(path: &str) -> Vec<PartitionValue> {
+        path.split(is_separator)
+            .filter_map(key_value)
+            .map(PartitionValue::from)
+            .collect()
+    }
+}
+
+impl<'a> From<PartitionPath<'a>> for PartitionValue {
+    fn from(path: PartitionPath<'a>) -> PartitionValue {
+        PartitionValue {
+            key: path.key.to_string(),
+            value: path.value.map(Cow::into_owned),
+        }
+    }
+}
+
+/// the directory segment, without the trailing separator.
+impl std::fmt::Display for PartitionValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}={}", self.key, partition_value(&self.value))
+    }
+}
+
+/// the path segment value representing a `null` partition value, following hive conventions.
+pub const NULL_PARTITION_VALUE: &str = "__HIVE_DEFAULT_PARTITION__";
+
+fn partition_value<V: Deref<Target = str>>(value: &Option<V>) -> &str {
+    value.as_deref().unwrap_or(NULL_PARTITION_VALUE)
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub enum CompressionType {
     SNAPPY,
     GZIP,
@@ -64,143 +371,742 @@ impl CompressionType {
     }
 }
 
-lazy_static! {
-    static ref FILENAME_REGEX: Regex = Regex::new(
-        "^part-(?P<part>\\d{5})-\
-                (?P<uuid>[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-\
-                [0-9a-fA-F]{4}-[0-9a-fA-F]{12})\\.c(?P<c>\\d{3})\\.\
-                (?P<compression>(snappy|gzip|none)).parquet"
-    )
-    .unwrap();
+impl AsRef<ParquetDeltaFile> for ParquetDeltaFile {
+    fn as_ref(&self) -> &ParquetDeltaFile {
+        self
+    }
 }
 
 impl ParquetDeltaFile {
-    fn from_string(name: &str) -> ParquetDeltaFile {
-        if let Some(caps) = FILENAME_REGEX.captures(name) {
-            let partition = caps["part"]
-                .parse::<u32>()
-                .unwrap_or_else(|_err| <u32>::max_value());
-            let uuid = Uuid::parse_str(&caps["uuid"]).unwrap();
-            let cluster = caps["c"].parse().unwrap();
-            let compression = CompressionType::from_str(&caps["compression"]);
-
-            ParquetDeltaFile {
-                partition,
-                uuid,
-                cluster,
-                compression,
-            }
-        } else {
-            panic!("unable to parse '{}'", name)
+    pub fn new(
+        partition: u32,
+        uuid: Uuid,
+        cluster: Option<u8>,
+        compression: Option<CompressionType>,
+    ) -> ParquetDeltaFile {
+        ParquetDeltaFile {
+            partition,
+            uuid,
+            cluster,
+            compression,
+            layout: NameLayout::Dotted,
+            kind: FileKind::Data,
         }
     }
-    fn name(&self) -> String {
-        format!(
-            "part-{:05}-{}.c{:03}.{}.parquet",
-            self.partition,
-            self.uuid,
-            self.cluster,
-            self.compression.to_string()
-        )
+
+    pub fn with_layout(self, layout: NameLayout) -> ParquetDeltaFile {
+        ParquetDeltaFile { layout, ..self }
+    }
+
+    /// a data file by default, or a change data file named `cdc-...`.
+    pub fn with_kind(self, kind: FileKind) -> ParquetDeltaFile {
+        assert!(
+            kind != FileKind::DeletionVector,
+            "deletion vectors aren't parquet files"
+        );
+        ParquetDeltaFile { kind, ..self }
+    }
+
+    pub fn partition(&self) -> u32 {
+        self.partition
     }
+
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    pub fn cluster(&self) -> Option<u8> {
+        self.cluster
+    }
+
+    pub fn compression(&self) -> Option<CompressionType> {
+        self.compression
+    }
+
+    pub fn layout(&self) -> NameLayout {
+        self.layout
+    }
+
+    pub fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    /// parse a file name using the default `SparkFileNameCodec`, panicking on foreign names.
+    pub fn from_string(name: &str) -> ParquetDeltaFile {
+        SparkFileNameCodec
+            .decode(name)
+            .unwrap_or_else(|| panic!("unable to parse '{}'", name))
+    }
+
+    /// the file name according to the default `SparkFileNameCodec`.
+    pub fn name(&self) -> String {
+        SparkFileNameCodec.encode(self)
+    }
+}
+
+/// the directory of `file` in storage: its entry in `raw_dirs`, else `dir` rendered from the
+/// partition values leading to it.
+fn raw_dir<'a>(
+    raw_dirs: &'a HashMap<ParquetDeltaFile, String>,
+    file: &ParquetDeltaFile,
+    dir: &'a str,
+) -> &'a str {
+    raw_dirs.get(file).map_or(dir, String::as_str)
+}
+
+/// a value of a partition and the node below it.
+pub(crate) type Child<'a, F, S> = (&'a Option<Arc<str>>, &'a TreeNode<F, S>);
+
+/// the children of a partition, by value with `null` first if `ordered`, else in the order
+/// of the map.
+fn children<F, S>(
+    values: &HashMap<Option<Arc<str>>, TreeNode<F, S>, S>,
+    ordered: bool,
+) -> Vec<Child<'_, F, S>> {
+    let mut children: Vec<_> = values.iter().collect();
+    if ordered {
+        children.sort_by_key(|(value, _)| *value);
+    }
+    children
+}
+
+/// the paths of all files below `node`, each starting with `prefix`.
+fn files_in_subtree<F: AsRef<ParquetDeltaFile>, S, C: FileNameCodec>(
+    prefix: &str,
+    node: &TreeNode<F, S>,
+    raw_dirs: &HashMap<ParquetDeltaFile, String>,
+    ordered: bool,
+    codec: &C,
+) -> Vec<String> {
+    match node {
+        TreeNode::FileEntries { files } => files
+            .iter()
+            .map(|f| {
+                let dir = raw_dir(raw_dirs, f.as_ref(), prefix);
+                format!("{}{}", dir, codec.encode(f.as_ref()))
+            })
+            .collect(),
+        TreeNode::Partition { name, values } => children(values, ordered)
+            .into_iter()
+            .flat_map(|(value, node)| {
+                let sub_prefix = format!("{}{}={}/", prefix, name, partition_value(value));
+                files_in_subtree(&sub_prefix, node, raw_dirs, ordered, codec)
+            })
+            .collect(), // vec![],
+    }
+}
+
+/// split off a leading URI scheme including the `://` separator, e.g. `s3://` or `abfss://`.
+fn split_scheme(path: &str) -> (&str, &str) {
+    match path.find("://") {
+        Some(idx)
+            if idx > 0
+                && path[..idx]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.') =>
+        {
+            path.split_at(idx + 3)
+        }
+        _ => ("", path),
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+fn is_redundant(segment: &str) -> bool {
+    segment.is_empty() || segment == "."
 }
 
 impl DeltaTree {
     pub fn new(delta_table: &deltalake::DeltaTable) -> DeltaTree {
-        DeltaTree::from_paths(delta_table.get_files())
+        DeltaTree::from_paths(delta_table.get_files()).with_txns(delta_table)
+    }
+
+    /// open the delta table at `table_uri` in its latest version and build its tree.
+    pub async fn open(table_uri: &str) -> Result<DeltaTree, deltalake::DeltaTableError> {
+        DeltaTree::open_with_options(table_uri, &StorageOptions::default()).await
+    }
+
+    /// like `open`, with credentials and other settings of the object store.
+    pub async fn open_with_options(
+        table_uri: &str,
+        options: &StorageOptions,
+    ) -> Result<DeltaTree, deltalake::DeltaTableError> {
+        Ok(DeltaTree::new(&options.open_table(table_uri).await?))
+    }
+
+    /// like `open`, for the given version of the table.
+    pub async fn open_with_version(
+        table_uri: &str,
+        version: deltalake::DeltaDataTypeVersion,
+    ) -> Result<DeltaTree, deltalake::DeltaTableError> {
+        let delta_table = StorageOptions::default()
+            .open_table_with_version(table_uri, version)
+            .await?;
+        Ok(DeltaTree::new(&delta_table))
+    }
+
+    /// like `open`, for the version of the table at `timestamp`, an RFC 3339 date time string
+    /// (`2021-03-01T12:00:00Z`).
+    pub async fn open_with_timestamp(
+        table_uri: &str,
+        timestamp: &str,
+    ) -> Result<DeltaTree, deltalake::DeltaTableError> {
+        let delta_table = StorageOptions::default()
+            .open_table_with_timestamp(table_uri, timestamp)
+            .await?;
+        Ok(DeltaTree::new(&delta_table))
+    }
+
+    /// like `new`, but partition values are brought into a canonical form according to the
+    /// column types in the table schema, so `day=2024-1-5` and `day=2024-01-05` end up in
+    /// the same partition.
+    pub fn new_canonical(delta_table: &deltalake::DeltaTable) -> DeltaTree {
+        let types = delta_table
+            .schema()
+            .map(canonical::partition_types)
+            .unwrap_or_default();
+        DeltaTree::from_paths_canonical(delta_table.get_files(), &types).with_txns(delta_table)
+    }
+
+    /// build a tree of only the files in partitions matching all `predicates`. other files
+    /// are skipped before their names are parsed, so the cost of the build and the size of the
+    /// tree depend on the selected partitions only.
+    pub fn load_filtered(
+        delta_table: &deltalake::DeltaTable,
+        predicates: &[PartitionPredicate],
+    ) -> DeltaTree {
+        DeltaTree::load_filtered_with_options(delta_table, predicates, &DeltaTreeOptions::default())
+    }
+
+    /// build a tree from the paths of its files, e.g. a `&Vec<String>`, a `&[&str]` or an
+    /// iterator of owned strings.
+    pub fn from_paths<I>(input_files: I) -> DeltaTree
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        DeltaTree::from_paths_with_options(input_files, &DeltaTreeOptions::default())
+    }
+
+    pub fn from_paths_filtered<I>(input_files: I, predicates: &[PartitionPredicate]) -> DeltaTree
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let options = DeltaTreeOptions::default();
+        DeltaTree::from_paths_filtered_with_options(input_files, predicates, &options)
+    }
+
+    /// build a tree, canonicalizing the values of the partition columns given in `types`.
+    /// the tree is `canonical`, its files keep their paths in storage, see `raw_dirs`.
+    pub fn from_paths_canonical<I>(
+        input_files: I,
+        types: &HashMap<String, PartitionType>,
+    ) -> DeltaTree
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let options = DeltaTreeOptions::new().partition_types(types.clone());
+        DeltaTree::from_paths_with_options(input_files, &options)
+    }
+
+    /// build a tree from files that don't follow spark's naming scheme. the same codec has to
+    /// be passed to `files_with_codec` to reconstruct the paths.
+    pub fn from_paths_with_codec<I, C>(input_files: I, codec: &C) -> DeltaTree
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        C: FileNameCodec,
+    {
+        let options = DeltaTreeOptions::default();
+        DeltaTree::from_paths_with_codec_and_options(input_files, codec, &options)
+    }
+}
+
+/// collect paths into a tree, like `from_paths`.
+impl FromIterator<String> for DeltaTree {
+    fn from_iter<I: IntoIterator<Item = String>>(paths: I) -> DeltaTree {
+        DeltaTree::from_paths(paths)
+    }
+}
+
+impl<'a> FromIterator<&'a String> for DeltaTree {
+    fn from_iter<I: IntoIterator<Item = &'a String>>(paths: I) -> DeltaTree {
+        DeltaTree::from_paths(paths)
+    }
+}
+
+impl<'a> FromIterator<&'a str> for DeltaTree {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(paths: I) -> DeltaTree {
+        DeltaTree::from_paths(paths)
+    }
+}
+
+/// the paths of all files including the common prefix, see `files_with_prefix`.
+impl<F: AsRef<ParquetDeltaFile>, S> IntoIterator for DeltaTree<F, S> {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.files_with_prefix().into_iter()
+    }
+}
+
+impl<F, S: BuildHasher + Default> DeltaTree<F, S> {
+    /// build a tree storing a richer payload per file, e.g. the size or statistics of its add
+    /// action. `payload` combines the parsed file and the data passed along with its path.
+    pub fn from_entries<T, P>(entries: Vec<(String, T)>, payload: P) -> DeltaTree<F, S>
+    where
+        P: FnMut(ParquetDeltaFile, T) -> F,
+    {
+        DeltaTree::from_entries_with_codec(entries, &SparkFileNameCodec, payload)
+    }
+
+    /// like `from_entries`, consuming the entries lazily while their paths are parsed, e.g. to
+    /// report the progress of the build.
+    pub fn from_entry_iter<'a, T, P>(
+        entries: impl Iterator<Item = (&'a str, T)>,
+        payload: P,
+    ) -> DeltaTree<F, S>
+    where
+        P: FnMut(ParquetDeltaFile, T) -> F,
+    {
+        DeltaTree::from_entry_iter_with_options(entries, &DeltaTreeOptions::default(), payload)
+    }
+
+    pub fn from_entries_with_codec<T, C, P>(
+        entries: Vec<(String, T)>,
+        codec: &C,
+        payload: P,
+    ) -> DeltaTree<F, S>
+    where
+        C: FileNameCodec,
+        P: FnMut(ParquetDeltaFile, T) -> F,
+    {
+        let options = DeltaTreeOptions::default();
+        DeltaTree::from_entries_with_codec_and_options(entries, codec, &options, payload)
     }
 
-    pub fn from_paths(input_files: &Vec<String>) -> DeltaTree {
-        if input_files.is_empty() {
-            DeltaTree {
-                root: TreeNode::FileEntries { files: vec![] },
+    /// replace the payload of every file, keeping the structure of the tree.
+    pub fn map_files<G, M: FnMut(F) -> G>(self, mut f: M) -> DeltaTree<G, S> {
+        fn map_node<F, G, S, M>(node: TreeNode<F, S>, f: &mut M) -> TreeNode<G, S>
+        where
+            S: BuildHasher + Default,
+            M: FnMut(F) -> G,
+        {
+            match node {
+                TreeNode::FileEntries { files } => TreeNode::FileEntries {
+                    files: files.into_iter().map(&mut *f).collect(),
+                },
+                TreeNode::Partition { name, values } => TreeNode::Partition {
+                    name,
+                    values: values
+                        .into_iter()
+                        .map(|(value, node)| (value, map_node(node, f)))
+                        .collect(),
+                },
             }
-        } else {
-            let components: Vec<(Vec<PartitionPath>, ParquetDeltaFile)> = input_files
-                .iter()
-                .map(|f| f.split('/').collect())
-                .map(|path| DeltaTree::parse_path(path))
-                .sorted()
-                .collect();
-            let partition = DeltaTree::build_partition(components.as_slice(), 0);
-            DeltaTree { root: partition }
+        }
+
+        DeltaTree {
+            root: map_node(self.root, &mut f),
+            prefix: self.prefix,
+            txns: self.txns,
+            canonical: self.canonical,
+            raw_dirs: self.raw_dirs,
+            ordered: self.ordered,
         }
     }
 
-    pub fn files(&self) -> Vec<String> {
-        fn files_in_subtree<'a>(prefix: &'a str, node: &TreeNode) -> Vec<String> {
+    /// release the spare capacity of all names, maps and file lists, e.g. after
+    /// pruning a tree or before measuring its memory.
+    pub fn shrink_to_fit(&mut self) {
+        fn shrink_node<F, S: BuildHasher + Default>(node: &mut TreeNode<F, S>) {
             match node {
-                TreeNode::FileEntries { files } => files
-                    .iter()
-                    .map(|f| format!("{}{}", prefix, f.name()))
-                    .collect(),
-                TreeNode::Partition { name, values } => values
-                    .iter()
-                    .flat_map(|(value, node)| {
-                        let sub_prefix = format!("{}{}={}/", prefix, name, value);
-                        files_in_subtree(&sub_prefix, node)
-                    })
-                    .collect(), // vec![],
+                TreeNode::FileEntries { files } => files.shrink_to_fit(),
+                TreeNode::Partition { name, values } => {
+                    name.shrink_to_fit();
+                    // keys can't be changed in place, move the entries to a map of exact size.
+                    let mut shrunk = HashMap::with_capacity_and_hasher(values.len(), S::default());
+                    // values are exact already, `Arc<str>` has no spare capacity.
+                    for (value, mut child) in values.drain() {
+                        shrink_node(&mut child);
+                        shrunk.insert(value, child);
+                    }
+                    *values = shrunk;
+                }
             }
         }
 
-        files_in_subtree("", &self.root)
+        shrink_node(&mut self.root);
+        self.prefix.shrink_to_fit();
+        self.txns.shrink_to_fit();
+        self.raw_dirs.shrink_to_fit();
+    }
+}
+
+impl<F, S> DeltaTree<F, S> {
+    /// take the application transactions of `delta_table`, as of the version the tree was
+    /// built from.
+    pub fn with_txns(self, delta_table: &deltalake::DeltaTable) -> DeltaTree<F, S> {
+        DeltaTree {
+            txns: delta_table.get_app_transaction_version().clone(),
+            ..self
+        }
     }
 
-    fn parse_path(mut path: Vec<&str>) -> (Vec<PartitionPath>, ParquetDeltaFile) {
-        let parquet = ParquetDeltaFile::from_string(path.pop().unwrap());
-        let remaining_path = path
+    /// the latest committed version of the application `app_id`, if it ever committed.
+    pub fn txn_version(&self, app_id: &str) -> Option<deltalake::DeltaDataTypeVersion> {
+        self.txns.get(app_id).copied()
+    }
+
+    /// the leaves of the tree with the partition values leading to them, outermost first.
+    pub fn partitions(&self) -> Vec<(Vec<PartitionValue>, &[F])> {
+        fn collect<'a, F, S>(
+            node: &'a TreeNode<F, S>,
+            ordered: bool,
+            values: &mut Vec<PartitionValue>,
+            leaves: &mut Vec<(Vec<PartitionValue>, &'a [F])>,
+        ) {
+            match node {
+                TreeNode::FileEntries { files } => leaves.push((values.clone(), &files[..])),
+                TreeNode::Partition {
+                    name,
+                    values: children,
+                } => {
+                    for (value, child) in self::children(children, ordered) {
+                        values.push(PartitionValue::new(name, value.as_deref()));
+                        collect(child, ordered, values, leaves);
+                        values.pop();
+                    }
+                }
+            }
+        }
+
+        let mut leaves = vec![];
+        collect(&self.root, self.ordered, &mut vec![], &mut leaves);
+        leaves
+    }
+
+    /// each file with its partition values, shaped like the `partitionValues` of its add
+    /// action.
+    pub fn partition_values(
+        &self,
+    ) -> impl Iterator<Item = (HashMap<String, Option<String>>, &F)> + '_ {
+        self.partitions().into_iter().flat_map(|(values, files)| {
+            let values: HashMap<String, Option<String>> =
+                values.into_iter().map(|p| (p.key, p.value)).collect();
+            files.iter().map(move |file| (values.clone(), file))
+        })
+    }
+}
+
+impl<F, S: BuildHasher> DeltaTree<F, S> {
+    /// the node below the partition directories given as `(column, value)` from the top,
+    /// e.g. `[("date", "2021-03-01")]` for all files of a day. `NULL_PARTITION_VALUE` stands
+    /// for `null`. `None` if a column doesn't match the level or the value doesn't exist.
+    pub fn subtree(&self, partitions: &[(&str, &str)]) -> Option<&TreeNode<F, S>> {
+        let mut node = &self.root;
+        for (column, value) in partitions {
+            node = match node {
+                TreeNode::Partition { name, values } if name == column => {
+                    let value = Some(*value)
+                        .filter(|&v| v != NULL_PARTITION_VALUE)
+                        .map(Arc::<str>::from);
+                    values.get(&value)?
+                }
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// the files of the leaf at the full tuple of partition values, see `subtree`. `None` if
+    /// `partitions` doesn't reach down to a leaf.
+    pub fn get(&self, partitions: &[(&str, &str)]) -> Option<&[F]> {
+        match self.subtree(partitions)? {
+            TreeNode::FileEntries { files } => Some(&files[..]),
+            TreeNode::Partition { .. } => None,
+        }
+    }
+}
+
+impl<F: AsRef<ParquetDeltaFile>, S> DeltaTree<F, S> {
+    /// the paths of all files, relative to the prefix. the files of canonical trees keep
+    /// their directories in storage, see `raw_dirs`.
+    pub fn files(&self) -> Vec<String> {
+        self.files_with_codec(&SparkFileNameCodec)
+    }
+
+    pub fn files_with_codec<C: FileNameCodec>(&self, codec: &C) -> Vec<String> {
+        files_in_subtree("", &self.root, &self.raw_dirs, self.ordered, codec)
+    }
+
+    /// all files including the common prefix, i.e. the paths / URIs as they were passed in
+    /// (modulo normalization of redundant segments).
+    pub fn files_with_prefix(&self) -> Vec<String> {
+        self.files()
             .into_iter()
-            .map(|part| DeltaTree::key_value(part).unwrap())
-            .collect();
-        (remaining_path, parquet)
+            .map(|f| format!("{}{}", self.prefix, f))
+            .collect()
     }
 
-    fn key_value(path: &str) -> Option<PartitionPath> {
-        if let Some(idx) = path.find('=') {
-            Some(PartitionPath {
-                key: &path[0..idx],
-                value: &path[idx + 1..],
+    /// the files in partitions matching all `predicates`, rendered on demand.
+    pub fn file_iter<'a>(&'a self, predicates: &'a [PartitionPredicate]) -> FileIter<'a, F, S> {
+        FileIter::new(&self.root, &self.raw_dirs, self.ordered, predicates)
+    }
+
+    /// like `file_iter`, as a stream for async consumers that want to start working on the
+    /// first files before the whole listing is rendered.
+    pub fn file_stream<'a>(
+        &'a self,
+        predicates: &'a [PartitionPredicate],
+    ) -> impl Stream<Item = String> + 'a {
+        futures::stream::iter(self.file_iter(predicates))
+    }
+}
+
+/// build a tree from paths and the data to be combined with each parsed file by `payload`.
+fn build<'a, T, F, S, C, P>(
+    entries: impl Iterator<Item = (&'a str, T)>,
+    options: &DeltaTreeOptions,
+    codec: &C,
+    payload: P,
+) -> DeltaTree<F, S>
+where
+    S: BuildHasher + Default,
+    C: FileNameCodec,
+    P: FnMut(ParquetDeltaFile, T) -> F,
+{
+    build_with_metrics(entries, options, codec, payload).0
+}
+
+/// like `build`, along with the time taken by each phase.
+fn build_with_metrics<'a, T, F, S, C, P>(
+    entries: impl Iterator<Item = (&'a str, T)>,
+    options: &DeltaTreeOptions,
+    codec: &C,
+    mut payload: P,
+) -> (DeltaTree<F, S>, BuildMetrics)
+where
+    S: BuildHasher + Default,
+    C: FileNameCodec,
+    P: FnMut(ParquetDeltaFile, T) -> F,
+{
+    let start = Instant::now();
+    let options = &*options.for_build();
+    let mut prefix: Option<(&str, Vec<&str>)> = None;
+    let mut raw_dirs = HashMap::new();
+    let mut components: Vec<(Vec<PartitionPath>, ParquetDeltaFile, T)> = entries
+        .filter_map(|(f, data)| {
+            let parsed = match parse_path(f, options, codec) {
+                Ok(parsed) => parsed,
+                Err(_) if options.is_lenient() => return None,
+                Err(err) => panic!("{}", err),
+            };
+            let (scheme, dirs, partitions, file, raw_dir) = parsed;
+            match &prefix {
+                Some(p) if p != &(scheme, dirs.clone()) && options.is_lenient() => return None,
+                Some(p) => assert_eq!(p, &(scheme, dirs), "unexpected prefix in '{}'", f),
+                None => prefix = Some((scheme, dirs)),
+            }
+            if let Some(raw_dir) = raw_dir {
+                raw_dirs.insert(file, raw_dir);
+            }
+            Some((partitions, file, data))
+        })
+        .collect();
+    let parsed = Instant::now();
+    components.sort_by(|(p1, f1, _), (p2, f2, _)| (p1, f1).cmp(&(p2, f2)));
+    let sorted = Instant::now();
+
+    let paths_parsed = components.len();
+    let mut paths = Vec::with_capacity(components.len());
+    let mut files = Vec::with_capacity(components.len());
+    for (partitions, file, data) in components {
+        paths.push(partitions);
+        files.push(payload(file, data));
+    }
+    let mut nodes_created = 0;
+    let root = build_partition(
+        paths.as_slice(),
+        0,
+        options,
+        &mut files.into_iter(),
+        &mut nodes_created,
+    );
+    let prefix = match prefix {
+        Some((scheme, dirs)) => prefix_of(scheme, &dirs),
+        None => String::new(),
+    };
+    let tree = DeltaTree {
+        root,
+        prefix,
+        txns: HashMap::new(),
+        canonical: options.is_canonical(),
+        raw_dirs,
+        ordered: options.is_ordered(),
+    };
+    let metrics = BuildMetrics {
+        paths_parsed,
+        parse_ns: (parsed - start).as_nanos() as u64,
+        sort_ns: (sorted - parsed).as_nanos() as u64,
+        build_ns: sorted.elapsed().as_nanos() as u64,
+        nodes_created,
+    };
+    (tree, metrics)
+}
+
+/// the prefix of a tree, from the scheme and leading directories split off by `parse_path`.
+fn prefix_of(scheme: &str, dirs: &[&str]) -> String {
+    if dirs.is_empty() {
+        scheme.to_string()
+    } else {
+        format!("{}{}/", scheme, dirs.join("/"))
+    }
+}
+
+/// split a path into its URI scheme, its leading non-partition directories, the partition
+/// segments and the parquet file. both `/` and windows' `\` are accepted as separators.
+/// empty and `.` segments (`a=1//./b=2`, `./a=1`) are dropped, the empty segment in front
+/// of an absolute path is kept so the prefix can be reconstructed.
+fn parse_path<'a, C: FileNameCodec>(
+    path: &'a str,
+    options: &DeltaTreeOptions,
+    codec: &C,
+) -> Result<ParsedPath<'a>, String> {
+    let (scheme, path) = split_scheme(path);
+    let mut segments: Vec<&str> = path
+        .split(is_separator)
+        .enumerate()
+        .filter(|&(idx, segment)| (idx == 0 && segment.is_empty()) || !is_redundant(segment))
+        .map(|(_, segment)| segment)
+        .collect();
+    let name = segments.pop().unwrap();
+    let parquet = codec
+        .decode(name)
+        .ok_or_else(|| format!("unable to parse '{}'", name))?;
+    let first_partition = segments
+        .iter()
+        .position(|segment| key_value(segment).is_some())
+        .unwrap_or(segments.len());
+    let partition_segments = segments.split_off(first_partition);
+    let remaining_path: Vec<PartitionPath> = partition_segments
+        .iter()
+        .map(|part| {
+            key_value(part)
+                .map(|partition| options.partition(partition))
+                .ok_or_else(|| format!("not a partition directory: '{}' in '{}'", part, path))
+        })
+        .collect::<Result<_, _>>()?;
+    // the directories rendered from changed values wouldn't exist in storage, keep the raw
+    // ones to rebuild the path.
+    let raw_dir = if options.is_canonical() {
+        let raw: String = partition_segments
+            .iter()
+            .map(|s| format!("{}/", s))
+            .collect();
+        let rendered: String = remaining_path
+            .iter()
+            .map(|p| {
+                format!(
+                    "{}={}/",
+                    p.key,
+                    p.value.as_deref().unwrap_or(NULL_PARTITION_VALUE)
+                )
             })
-        } else {
-            None
-        }
+            .collect();
+        Some(raw).filter(|raw| *raw != rendered)
+    } else {
+        None
+    };
+    Ok((scheme, segments, remaining_path, parquet, raw_dir))
+}
+
+/// the URI scheme, leading non-partition directories, partition segments and file of a path,
+/// and the raw partition directories if they differ from those rendered from the values.
+type ParsedPath<'a> = (
+    &'a str,
+    Vec<&'a str>,
+    Vec<PartitionPath<'a>>,
+    ParquetDeltaFile,
+    Option<String>,
+);
+
+fn key_value(path: &str) -> Option<PartitionPath> {
+    if let Some(idx) = path.find('=') {
+        Some(PartitionPath {
+            key: &path[0..idx],
+            value: Some(&path[idx + 1..])
+                .filter(|&v| v != NULL_PARTITION_VALUE)
+                .map(Cow::Borrowed),
+        })
+    } else {
+        None
     }
+}
 
-    fn build_partition(paths: &[(Vec<PartitionPath>, ParquetDeltaFile)], level: usize) -> TreeNode {
-        match paths {
-            [first_entry, ..] => {
-                if let Some(p1) = first_entry.0.get(level) {
-                    let name = p1.key;
-                    let mut current_value = p1.value;
-                    let mut current_index = 0;
-                    let mut children: HashMap<String, TreeNode> = HashMap::new();
-                    // paths.partition_point()
-                    for (idx, path) in paths.iter().enumerate() {
-                        assert_eq!(path.0.len(), first_entry.0.len());
-                        let &PartitionPath { key, value } = path.0.get(level).unwrap();
-                        assert_eq!(key, name);
-                        if value != current_value {
-                            let child =
-                                DeltaTree::build_partition(&paths[current_index..idx], level + 1);
-                            children.insert(current_value.to_string(), child);
-                            current_value = value;
-                            current_index = idx;
-                        }
-                    }
-                    let last_child = DeltaTree::build_partition(&paths[current_index..], level + 1);
-                    children.insert(current_value.to_string(), last_child);
-                    TreeNode::Partition {
-                        name: name.to_string(),
-                        values: children,
+/// build the node for `paths` (sorted), taking the payloads of its leaves from `files` in the
+/// same order. `nodes` counts the nodes created.
+fn build_partition<F, S, I>(
+    paths: &[Vec<PartitionPath>],
+    level: usize,
+    options: &DeltaTreeOptions,
+    files: &mut I,
+    nodes: &mut usize,
+) -> TreeNode<F, S>
+where
+    S: BuildHasher + Default,
+    I: Iterator<Item = F>,
+{
+    *nodes += 1;
+    match paths {
+        [first_entry, ..] => {
+            if let Some(p1) = first_entry.get(level) {
+                let name = p1.key;
+                let mut current_value = &p1.value;
+                let mut current_index = 0;
+                // the paths are sorted, every change of the value starts another child.
+                let distinct = 1 + paths
+                    .windows(2)
+                    .filter(|w| w[0][level].value != w[1][level].value)
+                    .count();
+                let mut children: HashMap<Option<Arc<str>>, TreeNode<F, S>, S> =
+                    HashMap::with_capacity_and_hasher(distinct, S::default());
+                for (idx, path) in paths.iter().enumerate() {
+                    assert_eq!(path.len(), first_entry.len());
+                    let PartitionPath { key, value } = path.get(level).unwrap();
+                    assert_eq!(*key, name);
+                    if value != current_value {
+                        let paths = &paths[current_index..idx];
+                        let child = build_partition(paths, level + 1, options, files, nodes);
+                        children.insert(current_value.as_deref().map(|v| options.value(v)), child);
+                        current_value = value;
+                        current_index = idx;
                     }
-                } else {
-                    let files: Vec<ParquetDeltaFile> = paths.iter().map(|pf| pf.1).collect();
-                    TreeNode::FileEntries { files }
                 }
+                let paths = &paths[current_index..];
+                let last_child = build_partition(paths, level + 1, options, files, nodes);
+                children.insert(
+                    current_value.as_deref().map(|v| options.value(v)),
+                    last_child,
+                );
+                TreeNode::Partition {
+                    name: name.to_string(),
+                    values: children,
+                }
+            } else {
+                let files: FileList<F> = files.take(paths.len()).collect();
+                TreeNode::FileEntries { files }
             }
-            [] => TreeNode::FileEntries { files: vec![] },
         }
+        [] => TreeNode::FileEntries {
+            files: FileList::new(),
+        },
     }
 }
 
@@ -219,26 +1125,34 @@ mod tests {
     const FE1: ParquetDeltaFile = ParquetDeltaFile {
         partition: 7,
         uuid: Uuid::from_u128(0),
-        cluster: 0,
-        compression: SNAPPY,
+        cluster: Some(0),
+        compression: Some(SNAPPY),
+        layout: NameLayout::Dotted,
+        kind: FileKind::Data,
     };
     const FE2: ParquetDeltaFile = ParquetDeltaFile {
         partition: 7,
         uuid: Uuid::from_u128(1),
-        cluster: 1,
-        compression: SNAPPY,
+        cluster: Some(1),
+        compression: Some(SNAPPY),
+        layout: NameLayout::Dotted,
+        kind: FileKind::Data,
     };
     const FE3: ParquetDeltaFile = ParquetDeltaFile {
         partition: 7,
         uuid: Uuid::from_u128(2),
-        cluster: 2,
-        compression: SNAPPY,
+        cluster: Some(2),
+        compression: Some(SNAPPY),
+        layout: NameLayout::Dotted,
+        kind: FileKind::Data,
     };
     const FE4: ParquetDeltaFile = ParquetDeltaFile {
         partition: 7,
         uuid: Uuid::from_u128(3),
-        cluster: 3,
-        compression: SNAPPY,
+        cluster: Some(3),
+        compression: Some(SNAPPY),
+        layout: NameLayout::Dotted,
+        kind: FileKind::Data,
     };
 
     #[test]
@@ -252,8 +1166,13 @@ mod tests {
         let tree = DeltaTree::from_paths(&paths);
         let expected = DeltaTree {
             root: TreeNode::FileEntries {
-                files: vec![FE1, FE2, FE3, FE4],
+                files: vec![FE1, FE2, FE3, FE4].into(),
             },
+            prefix: String::new(),
+            txns: HashMap::new(),
+            canonical: false,
+            raw_dirs: HashMap::new(),
+            ordered: false,
         };
         assert_eq!(expected, tree);
     }
@@ -267,6 +1186,142 @@ mod tests {
         assert_eq!(files, files_from_tree);
     }
 
+    #[test]
+    fn collect_and_iterate_paths() {
+        let paths = vec![
+            "s3://bucket/table/a=1/".to_string() + F1,
+            "s3://bucket/table/a=2/".to_string() + F2,
+        ];
+        let tree: DeltaTree = paths.iter().collect();
+        assert_eq!(tree, DeltaTree::from_paths(&paths));
+        assert_eq!(tree, paths.iter().map(String::as_str).collect());
+        assert_eq!(tree, paths.clone().into_iter().collect());
+
+        let mut files: Vec<String> = tree.into_iter().collect();
+        files.sort();
+        assert_eq!(files, paths);
+    }
+
+    #[test]
+    fn paths_from_slices_and_iterators() {
+        let paths = ["a=1/".to_string() + F1, "a=2/".to_string() + F2];
+        let expected = DeltaTree::from_paths(&paths.to_vec());
+        let slice: Vec<&str> = paths.iter().map(String::as_str).collect();
+        assert_eq!(DeltaTree::from_paths(&slice[..]), expected);
+        assert_eq!(DeltaTree::from_paths(paths.iter()), expected);
+        assert_eq!(DeltaTree::from_paths(paths.iter().cloned()), expected);
+    }
+
+    #[test]
+    fn lookup_partitions() {
+        let tree = DeltaTree::from_paths(&vec![
+            "a=1/b=7/".to_string() + F1,
+            "a=1/b=7/".to_string() + F2,
+            "a=1/b=__HIVE_DEFAULT_PARTITION__/".to_string() + F3,
+            "a=2/b=7/".to_string() + F4,
+        ]);
+        let mut files = tree.get(&[("a", "1"), ("b", "7")]).unwrap().to_vec();
+        files.sort();
+        assert_eq!(files, vec![FE1, FE2]);
+        assert_eq!(
+            tree.get(&[("a", "1"), ("b", NULL_PARTITION_VALUE)]),
+            Some(&[FE3][..])
+        );
+        assert_eq!(tree.get(&[("a", "1")]), None);
+        assert_eq!(tree.get(&[("a", "3"), ("b", "7")]), None);
+        assert_eq!(tree.get(&[("b", "7"), ("a", "1")]), None);
+        assert_eq!(tree.get(&[("a", "1"), ("b", "7"), ("c", "1")]), None);
+        assert!(tree.subtree(&[("a", "2")]).is_some());
+        assert_eq!(tree.subtree(&[]), Some(&tree.root));
+    }
+
+    #[test]
+    fn owned_partition_values() {
+        let values = PartitionValue::from_dir("s3://bucket/t/a=1/b=__HIVE_DEFAULT_PARTITION__/");
+        assert_eq!(
+            values,
+            vec![
+                PartitionValue::new("a", Some("1")),
+                PartitionValue::new("b", None)
+            ]
+        );
+        let dirs: Vec<String> = values.iter().map(PartitionValue::to_string).collect();
+        assert_eq!(dirs, vec!["a=1", "b=__HIVE_DEFAULT_PARTITION__"]);
+
+        let tree = DeltaTree::from_paths(&vec![
+            "a=1/b=__HIVE_DEFAULT_PARTITION__/".to_string() + F1,
+            "a=2/b=7/".to_string() + F2,
+        ]);
+        let mut partitions = tree.partitions();
+        partitions.sort();
+        assert_eq!(
+            partitions,
+            vec![
+                (values, &[FE1][..]),
+                (
+                    vec![
+                        PartitionValue::new("a", Some("2")),
+                        PartitionValue::new("b", Some("7"))
+                    ],
+                    &[FE2][..]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn files_with_partition_values() {
+        let paths = vec![
+            "a=1/b=__HIVE_DEFAULT_PARTITION__/".to_string() + F1,
+            "a=1/b=__HIVE_DEFAULT_PARTITION__/".to_string() + F2,
+            "a=2/b=7/".to_string() + F3,
+        ];
+        let tree = DeltaTree::from_paths(&paths);
+        let mut files: Vec<_> = tree
+            .partition_values()
+            .map(|(values, file)| (file.name(), values))
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        let null: HashMap<_, _> = vec![
+            ("a".to_string(), Some("1".to_string())),
+            ("b".to_string(), None),
+        ]
+        .into_iter()
+        .collect();
+        let seven: HashMap<_, _> = vec![
+            ("a".to_string(), Some("2".to_string())),
+            ("b".to_string(), Some("7".to_string())),
+        ]
+        .into_iter()
+        .collect();
+        let mut expected = vec![
+            (F1.to_string(), null.clone()),
+            (F2.to_string(), null),
+            (F3.to_string(), seven),
+        ];
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(files, expected);
+    }
+
+    #[test]
+    fn shrink_a_tree() {
+        let paths = vec!["a=1/b=1/".to_string() + F1, "a=4/b=2/".to_string() + F2];
+        let mut tree = DeltaTree::from_paths(&paths);
+        if let TreeNode::Partition { name, values } = &mut tree.root {
+            name.reserve(100);
+            values.reserve(100);
+        }
+        tree.shrink_to_fit();
+        match &tree.root {
+            TreeNode::Partition { name, values } => {
+                assert_eq!(name.capacity(), 1);
+                assert!(values.capacity() < 100);
+            }
+            TreeNode::FileEntries { .. } => panic!("expected a partition"),
+        }
+        assert_eq!(tree, DeltaTree::from_paths(&paths));
+    }
+
     #[test]
     fn tree_parse_nested_partitions() {
         let nested_paths: Vec<String> = vec![
@@ -279,15 +1334,378 @@ mod tests {
         let level_a_1_b = create_leaf_partition("b", vec![("1", FE1), ("7", FE3)]);
         let level_a_4_b = create_leaf_partition("b", vec![("1", FE4), ("2", FE2)]);
         let root = create_partition("a", vec![("1", level_a_1_b), ("4", level_a_4_b)]);
-        let expected = DeltaTree { root };
+        let expected = DeltaTree {
+            root,
+            prefix: String::new(),
+            txns: HashMap::new(),
+            canonical: false,
+            raw_dirs: HashMap::new(),
+            ordered: false,
+        };
 
         let actual = DeltaTree::from_paths(&nested_paths);
 
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn tree_parse_redundant_segments() {
+        let relative: Vec<String> = vec!["a=1/b=1/".to_string() + F1, "a=4/b=2/".to_string() + F2];
+        let redundant: Vec<String> = vec![
+            "./a=1//b=1/".to_string() + F1,
+            "a=4/./b=2//".to_string() + F2,
+        ];
+        assert_eq!(
+            DeltaTree::from_paths(&relative),
+            DeltaTree::from_paths(&redundant)
+        );
+    }
+
+    #[test]
+    fn tree_parse_absolute_paths() {
+        let absolute: Vec<String> = vec![
+            "/data//table/a=1/b=1/".to_string() + F1,
+            "/data/table/./a=4/b=2/".to_string() + F2,
+        ];
+        let tree = DeltaTree::from_paths(&absolute);
+        assert_eq!(tree.prefix, "/data/table/");
+        assert_eq!(
+            tree.root,
+            DeltaTree::from_paths(&vec![
+                "a=1/b=1/".to_string() + F1,
+                "a=4/b=2/".to_string() + F2
+            ])
+            .root
+        );
+
+        let flat = DeltaTree::from_paths(&vec!["/".to_string() + F1]);
+        assert_eq!(flat.prefix, "/");
+        assert_eq!(flat.files(), vec![F1.to_string()]);
+    }
+
+    #[test]
+    fn tree_parse_uris() {
+        let uris: Vec<String> = vec![
+            "s3://bucket/tables/events/a=1/b=1/".to_string() + F1,
+            "s3://bucket/tables//events/a=4/b=2/".to_string() + F2,
+        ];
+        let tree = DeltaTree::from_paths(&uris);
+        assert_eq!(tree.prefix, "s3://bucket/tables/events/");
+        assert_eq!(
+            tree.root,
+            DeltaTree::from_paths(&vec![
+                "a=1/b=1/".to_string() + F1,
+                "a=4/b=2/".to_string() + F2
+            ])
+            .root
+        );
+
+        let mut expected = vec![
+            "s3://bucket/tables/events/a=1/b=1/".to_string() + F1,
+            "s3://bucket/tables/events/a=4/b=2/".to_string() + F2,
+        ];
+        let mut actual = tree.files_with_prefix();
+        expected.sort();
+        actual.sort();
+        assert_eq!(expected, actual);
+
+        let file_uri = DeltaTree::from_paths(&vec!["file:///data/table/".to_string() + F1]);
+        assert_eq!(file_uri.prefix, "file:///data/table/");
+
+        let container = "abfss://container@account.dfs.core.windows.net/".to_string() + F1;
+        let abfss = DeltaTree::from_paths(&vec![container.clone()]);
+        assert_eq!(
+            abfss.prefix,
+            "abfss://container@account.dfs.core.windows.net/"
+        );
+        assert_eq!(abfss.files_with_prefix(), vec![container]);
+    }
+
+    #[test]
+    fn tree_filtered_paths() {
+        let paths: Vec<String> = vec![
+            "s3://bucket/t/a=1/b=1/".to_string() + F1,
+            "s3://bucket/t/a=4/b=2/".to_string() + F2,
+            "s3://bucket/t/a=12/b=2/".to_string() + F3,
+        ];
+        let predicates = vec!["a>=4".parse().unwrap(), "b=2".parse().unwrap()];
+        let tree = DeltaTree::from_paths_filtered(&paths, &predicates);
+        assert_eq!(tree.prefix, "s3://bucket/t/");
+        assert_eq!(
+            tree,
+            DeltaTree::from_paths(&vec![paths[1].clone(), paths[2].clone()])
+        );
+
+        let none = DeltaTree::from_paths_filtered(&paths, &["a=2".parse().unwrap()]);
+        assert_eq!(none.files(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn file_iter_and_stream() {
+        use futures::StreamExt;
+
+        let paths: Vec<String> = vec![
+            "a=1/b=1/".to_string() + F1,
+            "a=4/b=2/".to_string() + F2,
+            "a=4/b=__HIVE_DEFAULT_PARTITION__/".to_string() + F3,
+        ];
+        let tree = DeltaTree::from_paths(&paths);
+        let mut all: Vec<String> = tree.file_iter(&[]).collect();
+        all.sort();
+        assert_eq!(all, paths);
+
+        let predicates = vec!["a=4".parse().unwrap(), "b!=2".parse().unwrap()];
+        let streamed: Vec<String> =
+            futures::executor::block_on(tree.file_stream(&predicates).collect());
+        assert_eq!(streamed, vec![paths[2].clone()]);
+    }
+
+    #[test]
+    fn tree_parse_windows_separators() {
+        let windows: Vec<String> = vec![
+            "C:\\data\\table\\a=1\\b=1\\".to_string() + F1,
+            "C:\\data\\table\\a=4/b=2\\".to_string() + F2,
+        ];
+        let tree = DeltaTree::from_paths(&windows);
+        assert_eq!(tree.prefix, "C:/data/table/");
+        let mut files = tree.files();
+        files.sort();
+        assert_eq!(
+            files,
+            vec!["a=1/b=1/".to_string() + F1, "a=4/b=2/".to_string() + F2]
+        );
+        let mut files = tree.files_with_prefix();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                "C:/data/table/a=1/b=1/".to_string() + F1,
+                "C:/data/table/a=4/b=2/".to_string() + F2,
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_parse_empty_and_null_values() {
+        let paths: Vec<String> = vec![
+            "region=/".to_string() + F1,
+            "region=__HIVE_DEFAULT_PARTITION__/".to_string() + F2,
+            "region=eu/".to_string() + F3,
+        ];
+        let mut values = HashMap::default();
+        values.insert(Some("".into()), single_file_entries(FE1));
+        values.insert(None, single_file_entries(FE2));
+        values.insert(Some("eu".into()), single_file_entries(FE3));
+        let expected = TreeNode::Partition {
+            name: "region".to_string(),
+            values,
+        };
+
+        assert_eq!(DeltaTree::from_paths(&paths).root, expected);
+        tree_round_trip(paths);
+    }
+
+    #[test]
+    fn tree_parse_canonical_values() {
+        let paths: Vec<String> = vec![
+            "day=2024-1-5/x=1.0/".to_string() + F1,
+            "day=2024-01-05/x=1/".to_string() + F2,
+            "day=2024-01-05/x=01.50/".to_string() + F3,
+        ];
+        let mut types = HashMap::new();
+        types.insert("day".to_string(), PartitionType::Date);
+        types.insert("x".to_string(), PartitionType::Decimal);
+
+        let level_x = TreeNode::Partition {
+            name: "x".to_string(),
+            values: vec![
+                (
+                    Some("1".into()),
+                    TreeNode::FileEntries {
+                        files: vec![FE1, FE2].into(),
+                    },
+                ),
+                (Some("1.5".into()), single_file_entries(FE3)),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let root = create_partition("day", vec![("2024-01-05", level_x)]);
+
+        let canonical = DeltaTree::from_paths_canonical(&paths, &types);
+        assert_eq!(canonical.root, root);
+        assert!(canonical.canonical);
+        assert!(!DeltaTree::from_paths(&paths).canonical);
+    }
+
+    #[test]
+    fn canonical_tree_renders_raw_paths() {
+        let mut paths: Vec<String> = vec![
+            "day=2024-1-5/x=1.0/".to_string() + F1,
+            "day=2024-01-05/x=1/".to_string() + F2,
+            "day=2024-01-05/x=01.50/".to_string() + F3,
+        ];
+        paths.sort();
+        let mut types = HashMap::new();
+        types.insert("day".to_string(), PartitionType::Date);
+        types.insert("x".to_string(), PartitionType::Decimal);
+        let sorted = |mut files: Vec<String>| {
+            files.sort();
+            files
+        };
+
+        let canonical = DeltaTree::from_paths_canonical(&paths, &types);
+        assert_eq!(sorted(canonical.files()), paths);
+        assert_eq!(sorted(canonical.file_iter(&[]).collect()), paths);
+        let mut out = vec![];
+        canonical.write_files(&mut out, "\n", &[]).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert_eq!(sorted(written.lines().map(String::from).collect()), paths);
+
+        let raw = "day=2024-01-05/x=a%3Ab/".to_string() + F1;
+        let mut escaped = DeltaTree::from_paths(&vec![raw.clone()]);
+        escaped.canonicalize();
+        assert!(escaped
+            .get(&[("day", "2024-01-05"), ("x", "a:b")])
+            .is_some());
+        assert_eq!(escaped.files(), vec![raw]);
+    }
+
+    /// names like `<uuid>-<partition>.parquet`, everything else fixed.
+    struct UuidFirstCodec;
+
+    impl FileNameCodec for UuidFirstCodec {
+        fn decode(&self, name: &str) -> Option<ParquetDeltaFile> {
+            let stem = name.strip_suffix(".parquet")?;
+            let uuid = Uuid::parse_str(stem.get(..36)?).ok()?;
+            let partition = stem.get(37..)?.parse().ok()?;
+            Some(ParquetDeltaFile::new(partition, uuid, None, None))
+        }
+
+        fn encode(&self, file: &ParquetDeltaFile) -> String {
+            format!("{}-{}.parquet", file.uuid(), file.partition())
+        }
+    }
+
+    #[test]
+    fn custom_codec_round_trip() {
+        let mut paths: Vec<String> = vec![
+            "a=1/00000000-0000-0000-0000-000000000000-3.parquet".to_string(),
+            "a=2/00000000-0000-0000-0000-000000000001-12.parquet".to_string(),
+        ];
+        let tree = DeltaTree::from_paths_with_codec(&paths, &UuidFirstCodec);
+        let mut files = tree.files_with_codec(&UuidFirstCodec);
+        paths.sort();
+        files.sort();
+        assert_eq!(paths, files);
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct SizedFile {
+        file: ParquetDeltaFile,
+        size: u64,
+    }
+
+    impl AsRef<ParquetDeltaFile> for SizedFile {
+        fn as_ref(&self) -> &ParquetDeltaFile {
+            &self.file
+        }
+    }
+
+    #[test]
+    fn tree_with_custom_payload() {
+        let entries = vec![
+            ("a=4/".to_string() + F2, 200),
+            ("a=1/".to_string() + F1, 100),
+            ("a=1/".to_string() + F3, 300),
+        ];
+        let tree = DeltaTree::from_entries(entries, |file, size| SizedFile { file, size });
+
+        let expected = create_partition(
+            "a",
+            vec![
+                (
+                    "1",
+                    TreeNode::FileEntries {
+                        files: vec![
+                            SizedFile {
+                                file: FE1,
+                                size: 100,
+                            },
+                            SizedFile {
+                                file: FE3,
+                                size: 300,
+                            },
+                        ]
+                        .into(),
+                    },
+                ),
+                (
+                    "4",
+                    TreeNode::FileEntries {
+                        files: vec![SizedFile {
+                            file: FE2,
+                            size: 200,
+                        }]
+                        .into(),
+                    },
+                ),
+            ],
+        );
+        assert_eq!(tree.root, expected);
+        assert_eq!(tree.files().len(), 3);
+
+        let plain = tree.map_files(|sized| sized.file);
+        assert_eq!(
+            plain,
+            DeltaTree::from_paths(&vec![
+                "a=1/".to_string() + F1,
+                "a=1/".to_string() + F3,
+                "a=4/".to_string() + F2
+            ])
+        );
+    }
+
+    #[test]
+    fn tree_with_std_hasher() {
+        use std::collections::hash_map::RandomState;
+
+        let paths: Vec<(String, ())> = vec![
+            ("a=1/b=1/".to_string() + F1, ()),
+            ("a=4/b=2/".to_string() + F2, ()),
+        ];
+        let tree: DeltaTree<ParquetDeltaFile, RandomState> =
+            DeltaTree::from_entries(paths, |file, ()| file);
+        let mut files = tree.files();
+        files.sort();
+        assert_eq!(
+            files,
+            vec!["a=1/b=1/".to_string() + F1, "a=4/b=2/".to_string() + F2]
+        );
+    }
+
     #[test]
     fn file_name_round_trip() {
+        let without_cluster = "part-00007-00000000-0000-0000-0000-000000000000.snappy.parquet";
+        let uncompressed = "part-00007-00000000-0000-0000-0000-000000000000.c000.parquet";
+        let bare = "part-00007-00000000-0000-0000-0000-000000000000.parquet";
+        assert_eq!(
+            ParquetDeltaFile::from_string(uncompressed).compression(),
+            None
+        );
+        assert_eq!(
+            ParquetDeltaFile::from_string(uncompressed).name(),
+            uncompressed
+        );
+        assert_eq!(ParquetDeltaFile::from_string(bare).name(), bare);
+        assert_eq!(
+            ParquetDeltaFile::from_string(without_cluster).cluster(),
+            None
+        );
+        assert_eq!(
+            ParquetDeltaFile::from_string(without_cluster).name(),
+            without_cluster
+        );
         assert_eq!(ParquetDeltaFile::from_string(F1).name(), F1);
         assert_eq!(ParquetDeltaFile::from_string(F2).name(), F2);
         assert_eq!(ParquetDeltaFile::from_string(F3).name(), F3);
@@ -317,14 +1735,16 @@ mod tests {
     }
 
     fn single_file_entries(file: ParquetDeltaFile) -> TreeNode {
-        TreeNode::FileEntries { files: vec![file] }
+        TreeNode::FileEntries {
+            files: vec![file].into(),
+        }
     }
 
     /// test only. helpers to build a hashmap.
     fn create_leaf_partition(name: &str, entries: Vec<(&str, ParquetDeltaFile)>) -> TreeNode {
-        let mut values = HashMap::new();
+        let mut values = HashMap::default();
         entries.into_iter().for_each(|(k, v)| {
-            values.insert(k.to_string(), single_file_entries(v));
+            values.insert(Some(k.into()), single_file_entries(v));
         });
         TreeNode::Partition {
             name: name.to_string(),
@@ -332,10 +1752,10 @@ mod tests {
         }
     }
 
-    fn create_partition(name: &str, entries: Vec<(&str, TreeNode)>) -> TreeNode {
-        let mut values = HashMap::new();
+    fn create_partition<F>(name: &str, entries: Vec<(&str, TreeNode<F>)>) -> TreeNode<F> {
+        let mut values = HashMap::default();
         entries.into_iter().for_each(|(k, v)| {
-            values.insert(k.to_string(), v);
+            values.insert(Some(k.into()), v);
         });
         TreeNode::Partition {
             name: name.to_string(),
@@ -368,38 +1788,44 @@ mod tests {
             ParquetDeltaFile {
                 partition: 9,
                 uuid: Uuid::from_string("477077ae-1429-4633-b07a-0c0cb75caf55").unwrap(),
-                cluster: 177,
-                compression: SNAPPY
+                cluster: Some(177),
+                compression: Some(SNAPPY),
+                layout: NameLayout::Dotted,
+                kind: FileKind::Data,
             }
         );
     }
 
-    #[test]
-    fn test_regex_filename() {
-        let name = "part-00009-477077ae-1429-4633-b07a-0c0cb75caf55.c003.snappy.parquet";
-        let caps = FILENAME_REGEX.captures(name).unwrap();
-        assert_eq!(&caps["part"], "00009");
-        assert_eq!(&caps["uuid"], "477077ae-1429-4633-b07a-0c0cb75caf55");
-        assert_eq!(&caps["c"], "003");
-        assert_eq!(&caps["compression"], "snappy");
-    }
-
     #[test]
     fn test_key_value() {
         assert_eq!(
-            DeltaTree::key_value("a=13"),
+            key_value("a=13"),
             Some(PartitionPath {
                 key: "a",
-                value: "13"
+                value: Some("13".into())
             })
         );
-        assert_eq!(DeltaTree::key_value("askaban"), None);
+        assert_eq!(key_value("askaban"), None);
         assert_eq!(
-            DeltaTree::key_value("some-key=some-value-with-=-sign-in-the-middle"),
+            key_value("some-key=some-value-with-=-sign-in-the-middle"),
             Some(PartitionPath {
                 key: "some-key",
-                value: "some-value-with-=-sign-in-the-middle"
+                value: Some("some-value-with-=-sign-in-the-middle".into())
+            })
+        );
+        assert_eq!(
+            key_value("a="),
+            Some(PartitionPath {
+                key: "a",
+                value: Some("".into())
             })
-        )
+        );
+        assert_eq!(
+            key_value("a=__HIVE_DEFAULT_PARTITION__"),
+            Some(PartitionPath {
+                key: "a",
+                value: None
+            })
+        );
     }
 }
@@ -0,0 +1,151 @@
+use super::commit::CommitBuilder;
+use super::compaction::CompactionPlan;
+use super::sized::SizedDeltaFile;
+use super::{stats, DeltaTree};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// an OPTIMIZE of a table: the compaction plan with a name for each file to write, and the
+/// commit replacing the files read by the files written. whoever executes the plan only has
+/// to merge the bytes of each rewrite and report the sizes of the outputs.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct OptimizePlan {
+    pub target_size: u64,
+    /// the files to be rewritten and their bytes.
+    pub files: usize,
+    pub bytes: u64,
+    /// one rewrite per output file, sorted by partition.
+    pub rewrites: Vec<Rewrite>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Rewrite {
+    /// the path of the file to write, relative to the table root.
+    pub output: String,
+    pub inputs: Vec<PlannedFile>,
+    /// the bytes of the inputs, a rough estimate of the size of the output.
+    pub bytes: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PlannedFile {
+    /// relative to the table root.
+    pub path: String,
+    pub size: u64,
+}
+
+impl OptimizePlan {
+    /// plan the compaction of `tree` into files of about `target_size` bytes, see
+    /// `CompactionPlan::new`, naming each output file like spark does.
+    pub fn new<S>(tree: &DeltaTree<SizedDeltaFile, S>, target_size: u64) -> OptimizePlan {
+        OptimizePlan::from_compaction(tree, &CompactionPlan::new(tree, target_size))
+    }
+
+    /// the rewrites of `plan`, made for `tree`.
+    pub fn from_compaction<S>(
+        tree: &DeltaTree<SizedDeltaFile, S>,
+        plan: &CompactionPlan,
+    ) -> OptimizePlan {
+        let sizes: HashMap<String, u64> = stats::leaves(tree)
+            .into_iter()
+            .filter(|(dir, _)| plan.partitions.iter().any(|p| &p.path == dir))
+            .flat_map(|(dir, files)| {
+                files
+                    .iter()
+                    .map(move |f| (format!("{}{}", dir, f.file.name()), f.size))
+            })
+            .collect();
+        let mut rewrites = vec![];
+        for partition in &plan.partitions {
+            for group in &partition.groups {
+                let inputs = group
+                    .files
+                    .iter()
+                    .map(|name| {
+                        let path = format!("{}{}", partition.path, name);
+                        let size = *sizes
+                            .get(&path)
+                            .unwrap_or_else(|| panic!("planned file '{}' not in the tree", path));
+                        PlannedFile { path, size }
+                    })
+                    .collect();
+                rewrites.push(Rewrite {
+                    output: format!(
+                        "{}part-00000-{}.c000.snappy.parquet",
+                        partition.path,
+                        uuid::Uuid::new_v4()
+                    ),
+                    inputs,
+                    bytes: group.bytes,
+                });
+            }
+        }
+        OptimizePlan {
+            target_size: plan.target_size,
+            files: plan.files,
+            bytes: plan.bytes,
+            rewrites,
+        }
+    }
+
+    /// the commit of the plan, once each output has been written with the size given by
+    /// `written`. the commit doesn't change data, so concurrent appends don't conflict.
+    pub fn commit(&self, written: &HashMap<String, u64>) -> CommitBuilder {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        let mut commit = CommitBuilder::new().data_change(false);
+        for rewrite in &self.rewrites {
+            for input in &rewrite.inputs {
+                commit = commit.remove(&input.path, input.size);
+            }
+            let size = written
+                .get(&rewrite.output)
+                .unwrap_or_else(|| panic!("output '{}' was not written", rewrite.output));
+            commit = commit.add(&rewrite.output, *size, now);
+        }
+        commit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::{path, sized_tree};
+    use deltalake::action::Action;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn plan_and_commit_optimize() {
+        let entries = [
+            ("d=1/", 1, 60),
+            ("d=1/", 2, 30),
+            ("d=1/", 3, 200),
+            ("d=2/", 4, 10),
+        ]
+        .iter()
+        .map(|(dir, id, size)| (path(dir, *id), *size))
+        .collect();
+        let tree = sized_tree(entries);
+        let plan = OptimizePlan::new(&tree, 100);
+        assert_eq!((plan.files, plan.bytes), (2, 90));
+        assert_eq!(plan.rewrites.len(), 1);
+        let rewrite = &plan.rewrites[0];
+        assert!(rewrite.output.starts_with("d=1/part-00000-"));
+        let inputs: Vec<_> = rewrite.inputs.iter().map(|f| f.size).collect();
+        assert_eq!(inputs, vec![60, 30]);
+
+        let written = std::iter::once((rewrite.output.clone(), 85)).collect();
+        let actions = plan.commit(&written).actions();
+        let summary: Vec<_> = actions
+            .iter()
+            .map(|action| match action {
+                Action::remove(remove) => ("remove", remove.size.unwrap_or(0)),
+                Action::add(add) => ("add", add.size),
+                action => panic!("unexpected action {:?}", action),
+            })
+            .collect();
+        assert_eq!(summary, vec![("remove", 60), ("remove", 30), ("add", 85)]);
+    }
+}
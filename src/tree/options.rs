@@ -0,0 +1,384 @@
+use super::canonical::PartitionType;
+use super::codec::{FileNameCodec, SparkFileNameCodec};
+use super::intern::Interner;
+use super::predicate::{self, PartitionPredicate};
+use super::{build, DeltaTree, ParquetDeltaFile, PartitionPath};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+/// how paths are turned into a tree. the default is what `from_paths` does: strict parsing
+/// and partition values kept as they appear in the paths.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DeltaTreeOptions {
+    lenient: bool,
+    types: HashMap<String, PartitionType>,
+    decode_values: bool,
+    interning: bool,
+    interner: Option<Interner>,
+    ordered: bool,
+}
+
+impl DeltaTreeOptions {
+    pub fn new() -> DeltaTreeOptions {
+        DeltaTreeOptions::default()
+    }
+
+    /// skip paths with a file name the codec doesn't understand, a different prefix or a
+    /// plain directory between partitions, instead of panicking. paths with other partition
+    /// columns than the rest still panic, see `validate` to check for those.
+    pub fn lenient(self, lenient: bool) -> DeltaTreeOptions {
+        DeltaTreeOptions { lenient, ..self }
+    }
+
+    /// canonicalize the values of the partition columns given in `types`, see
+    /// `DeltaTree::from_paths_canonical`.
+    pub fn partition_types(self, types: HashMap<String, PartitionType>) -> DeltaTreeOptions {
+        DeltaTreeOptions { types, ..self }
+    }
+
+    /// decode the percent-escaped characters in partition values, e.g. `a%3Ab` to `a:b`, as
+    /// spark escapes them in directory names. the tree becomes `canonical`, its files keep
+    /// their escaped directories, see `DeltaTree::raw_dirs`.
+    pub fn decode_values(self, decode_values: bool) -> DeltaTreeOptions {
+        DeltaTreeOptions {
+            decode_values,
+            ..self
+        }
+    }
+
+    /// share equal partition values between the partitions of a tree, e.g. the same hour
+    /// below every day, instead of storing a copy per partition. each build interns into a
+    /// fresh `Interner` unless one is given with `interner`.
+    pub fn interning(self, interning: bool) -> DeltaTreeOptions {
+        DeltaTreeOptions { interning, ..self }
+    }
+
+    /// share the partition values through `interner`, with other trees built with it and
+    /// between the partitions of the tree.
+    pub fn interner(self, interner: Interner) -> DeltaTreeOptions {
+        DeltaTreeOptions {
+            interning: true,
+            interner: Some(interner),
+            ..self
+        }
+    }
+
+    /// list the files in the order of their partition values, `null` first, instead of the
+    /// order of the hash maps, at the cost of sorting the values of each partition while
+    /// rendering. see `DeltaTree::ordered`.
+    pub fn ordered(self, ordered: bool) -> DeltaTreeOptions {
+        DeltaTreeOptions { ordered, ..self }
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
+    /// whether trees built with these options change partition values, by canonicalizing or
+    /// decoding them.
+    pub fn is_canonical(&self) -> bool {
+        !self.types.is_empty() || self.decode_values
+    }
+
+    /// the options of a single build: with an interner of its own if values are interned
+    /// without a shared one.
+    pub(super) fn for_build(&self) -> Cow<'_, DeltaTreeOptions> {
+        match &self.interner {
+            None if self.interning => Cow::Owned(self.clone().interner(Interner::new())),
+            _ => Cow::Borrowed(self),
+        }
+    }
+
+    /// a partition value as stored in a tree, the shared one if there's an interner.
+    pub(super) fn value(&self, value: &str) -> Arc<str> {
+        match &self.interner {
+            Some(interner) if self.interning => interner.intern(value),
+            _ => Arc::from(value),
+        }
+    }
+
+    /// the value of a partition directory with these options applied.
+    pub(super) fn partition<'a>(&self, partition: PartitionPath<'a>) -> PartitionPath<'a> {
+        let partition = match partition.value {
+            Some(Cow::Borrowed(value)) if self.decode_values => PartitionPath {
+                key: partition.key,
+                value: Some(unescape(value)),
+            },
+            _ => partition,
+        };
+        partition.canonicalize(&self.types)
+    }
+}
+
+impl DeltaTree {
+    /// like `new`, with `options`.
+    pub fn new_with_options(
+        delta_table: &deltalake::DeltaTable,
+        options: &DeltaTreeOptions,
+    ) -> DeltaTree {
+        DeltaTree::from_paths_with_options(delta_table.get_files(), options).with_txns(delta_table)
+    }
+
+    /// like `load_filtered`, with `options`.
+    pub fn load_filtered_with_options(
+        delta_table: &deltalake::DeltaTable,
+        predicates: &[PartitionPredicate],
+        options: &DeltaTreeOptions,
+    ) -> DeltaTree {
+        DeltaTree::from_paths_filtered_with_options(delta_table.get_files(), predicates, options)
+            .with_txns(delta_table)
+    }
+
+    /// like `from_paths`, with `options`.
+    pub fn from_paths_with_options<I>(input_files: I, options: &DeltaTreeOptions) -> DeltaTree
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        DeltaTree::from_paths_with_codec_and_options(input_files, &SparkFileNameCodec, options)
+    }
+
+    /// like `from_paths_filtered`, with `options`. the predicates apply to the values in the
+    /// paths, before they are canonicalized or decoded.
+    pub fn from_paths_filtered_with_options<I>(
+        input_files: I,
+        predicates: &[PartitionPredicate],
+        options: &DeltaTreeOptions,
+    ) -> DeltaTree
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let input_files = input_files
+            .into_iter()
+            .filter(|f| predicate::path_matches(f.as_ref(), predicates));
+        DeltaTree::from_paths_with_options(input_files, options)
+    }
+
+    /// like `from_paths_with_codec`, with `options`.
+    pub fn from_paths_with_codec_and_options<I, C>(
+        input_files: I,
+        codec: &C,
+        options: &DeltaTreeOptions,
+    ) -> DeltaTree
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        C: FileNameCodec,
+    {
+        let input_files: Vec<I::Item> = input_files.into_iter().collect();
+        let entries = input_files.iter().map(|f| (f.as_ref(), ()));
+        build(entries, options, codec, |file, ()| file)
+    }
+}
+
+impl<F, S: BuildHasher + Default> DeltaTree<F, S> {
+    /// like `from_entries`, with `options`.
+    pub fn from_entries_with_options<T, P>(
+        entries: Vec<(String, T)>,
+        options: &DeltaTreeOptions,
+        payload: P,
+    ) -> DeltaTree<F, S>
+    where
+        P: FnMut(ParquetDeltaFile, T) -> F,
+    {
+        DeltaTree::from_entries_with_codec_and_options(
+            entries,
+            &SparkFileNameCodec,
+            options,
+            payload,
+        )
+    }
+
+    /// like `from_entry_iter`, with `options`.
+    pub fn from_entry_iter_with_options<'a, T, P>(
+        entries: impl Iterator<Item = (&'a str, T)>,
+        options: &DeltaTreeOptions,
+        payload: P,
+    ) -> DeltaTree<F, S>
+    where
+        P: FnMut(ParquetDeltaFile, T) -> F,
+    {
+        build(entries, options, &SparkFileNameCodec, payload)
+    }
+
+    /// like `from_entries_with_codec`, with `options`.
+    pub fn from_entries_with_codec_and_options<T, C, P>(
+        entries: Vec<(String, T)>,
+        codec: &C,
+        options: &DeltaTreeOptions,
+        payload: P,
+    ) -> DeltaTree<F, S>
+    where
+        C: FileNameCodec,
+        P: FnMut(ParquetDeltaFile, T) -> F,
+    {
+        let (paths, data): (Vec<String>, Vec<T>) = entries.into_iter().unzip();
+        let entries = paths.iter().map(String::as_str).zip(data);
+        build(entries, options, codec, payload)
+    }
+}
+
+/// decode `%XX` escapes, keeping malformed ones as they are.
+pub(super) fn unescape(value: &str) -> Cow<'_, str> {
+    if !value.contains('%') {
+        return Cow::Borrowed(value);
+    }
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_or(Cow::Borrowed(value), Cow::Owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use crate::tree::TreeNode;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn unescape_values() {
+        assert_eq!(unescape("2021-03-01 10%3A00%3A00"), "2021-03-01 10:00:00");
+        assert_eq!(unescape("a%2Fb%25"), "a/b%");
+        assert_eq!(unescape("100%"), "100%");
+        assert_eq!(unescape("%zz"), "%zz");
+    }
+
+    #[test]
+    fn build_with_options() {
+        let paths = vec![
+            path("s3://bucket/t/ts=2021-03-01 10%3A00%3A00/n=01/", 1),
+            path("s3://bucket/t/ts=2021-03-01 10%3A00%3A00/n=1/", 2),
+            "s3://bucket/t/ts=x/n=1/not-a-data-file.json".to_string(),
+            path("s3://bucket/other/ts=x/n=1/", 3),
+        ];
+        let types = vec![("n".to_string(), PartitionType::Integer)]
+            .into_iter()
+            .collect();
+        let options = DeltaTreeOptions::new()
+            .lenient(true)
+            .decode_values(true)
+            .partition_types(types);
+        let tree = DeltaTree::from_paths_with_options(&paths, &options);
+        assert_eq!(tree.prefix, "s3://bucket/t/");
+        assert_eq!(
+            tree.get(&[("ts", "2021-03-01 10:00:00"), ("n", "1")])
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(tree.partitions().len(), 1);
+        assert!(tree.canonical);
+        assert_eq!(tree.files_with_prefix(), &paths[..2]);
+
+        let decoded = DeltaTree::from_paths_with_options(
+            &paths[..1],
+            &DeltaTreeOptions::new().decode_values(true),
+        );
+        assert!(decoded.canonical);
+        assert!(decoded
+            .get(&[("ts", "2021-03-01 10:00:00"), ("n", "01")])
+            .is_some());
+        assert_eq!(decoded.files_with_prefix(), &paths[..1]);
+        assert_eq!(
+            DeltaTree::from_paths_with_options(&paths[..2], &DeltaTreeOptions::new()),
+            DeltaTree::from_paths(&paths[..2])
+        );
+    }
+
+    #[test]
+    fn ordered_and_interned() {
+        let paths: Vec<String> = ["d=2/h=1/", "d=1/h=2/", "d=10/h=1/", "d=1/h=1/"]
+            .iter()
+            .enumerate()
+            .map(|(id, dir)| path(dir, id as u128))
+            .collect();
+        let options = DeltaTreeOptions::new().ordered(true).interning(true);
+        let tree = DeltaTree::from_paths_with_options(&paths, &options);
+        assert!(tree.ordered);
+        let expected = vec![
+            paths[3].clone(),
+            paths[1].clone(),
+            paths[2].clone(),
+            paths[0].clone(),
+        ];
+        assert_eq!(tree.files(), expected);
+        assert_eq!(tree.file_iter(&[]).collect::<Vec<_>>(), expected);
+        let mut rendered = vec![];
+        tree.for_each_path(&[], |path| rendered.push(path.to_string()));
+        assert_eq!(rendered, expected);
+        let values: Vec<_> = tree.partitions().into_iter().map(|(v, _)| v).collect();
+        assert_eq!(values[0][0].value.as_deref(), Some("1"));
+        assert_eq!(values[1][1].value.as_deref(), Some("2"));
+
+        let hour = |day: &str| match tree.subtree(&[("d", day)]) {
+            Some(TreeNode::Partition { values, .. }) => values.keys().next().cloned().flatten(),
+            _ => None,
+        };
+        assert!(Arc::ptr_eq(&hour("2").unwrap(), &hour("10").unwrap()));
+        let plain = DeltaTree::from_paths(&paths);
+        assert!(!plain.ordered);
+        assert_eq!(plain, tree);
+    }
+
+    #[test]
+    fn options_passed_to_all_constructors() {
+        let paths = vec![path("n=01/", 1), "n=1/not-a-data-file.json".to_string()];
+        let options = DeltaTreeOptions::new().lenient(true).partition_types(
+            vec![("n".to_string(), PartitionType::Integer)]
+                .into_iter()
+                .collect(),
+        );
+        let expected = DeltaTree::from_paths_with_options(&paths, &options);
+        assert!(expected.get(&[("n", "1")]).is_some());
+
+        let with_codec =
+            DeltaTree::from_paths_with_codec_and_options(&paths, &SparkFileNameCodec, &options);
+        assert_eq!(with_codec, expected);
+        let predicates = ["n=01".parse().unwrap()];
+        let filtered = DeltaTree::from_paths_filtered_with_options(&paths, &predicates, &options);
+        assert_eq!(filtered, expected);
+        let entries: Vec<(String, ())> = paths.iter().map(|p| (p.clone(), ())).collect();
+        let from_entries: DeltaTree =
+            DeltaTree::from_entries_with_options(entries, &options, |file, ()| file);
+        assert_eq!(from_entries, expected);
+        let iter = paths.iter().map(|p| (p.as_str(), ()));
+        let from_iter: DeltaTree =
+            DeltaTree::from_entry_iter_with_options(iter, &options, |file, ()| file);
+        assert_eq!(from_iter, expected);
+        assert!(from_iter.canonical);
+    }
+
+    #[test]
+    #[should_panic(expected = "unable to parse")]
+    fn strict_by_default() {
+        DeltaTree::from_paths_with_options(
+            vec!["a=1/not-a-data-file.json"],
+            &DeltaTreeOptions::new(),
+        );
+    }
+}
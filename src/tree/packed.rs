@@ -0,0 +1,181 @@
+use super::{CompressionType, FileKind, NameLayout, ParquetDeltaFile};
+use uuid::Uuid;
+
+// layout of the packed metadata word, from the least significant bit:
+// 32 bits partition, 8 bits cluster, 1 bit cluster present, 2 bits compression
+// (absent / snappy / gzip / none), 1 bit dashed layout, 1 bit change data.
+const CLUSTER_SHIFT: u32 = 32;
+const HAS_CLUSTER: u64 = 1 << 40;
+const COMPRESSION_SHIFT: u32 = 41;
+const DASHED: u64 = 1 << 43;
+const CHANGE_DATA: u64 = 1 << 44;
+
+/// a `ParquetDeltaFile` squeezed into a `u128` uuid and a single `u64` for the remaining
+/// components. file names in the `Task` layout carry too much information and can't be packed.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct PackedDeltaFile {
+    uuid: u128,
+    meta: u64,
+}
+
+impl PackedDeltaFile {
+    pub fn pack(file: &ParquetDeltaFile) -> Option<PackedDeltaFile> {
+        let layout = match file.layout() {
+            NameLayout::Dotted => 0,
+            NameLayout::Dashed => DASHED,
+            NameLayout::Task { .. } => return None,
+        };
+        let cluster = file
+            .cluster()
+            .map_or(0, |c| HAS_CLUSTER | (c as u64) << CLUSTER_SHIFT);
+        let compression = match file.compression() {
+            None => 0,
+            Some(CompressionType::SNAPPY) => 1,
+            Some(CompressionType::GZIP) => 2,
+            Some(CompressionType::NONE) => 3,
+        };
+        let kind = match file.kind() {
+            FileKind::ChangeData => CHANGE_DATA,
+            _ => 0,
+        };
+        Some(PackedDeltaFile {
+            uuid: file.uuid().as_u128(),
+            meta: file.partition() as u64
+                | cluster
+                | compression << COMPRESSION_SHIFT
+                | layout
+                | kind,
+        })
+    }
+
+    pub fn unpack(&self) -> ParquetDeltaFile {
+        unpack(self.uuid, self.meta)
+    }
+}
+
+fn unpack(uuid: u128, meta: u64) -> ParquetDeltaFile {
+    let cluster = if meta & HAS_CLUSTER != 0 {
+        Some((meta >> CLUSTER_SHIFT) as u8)
+    } else {
+        None
+    };
+    let compression = match (meta >> COMPRESSION_SHIFT) & 0b11 {
+        0 => None,
+        1 => Some(CompressionType::SNAPPY),
+        2 => Some(CompressionType::GZIP),
+        _ => Some(CompressionType::NONE),
+    };
+    let layout = if meta & DASHED != 0 {
+        NameLayout::Dashed
+    } else {
+        NameLayout::Dotted
+    };
+    let kind = if meta & CHANGE_DATA != 0 {
+        FileKind::ChangeData
+    } else {
+        FileKind::Data
+    };
+    ParquetDeltaFile::new(meta as u32, Uuid::from_u128(uuid), cluster, compression)
+        .with_layout(layout)
+        .with_kind(kind)
+}
+
+/// the files of a leaf stored column-wise, avoiding the padding of both `ParquetDeltaFile`
+/// and `PackedDeltaFile`: 24 bytes per file.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct PackedFiles {
+    uuids: Vec<u128>,
+    meta: Vec<u64>,
+}
+
+impl PackedFiles {
+    /// pack all `files`, `None` if any of them can't be packed.
+    pub fn pack<F: AsRef<ParquetDeltaFile>>(files: &[F]) -> Option<PackedFiles> {
+        let mut packed = PackedFiles {
+            uuids: Vec::with_capacity(files.len()),
+            meta: Vec::with_capacity(files.len()),
+        };
+        for file in files {
+            let file = PackedDeltaFile::pack(file.as_ref())?;
+            packed.uuids.push(file.uuid);
+            packed.meta.push(file.meta);
+        }
+        Some(packed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.uuids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.uuids.is_empty()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<ParquetDeltaFile> {
+        Some(unpack(*self.uuids.get(idx)?, self.meta[idx]))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ParquetDeltaFile> + '_ {
+        self.uuids
+            .iter()
+            .zip(self.meta.iter())
+            .map(|(uuid, meta)| unpack(*uuid, *meta))
+    }
+
+    /// bytes allocated on the heap for the columns.
+    pub fn heap_size(&self) -> usize {
+        std::mem::size_of::<u128>() * self.uuids.capacity()
+            + std::mem::size_of::<u64>() * self.meta.capacity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn files() -> Vec<ParquetDeltaFile> {
+        let uuid = Uuid::parse_str("4ba1e0aa-a3e8-4a8b-b1d7-50e4d3e1e3de").unwrap();
+        vec![
+            ParquetDeltaFile::new(0, uuid, Some(0), Some(CompressionType::SNAPPY)),
+            ParquetDeltaFile::new(u32::MAX, uuid, Some(255), Some(CompressionType::GZIP))
+                .with_layout(NameLayout::Dashed),
+            ParquetDeltaFile::new(7, uuid, None, Some(CompressionType::NONE)),
+            ParquetDeltaFile::new(7, uuid, Some(1), Some(CompressionType::SNAPPY))
+                .with_kind(FileKind::ChangeData),
+            ParquetDeltaFile::new(12345, Uuid::from_u128(u128::MAX), Some(3), None),
+            ParquetDeltaFile::new(1, Uuid::from_u128(0), None, None)
+                .with_layout(NameLayout::Dashed),
+        ]
+    }
+
+    #[test]
+    fn packed_file_round_trip() {
+        for file in files() {
+            assert_eq!(PackedDeltaFile::pack(&file).unwrap().unpack(), file);
+        }
+    }
+
+    #[test]
+    fn packed_files_round_trip() {
+        let files = files();
+        let packed = PackedFiles::pack(&files).unwrap();
+        assert_eq!(packed.len(), files.len());
+        assert_eq!(packed.iter().collect::<Vec<_>>(), files);
+        assert_eq!(packed.get(3), Some(files[3]));
+        assert_eq!(packed.get(files.len()), None);
+        assert_eq!(packed.heap_size(), 24 * files.len());
+    }
+
+    #[test]
+    fn task_layout_is_not_packed() {
+        let mut files = files();
+        files.push(files[0].with_layout(NameLayout::Task {
+            tid: 42,
+            task: 1,
+            attempt: 0,
+        }));
+        assert_eq!(PackedDeltaFile::pack(&files[files.len() - 1]), None);
+        assert_eq!(PackedFiles::pack(&files), None);
+    }
+}
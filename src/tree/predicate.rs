@@ -0,0 +1,176 @@
+use super::{is_separator, key_value, NULL_PARTITION_VALUE};
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// a condition on the value of a single partition column. `None` stands for the `null`
+/// partition value, which only matches `Eq` / `NotEq` / `In`. values are compared as numbers
+/// if both sides are numeric, lexicographically otherwise - which orders zero-padded dates and
+/// timestamps correctly.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PartitionPredicate {
+    Eq(String, Option<String>),
+    NotEq(String, Option<String>),
+    In(String, Vec<Option<String>>),
+    Lt(String, String),
+    LtEq(String, String),
+    Gt(String, String),
+    GtEq(String, String),
+}
+
+impl PartitionPredicate {
+    pub fn column(&self) -> &str {
+        match self {
+            PartitionPredicate::Eq(column, _)
+            | PartitionPredicate::NotEq(column, _)
+            | PartitionPredicate::In(column, _)
+            | PartitionPredicate::Lt(column, _)
+            | PartitionPredicate::LtEq(column, _)
+            | PartitionPredicate::Gt(column, _)
+            | PartitionPredicate::GtEq(column, _) => column,
+        }
+    }
+
+    pub fn matches(&self, value: Option<&str>) -> bool {
+        match (self, value) {
+            (PartitionPredicate::Eq(_, expected), _) => expected.as_deref() == value,
+            (PartitionPredicate::NotEq(_, expected), _) => expected.as_deref() != value,
+            (PartitionPredicate::In(_, expected), _) => {
+                expected.iter().any(|e| e.as_deref() == value)
+            }
+            (_, None) => false,
+            (PartitionPredicate::Lt(_, bound), Some(value)) => compare(value, bound).is_lt(),
+            (PartitionPredicate::LtEq(_, bound), Some(value)) => compare(value, bound).is_le(),
+            (PartitionPredicate::Gt(_, bound), Some(value)) => compare(value, bound).is_gt(),
+            (PartitionPredicate::GtEq(_, bound), Some(value)) => compare(value, bound).is_ge(),
+        }
+    }
+}
+
+/// parse `column<op>value` with one of `=`, `!=`, `<`, `<=`, `>`, `>=`, or `column in a,b,c`.
+/// `__HIVE_DEFAULT_PARTITION__` denotes the `null` value.
+impl FromStr for PartitionPredicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PartitionPredicate, String> {
+        if let Some(idx) = s.find(" in ") {
+            let values = s[idx + 4..]
+                .split(',')
+                .map(|v| nullable(v.trim()))
+                .collect();
+            return Ok(PartitionPredicate::In(column(&s[..idx], s)?, values));
+        }
+        let idx = s
+            .find(&['=', '!', '<', '>'][..])
+            .ok_or_else(|| format!("no comparison in predicate '{}'", s))?;
+        let column = column(&s[..idx], s)?;
+        let rest = &s[idx..];
+        let (op, value) = ["!=", "<=", ">=", "=", "<", ">"]
+            .iter()
+            .find_map(|op| rest.strip_prefix(op).map(|value| (*op, value.trim())))
+            .ok_or_else(|| format!("invalid comparison in predicate '{}'", s))?;
+        let ordered = |value: &str| match nullable(value) {
+            Some(value) => Ok(value),
+            None => Err(format!("null can't be ordered in predicate '{}'", s)),
+        };
+        Ok(match op {
+            "=" => PartitionPredicate::Eq(column, nullable(value)),
+            "!=" => PartitionPredicate::NotEq(column, nullable(value)),
+            "<" => PartitionPredicate::Lt(column, ordered(value)?),
+            "<=" => PartitionPredicate::LtEq(column, ordered(value)?),
+            ">" => PartitionPredicate::Gt(column, ordered(value)?),
+            _ => PartitionPredicate::GtEq(column, ordered(value)?),
+        })
+    }
+}
+
+fn column(column: &str, predicate: &str) -> Result<String, String> {
+    match column.trim() {
+        "" => Err(format!("missing column in predicate '{}'", predicate)),
+        column => Ok(column.to_string()),
+    }
+}
+
+fn nullable(value: &str) -> Option<String> {
+    Some(value)
+        .filter(|&v| v != NULL_PARTITION_VALUE)
+        .map(str::to_string)
+}
+
+fn compare(value: &str, bound: &str) -> Ordering {
+    match (value.parse::<f64>(), bound.parse::<f64>()) {
+        (Ok(v), Ok(b)) => v.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => value.cmp(bound),
+    }
+}
+
+/// whether the partition values in `path` satisfy all `predicates`. predicates on columns the
+/// path has no partition directory for can't rule the path out.
+pub fn path_matches(path: &str, predicates: &[PartitionPredicate]) -> bool {
+    let partitions: Vec<_> = path.split(is_separator).filter_map(key_value).collect();
+    predicates.iter().all(|predicate| {
+        match partitions.iter().find(|p| p.key == predicate.column()) {
+            Some(partition) => predicate.matches(partition.value.as_deref()),
+            None => true,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PartitionPredicate::*;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn parse(s: &str) -> PartitionPredicate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_predicates() {
+        assert_eq!(
+            parse("date>=2021-03-01"),
+            GtEq("date".to_string(), "2021-03-01".to_string())
+        );
+        assert_eq!(parse("hour < 7"), Lt("hour".to_string(), "7".to_string()));
+        assert_eq!(
+            parse("country!=__HIVE_DEFAULT_PARTITION__"),
+            NotEq("country".to_string(), None)
+        );
+        assert_eq!(
+            parse("country in de, fr"),
+            In(
+                "country".to_string(),
+                vec![Some("de".to_string()), Some("fr".to_string())]
+            )
+        );
+        assert!("date".parse::<PartitionPredicate>().is_err());
+        assert!("=1".parse::<PartitionPredicate>().is_err());
+        assert!("date!1".parse::<PartitionPredicate>().is_err());
+        assert!("a>__HIVE_DEFAULT_PARTITION__"
+            .parse::<PartitionPredicate>()
+            .is_err());
+    }
+
+    #[test]
+    fn numeric_and_lexicographic_comparison() {
+        assert!(parse("hour<10").matches(Some("9")));
+        assert!(!parse("hour<10").matches(Some("10")));
+        assert!(parse("hour<=10").matches(Some("10.0")));
+        assert!(parse("date>2021-02-28").matches(Some("2021-03-01")));
+        assert!(!parse("date>2021-02-28").matches(None));
+        assert!(parse("date!=2021-02-28").matches(None));
+    }
+
+    #[test]
+    fn match_paths() {
+        let path = "s3://b/t/date=2021-03-01/hour=__HIVE_DEFAULT_PARTITION__/part-0.parquet";
+        assert!(path_matches(path, &[]));
+        assert!(path_matches(path, &[parse("date>=2021-03-01")]));
+        assert!(path_matches(path, &[Eq("hour".to_string(), None)]));
+        assert!(!path_matches(
+            path,
+            &[parse("date>=2021-03-01"), parse("hour=1")]
+        ));
+        assert!(path_matches(path, &[parse("country=de")]));
+    }
+}
@@ -0,0 +1,104 @@
+use super::predicate::PartitionPredicate;
+use super::{DeltaTree, FxBuildHasher, ParquetDeltaFile};
+use std::sync::Arc;
+
+/// a handle on a tree shared by concurrent readers, e.g. the request handlers of a server.
+/// cloning it is cheap, all clones see the same tree. queries only borrow the tree, so any
+/// number of threads can run them at the same time without locking.
+pub struct QueryContext<F = ParquetDeltaFile, S = FxBuildHasher> {
+    tree: Arc<DeltaTree<F, S>>,
+}
+
+impl<F, S> Clone for QueryContext<F, S> {
+    fn clone(&self) -> Self {
+        QueryContext {
+            tree: Arc::clone(&self.tree),
+        }
+    }
+}
+
+impl<F, S> From<Arc<DeltaTree<F, S>>> for QueryContext<F, S> {
+    fn from(tree: Arc<DeltaTree<F, S>>) -> QueryContext<F, S> {
+        QueryContext { tree }
+    }
+}
+
+impl<F, S> QueryContext<F, S> {
+    pub fn new(tree: DeltaTree<F, S>) -> QueryContext<F, S> {
+        QueryContext {
+            tree: Arc::new(tree),
+        }
+    }
+
+    pub fn tree(&self) -> &DeltaTree<F, S> {
+        &self.tree
+    }
+}
+
+impl<F: AsRef<ParquetDeltaFile>, S> QueryContext<F, S> {
+    /// the paths of the files in partitions matching all `predicates`, including the prefix.
+    pub fn files(&self, predicates: &[PartitionPredicate]) -> Vec<String> {
+        self.tree
+            .file_iter(predicates)
+            .map(|f| format!("{}{}", self.tree.prefix, f))
+            .collect()
+    }
+
+    /// the number of files in partitions matching all `predicates`.
+    pub fn file_count(&self, predicates: &[PartitionPredicate]) -> usize {
+        self.tree.file_iter(predicates).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use crate::tree::sized::SizedDeltaFile;
+    use crate::tree::TreeNode;
+    use pretty_assertions::assert_eq;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn trees_are_send_and_sync() {
+        assert_send_sync::<DeltaTree>();
+        assert_send_sync::<TreeNode>();
+        assert_send_sync::<DeltaTree<SizedDeltaFile>>();
+        assert_send_sync::<QueryContext>();
+    }
+
+    #[test]
+    fn concurrent_queries() {
+        let paths: Vec<String> = (0..64u128)
+            .map(|id| path(&format!("s3://bucket/table/d={}/h={}/", id % 8, id % 3), id))
+            .collect();
+        let context = QueryContext::new(DeltaTree::from_paths(&paths));
+        let handles: Vec<_> = (0..8)
+            .map(|day| {
+                let context = context.clone();
+                thread::spawn(move || {
+                    let predicates = [PartitionPredicate::Eq(
+                        "d".to_string(),
+                        Some(day.to_string()),
+                    )];
+                    let mut files = context.files(&predicates);
+                    files.sort();
+                    (context.file_count(&predicates), files)
+                })
+            })
+            .collect();
+        for (day, handle) in handles.into_iter().enumerate() {
+            let (count, files) = handle.join().unwrap();
+            let mut expected: Vec<_> = paths
+                .iter()
+                .filter(|p| p.contains(&format!("/d={}/", day)))
+                .cloned()
+                .collect();
+            expected.sort();
+            assert_eq!(count, 8);
+            assert_eq!(files, expected);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use super::predicate::PartitionPredicate;
+use super::{children, partition_value, DeltaTree, ParquetDeltaFile, TreeNode};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// the path of a file in a tree, rendered piece by piece when displayed instead of being
+/// built as a `String` first. only lives during the callback of `try_for_each_path`.
+pub struct PathDisplay<'a, F> {
+    prefix: &'a str,
+    /// the partition directories leading to the file, outermost first.
+    partitions: &'a [(&'a str, &'a Option<Arc<str>>)],
+    /// the directory in storage if the partition values were changed, see
+    /// `DeltaTree::raw_dirs`.
+    raw_dir: Option<&'a str>,
+    file: &'a F,
+}
+
+impl<'a, F> PathDisplay<'a, F> {
+    pub fn file(&self) -> &'a F {
+        self.file
+    }
+
+    /// the partition directories leading to the file as `(column, value)`, outermost first.
+    pub fn partitions(&self) -> &'a [(&'a str, &'a Option<Arc<str>>)] {
+        self.partitions
+    }
+}
+
+impl<'a, F: AsRef<ParquetDeltaFile>> fmt::Display for PathDisplay<'a, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.prefix)?;
+        match self.raw_dir {
+            Some(dir) => f.write_str(dir)?,
+            None => {
+                for (name, value) in self.partitions {
+                    write!(f, "{}={}/", name, partition_value(value))?;
+                }
+            }
+        }
+        write!(f, "{}", self.file.as_ref())
+    }
+}
+
+impl<F: AsRef<ParquetDeltaFile>, S> DeltaTree<F, S> {
+    /// call `f` with the path of each file in partitions matching all `predicates`, including
+    /// the prefix, stopping at the first error. no path is allocated.
+    pub fn try_for_each_path<E, P>(
+        &self,
+        predicates: &[PartitionPredicate],
+        mut f: P,
+    ) -> Result<(), E>
+    where
+        P: FnMut(&PathDisplay<F>) -> Result<(), E>,
+    {
+        let raw_dirs = &self.raw_dirs;
+        visit(
+            &self.prefix,
+            &self.root,
+            raw_dirs,
+            self.ordered,
+            predicates,
+            &mut vec![],
+            &mut f,
+        )
+    }
+
+    /// like `try_for_each_path`, for callbacks that can't fail.
+    pub fn for_each_path<P>(&self, predicates: &[PartitionPredicate], mut f: P)
+    where
+        P: FnMut(&PathDisplay<F>),
+    {
+        let result: Result<(), Infallible> = self.try_for_each_path(predicates, |path| {
+            f(path);
+            Ok(())
+        });
+        result.unwrap_or_else(|never| match never {})
+    }
+
+    /// write the paths of the files matching all `predicates` to `out`, each followed by
+    /// `delimiter`, e.g. `"\n"`, or `"\0"` for `xargs -0`. the paths are written straight to
+    /// `out`, wrap it in a `BufWriter` unless it buffers itself.
+    pub fn write_files<W: Write>(
+        &self,
+        out: &mut W,
+        delimiter: &str,
+        predicates: &[PartitionPredicate],
+    ) -> io::Result<()> {
+        self.try_for_each_path(predicates, |path| write!(out, "{}{}", path, delimiter))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<F: AsRef<ParquetDeltaFile> + Sync, S: Sync> DeltaTree<F, S> {
+    /// like `files`, rendering the subtrees of the partition values in parallel on rayon's
+    /// thread pool. in the same order as `files`.
+    pub fn files_par(&self) -> Vec<String> {
+        files_par("", &self.root, &self.raw_dirs, self.ordered)
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn files_par<F: AsRef<ParquetDeltaFile> + Sync, S: Sync>(
+    dir: &str,
+    node: &TreeNode<F, S>,
+    raw_dirs: &HashMap<ParquetDeltaFile, String>,
+    ordered: bool,
+) -> Vec<String> {
+    use rayon::prelude::*;
+
+    match node {
+        TreeNode::FileEntries { files } => files
+            .iter()
+            .map(|f| {
+                format!(
+                    "{}{}",
+                    super::raw_dir(raw_dirs, f.as_ref(), dir),
+                    f.as_ref()
+                )
+            })
+            .collect(),
+        TreeNode::Partition { name, values } => {
+            let children = children(values, ordered);
+            let subtrees: Vec<Vec<String>> = children
+                .par_iter()
+                .map(|(value, child)| {
+                    let dir = format!("{}{}={}/", dir, name, partition_value(value));
+                    files_par(&dir, child, raw_dirs, ordered)
+                })
+                .collect();
+            let mut files = Vec::with_capacity(subtrees.iter().map(Vec::len).sum());
+            for subtree in subtrees {
+                files.extend(subtree);
+            }
+            files
+        }
+    }
+}
+
+fn visit<'a, F: AsRef<ParquetDeltaFile>, S, E, P>(
+    prefix: &'a str,
+    node: &'a TreeNode<F, S>,
+    raw_dirs: &'a HashMap<ParquetDeltaFile, String>,
+    ordered: bool,
+    predicates: &[PartitionPredicate],
+    partitions: &mut Vec<(&'a str, &'a Option<Arc<str>>)>,
+    f: &mut P,
+) -> Result<(), E>
+where
+    P: FnMut(&PathDisplay<F>) -> Result<(), E>,
+{
+    match node {
+        TreeNode::FileEntries { files } => {
+            for file in files.iter() {
+                f(&PathDisplay {
+                    prefix,
+                    partitions,
+                    raw_dir: raw_dirs.get(file.as_ref()).map(String::as_str),
+                    file,
+                })?;
+            }
+        }
+        TreeNode::Partition { name, values } => {
+            for (value, child) in children(values, ordered) {
+                let matches = predicates
+                    .iter()
+                    .filter(|p| p.column() == name)
+                    .all(|p| p.matches(value.as_deref()));
+                if matches {
+                    partitions.push((name, value));
+                    visit(prefix, child, raw_dirs, ordered, predicates, partitions, f)?;
+                    partitions.pop();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::codec::{FileNameCodec, RegexFileNameCodec};
+    #[cfg(feature = "rayon")]
+    use crate::tree::fixtures;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn render_paths() {
+        let paths = vec![
+            "s3://bucket/t/d=1/h=2/part-00003-tid-123-00000000-0000-0000-0000-000000000002-5-1-c001.parquet",
+            "s3://bucket/t/d=1/h=__HIVE_DEFAULT_PARTITION__/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+            "s3://bucket/t/d=2/h=1/cdc-00001-00000000-0000-0000-0000-000000000003.c000.snappy.parquet",
+        ];
+        let tree = DeltaTree::from_paths(&paths);
+        let mut out = vec![];
+        tree.write_files(&mut out, "\n", &[]).unwrap();
+        let mut written: Vec<_> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        written.sort();
+        assert_eq!(written, paths);
+
+        let mut out = vec![];
+        tree.write_files(&mut out, "\0", &["d=2".parse().unwrap()])
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{}\0", paths[2]));
+
+        let mut rendered = vec![];
+        let predicates = ["d=1".parse().unwrap()];
+        tree.for_each_path(&predicates, |path| rendered.push(path.to_string()));
+        rendered.sort();
+        assert_eq!(rendered, paths[..2].to_vec());
+
+        let mut partitions = vec![];
+        tree.for_each_path(&["d=2".parse().unwrap()], |path| {
+            partitions.extend(
+                path.partitions()
+                    .iter()
+                    .map(|(k, v)| format!("{}={:?}", k, v)),
+            )
+        });
+        assert_eq!(partitions, vec!["d=Some(\"2\")", "h=Some(\"1\")"]);
+
+        for path in &paths {
+            let name = path.rsplit('/').next().unwrap();
+            let file = RegexFileNameCodec.decode(name).unwrap();
+            assert_eq!(file.to_string(), name);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn render_paths_in_parallel() {
+        let paths: Vec<String> = (0..100u128)
+            .map(|id| fixtures::path(&format!("d={}/h={}/", id % 7, id % 3), id))
+            .collect();
+        let tree = DeltaTree::from_paths(&paths);
+        assert_eq!(tree.files_par(), tree.files());
+    }
+}
@@ -0,0 +1,102 @@
+use deltalake::{DeltaTable, DeltaTableError, SchemaDataType, SchemaField};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// the schema, partition columns and properties of a table, as shown by `delta-tree schema`.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct TableSchema {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub columns: Vec<Column>,
+    pub partition_columns: Vec<String>,
+    /// the liquid clustering columns, `None` if unknown. see `clustering::clustering_columns`.
+    pub clustering_columns: Option<Vec<String>>,
+    /// the table configuration, e.g. `delta.logRetentionDuration`.
+    pub properties: BTreeMap<String, Option<String>>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Column {
+    pub name: String,
+    /// the type in spark's notation, e.g. `long` or `array<struct<a:int,b:string>>`.
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+impl TableSchema {
+    pub fn new(delta_table: &DeltaTable) -> Result<TableSchema, DeltaTableError> {
+        let metadata = delta_table.get_metadata()?;
+        Ok(TableSchema {
+            name: metadata.name.clone(),
+            description: metadata.description.clone(),
+            columns: metadata.schema.get_fields().iter().map(column).collect(),
+            partition_columns: metadata.partition_columns.clone(),
+            clustering_columns: None,
+            properties: metadata
+                .configuration
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        })
+    }
+}
+
+fn column(field: &SchemaField) -> Column {
+    Column {
+        name: field.get_name().to_string(),
+        data_type: type_name(field.get_type()),
+        nullable: field.is_nullable(),
+    }
+}
+
+/// render a type the way spark's `simpleString` does.
+pub fn type_name(data_type: &SchemaDataType) -> String {
+    match data_type {
+        SchemaDataType::primitive(name) => name.clone(),
+        SchemaDataType::r#struct(fields) => {
+            let fields: Vec<String> = fields
+                .get_fields()
+                .iter()
+                .map(|f| format!("{}:{}", f.get_name(), type_name(f.get_type())))
+                .collect();
+            format!("struct<{}>", fields.join(","))
+        }
+        SchemaDataType::array(array) => format!("array<{}>", type_name(array.get_element_type())),
+        SchemaDataType::map(map) => format!(
+            "map<{},{}>",
+            type_name(map.get_key_type()),
+            type_name(map.get_value_type())
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deltalake::{Schema, SchemaTypeArray, SchemaTypeMap};
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    fn primitive(name: &str) -> SchemaDataType {
+        SchemaDataType::primitive(name.to_string())
+    }
+
+    #[test]
+    fn type_names() {
+        let inner = Schema::new(vec![
+            SchemaField::new("a".to_string(), primitive("integer"), true, HashMap::new()),
+            SchemaField::new("b".to_string(), primitive("string"), true, HashMap::new()),
+        ]);
+        let array = SchemaDataType::array(SchemaTypeArray::new(
+            Box::new(SchemaDataType::r#struct(inner)),
+            true,
+        ));
+        assert_eq!(type_name(&array), "array<struct<a:integer,b:string>>");
+        let map = SchemaDataType::map(SchemaTypeMap::new(
+            Box::new(primitive("string")),
+            Box::new(primitive("long")),
+            false,
+        ));
+        assert_eq!(type_name(&map), "map<string,long>");
+    }
+}
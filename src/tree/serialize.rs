@@ -0,0 +1,378 @@
+use super::{
+    CompressionType, DeltaTree, FileKind, FileList, NameLayout, ParquetDeltaFile, TreeNode,
+};
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use uuid::Uuid;
+
+// a compact binary encoding of (sub)trees. lengths are varints, fixed size integers little
+// endian. a node is a tag byte followed by either
+// - leaf: the number of files, then per file: partition (u32), uuid (u128), cluster (flag byte
+//   plus u8, the flag's second bit marking change data files), compression (u8, 0 for absent)
+//   and layout (u8, task layouts followed by tid, task and attempt).
+// - partition: the column name, the number of children, then per child the value (flag byte
+//   plus string for non-null values) and the child node.
+// a whole tree is stored behind a header of magic bytes and the format version, followed by
+// the table version (i64), the prefix, the application transactions (their number, then per
+// transaction the app id and the version as i64, sorted by app id), flags (u8, the first bit
+// marking canonical trees, the second ordered ones), its raw directories (their number, then
+// per file the file and the directory, sorted by file) and the root node.
+const LEAF: u8 = 0;
+const PARTITION: u8 = 1;
+const MAGIC: &[u8] = b"DTREE";
+/// the least bytes a file takes: partition, uuid, cluster, compression and layout.
+const MIN_FILE_LEN: usize = 4 + 16 + 2 + 1 + 1;
+const FORMAT_VERSION: u8 = 6;
+
+/// append the encoding of `tree`, built from the given version of its table, to `out`.
+pub fn write_tree<F: AsRef<ParquetDeltaFile>, S>(
+    tree: &DeltaTree<F, S>,
+    version: i64,
+    out: &mut Vec<u8>,
+) {
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    out.extend_from_slice(&version.to_le_bytes());
+    write_str(out, &tree.prefix);
+    let mut txns: Vec<_> = tree.txns.iter().collect();
+    txns.sort();
+    write_varint(out, txns.len());
+    for (app_id, version) in txns {
+        write_str(out, app_id);
+        out.extend_from_slice(&version.to_le_bytes());
+    }
+    out.push(tree.canonical as u8 | (tree.ordered as u8) << 1);
+    let mut raw_dirs: Vec<_> = tree.raw_dirs.iter().collect();
+    raw_dirs.sort();
+    write_varint(out, raw_dirs.len());
+    for (file, dir) in raw_dirs {
+        write_file(file, out);
+        write_str(out, dir);
+    }
+    write_node(&tree.root, out);
+}
+
+/// decode a tree and its table version written by `write_tree`.
+pub fn read_tree<S: BuildHasher + Default>(
+    bytes: &[u8],
+) -> Option<(DeltaTree<ParquetDeltaFile, S>, i64)> {
+    let bytes = bytes.strip_prefix(MAGIC)?;
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.u8()? != FORMAT_VERSION {
+        return None;
+    }
+    let version = i64::from_le_bytes(reader.array()?);
+    let prefix = reader.string()?;
+    let mut txns = HashMap::new();
+    for _ in 0..reader.varint()? {
+        let app_id = reader.string()?;
+        txns.insert(app_id, i64::from_le_bytes(reader.array()?));
+    }
+    let flags = reader.u8()?;
+    if flags > 3 {
+        return None;
+    }
+    let (canonical, ordered) = (flags & 1 != 0, flags & 2 != 0);
+    let len = reader.varint()?;
+    // each entry takes a file and the length of its directory at least.
+    if len > reader.remaining() / (MIN_FILE_LEN + 1) {
+        return None;
+    }
+    let mut raw_dirs = HashMap::with_capacity(len);
+    for _ in 0..len {
+        let file = reader.file()?;
+        raw_dirs.insert(file, reader.string()?);
+    }
+    let root = reader.node()?;
+    if reader.pos == bytes.len() {
+        let tree = DeltaTree {
+            root,
+            prefix,
+            txns,
+            canonical,
+            raw_dirs,
+            ordered,
+        };
+        Some((tree, version))
+    } else {
+        None
+    }
+}
+
+/// append the encoding of `node` to `out`.
+pub fn write_node<F: AsRef<ParquetDeltaFile>, S>(node: &TreeNode<F, S>, out: &mut Vec<u8>) {
+    match node {
+        TreeNode::FileEntries { files } => {
+            out.push(LEAF);
+            write_varint(out, files.len());
+            for file in files.iter() {
+                write_file(file.as_ref(), out);
+            }
+        }
+        TreeNode::Partition { name, values } => {
+            out.push(PARTITION);
+            write_str(out, name);
+            write_varint(out, values.len());
+            for (value, child) in values.iter() {
+                match value {
+                    None => out.push(0),
+                    Some(value) => {
+                        out.push(1);
+                        write_str(out, value);
+                    }
+                }
+                write_node(child, out);
+            }
+        }
+    }
+}
+
+/// decode a node written by `write_node`, `None` if `bytes` is not a valid encoding.
+pub fn read_node<S: BuildHasher + Default>(bytes: &[u8]) -> Option<TreeNode<ParquetDeltaFile, S>> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let node = reader.node()?;
+    if reader.pos == bytes.len() {
+        Some(node)
+    } else {
+        None
+    }
+}
+
+fn write_file(file: &ParquetDeltaFile, out: &mut Vec<u8>) {
+    out.extend_from_slice(&file.partition().to_le_bytes());
+    out.extend_from_slice(&file.uuid().as_u128().to_le_bytes());
+    let change_data = match file.kind() {
+        FileKind::ChangeData => 2,
+        _ => 0,
+    };
+    match file.cluster() {
+        None => out.extend_from_slice(&[change_data, 0]),
+        Some(cluster) => out.extend_from_slice(&[change_data | 1, cluster]),
+    }
+    out.push(match file.compression() {
+        None => 0,
+        Some(CompressionType::SNAPPY) => 1,
+        Some(CompressionType::GZIP) => 2,
+        Some(CompressionType::NONE) => 3,
+    });
+    match file.layout() {
+        NameLayout::Dotted => out.push(0),
+        NameLayout::Dashed => out.push(1),
+        NameLayout::Task { tid, task, attempt } => {
+            out.push(2);
+            out.extend_from_slice(&tid.to_le_bytes());
+            out.extend_from_slice(&task.to_le_bytes());
+            out.extend_from_slice(&attempt.to_le_bytes());
+        }
+    }
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_varint(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn node<S: BuildHasher + Default>(&mut self) -> Option<TreeNode<ParquetDeltaFile, S>> {
+        match self.u8()? {
+            LEAF => {
+                // lengths are checked against the input before allocating for them, so corrupt
+                // input can't make us reserve memory it couldn't fill.
+                let len = self.varint()?;
+                if len > self.remaining() / MIN_FILE_LEN {
+                    return None;
+                }
+                let files = (0..len)
+                    .map(|_| self.file())
+                    .collect::<Option<FileList<_>>>()?;
+                Some(TreeNode::FileEntries { files })
+            }
+            PARTITION => {
+                let name = self.string()?;
+                let len = self.varint()?;
+                // each child takes a value flag and a node tag at least.
+                if len > self.remaining() / 2 {
+                    return None;
+                }
+                let mut values = HashMap::with_capacity_and_hasher(len, S::default());
+                for _ in 0..len {
+                    let value = match self.u8()? {
+                        0 => None,
+                        1 => Some(Arc::from(self.string()?)),
+                        _ => return None,
+                    };
+                    values.insert(value, self.node()?);
+                }
+                Some(TreeNode::Partition { name, values })
+            }
+            _ => None,
+        }
+    }
+
+    fn file(&mut self) -> Option<ParquetDeltaFile> {
+        let partition = u32::from_le_bytes(self.array()?);
+        let uuid = Uuid::from_u128(u128::from_le_bytes(self.array()?));
+        let [flags, cluster] = self.array()?;
+        let cluster = Some(cluster).filter(|_| flags & 1 != 0);
+        let kind = if flags & 2 != 0 {
+            FileKind::ChangeData
+        } else {
+            FileKind::Data
+        };
+        let compression = match self.u8()? {
+            0 => None,
+            1 => Some(CompressionType::SNAPPY),
+            2 => Some(CompressionType::GZIP),
+            3 => Some(CompressionType::NONE),
+            _ => return None,
+        };
+        let layout = match self.u8()? {
+            0 => NameLayout::Dotted,
+            1 => NameLayout::Dashed,
+            2 => NameLayout::Task {
+                tid: u64::from_le_bytes(self.array()?),
+                task: u32::from_le_bytes(self.array()?),
+                attempt: u32::from_le_bytes(self.array()?),
+            },
+            _ => return None,
+        };
+        let file = ParquetDeltaFile::new(partition, uuid, cluster, compression)
+            .with_layout(layout)
+            .with_kind(kind);
+        Some(file)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes.get(self.pos..self.pos + N)?);
+        self.pos += N;
+        Some(array)
+    }
+
+    fn varint(&mut self) -> Option<usize> {
+        let mut value = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.varint()?;
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DeltaTree, FxBuildHasher};
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn node_round_trip() {
+        let uuid = Uuid::from_u128(42);
+        let paths = vec![
+            format!("a=1/b=x/part-00000-{}.c000.snappy.parquet", uuid),
+            format!("a=1/b=y/part-00001-{}-c001.gzip.parquet", uuid),
+            format!(
+                "a=2/b=__HIVE_DEFAULT_PARTITION__/part-00002-{}.parquet",
+                uuid
+            ),
+            format!(
+                "a=2/b=z/part-00003-tid-123-{}-4-5-c000.snappy.parquet",
+                uuid
+            ),
+        ];
+        let tree = DeltaTree::from_paths(&paths);
+        let mut bytes = vec![];
+        write_node(&tree.root, &mut bytes);
+        let root = read_node::<FxBuildHasher>(&bytes).unwrap();
+        assert_eq!(root, tree.root);
+    }
+
+    #[test]
+    fn tree_round_trip() {
+        let paths = vec![path("s3://bucket/table/a=%31/", 7)];
+        let mut tree = DeltaTree::from_paths(&paths);
+        tree.txns.insert("stream".to_string(), 4);
+        tree.txns.insert("batch".to_string(), 9);
+        let mut bytes = vec![];
+        write_tree(&tree, 12, &mut bytes);
+        let (read, version) = read_tree::<FxBuildHasher>(&bytes).unwrap();
+        assert_eq!(version, 12);
+        assert_eq!(read, tree);
+        assert_eq!(read.files_with_prefix(), paths);
+
+        tree.canonicalize();
+        let mut canonical = vec![];
+        write_tree(&tree, 12, &mut canonical);
+        let (read, _) = read_tree::<FxBuildHasher>(&canonical).unwrap();
+        assert!(read.canonical);
+        assert!(!read.ordered);
+        assert_eq!(read.raw_dirs, tree.raw_dirs);
+        assert_eq!(read.files_with_prefix(), paths);
+
+        tree.ordered = true;
+        let mut ordered = vec![];
+        write_tree(&tree, 12, &mut ordered);
+        assert!(read_tree::<FxBuildHasher>(&ordered).unwrap().0.ordered);
+
+        bytes[MAGIC.len()] = FORMAT_VERSION + 1;
+        assert!(read_tree::<FxBuildHasher>(&bytes).is_none());
+        assert!(read_tree::<FxBuildHasher>(b"PAR1").is_none());
+    }
+
+    #[test]
+    fn truncated_input_is_rejected() {
+        let paths = vec![path("a=1/", 1)];
+        let mut bytes = vec![];
+        write_node(&DeltaTree::from_paths(&paths).root, &mut bytes);
+        for len in 0..bytes.len() {
+            assert_eq!(read_node::<FxBuildHasher>(&bytes[..len]), None);
+        }
+        bytes.push(0);
+        assert_eq!(read_node::<FxBuildHasher>(&bytes), None);
+    }
+
+    #[test]
+    fn oversized_lengths_are_rejected() {
+        let huge = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        let leaf = [&[LEAF][..], &huge].concat();
+        assert_eq!(read_node::<FxBuildHasher>(&leaf), None);
+        let partition = [&[PARTITION, 1, b'a'][..], &huge, &[0, LEAF, 0]].concat();
+        assert_eq!(read_node::<FxBuildHasher>(&partition), None);
+    }
+}
@@ -0,0 +1,208 @@
+use super::options::DeltaTreeOptions;
+use super::verify::{is_data_file, list_objects};
+use super::{
+    build, DeltaTree, FileKind, FxBuildHasher, ParquetDeltaFile, SparkFileNameCodec, TreeNode,
+};
+use deltalake::storage::{ObjectMeta, StorageBackend, StorageError};
+use std::time::UNIX_EPOCH;
+
+/// a file along with the size and modification time of its add action, the payload of trees
+/// used for reports on the bytes of a table rather than just its files.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SizedDeltaFile {
+    pub file: ParquetDeltaFile,
+    /// size of the file in bytes.
+    pub size: u64,
+    /// milliseconds since the epoch.
+    pub modification_time: i64,
+}
+
+impl AsRef<ParquetDeltaFile> for SizedDeltaFile {
+    fn as_ref(&self) -> &ParquetDeltaFile {
+        &self.file
+    }
+}
+
+impl DeltaTree<SizedDeltaFile, FxBuildHasher> {
+    /// build the tree of the active files of `delta_table`, keeping their sizes.
+    pub fn new_sized(delta_table: &deltalake::DeltaTable) -> DeltaTree<SizedDeltaFile> {
+        DeltaTree::new_sized_with_options(delta_table, &DeltaTreeOptions::default())
+    }
+
+    /// like `new_sized`, with `options`.
+    pub fn new_sized_with_options(
+        delta_table: &deltalake::DeltaTable,
+        options: &DeltaTreeOptions,
+    ) -> DeltaTree<SizedDeltaFile> {
+        let adds = delta_table.get_active_add_actions().iter();
+        DeltaTree::from_add_actions_with_options(adds, options).with_txns(delta_table)
+    }
+
+    /// build the tree of the files of `adds`, consumed while the tree is built.
+    pub fn from_add_actions<'a>(
+        adds: impl Iterator<Item = &'a deltalake::action::Add>,
+    ) -> DeltaTree<SizedDeltaFile> {
+        DeltaTree::from_add_actions_with_options(adds, &DeltaTreeOptions::default())
+    }
+
+    /// like `from_add_actions`, with `options`.
+    pub fn from_add_actions_with_options<'a>(
+        adds: impl Iterator<Item = &'a deltalake::action::Add>,
+        options: &DeltaTreeOptions,
+    ) -> DeltaTree<SizedDeltaFile> {
+        let entries = adds.map(|add| (add.path.as_str(), (add.size, add.modification_time)));
+        DeltaTree::from_entry_iter_with_options(entries, options, |file, (size, time)| {
+            SizedDeltaFile {
+                file,
+                size: size.max(0) as u64,
+                modification_time: time,
+            }
+        })
+    }
+
+    /// build the tree of the data files found below `prefix` in `store`, ignoring the log,
+    /// e.g. to compare a table to its storage location or for parquet datasets without a log.
+    /// see `from_objects`.
+    pub async fn from_store_scan(
+        store: &dyn StorageBackend,
+        prefix: &str,
+    ) -> Result<DeltaTree<SizedDeltaFile>, StorageError> {
+        let objects = list_objects(store, prefix).await?;
+        Ok(DeltaTree::from_objects(&objects, prefix))
+    }
+
+    /// build the tree of the data files of a listing of the objects below `prefix`, with their
+    /// paths relative to it. hidden files like the log and paths that don't parse as data
+    /// files are skipped, objects without a size have 0.
+    pub fn from_objects(objects: &[ObjectMeta], prefix: &str) -> DeltaTree<SizedDeltaFile> {
+        let root = format!("{}/", prefix.trim_end_matches('/'));
+        let entries = objects
+            .iter()
+            .filter_map(|object| Some((object.path.strip_prefix(&root)?, object)))
+            .filter(|(path, _)| is_data_file(path) && FileKind::of(path) == FileKind::Data);
+        build(
+            entries,
+            &DeltaTreeOptions::new().lenient(true),
+            &SparkFileNameCodec,
+            |file, object| SizedDeltaFile {
+                file,
+                size: object.size.unwrap_or(0).max(0) as u64,
+                modification_time: object
+                    .modified
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |since| since.as_millis() as i64),
+            },
+        )
+    }
+}
+
+/// the total size of all files below `node`.
+pub fn subtree_size<S>(node: &TreeNode<SizedDeltaFile, S>) -> u64 {
+    match node {
+        TreeNode::FileEntries { files } => files.iter().map(|f| f.size).sum(),
+        TreeNode::Partition { values, .. } => values.values().map(subtree_size).sum(),
+    }
+}
+
+/// a size in bytes with an optional unit, `KB` / `MB` / `GB` / `TB` or the binary `KiB` /
+/// `MiB` / `GiB` / `TiB`.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let split = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (value, unit) = size.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", size))?;
+    let factor: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "KB" => 1000,
+        "MB" => 1000 * 1000,
+        "GB" => 1000 * 1000 * 1000,
+        "TB" => 1000 * 1000 * 1000 * 1000,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        "TiB" => 1 << 40,
+        _ => return Err(format!("invalid size '{}', expected e.g. 256MB", size)),
+    };
+    Ok(value * factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::{path, sized_tree};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn sizes_of_subtrees() {
+        let tree = sized_tree(vec![
+            (path("a=1/", 1), 100),
+            (path("a=1/", 2), 20),
+            (path("a=2/", 3), 3),
+        ]);
+        assert_eq!(subtree_size(&tree.root), 123);
+        match &tree.root {
+            TreeNode::Partition { values, .. } => {
+                assert_eq!(
+                    subtree_size(&values[&Some(std::sync::Arc::<str>::from("1"))]),
+                    120
+                );
+            }
+            _ => panic!("expected a partition"),
+        }
+    }
+
+    #[test]
+    fn tree_of_add_actions() {
+        let adds: Vec<_> = [("a=1/", 1, 10), ("a=2/", 2, 32)]
+            .iter()
+            .map(|(dir, id, size)| deltalake::action::Add {
+                path: path(dir, *id),
+                size: *size,
+                modification_time: 1614600000000,
+                ..Default::default()
+            })
+            .collect();
+        let tree = DeltaTree::from_add_actions(adds.iter());
+        assert_eq!(subtree_size(&tree.root), 42);
+        let paths: Vec<_> = adds.iter().map(|add| add.path.clone()).collect();
+        assert_eq!(tree.files(), paths);
+    }
+
+    #[test]
+    fn tree_of_objects() {
+        let object = |path: &str, size| ObjectMeta {
+            path: format!("s3://bucket/t/{}", path),
+            modified: UNIX_EPOCH + std::time::Duration::from_millis(1614600000000),
+            size,
+        };
+        let file = "part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet";
+        let objects = vec![
+            object(&format!("a=1/{}", file), Some(10)),
+            object("_delta_log/00000000000000000000.json", Some(100)),
+            object("a=2/notes.txt", Some(1)),
+            object(&format!("a=2/{}", file), None),
+        ];
+        let tree = DeltaTree::from_objects(&objects, "s3://bucket/t/");
+        let mut paths = tree.files();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![format!("a=1/{}", file), format!("a=2/{}", file)]
+        );
+        assert_eq!(subtree_size(&tree.root), 10);
+        let (_, files) = &tree.partitions()[0];
+        assert_eq!(files[0].modification_time, 1614600000000);
+    }
+
+    #[test]
+    fn parse_sizes() {
+        assert_eq!(parse_size("123"), Ok(123));
+        assert_eq!(parse_size("256MB"), Ok(256_000_000));
+        assert_eq!(parse_size("32 MiB"), Ok(32 << 20));
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("12 parsecs").is_err());
+    }
+}
@@ -0,0 +1,358 @@
+use super::builder::empty_node;
+use super::codec::SparkFileNameCodec;
+use super::history::commit_actions;
+use super::options::DeltaTreeOptions;
+use super::serialize::read_tree;
+use super::storage::StorageOptions;
+use super::{parse_path, prefix_of, DeltaTree, ParquetDeltaFile, PartitionPath, TreeNode};
+use deltalake::storage::{StorageBackend, StorageError};
+use deltalake::{DeltaDataTypeVersion, DeltaTableError};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+/// how `restore` brought a snapshot up to date.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUp {
+    /// there were no commits newer than the snapshot.
+    Current,
+    /// the commits newer than the snapshot were replayed onto it.
+    Replayed { commits: usize },
+    /// the snapshot was missing, unreadable or older than the log, the table was loaded from
+    /// scratch.
+    Reloaded,
+}
+
+/// a tree restored by `restore`, as of `version` of its table.
+#[derive(Debug)]
+pub struct Restored {
+    pub tree: DeltaTree,
+    pub version: DeltaDataTypeVersion,
+    pub catch_up: CatchUp,
+}
+
+/// why a commit couldn't be applied to a tree.
+#[derive(Debug)]
+pub enum ApplyError {
+    Json(serde_json::Error),
+    /// the tree's partition values were canonicalized, the paths of the commit can't be
+    /// matched against them.
+    Canonical,
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApplyError::Json(err) => write!(f, "can't parse commit: {}", err),
+            ApplyError::Canonical => {
+                write!(
+                    f,
+                    "can't apply a commit to a tree with canonical partition values"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApplyError {}
+
+impl From<serde_json::Error> for ApplyError {
+    fn from(err: serde_json::Error) -> ApplyError {
+        ApplyError::Json(err)
+    }
+}
+
+impl DeltaTree {
+    /// apply the adds, removes and application transactions of a commit file, one json action
+    /// per line, as if the tree had been built from the next version of its table. paths that
+    /// don't parse with the default options or have another prefix than the tree are skipped.
+    /// trees built with canonical partition values can't be updated this way.
+    pub fn apply_commit(&mut self, commit: &[u8]) -> Result<(), ApplyError> {
+        if self.canonical {
+            return Err(ApplyError::Canonical);
+        }
+        let options = DeltaTreeOptions::default();
+        for action in commit_actions(commit) {
+            let action = action?;
+            if let Some(path) = action["add"]["path"].as_str() {
+                if let Some((partitions, file)) = self.parse(path, &options) {
+                    add_file(&mut self.root, &partitions, file);
+                }
+            } else if let Some(path) = action["remove"]["path"].as_str() {
+                if let Some((partitions, file)) = self.parse(path, &options) {
+                    remove_file(&mut self.root, &partitions, &file);
+                }
+            } else if let Some(app_id) = action["txn"]["appId"].as_str() {
+                if let Some(version) = action["txn"]["version"].as_i64() {
+                    self.txns.insert(app_id.to_string(), version);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn parse<'a>(
+        &self,
+        path: &'a str,
+        options: &DeltaTreeOptions,
+    ) -> Option<(Vec<PartitionPath<'a>>, ParquetDeltaFile)> {
+        let (scheme, dirs, partitions, file, _) =
+            parse_path(path, options, &SparkFileNameCodec).ok()?;
+        if prefix_of(scheme, &dirs) == self.prefix {
+            Some((partitions, file))
+        } else {
+            None
+        }
+    }
+}
+
+/// insert `file` into the leaf below `partitions`, keeping the files of the leaf sorted like a
+/// build does. files already in the tree aren't added twice.
+fn add_file(root: &mut TreeNode, partitions: &[PartitionPath], file: ParquetDeltaFile) {
+    if matches!(root, TreeNode::FileEntries { files } if files.is_empty()) {
+        // the tree of a table without files, its shape is only known with the first file.
+        *root = empty_node(partitions);
+    }
+    let mut node = root;
+    for (level, partition) in partitions.iter().enumerate() {
+        node = match node {
+            TreeNode::Partition { name, values } if name == partition.key => {
+                let value = partition.value.as_deref().map(Arc::from);
+                values
+                    .entry(value)
+                    .or_insert_with(|| empty_node(&partitions[level + 1..]))
+            }
+            _ => return,
+        };
+    }
+    if let TreeNode::FileEntries { files } = node {
+        if let Err(idx) = files.binary_search(&file) {
+            files.insert(idx, file);
+        }
+    }
+}
+
+/// remove `file` from the leaf below `partitions`, along with partitions left empty.
+fn remove_file(node: &mut TreeNode, partitions: &[PartitionPath], file: &ParquetDeltaFile) {
+    match (node, partitions.split_first()) {
+        (TreeNode::FileEntries { files }, None) => {
+            if let Ok(idx) = files.binary_search(file) {
+                files.remove(idx);
+            }
+        }
+        (TreeNode::Partition { name, values }, Some((partition, rest)))
+            if name == partition.key =>
+        {
+            let value = partition.value.as_deref().map(Arc::from);
+            if let Some(child) = values.get_mut(&value) {
+                remove_file(child, rest, file);
+                if is_empty(child) {
+                    values.remove(&value);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_empty(node: &TreeNode) -> bool {
+    match node {
+        TreeNode::FileEntries { files } => files.is_empty(),
+        TreeNode::Partition { values, .. } => values.is_empty(),
+    }
+}
+
+/// the latest checkpoint named by `_last_checkpoint`.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct LastCheckpoint {
+    pub version: DeltaDataTypeVersion,
+    /// the number of files of a multi-part checkpoint.
+    pub parts: Option<u32>,
+}
+
+impl LastCheckpoint {
+    /// the names of the files of the checkpoint within `_delta_log`.
+    pub fn files(&self) -> Vec<String> {
+        match self.parts {
+            Some(parts) => (1..=parts)
+                .map(|part| {
+                    format!(
+                        "{:020}.checkpoint.{:010}.{:010}.parquet",
+                        self.version, part, parts
+                    )
+                })
+                .collect(),
+            None => vec![format!("{:020}.checkpoint.parquet", self.version)],
+        }
+    }
+}
+
+/// the latest checkpoint named by `_last_checkpoint`, if there is one.
+pub(super) async fn last_checkpoint(
+    backend: &dyn StorageBackend,
+    log_uri: &str,
+) -> Result<Option<LastCheckpoint>, DeltaTableError> {
+    match backend
+        .get_obj(&backend.join_path(log_uri, "_last_checkpoint"))
+        .await
+    {
+        Ok(bytes) => {
+            let checkpoint: Value = serde_json::from_slice(&bytes)?;
+            Ok(checkpoint["version"]
+                .as_i64()
+                .map(|version| LastCheckpoint {
+                    version,
+                    parts: checkpoint["parts"].as_u64().map(|parts| parts as u32),
+                }))
+        }
+        Err(StorageError::NotFound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// restore the tree of the table at `table_uri` from a `snapshot` written by `write_tree` and
+/// replay the commits newer than it, instead of loading the whole table. falls back to loading
+/// the table if there's no readable snapshot, if log cleanup already removed the commits
+/// following it, see `newer_commits`, or if the snapshot is of a canonical tree that commits
+/// can't be applied to.
+pub async fn restore(
+    table_uri: &str,
+    snapshot: Option<&[u8]>,
+    options: &StorageOptions,
+) -> Result<Restored, DeltaTableError> {
+    let (mut tree, version) = match snapshot.and_then(read_tree) {
+        Some(restored) => restored,
+        None => return reload(table_uri, options).await,
+    };
+    let backend = options.backend(table_uri)?;
+    let commits = match options
+        .retry(|| newer_commits(backend.as_ref(), table_uri, version))
+        .await?
+    {
+        Some(commits) => commits,
+        None => return reload(table_uri, options).await,
+    };
+    for commit in &commits {
+        match tree.apply_commit(commit) {
+            Ok(()) => {}
+            Err(ApplyError::Json(err)) => return Err(err.into()),
+            Err(ApplyError::Canonical) => return reload(table_uri, options).await,
+        }
+    }
+    let catch_up = match commits.len() {
+        0 => CatchUp::Current,
+        commits => CatchUp::Replayed { commits },
+    };
+    Ok(Restored {
+        tree,
+        version: version + commits.len() as DeltaDataTypeVersion,
+        catch_up,
+    })
+}
+
+/// the commit files following `version` of the table at `table_uri`, oldest first, or `None` if
+/// log cleanup already removed them: there's no next commit and `_last_checkpoint` is newer
+/// than `version`.
+pub async fn newer_commits(
+    backend: &dyn StorageBackend,
+    table_uri: &str,
+    version: DeltaDataTypeVersion,
+) -> Result<Option<Vec<Vec<u8>>>, DeltaTableError> {
+    let log_uri = backend.join_path(table_uri, "_delta_log");
+    let mut commits = vec![];
+    loop {
+        let next = version + commits.len() as DeltaDataTypeVersion + 1;
+        let commit_uri = backend.join_path(&log_uri, &format!("{:020}.json", next));
+        match backend.get_obj(&commit_uri).await {
+            Ok(commit) => commits.push(commit),
+            Err(StorageError::NotFound) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    if commits.is_empty() {
+        let checkpoint = last_checkpoint(backend, &log_uri).await?;
+        if matches!(checkpoint, Some(checkpoint) if checkpoint.version > version) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(commits))
+}
+
+/// load the latest version of the table at `table_uri` from scratch.
+pub(super) async fn reload(
+    table_uri: &str,
+    options: &StorageOptions,
+) -> Result<Restored, DeltaTableError> {
+    let delta_table = options.open_table(table_uri).await?;
+    Ok(Restored {
+        tree: DeltaTree::new(&delta_table),
+        version: delta_table.version,
+        catch_up: CatchUp::Reloaded,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use crate::tree::serialize::write_tree;
+    use pretty_assertions::assert_eq;
+
+    fn add(path: &str) -> String {
+        format!(
+            r#"{{"add":{{"path":"{}","size":1,"dataChange":true}}}}"#,
+            path
+        )
+    }
+
+    fn remove(path: &str) -> String {
+        format!(r#"{{"remove":{{"path":"{}","dataChange":true}}}}"#, path)
+    }
+
+    #[test]
+    fn replay_commits() {
+        let mut tree = DeltaTree::from_paths(&[path("a=1/", 1), path("a=2/", 2)]);
+        let commit = [
+            r#"{"commitInfo":{"operation":"WRITE"}}"#.to_string(),
+            add(&path("a=1/", 3)),
+            add(&path("a=3/", 4)),
+            remove(&path("a=2/", 2)),
+            r#"{"txn":{"appId":"stream","version":7}}"#.to_string(),
+        ]
+        .join("\n");
+        tree.apply_commit(commit.as_bytes()).unwrap();
+        let expected = DeltaTree::from_paths(&[path("a=1/", 1), path("a=1/", 3), path("a=3/", 4)]);
+        assert_eq!(tree.root, expected.root);
+        assert_eq!(tree.txn_version("stream"), Some(7));
+
+        let mut snapshot = vec![];
+        write_tree(&tree, 3, &mut snapshot);
+        let (mut restored, version): (DeltaTree, _) = read_tree(&snapshot).unwrap();
+        assert_eq!(version, 3);
+        restored
+            .apply_commit(remove(&path("a=1/", 1)).as_bytes())
+            .unwrap();
+        let expected = DeltaTree::from_paths(&[path("a=1/", 3), path("a=3/", 4)]);
+        assert_eq!(restored.root, expected.root);
+        assert_eq!(restored.txn_version("stream"), Some(7));
+
+        let mut empty = DeltaTree::from_paths(Vec::<String>::new());
+        empty
+            .apply_commit(add(&path("a=1/b=2/", 5)).as_bytes())
+            .unwrap();
+        assert_eq!(empty, DeltaTree::from_paths(&[path("a=1/b=2/", 5)]));
+        assert!(matches!(
+            tree.apply_commit(b"{\"add\":"),
+            Err(ApplyError::Json(_))
+        ));
+
+        let mut canonical = DeltaTree::from_paths(&[path("a=1/", 1)]);
+        canonical.canonicalize();
+        assert!(matches!(
+            canonical.apply_commit(add(&path("a=1/", 3)).as_bytes()),
+            Err(ApplyError::Canonical)
+        ));
+    }
+}
@@ -0,0 +1,184 @@
+use super::sized::SizedDeltaFile;
+use super::{partition_value, DeltaTree, PartitionValue, TreeNode};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// a health report of a table: how its bytes are spread over partitions and files.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct TableStats {
+    pub files: usize,
+    pub bytes: u64,
+    /// leaf partitions, i.e. directories containing files.
+    pub partitions: usize,
+    /// the partition columns, outermost first.
+    pub columns: Vec<String>,
+    /// the number of distinct values of each partition column.
+    pub cardinalities: Vec<usize>,
+    /// the liquid clustering columns, which the tree doesn't know about, `None` if unknown.
+    /// see `clustering::clustering_columns`.
+    pub clustering_columns: Option<Vec<String>>,
+    /// the leaf partitions with the fewest and the most bytes, `top` of each.
+    pub smallest: Vec<PartitionSize>,
+    pub largest: Vec<PartitionSize>,
+    /// files smaller than `small_file_threshold` bytes, and their share of all files.
+    pub small_file_threshold: u64,
+    pub small_files: usize,
+    pub small_file_ratio: f64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PartitionSize {
+    /// the directory of the partition, e.g. `date=2021-03-01/hour=00/`.
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl PartitionSize {
+    /// the partition values of `path`.
+    pub fn values(&self) -> Vec<PartitionValue> {
+        PartitionValue::from_dir(&self.path)
+    }
+}
+
+impl TableStats {
+    pub fn new<S>(
+        tree: &DeltaTree<SizedDeltaFile, S>,
+        small_file_threshold: u64,
+        top: usize,
+    ) -> TableStats {
+        let mut columns = vec![];
+        let mut values: Vec<HashSet<Option<&str>>> = vec![];
+        let mut leaves = vec![];
+        collect(
+            String::new(),
+            &tree.root,
+            0,
+            &mut columns,
+            &mut values,
+            &mut leaves,
+        );
+
+        let files = leaves.iter().map(|(_, files)| files.len()).sum();
+        let small_files = leaves
+            .iter()
+            .flat_map(|(_, files)| files.iter())
+            .filter(|f| f.size < small_file_threshold)
+            .count();
+        let mut partitions: Vec<PartitionSize> = leaves
+            .into_iter()
+            .map(|(path, files)| PartitionSize {
+                path,
+                files: files.len(),
+                bytes: files.iter().map(|f| f.size).sum(),
+            })
+            .collect();
+        partitions.sort_by(|a, b| (a.bytes, &a.path).cmp(&(b.bytes, &b.path)));
+        let smallest = partitions.iter().take(top).cloned().collect();
+        let largest = partitions.iter().rev().take(top).cloned().collect();
+
+        TableStats {
+            files,
+            bytes: partitions.iter().map(|p| p.bytes).sum(),
+            partitions: partitions.len(),
+            columns,
+            cardinalities: values.iter().map(HashSet::len).collect(),
+            clustering_columns: None,
+            smallest,
+            largest,
+            small_file_threshold,
+            small_files,
+            small_file_ratio: if files == 0 {
+                0.0
+            } else {
+                small_files as f64 / files as f64
+            },
+        }
+    }
+}
+
+/// the leaf partitions of `tree` with their directories and files.
+pub(super) fn leaves<S>(tree: &DeltaTree<SizedDeltaFile, S>) -> Vec<(String, &[SizedDeltaFile])> {
+    let mut leaves = vec![];
+    collect(
+        String::new(),
+        &tree.root,
+        0,
+        &mut vec![],
+        &mut vec![],
+        &mut leaves,
+    );
+    leaves
+}
+
+fn collect<'a, S>(
+    dir: String,
+    node: &'a TreeNode<SizedDeltaFile, S>,
+    level: usize,
+    columns: &mut Vec<String>,
+    values: &mut Vec<HashSet<Option<&'a str>>>,
+    leaves: &mut Vec<(String, &'a [SizedDeltaFile])>,
+) {
+    match node {
+        TreeNode::FileEntries { files } => leaves.push((dir, files)),
+        TreeNode::Partition {
+            name,
+            values: children,
+        } => {
+            if columns.len() == level {
+                columns.push(name.clone());
+                values.push(HashSet::new());
+            }
+            for (value, child) in children.iter() {
+                values[level].insert(value.as_deref());
+                let child_dir = format!("{}{}={}/", dir, name, partition_value(value));
+                collect(child_dir, child, level + 1, columns, values, leaves);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::{path, sized_tree};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn stats_of_a_table() {
+        let entries = vec![
+            ("d=1/h=0/", 1, 10),
+            ("d=1/h=0/", 2, 500),
+            ("d=1/h=1/", 3, 20),
+            ("d=2/h=0/", 4, 1000),
+        ]
+        .into_iter()
+        .map(|(dir, id, size)| (path(dir, id), size))
+        .collect();
+        let tree = sized_tree(entries);
+        let stats = TableStats::new(&tree, 100, 1);
+        assert_eq!(stats.files, 4);
+        assert_eq!(stats.bytes, 1530);
+        assert_eq!(stats.partitions, 3);
+        assert_eq!(stats.columns, vec!["d", "h"]);
+        assert_eq!(stats.cardinalities, vec![2, 2]);
+        assert_eq!(stats.smallest[0].path, "d=1/h=1/");
+        assert_eq!(
+            stats.smallest[0].values(),
+            vec![
+                PartitionValue::new("d", Some("1")),
+                PartitionValue::new("h", Some("1"))
+            ]
+        );
+        assert_eq!(
+            stats.largest,
+            vec![PartitionSize {
+                path: "d=2/h=0/".to_string(),
+                files: 1,
+                bytes: 1000
+            }]
+        );
+        assert_eq!(stats.small_files, 2);
+        assert_eq!(stats.small_file_ratio, 0.5);
+    }
+}
@@ -0,0 +1,551 @@
+use super::watcher::{next_delay, random};
+use deltalake::storage::{StorageBackend, StorageError};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+/// configuration of the object store a table lives in, passed along whenever a table is
+/// loaded. unset options fall back to the object store's defaults (environment, instance
+/// profile, ...).
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct StorageOptions {
+    options: HashMap<String, String>,
+    timeout: Option<Duration>,
+    cancellation: Option<Cancellation>,
+    retries: Option<RetryPolicy>,
+    retry_stats: Option<RetryStats>,
+}
+
+/// how often to retry a storage operation failing with a transient error, like throttling, a
+/// server error or a timeout, and how long to wait in between.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    /// in thousandths of the delay.
+    jitter_permille: u16,
+}
+
+/// the retries of all options it was given to, e.g. for a service to export them. clones
+/// share the same counts.
+#[derive(Debug, Default, Clone)]
+pub struct RetryStats {
+    retries: Arc<AtomicU64>,
+    exhausted: Arc<AtomicU64>,
+}
+
+/// cancels the storage operations of all options it was given to, e.g. when a service shuts
+/// down. clones share the same state.
+#[derive(Debug, Default, Clone)]
+pub struct Cancellation {
+    cancelled: Arc<AtomicBool>,
+    /// the wakers of the pending operations, by the id of the operation.
+    wakers: Arc<Mutex<HashMap<u64, Waker>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+/// removes the waker of a guarded operation once it completes or is dropped.
+struct Registration<'a> {
+    cancellation: &'a Cancellation,
+    id: u64,
+}
+
+impl Cancellation {
+    pub fn new() -> Cancellation {
+        Cancellation::default()
+    }
+
+    /// fail pending and future operations with a `StorageError::Generic`.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        for (_, waker) in self.wakers.lock().unwrap().drain() {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn registration(&self) -> Registration<'_> {
+        Registration {
+            cancellation: self,
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+impl Registration<'_> {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.cancellation.wakers.lock().unwrap();
+        match wakers.get(&self.id) {
+            Some(registered) if registered.will_wake(waker) => {}
+            _ => {
+                wakers.insert(self.id, waker.clone());
+            }
+        }
+    }
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.cancellation.wakers.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl PartialEq for Cancellation {
+    fn eq(&self, other: &Cancellation) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+impl Eq for Cancellation {}
+
+impl RetryPolicy {
+    /// up to `max_retries` retries, the first after 100 milliseconds, doubling the delay up to
+    /// 10 seconds and spreading it by up to half of it in either direction.
+    pub fn new(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter_permille: 500,
+        }
+    }
+
+    /// the delay before the first retry.
+    pub fn base_delay(self, base_delay: Duration) -> RetryPolicy {
+        RetryPolicy { base_delay, ..self }
+    }
+
+    pub fn max_delay(self, max_delay: Duration) -> RetryPolicy {
+        RetryPolicy { max_delay, ..self }
+    }
+
+    /// spread each delay by up to this fraction of it in either direction.
+    pub fn jitter(self, jitter: f64) -> RetryPolicy {
+        assert!(
+            (0.0..=1.0).contains(&jitter),
+            "jitter must be within 0 and 1"
+        );
+        RetryPolicy {
+            jitter_permille: (jitter * 1000.0).round() as u16,
+            ..self
+        }
+    }
+
+    /// the delay before retry number `retry`, counting from 1.
+    fn delay(&self, retry: u32) -> Duration {
+        next_delay(
+            self.base_delay,
+            retry - 1,
+            self.max_delay,
+            f64::from(self.jitter_permille) / 1000.0,
+            random(),
+        )
+    }
+}
+
+impl RetryStats {
+    pub fn new() -> RetryStats {
+        RetryStats::default()
+    }
+
+    /// the retries of operations so far.
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// the operations that still failed with a transient error after their last retry.
+    pub fn exhausted(&self) -> u64 {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+}
+
+impl PartialEq for RetryStats {
+    fn eq(&self, other: &RetryStats) -> bool {
+        Arc::ptr_eq(&self.retries, &other.retries)
+    }
+}
+
+impl Eq for RetryStats {}
+
+/// whether an error of the object store is worth retrying: throttling, a server error, a
+/// timeout or a dropped connection. judged by the message, as each object store reports them
+/// differently. status codes only count following `status`, `code`, `http` or `response`, so
+/// numbers in paths like `d=500/` don't.
+pub fn is_transient(message: &str) -> bool {
+    let message = message.to_lowercase();
+    let statuses = ["429", "500", "502", "503", "504"];
+    let phrases = [
+        "timed out",
+        "timeout",
+        "throttl",
+        "slowdown",
+        "slow down",
+        "too many requests",
+        "internal error",
+        "internalerror",
+        "bad gateway",
+        "service unavailable",
+        "serviceunavailable",
+        "server busy",
+        "serverbusy",
+        "connection reset",
+        "connection closed",
+        "broken pipe",
+    ];
+    let labels = ["status", "code", "http", "response"];
+    let tokens: Vec<&str> = message
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .collect();
+    let status = tokens
+        .windows(2)
+        .any(|pair| labels.contains(&pair[0]) && statuses.contains(&pair[1]));
+    status || phrases.iter().any(|phrase| message.contains(phrase))
+}
+
+impl StorageOptions {
+    pub fn new() -> StorageOptions {
+        StorageOptions::default()
+    }
+
+    /// the AWS region of an S3 bucket.
+    pub fn region(self, region: &str) -> StorageOptions {
+        self.option("AWS_REGION", region)
+    }
+
+    /// a custom S3 compatible endpoint, e.g. MinIO or localstack.
+    pub fn endpoint(self, endpoint: &str) -> StorageOptions {
+        self.option("AWS_ENDPOINT_URL", endpoint)
+    }
+
+    /// access public buckets without credentials, skipping request signing.
+    pub fn anonymous(self, anonymous: bool) -> StorageOptions {
+        self.option(
+            "AWS_SKIP_SIGNATURE",
+            if anonymous { "true" } else { "false" },
+        )
+    }
+
+    /// a named profile of the AWS shared config / credentials files.
+    pub fn profile(self, profile: &str) -> StorageOptions {
+        self.option("AWS_PROFILE", profile)
+    }
+
+    /// a shared access signature for an ADLS / Azure blob storage container.
+    pub fn sas_token(self, token: &str) -> StorageOptions {
+        self.option("AZURE_STORAGE_SAS_TOKEN", token)
+    }
+
+    /// a GCS service account key file.
+    pub fn service_account(self, path: &str) -> StorageOptions {
+        self.option("GOOGLE_SERVICE_ACCOUNT", path)
+    }
+
+    /// give up on each request to the object store and each load of a table after `timeout`,
+    /// failing with a `StorageError::Generic`.
+    pub fn timeout(mut self, timeout: Duration) -> StorageOptions {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// fail the pending operations once `cancellation` is cancelled.
+    pub fn cancellation(mut self, cancellation: &Cancellation) -> StorageOptions {
+        self.cancellation = Some(cancellation.clone());
+        self
+    }
+
+    /// retry operations failing with a transient error, see `retry`. none are by default.
+    pub fn retries(mut self, policy: RetryPolicy) -> StorageOptions {
+        self.retries = Some(policy);
+        self
+    }
+
+    /// count the retries of operations in `stats`.
+    pub fn retry_stats(mut self, stats: &RetryStats) -> StorageOptions {
+        self.retry_stats = Some(stats.clone());
+        self
+    }
+
+    /// any other option understood by the object store, by its configuration key.
+    pub fn option(mut self, key: &str, value: &str) -> StorageOptions {
+        self.options.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// add the `options` given as `KEY=VALUE`, e.g. by `--storage-option` on the command line.
+    pub fn parse_options<S: AsRef<str>>(self, options: &[S]) -> Result<StorageOptions, String> {
+        options.iter().try_fold(self, |storage, option| {
+            match option.as_ref().split_once('=') {
+                Some((key, value)) if !key.is_empty() => Ok(storage.option(key, value)),
+                _ => Err(format!(
+                    "expected KEY=VALUE storage option, got '{}'",
+                    option.as_ref()
+                )),
+            }
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    /// whether no options for the object store are set. the timeout, cancellation and retries
+    /// aren't passed to the object store and don't count.
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+
+    /// await `operation` within the timeout, unless cancelled first.
+    pub async fn guard<T, E: From<StorageError>>(
+        &self,
+        operation: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let timeout = self.timeout;
+        let mut operation = Box::pin(async move {
+            match timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, operation).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        Err(StorageError::Generic(format!("timed out after {:?}", timeout)).into())
+                    }
+                },
+                None => operation.await,
+            }
+        });
+        let registration = self.cancellation.as_ref().map(Cancellation::registration);
+        futures::future::poll_fn(|cx| match &registration {
+            Some(registration) if registration.cancellation.is_cancelled() => {
+                Poll::Ready(Err(StorageError::Generic("cancelled".to_string()).into()))
+            }
+            Some(registration) => {
+                registration.register(cx.waker());
+                operation.as_mut().poll(cx)
+            }
+            None => operation.as_mut().poll(cx),
+        })
+        .await
+    }
+
+    /// await `operation` like `guard`, starting it over after a transient error, see
+    /// `is_transient`, as long as the retry policy allows.
+    pub async fn retry<T, E, F, Fut>(&self, mut operation: F) -> Result<T, E>
+    where
+        E: From<StorageError> + Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut retries = 0;
+        loop {
+            let err = match self.guard(operation()).await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            let transient = is_transient(&err.to_string());
+            let policy = match self.retries {
+                Some(policy) if transient && retries < policy.max_retries => policy,
+                _ => {
+                    match &self.retry_stats {
+                        Some(stats) if transient && retries > 0 => {
+                            stats.exhausted.fetch_add(1, Ordering::Relaxed);
+                        }
+                        _ => {}
+                    }
+                    return Err(err);
+                }
+            };
+            retries += 1;
+            if let Some(stats) = &self.retry_stats {
+                stats.retries.fetch_add(1, Ordering::Relaxed);
+            }
+            tokio::time::sleep(policy.delay(retries)).await;
+        }
+    }
+
+    /// the options as passed to delta-rs.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        self.options.clone()
+    }
+
+    /// load the latest version of the table at `table_uri`.
+    pub async fn open_table(
+        &self,
+        table_uri: &str,
+    ) -> Result<deltalake::DeltaTable, deltalake::DeltaTableError> {
+        self.retry(|| self.builder(table_uri).load()).await
+    }
+
+    pub async fn open_table_with_version(
+        &self,
+        table_uri: &str,
+        version: deltalake::DeltaDataTypeVersion,
+    ) -> Result<deltalake::DeltaTable, deltalake::DeltaTableError> {
+        self.retry(|| self.builder(table_uri).with_version(version).load())
+            .await
+    }
+
+    /// load the version of the table at `timestamp`, an RFC 3339 date time string.
+    pub async fn open_table_with_timestamp(
+        &self,
+        table_uri: &str,
+        timestamp: &str,
+    ) -> Result<deltalake::DeltaTable, deltalake::DeltaTableError> {
+        self.retry(|| async move {
+            self.builder(table_uri)
+                .with_datestring(timestamp)?
+                .load()
+                .await
+        })
+        .await
+    }
+
+    /// direct access to the object store at `uri`, e.g. to read log files or probe data files.
+    pub fn backend(
+        &self,
+        uri: &str,
+    ) -> Result<Box<dyn StorageBackend>, deltalake::storage::StorageError> {
+        deltalake::storage::get_backend_for_uri_with_options(uri, self.to_map())
+    }
+
+    fn builder(&self, table_uri: &str) -> deltalake::DeltaTableBuilder {
+        deltalake::DeltaTableBuilder::from_uri(table_uri).with_storage_options(self.to_map())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn storage_option_keys() {
+        let options = StorageOptions::new()
+            .profile("prod")
+            .anonymous(false)
+            .sas_token("sv=2021&sig=abc")
+            .option("AWS_ALLOW_HTTP", "true");
+        let mut keys: Vec<_> = options.to_map().into_iter().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                ("AWS_ALLOW_HTTP".to_string(), "true".to_string()),
+                ("AWS_PROFILE".to_string(), "prod".to_string()),
+                ("AWS_SKIP_SIGNATURE".to_string(), "false".to_string()),
+                (
+                    "AZURE_STORAGE_SAS_TOKEN".to_string(),
+                    "sv=2021&sig=abc".to_string()
+                ),
+            ]
+        );
+        assert!(StorageOptions::new().is_empty());
+    }
+
+    #[test]
+    fn parse_storage_options() {
+        let options = StorageOptions::new()
+            .region("eu-central-1")
+            .parse_options(&[
+                "AWS_ALLOW_HTTP=true",
+                "AZURE_STORAGE_SAS_TOKEN=sv=2021&sig=abc",
+            ])
+            .unwrap();
+        assert_eq!(options.get("AWS_REGION"), Some("eu-central-1"));
+        assert_eq!(options.get("AWS_ALLOW_HTTP"), Some("true"));
+        assert_eq!(
+            options.get("AZURE_STORAGE_SAS_TOKEN"),
+            Some("sv=2021&sig=abc")
+        );
+        assert_eq!(
+            StorageOptions::new().parse_options(&["AWS_ALLOW_HTTP"]),
+            Err("expected KEY=VALUE storage option, got 'AWS_ALLOW_HTTP'".to_string())
+        );
+        assert!(StorageOptions::new().parse_options(&["=true"]).is_err());
+        assert!(StorageOptions::new()
+            .parse_options::<String>(&[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn cancel_operations() {
+        use futures::FutureExt;
+
+        assert!(StorageOptions::new()
+            .timeout(Duration::from_secs(5))
+            .is_empty());
+        let cancellation = Cancellation::new();
+        let options = StorageOptions::new().cancellation(&cancellation);
+        let ok = options.guard(async { Ok::<_, StorageError>(7) });
+        assert!(matches!(ok.now_or_never(), Some(Ok(7))));
+
+        let pending = options.guard(futures::future::pending::<Result<(), StorageError>>());
+        let mut pending = Box::pin(pending);
+        assert!(pending.as_mut().now_or_never().is_none());
+        assert_eq!(cancellation.wakers.lock().unwrap().len(), 1);
+        let mut other =
+            Box::pin(options.guard(futures::future::pending::<Result<(), StorageError>>()));
+        assert!(other.as_mut().now_or_never().is_none());
+        drop(other);
+        assert_eq!(cancellation.wakers.lock().unwrap().len(), 1);
+        cancellation.cancel();
+        assert!(matches!(
+            pending.now_or_never(),
+            Some(Err(StorageError::Generic(_)))
+        ));
+        assert!(cancellation.wakers.lock().unwrap().is_empty());
+        assert_eq!(options.clone(), options);
+    }
+
+    #[tokio::test]
+    async fn retry_transient_errors() {
+        assert!(is_transient("S3 request failed: 503 Service Unavailable"));
+        assert!(is_transient("request failed with status: 502"));
+        assert!(!is_transient("d=500/part-00000.parquet: access denied"));
+        assert!(is_transient("<Code>SlowDown</Code>"));
+        assert!(!is_transient("Object not found"));
+        assert!(!is_transient("d=1/part-00500-a.parquet: access denied"));
+
+        let stats = RetryStats::new();
+        let options = StorageOptions::new()
+            .retries(RetryPolicy::new(2).base_delay(Duration::from_millis(1)))
+            .retry_stats(&stats);
+        let attempts = std::cell::Cell::new(0);
+        let flaky = options
+            .retry(|| {
+                let attempt = attempts.replace(attempts.get() + 1);
+                async move {
+                    match attempt {
+                        0 | 1 => Err(StorageError::Generic("503 Slow Down".to_string())),
+                        attempt => Ok(attempt),
+                    }
+                }
+            })
+            .await;
+        assert!(matches!(flaky, Ok(2)));
+        assert_eq!((stats.retries(), stats.exhausted()), (2, 0));
+
+        let throttled = options
+            .retry(|| async { Err::<(), _>(StorageError::Generic("throttled".to_string())) })
+            .await;
+        assert!(throttled.is_err());
+        assert_eq!((stats.retries(), stats.exhausted()), (4, 1));
+
+        attempts.set(0);
+        let missing = options
+            .retry(|| {
+                attempts.set(attempts.get() + 1);
+                async { Err::<(), _>(StorageError::NotFound) }
+            })
+            .await;
+        assert!(matches!(missing, Err(StorageError::NotFound)));
+        assert_eq!((attempts.get(), stats.retries()), (1, 4));
+    }
+}
@@ -0,0 +1,166 @@
+//! proptest strategies and `Arbitrary` impls for property tests and fuzzing of file name and
+//! path parsing, in this crate and downstream.
+
+use super::{CompressionType, NameLayout, ParquetDeltaFile, NULL_PARTITION_VALUE};
+use arbitrary::{Arbitrary, Unstructured};
+use proptest::prelude::*;
+use uuid::Uuid;
+
+const COMPRESSIONS: [Option<CompressionType>; 4] = [
+    None,
+    Some(CompressionType::SNAPPY),
+    Some(CompressionType::GZIP),
+    Some(CompressionType::NONE),
+];
+
+const KEY_START: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const KEY: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789_";
+const VALUE: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_.-";
+
+/// files whose names round trip through `SparkFileNameCodec`: partition numbers of at most
+/// five digits, and the dashed layout only with a cluster, as it can't be told apart from the
+/// dotted one otherwise.
+pub fn parquet_delta_file() -> impl Strategy<Value = ParquetDeltaFile> {
+    let layout = (0..3u8, any::<u64>(), any::<u32>(), any::<u32>());
+    (
+        0..100_000u32,
+        any::<u128>(),
+        proptest::option::of(any::<u8>()),
+        proptest::sample::select(COMPRESSIONS.to_vec()),
+        layout,
+    )
+        .prop_map(|(partition, uuid, cluster, compression, layout)| {
+            file(partition, uuid, cluster, compression, layout)
+        })
+}
+
+/// the file paths of a table with up to three partition columns, including `null` values.
+pub fn table_paths() -> impl Strategy<Value = Vec<String>> {
+    proptest::collection::vec("[a-z][a-z0-9_]{0,7}", 0..=3).prop_flat_map(|mut columns| {
+        columns.sort();
+        columns.dedup();
+        let values =
+            proptest::collection::vec(proptest::option::of("[a-zA-Z0-9_.-]{1,10}"), columns.len());
+        proptest::collection::vec((values, parquet_delta_file()), 1..50).prop_map(move |rows| {
+            rows.iter()
+                .map(|(values, file)| path(&columns, values, file))
+                .collect()
+        })
+    })
+}
+
+impl<'a> Arbitrary<'a> for ParquetDeltaFile {
+    /// like `parquet_delta_file`, only files whose names round trip.
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<ParquetDeltaFile> {
+        Ok(file(
+            u.int_in_range(0..=99_999)?,
+            u.arbitrary()?,
+            u.arbitrary()?,
+            *u.choose(&COMPRESSIONS)?,
+            (
+                u.int_in_range(0..=2)?,
+                u.arbitrary()?,
+                u.arbitrary()?,
+                u.arbitrary()?,
+            ),
+        ))
+    }
+}
+
+/// the file paths of a table, all with the same partition columns.
+#[derive(Debug, Clone)]
+pub struct TablePaths(pub Vec<String>);
+
+impl<'a> Arbitrary<'a> for TablePaths {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<TablePaths> {
+        let mut columns = vec![];
+        for _ in 0..u.int_in_range(0..=3)? {
+            columns.push(word(u, KEY_START, KEY)?);
+        }
+        columns.sort();
+        columns.dedup();
+        let mut paths = vec![];
+        for _ in 0..u.int_in_range(1..=50)? {
+            let mut values = vec![];
+            for _ in &columns {
+                values.push(match u.arbitrary()? {
+                    true => Some(word(u, VALUE, VALUE)?),
+                    false => None,
+                });
+            }
+            paths.push(path(&columns, &values, &u.arbitrary()?));
+        }
+        Ok(TablePaths(paths))
+    }
+}
+
+fn file(
+    partition: u32,
+    uuid: u128,
+    cluster: Option<u8>,
+    compression: Option<CompressionType>,
+    (layout, tid, task, attempt): (u8, u64, u32, u32),
+) -> ParquetDeltaFile {
+    let layout = match layout {
+        0 => NameLayout::Dotted,
+        1 if cluster.is_some() => NameLayout::Dashed,
+        1 => NameLayout::Dotted,
+        _ => NameLayout::Task { tid, task, attempt },
+    };
+    ParquetDeltaFile::new(partition, Uuid::from_u128(uuid), cluster, compression)
+        .with_layout(layout)
+}
+
+fn path(columns: &[String], values: &[Option<String>], file: &ParquetDeltaFile) -> String {
+    let dirs: String = columns
+        .iter()
+        .zip(values)
+        .map(|(column, value)| {
+            let value = value.as_deref().unwrap_or(NULL_PARTITION_VALUE);
+            format!("{}={}/", column, value)
+        })
+        .collect();
+    format!("{}{}", dirs, file.name())
+}
+
+/// a word of one to ten characters, the first from `start`.
+fn word(u: &mut Unstructured, start: &[u8], rest: &[u8]) -> arbitrary::Result<String> {
+    let mut word = String::new();
+    word.push(*u.choose(start)? as char);
+    for _ in 0..u.int_in_range(0..=9)? {
+        word.push(*u.choose(rest)? as char);
+    }
+    Ok(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::codec::{FileNameCodec, RegexFileNameCodec, SparkFileNameCodec};
+    use crate::tree::DeltaTree;
+
+    proptest! {
+        #[test]
+        fn file_names_round_trip(file in parquet_delta_file()) {
+            let name = file.name();
+            prop_assert_eq!(SparkFileNameCodec.decode(&name), Some(file));
+            prop_assert_eq!(RegexFileNameCodec.decode(&name), Some(file));
+        }
+
+        #[test]
+        fn parsing_is_idempotent(name in "part-[0-9]{4,6}-(tid-[0-9]{1,3}-)?[0-9a-f-]{34,38}[-.a-z0-9]{0,20}") {
+            if let Some(file) = SparkFileNameCodec.decode(&name) {
+                prop_assert_eq!(SparkFileNameCodec.decode(&file.name()), Some(file));
+            }
+        }
+
+        #[test]
+        fn trees_round_trip(paths in table_paths()) {
+            let mut files = DeltaTree::from_paths(&paths).files_with_prefix();
+            files.sort();
+            let mut expected = paths;
+            expected.sort();
+            prop_assert_eq!(files, expected);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use super::sized::SizedDeltaFile;
+use super::{stats, DeltaTree, FxBuildHasher, ParquetDeltaFile, TreeNode};
+use deltalake::action::{Add, Remove};
+
+/// a file removed from the table but not yet deleted by vacuum, along with its tombstone.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RemovedDeltaFile {
+    pub file: ParquetDeltaFile,
+    /// milliseconds since the epoch, 0 if the writer didn't record it.
+    pub deletion_timestamp: i64,
+    /// size of the file in bytes, if the writer recorded it.
+    pub size: Option<u64>,
+}
+
+impl AsRef<ParquetDeltaFile> for RemovedDeltaFile {
+    fn as_ref(&self) -> &ParquetDeltaFile {
+        &self.file
+    }
+}
+
+/// the live files of a table and its tombstones, each organized as a tree. answers which
+/// files vacuum may delete and what changed recently without going back to the log.
+#[derive(Debug)]
+pub struct TableFiles<S = FxBuildHasher> {
+    pub live: DeltaTree<SizedDeltaFile, S>,
+    pub removed: DeltaTree<RemovedDeltaFile, S>,
+}
+
+impl TableFiles {
+    pub fn new(delta_table: &deltalake::DeltaTable) -> TableFiles {
+        TableFiles {
+            live: DeltaTree::new_sized(delta_table),
+            removed: removed_tree(delta_table.get_tombstones().iter()),
+        }
+    }
+
+    /// the files of the active `adds` and the `removes` not yet vacuumed.
+    pub fn from_actions<'a>(
+        adds: impl Iterator<Item = &'a Add>,
+        removes: impl Iterator<Item = &'a Remove>,
+    ) -> TableFiles {
+        TableFiles {
+            live: DeltaTree::from_add_actions(adds),
+            removed: removed_tree(removes),
+        }
+    }
+}
+
+impl<S> TableFiles<S> {
+    /// the removed files with their tombstones, by path relative to the table root.
+    pub fn tombstones(&self) -> Vec<(String, &RemovedDeltaFile)> {
+        let mut tombstones = vec![];
+        collect_removed(String::new(), &self.removed.root, &mut tombstones);
+        tombstones
+    }
+
+    /// the paths of files removed at or after `timestamp`, in milliseconds since the epoch.
+    pub fn removed_since(&self, timestamp: i64) -> Vec<String> {
+        self.tombstones()
+            .into_iter()
+            .filter(|(_, removed)| removed.deletion_timestamp >= timestamp)
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /// the paths of live files written at or after `timestamp`. the log only knows the
+    /// modification time of a file, not when it was committed, so this is an approximation.
+    pub fn added_since(&self, timestamp: i64) -> Vec<String> {
+        stats::leaves(&self.live)
+            .into_iter()
+            .flat_map(|(dir, files)| {
+                files
+                    .iter()
+                    .filter(move |f| f.modification_time >= timestamp)
+                    .map(move |f| format!("{}{}", dir, f.file.name()))
+            })
+            .collect()
+    }
+}
+
+fn removed_tree<'a>(removes: impl Iterator<Item = &'a Remove>) -> DeltaTree<RemovedDeltaFile> {
+    let entries = removes.map(|remove| {
+        let size = remove.size.map(|size| size.max(0) as u64);
+        (
+            remove.path.as_str(),
+            (remove.deletion_timestamp.unwrap_or(0), size),
+        )
+    });
+    DeltaTree::from_entry_iter(entries, |file, (deletion_timestamp, size)| {
+        RemovedDeltaFile {
+            file,
+            deletion_timestamp,
+            size,
+        }
+    })
+}
+
+fn collect_removed<'a, S>(
+    dir: String,
+    node: &'a TreeNode<RemovedDeltaFile, S>,
+    tombstones: &mut Vec<(String, &'a RemovedDeltaFile)>,
+) {
+    match node {
+        TreeNode::FileEntries { files } => tombstones.extend(
+            files
+                .iter()
+                .map(|f| (format!("{}{}", dir, f.file.name()), f)),
+        ),
+        TreeNode::Partition { name, values } => {
+            for (value, child) in values {
+                let dir = format!("{}{}={}/", dir, name, super::partition_value(value));
+                collect_removed(dir, child, tombstones);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn live_and_removed_files() {
+        let adds = [
+            Add {
+                path: path("d=1/", 1),
+                size: 10,
+                modification_time: 100,
+                ..Default::default()
+            },
+            Add {
+                path: path("d=2/", 2),
+                size: 20,
+                modification_time: 300,
+                ..Default::default()
+            },
+        ];
+        let removes = [
+            Remove {
+                path: path("d=1/", 3),
+                deletion_timestamp: Some(200),
+                size: Some(30),
+                ..Default::default()
+            },
+            Remove {
+                path: path("d=3/", 4),
+                deletion_timestamp: None,
+                ..Default::default()
+            },
+        ];
+        let files = TableFiles::from_actions(adds.iter(), removes.iter());
+        assert_eq!(files.live.files().len(), 2);
+
+        let mut tombstones: Vec<_> = files
+            .tombstones()
+            .into_iter()
+            .map(|(path, removed)| (path, removed.deletion_timestamp, removed.size))
+            .collect();
+        tombstones.sort();
+        assert_eq!(
+            tombstones,
+            vec![(path("d=1/", 3), 200, Some(30)), (path("d=3/", 4), 0, None)]
+        );
+        assert_eq!(files.removed_since(150), vec![path("d=1/", 3)]);
+        assert_eq!(files.added_since(150), vec![path("d=2/", 2)]);
+    }
+}
@@ -0,0 +1,205 @@
+use super::tombstones::TableFiles;
+use deltalake::action::{Add, Remove};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// the files a vacuum would delete: those removed from the table before the retention
+/// cutoff and not added back since. files never referenced by the log are not known to the
+/// tree and not included.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct VacuumPlan {
+    /// tombstones deleted before this time, in milliseconds since the epoch, are expired.
+    pub cutoff: i64,
+    /// paths of the expired files, sorted.
+    pub files: Vec<String>,
+    /// bytes reclaimed, not counting tombstones without a size.
+    pub bytes: u64,
+    /// the partitions with expired files, sorted by path.
+    pub partitions: Vec<PartitionReclaim>,
+    /// partitions left without any files once the expired ones are deleted.
+    pub emptied: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PartitionReclaim {
+    /// the directory of the partition, e.g. `date=2021-03-01/`.
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl VacuumPlan {
+    /// plan the deletion of the `tombstones` deleted before `cutoff`, with `adds` being the
+    /// active files of the table. tombstones without a deletion time count as expired.
+    pub fn new<'a>(
+        tombstones: impl Iterator<Item = &'a Remove>,
+        adds: impl Iterator<Item = &'a Add>,
+        cutoff: i64,
+    ) -> VacuumPlan {
+        let tombstones = tombstones.map(|remove| {
+            let size = remove.size.unwrap_or(0).max(0) as u64;
+            (
+                remove.path.clone(),
+                remove.deletion_timestamp.unwrap_or(0),
+                size,
+            )
+        });
+        let live = adds.map(|add| add.path.clone()).collect();
+        VacuumPlan::build(tombstones, live, cutoff)
+    }
+
+    /// like `new`, for the tombstones and live files of `files`.
+    pub fn from_files<S>(files: &TableFiles<S>, cutoff: i64) -> VacuumPlan {
+        let tombstones = files
+            .tombstones()
+            .into_iter()
+            .map(|(path, removed)| (path, removed.deletion_timestamp, removed.size.unwrap_or(0)));
+        let live = files.live.files().into_iter().collect();
+        VacuumPlan::build(tombstones, live, cutoff)
+    }
+
+    /// plan from the path, deletion time and size of each tombstone, and the paths of the live
+    /// files. tombstones of live files, e.g. added back by a restore, are never deleted.
+    fn build(
+        tombstones: impl Iterator<Item = (String, i64, u64)>,
+        live: HashSet<String>,
+        cutoff: i64,
+    ) -> VacuumPlan {
+        let mut remaining: HashSet<String> =
+            live.iter().map(|path| dir(path).to_string()).collect();
+        let mut plan = VacuumPlan {
+            cutoff,
+            ..Default::default()
+        };
+        let mut partitions: BTreeMap<String, PartitionReclaim> = BTreeMap::new();
+        for (path, deletion_timestamp, bytes) in tombstones {
+            if live.contains(&path) {
+                continue;
+            }
+            let dir = dir(&path);
+            if deletion_timestamp >= cutoff {
+                remaining.insert(dir.to_string());
+                continue;
+            }
+            let partition = partitions
+                .entry(dir.to_string())
+                .or_insert_with(|| PartitionReclaim {
+                    path: dir.to_string(),
+                    files: 0,
+                    bytes: 0,
+                });
+            partition.files += 1;
+            partition.bytes += bytes;
+            plan.files.push(path);
+            plan.bytes += bytes;
+        }
+        plan.files.sort();
+        plan.emptied = partitions
+            .keys()
+            .filter(|dir| !remaining.contains(*dir))
+            .cloned()
+            .collect();
+        plan.partitions = partitions.into_values().collect();
+        plan
+    }
+}
+
+/// the directory of `path`, including the trailing `/`.
+fn dir(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(i) => &path[..=i],
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn plan_vacuum() {
+        let remove = |path: &str, deleted: i64, size: i64| Remove {
+            path: path.to_string(),
+            deletion_timestamp: Some(deleted),
+            size: Some(size),
+            ..Default::default()
+        };
+        let tombstones = [
+            remove("d=1/part-00000.parquet", 100, 10),
+            remove("d=1/part-00001.parquet", 100, 20),
+            remove("d=2/part-00000.parquet", 100, 30),
+            remove("d=3/part-00000.parquet", 100, 40),
+            remove("d=3/part-00001.parquet", 500, 50),
+        ];
+        let adds = [Add {
+            path: "d=1/part-00002.parquet".to_string(),
+            size: 30,
+            ..Default::default()
+        }];
+        let plan = VacuumPlan::new(tombstones.iter(), adds.iter(), 200);
+        assert_eq!(plan.files.len(), 4);
+        assert_eq!(plan.bytes, 100);
+        assert_eq!(
+            plan.partitions[0],
+            PartitionReclaim {
+                path: "d=1/".to_string(),
+                files: 2,
+                bytes: 30
+            }
+        );
+        assert_eq!(plan.emptied, vec!["d=2/"]);
+    }
+
+    #[test]
+    fn keep_files_added_back() {
+        let tombstones = [Remove {
+            path: "d=1/part-00000.parquet".to_string(),
+            deletion_timestamp: Some(100),
+            size: Some(10),
+            ..Default::default()
+        }];
+        let adds = [Add {
+            path: "d=1/part-00000.parquet".to_string(),
+            size: 10,
+            ..Default::default()
+        }];
+        let plan = VacuumPlan::new(tombstones.iter(), adds.iter(), 200);
+        assert_eq!(
+            plan,
+            VacuumPlan {
+                cutoff: 200,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn plan_vacuum_of_files() {
+        let tombstones = [
+            Remove {
+                path: path("d=1/", 1),
+                deletion_timestamp: Some(100),
+                size: Some(10),
+                ..Default::default()
+            },
+            Remove {
+                path: path("d=2/", 2),
+                deletion_timestamp: Some(100),
+                size: Some(20),
+                ..Default::default()
+            },
+        ];
+        let adds = [Add {
+            path: path("d=1/", 3),
+            size: 30,
+            ..Default::default()
+        }];
+        let files = TableFiles::from_actions(adds.iter(), tombstones.iter());
+        assert_eq!(
+            VacuumPlan::from_files(&files, 200),
+            VacuumPlan::new(tombstones.iter(), adds.iter(), 200)
+        );
+    }
+}
@@ -0,0 +1,576 @@
+use super::codec::{FileNameCodec, SparkFileNameCodec};
+use super::{
+    is_separator, key_value, partition_value, split_scheme, DeltaTree, ParquetDeltaFile, TreeNode,
+    NULL_PARTITION_VALUE,
+};
+use deltalake::DeltaTableError;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// how the partition directories of a path differ from the declared partition columns.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    /// a declared column without a directory.
+    Missing(String),
+    /// a directory of a column that isn't declared.
+    Extra(String),
+    /// all declared columns, in a different order.
+    Order,
+}
+
+/// the paths sharing a sequence of partition directories that doesn't match the table.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PartitionMismatch {
+    pub problem: Problem,
+    /// the columns of the directories, outermost first.
+    pub columns: Vec<String>,
+    /// one of the paths, and how many there are.
+    pub example: String,
+    pub files: usize,
+}
+
+impl fmt::Display for PartitionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.problem {
+            Problem::Missing(column) => write!(f, "no directory for column '{}'", column)?,
+            Problem::Extra(column) => write!(f, "undeclared partition column '{}'", column)?,
+            Problem::Order => write!(f, "partition columns in the order {:?}", self.columns)?,
+        }
+        write!(f, " in {} files, e.g. '{}'", self.files, self.example)
+    }
+}
+
+/// a file listed more than once, which points to a corrupted log or a bug in computing it.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Duplicate {
+    /// whether the paths are all the same, rather than different names of the same file in
+    /// the same directory, e.g. with and without the compression.
+    pub identical: bool,
+    /// the paths, in the order they were listed.
+    pub paths: Vec<String>,
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.identical {
+            write!(f, "'{}' listed {} times", self.paths[0], self.paths.len())
+        } else {
+            write!(f, "the same file listed as {:?}", self.paths)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ValidationError {
+    Delta(DeltaTableError),
+    Partitions(Vec<PartitionMismatch>),
+    Duplicates(Vec<Duplicate>),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::Delta(err) => write!(f, "{}", err),
+            ValidationError::Partitions(mismatches) => {
+                write!(f, "files don't match the partition columns of the table")?;
+                for mismatch in mismatches {
+                    write!(f, "\n  {}", mismatch)?;
+                }
+                Ok(())
+            }
+            ValidationError::Duplicates(duplicates) => {
+                write!(f, "files listed more than once")?;
+                for duplicate in duplicates {
+                    write!(f, "\n  {}", duplicate)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl From<DeltaTableError> for ValidationError {
+    fn from(err: DeltaTableError) -> ValidationError {
+        ValidationError::Delta(err)
+    }
+}
+
+/// compare the partition directories of `paths` to the declared `columns`, grouping the
+/// mismatches by their sequence of directories. empty if all paths match.
+pub fn validate_paths<'a>(
+    paths: impl Iterator<Item = &'a str>,
+    columns: &[String],
+) -> Vec<PartitionMismatch> {
+    let mut mismatches: BTreeMap<Vec<&str>, PartitionMismatch> = BTreeMap::new();
+    for path in paths {
+        let mut segments: Vec<&str> = split_scheme(path).1.split(is_separator).collect();
+        segments.pop();
+        let keys = segments
+            .iter()
+            .filter_map(|segment| key_value(segment).map(|p| p.key))
+            .collect();
+        add_keys(&mut mismatches, keys, columns, || path.to_string(), 1);
+    }
+    mismatches.into_values().collect()
+}
+
+/// the files listed more than once in `paths`, in the order of their first path. files are
+/// the same if they have the same path, or the same directory and the same kind, number,
+/// uuid and cluster in their name. spark uses the same uuid for all files of a write, so
+/// files in different directories or with other numbers are distinct.
+pub fn find_duplicates<'a>(paths: impl Iterator<Item = &'a str>) -> Vec<Duplicate> {
+    let mut groups: Vec<Vec<&str>> = vec![];
+    let mut index = HashMap::new();
+    for path in paths {
+        let (dir, name) = path.split_at(path.rfind(is_separator).map_or(0, |i| i + 1));
+        let key = match SparkFileNameCodec.decode(name) {
+            Some(file) => (
+                dir,
+                Some((file.kind(), file.partition(), file.uuid(), file.cluster())),
+                "",
+            ),
+            None => (dir, None, name),
+        };
+        let i = *index.entry(key).or_insert_with(|| {
+            groups.push(vec![]);
+            groups.len() - 1
+        });
+        groups[i].push(path);
+    }
+    groups
+        .into_iter()
+        .filter(|paths| paths.len() > 1)
+        .map(|paths| Duplicate {
+            identical: paths.iter().all(|p| *p == paths[0]),
+            paths: paths.into_iter().map(str::to_string).collect(),
+        })
+        .collect()
+}
+
+/// compare the partition levels of `tree` to the declared `columns`, like `validate_paths`.
+pub fn validate_tree<F: AsRef<ParquetDeltaFile>, S>(
+    tree: &DeltaTree<F, S>,
+    columns: &[String],
+) -> Vec<PartitionMismatch> {
+    let mut mismatches = BTreeMap::new();
+    walk(
+        &tree.root,
+        &mut vec![],
+        &mut String::new(),
+        &mut mismatches,
+        columns,
+    );
+    mismatches.into_values().collect()
+}
+
+fn walk<'a, F: AsRef<ParquetDeltaFile>, S>(
+    node: &'a TreeNode<F, S>,
+    keys: &mut Vec<&'a str>,
+    dir: &mut String,
+    mismatches: &mut BTreeMap<Vec<&'a str>, PartitionMismatch>,
+    columns: &[String],
+) {
+    match node {
+        TreeNode::FileEntries { files } => {
+            if let Some(first) = files.first() {
+                let example = || format!("{}{}", dir, first.as_ref().name());
+                add_keys(mismatches, keys.clone(), columns, example, files.len());
+            }
+        }
+        TreeNode::Partition { name, values } => {
+            keys.push(name);
+            for (value, child) in values {
+                let len = dir.len();
+                dir.push_str(&format!("{}={}/", name, partition_value(value)));
+                walk(child, keys, dir, mismatches, columns);
+                dir.truncate(len);
+            }
+            keys.pop();
+        }
+    }
+}
+
+fn add_keys<'a>(
+    mismatches: &mut BTreeMap<Vec<&'a str>, PartitionMismatch>,
+    keys: Vec<&'a str>,
+    columns: &[String],
+    example: impl FnOnce() -> String,
+    files: usize,
+) {
+    if keys.iter().eq(columns.iter()) {
+        return;
+    }
+    mismatches
+        .entry(keys)
+        .and_modify(|mismatch| mismatch.files += files)
+        .or_insert_with_key(|keys| PartitionMismatch {
+            problem: problem(keys, columns),
+            columns: keys.iter().map(|key| key.to_string()).collect(),
+            example: example(),
+            files,
+        });
+}
+
+fn problem(keys: &[&str], columns: &[String]) -> Problem {
+    if let Some(column) = columns.iter().find(|c| !keys.contains(&c.as_str())) {
+        return Problem::Missing(column.clone());
+    }
+    match keys.iter().find(|key| !columns.iter().any(|c| c == *key)) {
+        Some(key) => Problem::Extra(key.to_string()),
+        // the same columns, possibly some of them repeated.
+        None if keys.len() == columns.len() => Problem::Order,
+        None => Problem::Extra(
+            keys.iter()
+                .enumerate()
+                .find(|(i, key)| keys[..*i].contains(key))
+                .map_or_else(String::new, |(_, key)| key.to_string()),
+        ),
+    }
+}
+
+impl DeltaTree {
+    /// like `new`, first checking that the partition directories of all files match the
+    /// partition columns of the table, instead of building a tree of other levels or
+    /// panicking on paths of different depths, and that no file is listed twice.
+    pub fn new_validated(
+        delta_table: &deltalake::DeltaTable,
+    ) -> Result<DeltaTree, ValidationError> {
+        let columns = &delta_table.get_metadata()?.partition_columns;
+        let files = delta_table.get_files();
+        let mismatches = validate_paths(files.iter().map(String::as_str), columns);
+        if !mismatches.is_empty() {
+            return Err(ValidationError::Partitions(mismatches));
+        }
+        let duplicates = find_duplicates(files.iter().map(String::as_str));
+        if !duplicates.is_empty() {
+            return Err(ValidationError::Duplicates(duplicates));
+        }
+        Ok(DeltaTree::new(delta_table))
+    }
+}
+
+/// a broken invariant of a tree, found by `DeltaTree::validate`. `dir` is the directory of
+/// the node, e.g. `a=1/b=2/`, empty for the root.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[serde(tag = "invariant", rename_all = "snake_case")]
+pub enum Violation {
+    /// a node of another column than the first node at the same depth, `None` for a leaf.
+    LevelKey {
+        dir: String,
+        expected: Option<String>,
+        found: Option<String>,
+    },
+    /// a partition without values, or a leaf without files below the root.
+    Empty { dir: String },
+    /// the null value and the string `__HIVE_DEFAULT_PARTITION__`, both written as the same
+    /// directory.
+    DuplicateChild { dir: String, child: String },
+    /// the files of a leaf aren't sorted.
+    Unsorted { dir: String },
+    /// a file that appears more than once in a leaf.
+    DuplicateFile { dir: String, file: String },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = |column: &Option<String>| match column {
+            Some(column) => format!("partition '{}'", column),
+            None => "files".to_string(),
+        };
+        match self {
+            Violation::LevelKey {
+                dir,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} in '{}', expected {}",
+                level(found),
+                dir,
+                level(expected)
+            ),
+            Violation::Empty { dir } => write!(f, "empty node '{}'", dir),
+            Violation::DuplicateChild { dir, child } => {
+                write!(f, "'{}' in '{}' more than once", child, dir)
+            }
+            Violation::Unsorted { dir } => write!(f, "unsorted files in '{}'", dir),
+            Violation::DuplicateFile { dir, file } => {
+                write!(f, "'{}' in '{}' more than once", file, dir)
+            }
+        }
+    }
+}
+
+/// the result of `DeltaTree::validate`.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct ValidationReport {
+    /// the number of nodes and files checked.
+    pub nodes: usize,
+    pub files: usize,
+    /// the broken invariants, parents before their children.
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl<F: AsRef<ParquetDeltaFile>, S> DeltaTree<F, S> {
+    /// check the invariants of the tree that `build` establishes: all nodes at a depth are
+    /// of the same column, or all are leaves, no node is empty, no two values are written as
+    /// the same directory and the files of each leaf are sorted and distinct. e.g. after
+    /// modifying or deserializing a tree.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport {
+            nodes: 0,
+            files: 0,
+            violations: vec![],
+        };
+        check(&self.root, &mut vec![], &mut String::new(), &mut report);
+        report
+    }
+}
+
+/// check `node` at `dir`, taking the column expected at each depth from the first node
+/// visited there.
+fn check<'a, F: AsRef<ParquetDeltaFile>, S>(
+    node: &'a TreeNode<F, S>,
+    levels: &mut Vec<Option<&'a str>>,
+    dir: &mut String,
+    report: &mut ValidationReport,
+) {
+    report.nodes += 1;
+    let depth = dir.matches('/').count();
+    let column = match node {
+        TreeNode::Partition { name, .. } => Some(name.as_str()),
+        TreeNode::FileEntries { .. } => None,
+    };
+    match levels.get(depth) {
+        Some(expected) if *expected != column => report.violations.push(Violation::LevelKey {
+            dir: dir.clone(),
+            expected: expected.map(str::to_string),
+            found: column.map(str::to_string),
+        }),
+        Some(_) => {}
+        None => levels.push(column),
+    }
+    match node {
+        TreeNode::FileEntries { files } => {
+            report.files += files.len();
+            if files.is_empty() && depth > 0 {
+                report
+                    .violations
+                    .push(Violation::Empty { dir: dir.clone() });
+            }
+            if files.windows(2).any(|w| w[0].as_ref() > w[1].as_ref()) {
+                report
+                    .violations
+                    .push(Violation::Unsorted { dir: dir.clone() });
+            }
+            let mut sorted: Vec<&ParquetDeltaFile> = files.iter().map(AsRef::as_ref).collect();
+            sorted.sort();
+            sorted.dedup_by(|a, b| {
+                if a == b {
+                    report.violations.push(Violation::DuplicateFile {
+                        dir: dir.clone(),
+                        file: a.name(),
+                    });
+                }
+                a == b
+            });
+        }
+        TreeNode::Partition { name, values } => {
+            if values.is_empty() {
+                report
+                    .violations
+                    .push(Violation::Empty { dir: dir.clone() });
+            }
+            if values
+                .keys()
+                .filter(|key| partition_value(key) == NULL_PARTITION_VALUE)
+                .count()
+                > 1
+            {
+                report.violations.push(Violation::DuplicateChild {
+                    dir: dir.clone(),
+                    child: format!("{}={}", name, NULL_PARTITION_VALUE),
+                });
+            }
+            let mut children: Vec<_> = values.iter().collect();
+            children.sort_by(|a, b| a.0.cmp(b.0));
+            for (value, child) in children {
+                let len = dir.len();
+                dir.push_str(&format!("{}={}/", name, partition_value(value)));
+                check(child, levels, dir, report);
+                dir.truncate(len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures::path;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn mismatching_partition_columns() {
+        let columns = vec!["a".to_string(), "b".to_string()];
+        let paths = [
+            "a=1/b=1/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+            "s3://bucket/table/a=1/b=2/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+            "b=1/a=1/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+            "b=2/a=1/part-00001-00000000-0000-0000-0000-000000000002.c000.snappy.parquet",
+            "a=1/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+            "a=1/b=1/c=1/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+            "a=1/b=1/a=2/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+        ];
+        let mismatches = validate_paths(paths.iter().copied(), &columns);
+        let problems: Vec<_> = mismatches
+            .iter()
+            .map(|m| (m.problem.clone(), m.files))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                (Problem::Missing("b".to_string()), 1),
+                (Problem::Extra("a".to_string()), 1),
+                (Problem::Extra("c".to_string()), 1),
+                (Problem::Order, 2),
+            ]
+        );
+        assert_eq!(
+            mismatches[3].example,
+            "b=1/a=1/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet"
+        );
+        assert_eq!(mismatches[3].columns, vec!["b", "a"]);
+
+        let tree = DeltaTree::from_paths(&vec![
+            "b=1/a=1/part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet"
+                .to_string(),
+            "b=2/a=1/part-00001-00000000-0000-0000-0000-000000000002.c000.snappy.parquet"
+                .to_string(),
+        ]);
+        assert_eq!(validate_tree(&tree, &columns), mismatches[3..].to_vec());
+        assert_eq!(
+            validate_tree(&tree, &["b".to_string(), "a".to_string()]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn duplicate_files() {
+        let file = |dir: &str, name: &str| format!("{}{}", dir, name);
+        let name = "part-00000-00000000-0000-0000-0000-000000000001.c000.snappy.parquet";
+        let paths = [
+            file("a=1/", name),
+            file("a=2/", name),
+            file(
+                "a=1/",
+                "part-00001-00000000-0000-0000-0000-000000000001.c000.snappy.parquet",
+            ),
+            file(
+                "a=1/",
+                "part-00000-00000000-0000-0000-0000-000000000001-c000.snappy.parquet",
+            ),
+            file("a=1/", "_delta_index.json"),
+            file("a=1/", name),
+            file("a=1/", "_delta_index.json"),
+        ];
+        assert_eq!(
+            find_duplicates(paths.iter().map(String::as_str)),
+            vec![
+                Duplicate {
+                    identical: false,
+                    paths: vec![paths[0].clone(), paths[3].clone(), paths[5].clone()],
+                },
+                Duplicate {
+                    identical: true,
+                    paths: vec![paths[4].clone(), paths[6].clone()],
+                },
+            ]
+        );
+        assert_eq!(
+            find_duplicates(paths[..3].iter().map(String::as_str)),
+            vec![]
+        );
+        assert_eq!(
+            find_duplicates(paths[4..].iter().map(String::as_str))[0].to_string(),
+            "'a=1/_delta_index.json' listed 2 times"
+        );
+    }
+
+    #[test]
+    fn tree_invariants() {
+        let name = |id: u128| path("", id);
+        let mut tree = DeltaTree::from_paths(vec![
+            format!("a=1/b=1/{}", name(2)),
+            format!("a=1/b=1/{}", name(1)),
+            format!("a=2/b=__HIVE_DEFAULT_PARTITION__/{}", name(1)),
+        ]);
+        let report = tree.validate();
+        assert!(report.is_valid());
+        assert_eq!((report.nodes, report.files), (5, 3));
+
+        let file = |id| ParquetDeltaFile::from_string(&name(id));
+        if let TreeNode::Partition { values, .. } = &mut tree.root {
+            if let Some(TreeNode::Partition { values, .. }) = values.get_mut(&Some("1".into())) {
+                values.insert(
+                    Some("2".into()),
+                    TreeNode::FileEntries {
+                        files: vec![file(2), file(1), file(2)].into_iter().collect(),
+                    },
+                );
+            }
+            if let Some(TreeNode::Partition { values, .. }) = values.get_mut(&Some("2".into())) {
+                values.insert(
+                    Some(NULL_PARTITION_VALUE.into()),
+                    TreeNode::FileEntries {
+                        files: Default::default(),
+                    },
+                );
+            }
+            values.insert(
+                Some("3".into()),
+                TreeNode::Partition {
+                    name: "c".to_string(),
+                    values: Default::default(),
+                },
+            );
+        }
+        assert_eq!(
+            tree.validate().violations,
+            vec![
+                Violation::Unsorted {
+                    dir: "a=1/b=2/".to_string()
+                },
+                Violation::DuplicateFile {
+                    dir: "a=1/b=2/".to_string(),
+                    file: name(2)
+                },
+                Violation::DuplicateChild {
+                    dir: "a=2/".to_string(),
+                    child: "b=__HIVE_DEFAULT_PARTITION__".to_string()
+                },
+                Violation::Empty {
+                    dir: "a=2/b=__HIVE_DEFAULT_PARTITION__/".to_string()
+                },
+                Violation::LevelKey {
+                    dir: "a=3/".to_string(),
+                    expected: Some("b".to_string()),
+                    found: Some("c".to_string())
+                },
+                Violation::Empty {
+                    dir: "a=3/".to_string()
+                },
+            ]
+        );
+    }
+}
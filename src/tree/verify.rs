@@ -0,0 +1,342 @@
+use super::consistency::References;
+use super::kind::KindCounts;
+use super::storage::StorageOptions;
+use super::FileKind;
+use deltalake::storage::{ObjectMeta, StorageBackend, StorageError};
+use futures::{StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// the files of a table that are missing in storage, e.g. after a migration.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct VerifyReport {
+    /// the number of files checked.
+    pub files: usize,
+    pub missing: Vec<MissingFiles>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct MissingFiles {
+    /// the directory of the partition, e.g. `date=2021-03-01/`.
+    pub partition: String,
+    /// the names of the missing files within the partition.
+    pub files: Vec<String>,
+}
+
+impl VerifyReport {
+    /// group the `missing` of `files` paths, relative to the table root, by partition.
+    pub fn new(files: usize, missing: Vec<String>) -> VerifyReport {
+        let mut partitions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for path in missing {
+            let (partition, file) = match path.rfind('/') {
+                Some(i) => (path[..=i].to_string(), path[i + 1..].to_string()),
+                None => (String::new(), path),
+            };
+            partitions.entry(partition).or_default().push(file);
+        }
+        let missing = partitions
+            .into_iter()
+            .map(|(partition, mut files)| {
+                files.sort();
+                MissingFiles { partition, files }
+            })
+            .collect();
+        VerifyReport { files, missing }
+    }
+
+    pub fn missing_files(&self) -> usize {
+        self.missing.iter().map(|m| m.files.len()).sum()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// the files of a table compared to a listing of its storage location.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct ConsistencyReport {
+    /// the active files of the table, and the data files found in storage.
+    pub files: usize,
+    pub listed: usize,
+    /// the files found in storage by kind, including change data and deletion vectors.
+    pub kinds: KindCounts,
+    /// active files not found in storage.
+    pub missing: Vec<MissingFiles>,
+    /// data files in storage that are neither active nor removed by the log, sorted.
+    pub orphans: Vec<String>,
+    /// files removed by the log that are still in storage, waiting for vacuum, sorted.
+    pub unvacuumed: Vec<String>,
+    /// active files whose size in storage differs from the size in the log, sorted by path.
+    pub size_mismatches: Vec<SizeMismatch>,
+    /// change data files and deletion vectors in storage that the log doesn't refer to, sorted.
+    /// only checked against the references of the log, see `consistency::log_references`.
+    pub unreferenced_change_data: Vec<String>,
+    pub unreferenced_deletion_vectors: Vec<String>,
+    /// change data files and deletion vectors whose references may have been removed by log
+    /// cleanup, so they can't be told apart from unreferenced ones, sorted.
+    pub unknown_references: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct SizeMismatch {
+    pub path: String,
+    /// the size recorded in the log.
+    pub expected: u64,
+    /// the size of the object in storage.
+    pub actual: u64,
+}
+
+impl ConsistencyReport {
+    /// compare the `active` files of a table with their sizes and the `removed` ones, not yet
+    /// vacuumed, to the objects `listed` in storage, all relative to the table root. listed
+    /// objects without a size are only checked for existence. files in hidden directories
+    /// like `_delta_log/` or hidden themselves aren't data files and are ignored. change data
+    /// files and deletion vectors are only counted unless the `references` of the log are given.
+    pub fn new(
+        active: &HashMap<String, u64>,
+        removed: &HashSet<String>,
+        listed: impl Iterator<Item = (String, Option<u64>)>,
+        references: Option<&References>,
+    ) -> ConsistencyReport {
+        let mut report = ConsistencyReport {
+            files: active.len(),
+            ..Default::default()
+        };
+        let mut found = HashSet::new();
+        for (path, size) in listed {
+            let kind = FileKind::of(&path);
+            if kind != FileKind::Data {
+                report.kinds.add(kind);
+                let references = match references {
+                    Some(references) => references,
+                    None => continue,
+                };
+                match (references.referenced(&path), kind) {
+                    (Some(true), _) => {}
+                    (Some(false), FileKind::ChangeData) => {
+                        report.unreferenced_change_data.push(path)
+                    }
+                    (Some(false), _) => report.unreferenced_deletion_vectors.push(path),
+                    (None, _) => report.unknown_references.push(path),
+                }
+                continue;
+            }
+            if !is_data_file(&path) {
+                continue;
+            }
+            report.kinds.add(kind);
+            report.listed += 1;
+            match (active.get(&path), size) {
+                (Some(&expected), Some(actual)) if expected != actual => {
+                    report.size_mismatches.push(SizeMismatch {
+                        path: path.clone(),
+                        expected,
+                        actual,
+                    })
+                }
+                (Some(_), _) => {}
+                (None, _) if removed.contains(&path) => report.unvacuumed.push(path.clone()),
+                (None, _) => report.orphans.push(path.clone()),
+            }
+            found.insert(path);
+        }
+        let missing = active
+            .keys()
+            .filter(|path| !found.contains(*path))
+            .cloned()
+            .collect();
+        report.missing = VerifyReport::new(0, missing).missing;
+        report.orphans.sort();
+        report.unvacuumed.sort();
+        report.size_mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+        report.unreferenced_change_data.sort();
+        report.unreferenced_deletion_vectors.sort();
+        report.unknown_references.sort();
+        report
+    }
+
+    /// whether nothing is missing, orphaned, of the wrong size or unreferenced. files with
+    /// unknown references don't count.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+            && self.orphans.is_empty()
+            && self.size_mismatches.is_empty()
+            && self.unreferenced_change_data.is_empty()
+            && self.unreferenced_deletion_vectors.is_empty()
+    }
+}
+
+/// list the objects below `table_uri` and compare them to the `active` and `removed` files of
+/// the table and the `references` of its log, see `ConsistencyReport::new`. relies on the
+/// listing being recursive, as it is for object stores.
+pub async fn check_consistency(
+    table_uri: &str,
+    active: &HashMap<String, u64>,
+    removed: &HashSet<String>,
+    references: Option<&References>,
+    options: &StorageOptions,
+) -> Result<ConsistencyReport, StorageError> {
+    let backend = options.backend(table_uri)?;
+    let root = format!("{}/", table_uri.trim_end_matches('/'));
+    let objects = options
+        .retry(|| list_objects(backend.as_ref(), table_uri))
+        .await?;
+    let listed = objects.into_iter().filter_map(|object| {
+        let path = object.path.strip_prefix(&root)?.to_string();
+        Some((path, object.size.map(|size| size.max(0) as u64)))
+    });
+    Ok(ConsistencyReport::new(active, removed, listed, references))
+}
+
+/// all objects below `uri`, failing on the first error of the listing.
+pub(super) async fn list_objects(
+    backend: &dyn StorageBackend,
+    uri: &str,
+) -> Result<Vec<ObjectMeta>, StorageError> {
+    backend
+        .list_objs(uri)
+        .await?
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
+/// whether `path` is neither hidden nor in a hidden directory. partition directories are
+/// never hidden, even if their column starts with `_`.
+pub(super) fn is_data_file(path: &str) -> bool {
+    !path.split('/').any(|segment| {
+        (segment.starts_with('_') || segment.starts_with('.')) && !segment.contains('=')
+    })
+}
+
+/// probe storage for each of the `paths` of the table at `table_uri`, with at most
+/// `concurrency` requests in flight. errors other than a missing file abort the check.
+pub async fn verify(
+    table_uri: &str,
+    paths: impl Iterator<Item = String>,
+    concurrency: usize,
+    options: &StorageOptions,
+) -> Result<VerifyReport, StorageError> {
+    assert!(concurrency > 0, "concurrency must be positive");
+    let backend = options.backend(table_uri)?;
+    let backend = &backend;
+    let results: Vec<Option<String>> = futures::stream::iter(paths)
+        .map(|path| async move {
+            let uri = backend.join_path(table_uri, &path);
+            match options.retry(|| backend.head_obj(&uri)).await {
+                Ok(_) => Ok(None),
+                Err(StorageError::NotFound) => Ok(Some(path)),
+                Err(err) => Err(err),
+            }
+        })
+        .buffer_unordered(concurrency)
+        .try_collect()
+        .await?;
+    let files = results.len();
+    let missing = results.into_iter().flatten().collect();
+    Ok(VerifyReport::new(files, missing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn missing_files_by_partition() {
+        let missing = [
+            "d=2/h=0/part-00001.parquet",
+            "d=1/part-00003.parquet",
+            "d=2/h=0/part-00000.parquet",
+            "part-00007.parquet",
+        ];
+        let report = VerifyReport::new(10, missing.iter().map(|p| p.to_string()).collect());
+        assert_eq!(
+            report.missing,
+            vec![
+                MissingFiles {
+                    partition: "".to_string(),
+                    files: vec!["part-00007.parquet".to_string()]
+                },
+                MissingFiles {
+                    partition: "d=1/".to_string(),
+                    files: vec!["part-00003.parquet".to_string()]
+                },
+                MissingFiles {
+                    partition: "d=2/h=0/".to_string(),
+                    files: vec![
+                        "part-00000.parquet".to_string(),
+                        "part-00001.parquet".to_string()
+                    ]
+                },
+            ]
+        );
+        assert_eq!(report.missing_files(), 4);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn consistency_with_listing() {
+        let active: HashMap<String, u64> = [
+            ("d=1/part-00000.parquet", 10),
+            ("d=1/part-00001.parquet", 20),
+            ("d=2/part-00000.parquet", 30),
+        ]
+        .iter()
+        .map(|(path, size)| (path.to_string(), *size))
+        .collect();
+        let removed: HashSet<String> =
+            std::iter::once("d=1/part-00002.parquet".to_string()).collect();
+        let listed = [
+            ("_delta_log/00000000000000000000.json", Some(100)),
+            ("d=1/part-00000.parquet", Some(10)),
+            ("d=1/part-00001.parquet", Some(25)),
+            ("d=1/part-00002.parquet", Some(5)),
+            ("d=1/part-00003.parquet", None),
+            ("d=1/.part-00003.parquet.crc", Some(1)),
+            ("d=1/_SUCCESS", Some(0)),
+            ("_change_data/d=1/cdc-00000.parquet", Some(3)),
+            (
+                "deletion_vector_9f2b4e10-7d2a-4c8e-8f5b-2a1c3d4e5f60.bin",
+                Some(2),
+            ),
+        ];
+        let report = ConsistencyReport::new(
+            &active,
+            &removed,
+            listed.iter().map(|(path, size)| (path.to_string(), *size)),
+            None,
+        );
+        assert_eq!(report.files, 3);
+        assert_eq!(report.listed, 4);
+        assert_eq!(
+            report.kinds,
+            KindCounts {
+                data: 4,
+                change_data: 1,
+                deletion_vectors: 1
+            }
+        );
+        assert_eq!(report.orphans, vec!["d=1/part-00003.parquet"]);
+        assert_eq!(report.unvacuumed, vec!["d=1/part-00002.parquet"]);
+        assert!(report.unreferenced_change_data.is_empty());
+        assert_eq!(
+            report.size_mismatches,
+            vec![SizeMismatch {
+                path: "d=1/part-00001.parquet".to_string(),
+                expected: 20,
+                actual: 25
+            }]
+        );
+        assert_eq!(
+            report.missing,
+            vec![MissingFiles {
+                partition: "d=2/".to_string(),
+                files: vec!["part-00000.parquet".to_string()]
+            }]
+        );
+        assert!(!report.is_ok());
+    }
+}
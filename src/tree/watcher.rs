@@ -0,0 +1,369 @@
+use super::changes::{Backpressure, ChangeQueue, TreeChange, TreeChanges};
+use super::snapshot::{newer_commits, reload, CatchUp, Restored};
+use super::storage::StorageOptions;
+use deltalake::DeltaTableError;
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// the outcome of the refreshes of a `TableWatcher` so far, e.g. for a health check.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct RefreshHealth {
+    /// milliseconds since the epoch of the last successful refresh.
+    pub last_refresh: Option<i64>,
+    /// the number of refreshes failed in a row since.
+    pub failures: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    restored: RwLock<Restored>,
+    health: Mutex<RefreshHealth>,
+    subscribers: Mutex<Vec<Arc<ChangeQueue>>>,
+}
+
+/// keeps the tree of a table up to date in the background, replaying new commits onto it like
+/// `snapshot::restore`. refreshes are spread by a random jitter so many watchers don't hit
+/// the store at once, failures back off exponentially. the task stops when the watcher is
+/// dropped.
+#[derive(Debug)]
+pub struct TableWatcher {
+    table_uri: String,
+    options: StorageOptions,
+    shared: Arc<Shared>,
+    jitter: f64,
+    max_backoff: Duration,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl TableWatcher {
+    /// a watcher of the table at `table_uri` starting from `restored`, e.g. the result of
+    /// `snapshot::restore`. nothing happens before `spawn`.
+    pub fn new(table_uri: &str, restored: Restored, options: StorageOptions) -> TableWatcher {
+        TableWatcher {
+            table_uri: table_uri.to_string(),
+            options,
+            shared: Arc::new(Shared {
+                restored: RwLock::new(restored),
+                health: Mutex::new(RefreshHealth {
+                    last_refresh: Some(now_millis()),
+                    ..RefreshHealth::default()
+                }),
+                subscribers: Mutex::new(vec![]),
+            }),
+            jitter: 0.1,
+            max_backoff: Duration::from_secs(600),
+            task: None,
+        }
+    }
+
+    /// spread each delay by up to this fraction of it in either direction, 0.1 by default.
+    pub fn jitter(mut self, jitter: f64) -> TableWatcher {
+        assert!(
+            (0.0..=1.0).contains(&jitter),
+            "jitter must be within 0 and 1"
+        );
+        self.jitter = jitter;
+        self
+    }
+
+    /// the longest delay after failed refreshes, 10 minutes by default.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> TableWatcher {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// start refreshing the tree every `interval` on the tokio runtime.
+    pub fn spawn(mut self, interval: Duration) -> TableWatcher {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        let table_uri = self.table_uri.clone();
+        let options = self.options.clone();
+        let shared = self.shared.clone();
+        let (jitter, max_backoff) = (self.jitter, self.max_backoff);
+        self.task = Some(tokio::spawn(async move {
+            let mut failures = 0;
+            loop {
+                let delay = next_delay(interval, failures, max_backoff, jitter, random());
+                tokio::time::sleep(delay).await;
+                let result = refresh(&table_uri, &options, &shared).await;
+                let changes = {
+                    let mut health = shared.health.lock().unwrap();
+                    let changes = match result {
+                        Ok(changes) => {
+                            health.last_refresh = Some(now_millis());
+                            health.failures = 0;
+                            health.last_error = None;
+                            changes
+                        }
+                        Err(err) => {
+                            health.failures += 1;
+                            health.last_error = Some(err.to_string());
+                            vec![]
+                        }
+                    };
+                    failures = health.failures;
+                    changes
+                };
+                publish(&shared, changes).await;
+            }
+        }));
+        self
+    }
+
+    /// the tree as of the latest refresh, along with its version. refreshes wait while the
+    /// guard is held, don't keep it across awaits.
+    pub fn read(&self) -> RwLockReadGuard<'_, Restored> {
+        self.shared.restored.read().unwrap()
+    }
+
+    /// the changes of the tree from now on, buffering up to `capacity` of them for a slow
+    /// consumer and applying `backpressure` beyond that.
+    pub fn changes(&self, capacity: usize, backpressure: Backpressure) -> TreeChanges {
+        let queue = ChangeQueue::new(capacity, backpressure);
+        self.shared.subscribers.lock().unwrap().push(queue.clone());
+        TreeChanges::new(queue)
+    }
+
+    pub fn health(&self) -> RefreshHealth {
+        self.shared.health.lock().unwrap().clone()
+    }
+
+    /// whether the last successful refresh, or the start, is at most `max_age` ago.
+    pub fn is_healthy(&self, max_age: Duration) -> bool {
+        match self.health().last_refresh {
+            Some(last_refresh) => now_millis() - last_refresh <= max_age.as_millis() as i64,
+            None => false,
+        }
+    }
+}
+
+impl Drop for TableWatcher {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        for queue in self.shared.subscribers.lock().unwrap().drain(..) {
+            queue.close();
+        }
+    }
+}
+
+/// hand `changes` to each consumer in turn, forgetting those that are gone.
+async fn publish(shared: &Shared, changes: Vec<TreeChange>) {
+    if changes.is_empty() {
+        return;
+    }
+    let subscribers = shared.subscribers.lock().unwrap().clone();
+    for queue in subscribers {
+        for change in &changes {
+            if !queue.push(change.clone()).await {
+                break;
+            }
+        }
+    }
+    shared
+        .subscribers
+        .lock()
+        .unwrap()
+        .retain(|queue| !queue.is_closed());
+}
+
+/// bring the shared tree up to date and return its changes. the commits are read before the
+/// tree is locked, so readers only wait while they're applied. a commit failing half way
+/// through leaves the tree unusable, the table is loaded from scratch instead.
+async fn refresh(
+    table_uri: &str,
+    options: &StorageOptions,
+    shared: &Shared,
+) -> Result<Vec<TreeChange>, DeltaTableError> {
+    let version = shared.restored.read().unwrap().version;
+    let backend = options.backend(table_uri)?;
+    let commits = match options
+        .retry(|| newer_commits(backend.as_ref(), table_uri, version))
+        .await?
+    {
+        Some(commits) => commits,
+        None => return reload_shared(table_uri, options, shared).await,
+    };
+    let changes = commits
+        .iter()
+        .zip(version + 1..)
+        .map(|(commit, version)| TreeChange::of_commit(version, commit))
+        .collect::<Result<Vec<_>, _>>();
+    let changes = match changes {
+        Ok(changes) => changes,
+        Err(_) => return reload_shared(table_uri, options, shared).await,
+    };
+    if changes.is_empty() {
+        return Ok(changes);
+    }
+    let replayed = {
+        let mut restored = shared.restored.write().unwrap();
+        let replayed = commits
+            .iter()
+            .try_for_each(|commit| restored.tree.apply_commit(commit));
+        if replayed.is_ok() {
+            restored.version += commits.len() as i64;
+            restored.catch_up = CatchUp::Replayed {
+                commits: commits.len(),
+            };
+        }
+        replayed
+    };
+    match replayed {
+        Ok(()) => Ok(changes),
+        Err(_) => reload_shared(table_uri, options, shared).await,
+    }
+}
+
+async fn reload_shared(
+    table_uri: &str,
+    options: &StorageOptions,
+    shared: &Shared,
+) -> Result<Vec<TreeChange>, DeltaTableError> {
+    let restored = reload(table_uri, options).await?;
+    let version = restored.version;
+    *shared.restored.write().unwrap() = restored;
+    Ok(vec![TreeChange::Reloaded { version }])
+}
+
+/// the delay before the next refresh: `interval`, doubled for each of the `failures` in a row
+/// up to `max_backoff`, then spread by up to `jitter` of it in either direction, `random` being
+/// uniform in `[0, 1)`.
+pub(super) fn next_delay(
+    interval: Duration,
+    failures: u32,
+    max_backoff: Duration,
+    jitter: f64,
+    random: f64,
+) -> Duration {
+    let delay = match failures {
+        0 => interval,
+        failures => interval
+            .saturating_mul(1 << failures.min(31))
+            .min(max_backoff.max(interval)),
+    };
+    delay.mul_f64(1.0 + jitter * (2.0 * random - 1.0))
+}
+
+/// a random number in `[0, 1)`, from the randomly keyed hasher of the standard library.
+pub(super) fn random() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+    use crate::tree::DeltaTree;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn delays_with_backoff_and_jitter() {
+        let second = Duration::from_secs(1);
+        let minute = Duration::from_secs(60);
+        assert_eq!(next_delay(second, 0, minute, 0.0, 0.7), second);
+        assert_eq!(next_delay(second, 3, minute, 0.0, 0.7), second * 8);
+        assert_eq!(next_delay(second, 40, minute, 0.0, 0.7), minute);
+        assert_eq!(next_delay(second * 90, 2, minute, 0.0, 0.7), second * 90);
+        assert_eq!(
+            next_delay(second, 0, minute, 0.5, 0.0),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            next_delay(second, 1, minute, 0.5, 0.75),
+            Duration::from_millis(2500)
+        );
+        for _ in 0..100 {
+            assert!((0.0..1.0).contains(&random()));
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_in_background() {
+        use futures::StreamExt;
+
+        let path = |id: u128| fixtures::path(&format!("a={}/", id), id);
+        let dir = std::env::temp_dir().join(format!("delta-tree-watcher-{}", std::process::id()));
+        let log = dir.join("_delta_log");
+        std::fs::create_dir_all(&log).unwrap();
+        let commit = |version: i64, commit: &str| {
+            std::fs::write(log.join(format!("{:020}.json", version)), commit).unwrap();
+        };
+        let restored = Restored {
+            tree: DeltaTree::from_paths(&[path(1)]),
+            version: 0,
+            catch_up: CatchUp::Current,
+        };
+        let watcher = TableWatcher::new(dir.to_str().unwrap(), restored, StorageOptions::new())
+            .jitter(0.0)
+            .max_backoff(Duration::from_millis(40));
+        let mut changes = watcher.changes(4, Backpressure::Pause);
+        let watcher = watcher.spawn(Duration::from_millis(10));
+
+        commit(
+            1,
+            &format!(
+                r#"{{"add":{{"path":"{}","size":1,"dataChange":true}}}}"#,
+                path(2)
+            ),
+        );
+        let change = tokio::time::timeout(Duration::from_secs(10), changes.next()).await;
+        assert_eq!(
+            change.unwrap(),
+            Some(TreeChange::Commit {
+                version: 1,
+                added: vec![path(2)],
+                removed: vec![],
+            })
+        );
+        assert_eq!(watcher.read().version, 1);
+        assert_eq!(watcher.read().catch_up, CatchUp::Replayed { commits: 1 });
+        assert_eq!(
+            watcher.read().tree,
+            DeltaTree::from_paths(&[path(1), path(2)])
+        );
+
+        // a commit that doesn't parse makes the watcher reload the table, which fails too as
+        // the log has no first commit. the tree stays as it was and the refreshes back off.
+        commit(2, "{\"add\":");
+        let mut waited = Duration::from_secs(0);
+        while watcher.health().failures < 3 && waited < Duration::from_secs(10) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            waited += Duration::from_millis(10);
+        }
+        let health = watcher.health();
+        assert!(health.failures >= 3);
+        assert!(health.last_error.is_some());
+        assert_eq!(watcher.read().version, 1);
+
+        drop(watcher);
+        assert_eq!(changes.next().await, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn health_of_new_watcher() {
+        let restored = Restored {
+            tree: DeltaTree::from_paths(Vec::<String>::new()),
+            version: 3,
+            catch_up: CatchUp::Current,
+        };
+        let watcher = TableWatcher::new("s3://bucket/t", restored, StorageOptions::new());
+        assert_eq!(watcher.read().version, 3);
+        assert_eq!(watcher.health().failures, 0);
+        assert!(watcher.is_healthy(Duration::from_secs(60)));
+    }
+}